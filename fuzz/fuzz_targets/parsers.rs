@@ -0,0 +1,45 @@
+//! Feeds arbitrary input to every parser of user supplied text: sizes, durations, rates, globs,
+//! the protect, tag, alert, cost, ownership and fleet files, sessions and imported inventories.
+//! None of them may panic, however malformed the input.
+#![no_main]
+
+use ferris_files::alerts::AlertRules;
+use ferris_files::cost::{parse_rate, CostModel};
+use ferris_files::fleet::parse_hosts;
+use ferris_files::import::{import_entries, ImportFormat};
+use ferris_files::ownership::OwnershipMap;
+use ferris_files::protect::ProtectList;
+use ferris_files::quota::parse_mountinfo;
+use ferris_files::results::SavedScan;
+use ferris_files::retention::RetentionPolicy;
+use ferris_files::tags::TagMap;
+use ferris_files::units::{parse_duration, parse_size};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for format in [
+        ImportFormat::Du,
+        ImportFormat::Find,
+        ImportFormat::Ncdu,
+        ImportFormat::Wire,
+    ] {
+        let _ = import_entries(format, data, |_, _| {});
+    }
+
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let _ = parse_size(text);
+    let _ = parse_duration(text);
+    let _ = parse_rate(text);
+    let _ = RetentionPolicy::new(None, &[text.to_string()]);
+    let _ = ProtectList::parse(text);
+    let _ = TagMap::parse(text);
+    let _ = AlertRules::parse(text);
+    let _ = CostModel::parse(text, Some(0.02));
+    let _ = OwnershipMap::parse_csv(text);
+    let _ = OwnershipMap::parse_json(text);
+    let _ = parse_hosts(text);
+    let _ = parse_mountinfo(text);
+    let _ = SavedScan::from_json(text);
+});
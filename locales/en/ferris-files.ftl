@@ -0,0 +1,367 @@
+# Messages of ferris-files in English, also used for messages missing from other catalogs.
+# Sizes, paths and durations are passed pre-formatted; counts are numbers.
+
+## General
+
+error = Error: { $error }
+fatal-error = Fatal Error: { $error }
+arguments-invalid = Could not parse arguments: { $error }
+porcelain-conflict = --porcelain cannot be combined with --preview, --attribute, --quota, --deleted-open or actions
+format-conflict = --format { $format } cannot be combined with --porcelain, --preview, --attribute, --quota, --deleted-open or actions
+report-actions-need-top-files = Actions apply to the largest files; add --report top-files to see them
+dirs-no-actions = Actions apply to files and cannot be combined with --dirs
+report-by-tag-needs-tags = --report by-tag needs tag rules; add them to { $files }
+report-by-owner-needs-ownership = --report by-owner needs an ownership mapping given with --ownership
+export-needs-scan = { $option } writes the files of a scan and cannot be combined with --import
+tree-needs-scan = --tree shows the directories of a scan and cannot be combined with --import
+fan-out-needs-scan = --report fan-out counts the entries of scanned directories and cannot be combined with --import
+repo-needs-scan = --repo compares the .git directory of a scanned repository and cannot be combined with --import
+repo-not-found = { $dir } is not inside a git repository
+depth-needs-scan = --max-depth and --min-depth count levels below a scanned directory and cannot be combined with --import
+roots-conflict = { $option } works on a single directory and cannot be combined with several -d directories
+root-unreadable = Cannot scan { $dir }: { $error }
+roots-apart = { $first } and { $second } share no common directory to rank them under
+priorities-need-roots = The priorities in { $file } order several directories; give more than one -d
+read-only-option = { $option } writes files and cannot be combined with --assert-read-only
+read-only-command = This command writes or changes files and cannot be run with --assert-read-only
+read-only-write = refusing to write { $path } in read-only mode (--assert-read-only)
+scan-locked = another scan of { $root } is running (PID { $pid }); wait for it or pass --force
+scan-lock-forced = Taking over the lock of the scan running as PID { $pid } (--force)
+newest-needs-time = --newest needs --metric mtime or atime
+metric-needs-file-scan = --metric { $metric } ranks scanned files and cannot be combined with --dirs or --import
+output-unwritable = Cannot write { $option } to { $path }: { $error }
+parquet-unsupported = --export-parquet needs a ferris-files built with Parquet support (cargo install ferris-files --features parquet)
+portable-failed = Could not enable portable mode: { $error }
+completed = Program completed in { $seconds } seconds
+# Answers to yes/no prompts that mean yes, separated by commas
+answer-yes = y, yes
+windows-only = { $command } is only supported on Windows
+macos-only = { $command } is only supported on macOS
+
+## Scan
+
+preparing = Preparing to scan using { $threads } threads
+open-file-limit = Limiting open file handles to { $limit }
+own-files-only-unsupported = --own-files-only is only supported on Unix platforms and will be ignored
+by-user-unsupported = --report by-user needs file owners, which only Unix has; every file counts as of unknown user
+ads-unsupported = --ads is only supported on Windows and will be ignored
+exclusion-line-unreadable = Error reading line: { $error }
+exclusion-file-unreadable = Cannot read the excluded directories file { $file }: { $error }
+ignore-rules-invalid = Ignoring invalid rules in { $file }: { $error }
+searching = Searching for { $count } largest entries in { $root }:
+searching-oldest = Searching for the { $count } least recently modified files in { $root }:
+searching-newest = Searching for the { $count } most recently modified files in { $root }:
+searching-unread = Searching for the { $count } least recently accessed files in { $root }:
+searching-read = Searching for the { $count } most recently accessed files in { $root }:
+searching-longest = Searching for the { $count } files with the longest paths in { $root }:
+scanning = Scanning: { $dir }
+scan-complete = Directory scan complete: { $dirs } directories listed ({ $errors } errors encountered: run with -v for details)
+processing = Processing { $files } files, { $bytes } sized so far (successfully processed: { $processed }, failed: { $failed })...
+processed = Processed { $files } files, { $bytes } sized ({ $processed } successful, { $failed } failed)
+progress-feed-closed = Stopped streaming progress: { $error }
+serve-listening = Serving scans on http://{ $address }/api/ (WebSocket progress at /api/events)
+serve-connection-failed = Server connection failed: { $error }
+serve-scan-running = A scan is already running
+serve-busy = Too many connections; try again later
+serve-no-results = No results to serve yet: { $error }
+serve-not-found = No such endpoint: { $path }
+serve-method-not-allowed = { $method } is not supported by this endpoint
+serve-upgrade-required = /api/events is a WebSocket; connect with a WebSocket client
+serve-unauthorized = Missing or wrong token
+serve-foreign-origin = Requests must be addressed to this server and not come from another site
+serve-read-only = The server is read-only; start it with --allow-actions to apply actions
+serve-option-refused = The server does not accept --{ $option }
+serve-token-required = Listening on { $address } exposes the file system beyond this machine; give a --token (or set FERRIS_FILES_TOKEN)
+serve-actions-token-required = --allow-actions lets clients move and compress files; give a --token (or set FERRIS_FILES_TOKEN) too
+serve-actions-refused = Scans of the server would apply actions; start it with --allow-actions to allow that
+not-sized = Not sized: { $summary }
+scan-errors = Errors: { $summary }
+scan-errors-permission-denied = { $count } permission denied
+scan-errors-not-found = { $count } vanished during the scan
+scan-errors-hardware = { $count } I/O errors or timeouts
+scan-errors-other = { $count } other
+possible-failing-disk = WARNING: possible failing disk around { $path } ({ $io_errors } I/O errors, { $timeouts } timeouts while reading); back up this data and check the drive's health (e.g. SMART status)
+apparent-size-fallback = Note: { $mount } ({ $fs_type }) reported no allocated blocks for { $files } non-empty files; they were sized by their apparent size
+no-files-found = No files found - run with -v flag for error output
+stale-gone = gone since the scan
+stale-replaced = replaced since the scan
+stale-resized = now { $size }
+delta-new = new
+previous-settings-differ = The previous session of { $root } was produced with other settings (exclusions, entry count or filters); sizes are not compared with it
+previous-version-differs = The previous session of { $root } was written by ferris-files { $version }, which may size files differently; sizes are not compared with it
+delta-unchanged = unchanged
+recheck-incomplete = { $count } entries could not be re-checked in time and may be out of date
+full-scan-skipped = Full scan skipped
+saved-session = Saved session to { $path }
+session-not-saved = Could not save the results for follow-up commands: { $error }
+session-recovered = Recovered results whose save was interrupted: { $path }
+
+skipped-none = none
+skipped-symlinks = { $count ->
+        [one] { $count } symlink
+       *[other] { $count } symlinks
+    }
+skipped-sockets = { $count ->
+        [one] { $count } socket
+       *[other] { $count } sockets
+    }
+skipped-fifos = { $count ->
+        [one] { $count } FIFO
+       *[other] { $count } FIFOs
+    }
+skipped-block-devices = { $count ->
+        [one] { $count } block device
+       *[other] { $count } block devices
+    }
+skipped-char-devices = { $count ->
+        [one] { $count } character device
+       *[other] { $count } character devices
+    }
+skipped-excluded-dirs = { $count ->
+        [one] { $count } excluded directory
+       *[other] { $count } excluded directories
+    }
+skipped-unreadable-dirs = { $count ->
+        [one] { $count } unreadable directory
+       *[other] { $count } unreadable directories
+    }
+skipped-foreign-owned = { $count ->
+        [one] { $count } entry owned by another user
+       *[other] { $count } entries owned by other users
+    }
+skipped-too-deep-dirs = { $count ->
+        [one] { $count } directory beyond --depth-guard
+       *[other] { $count } directories beyond --depth-guard
+    }
+skipped-truncated-dirs = { $count ->
+        [one] { $count } partially listed directory beyond --max-dir-entries
+       *[other] { $count } partially listed directories beyond --max-dir-entries
+    }
+skipped-hard-links = { $count ->
+        [one] { $count } further hard link to a file already sized
+       *[other] { $count } further hard links to files already sized
+    }
+skipped-sparse = { $count ->
+        [one] { $count } sparse file (--skip-sparse)
+       *[other] { $count } sparse files (--skip-sparse)
+    }
+skipped-not-included = { $count ->
+        [one] { $count } file not matching --include
+       *[other] { $count } files not matching --include
+    }
+skipped-ignored = { $count ->
+        [one] { $count } entry ignored by .ferrisignore or .gitignore rules
+       *[other] { $count } entries ignored by .ferrisignore or .gitignore rules
+    }
+skipped-depth-limited-dirs = { $count ->
+        [one] { $count } directory at --max-depth, not listed
+       *[other] { $count } directories at --max-depth, not listed
+    }
+skipped-shallow-files = { $count ->
+        [one] { $count } file above --min-depth
+       *[other] { $count } files above --min-depth
+    }
+
+## Preview
+
+preview-heading = Preview of { $root } (sampled, approximate):
+preview-total = total
+preview-proceed = Proceed with the full scan? [y/N]
+
+## Import
+
+import-format-unknown = unknown import format { $format }: use du, find, ncdu or wire
+importing = Importing { $count } largest entries from { $file }:
+imported = Imported { $files } files ({ $skipped } malformed records skipped)
+import-empty = No files found in the inventory
+import-no-actions = Actions are not applied to imported inventories, whose files were not observed by a scan
+ncdu-exported = Exported the scanned tree to { $file } (browse it with ncdu -f)
+treemap-exported = Exported the scanned tree to { $file } as treemap JSON
+parquet-exported = Exported { $rows } files to { $file }
+parquet-export-halted = Stopped the Parquet export to { $file } after { $rows } files as less than { $reserve } was left free there (--min-free); the file is valid but incomplete
+snapshot-saved = Saved a snapshot of { $files } files to { $file } (compare it with a later one using diff OLD NEW)
+
+## Reports
+
+report-top-dirs = Largest directories:
+report-streams = Alternate data streams: { $count } holding { $size }, the largest:
+report-tree = Tree by size:
+report-by-ext = Space by extension:
+report-histogram = Files by size:
+report-stats = Size statistics:
+stats-line = { $files } files, { $total } in total, { $mean } on average, median about { $median }
+extension-none = (no extension)
+extension-largest = largest: { $path } ({ $size })
+report-by-tag = Space by tag:
+tag-none = (untagged)
+report-by-owner = Space by owner:
+owner-none = (no owner)
+report-by-user = Space by user:
+user-unknown = (unknown user)
+report-age-by-dir = Largest directories by file age (modified within 30 days / within a year / earlier):
+report-fan-out = Directories with the most entries:
+report-fan-out-deepest = Deepest directory, { $depth } levels below the root: { $path }
+report-dup-names = Large file names found in several directories:
+dup-name-copies = { $name } ({ $copies } copies)
+report-repo = Repository { $root }:
+repo-worktree = working tree
+report-artifacts = Build artifacts by project:
+artifact-clean = clean up with: { $command }
+cost-monthly = { $cost }/month
+cost-total = Estimated storage cost: { $cost } for { $size }
+alerts-heading = Alerts:
+alert-fired = Rule { $rule }: { $path } ({ $size })
+alert-action-failed = Could not run an action of alert rule '{ $rule }': { $error }
+alert-subject = ferris-files: { $count } storage alerts on { $host }
+audit-heading = Permission audit:
+audit-clean = No world-accessible or setuid/setgid files among the results
+audit-skipped = Permission audit skipped: { $error }
+attribution-heading = Attribution by owner and process:
+attribution-skipped = Attribution skipped: { $error }
+attribution-held-open = holds open
+attribution-working-dir = working directory
+attribution-owner = owner
+owner-unknown = Could not determine owner of { $path }: { $error }
+quota = Quota on { $mount } ({ $source }) for { $user }: { $usage }
+quota-unavailable = Quota for { $user } unavailable: { $error }
+quota-unsupported = Quota reporting is only supported on Linux
+quota-no-limit = { $used } used, no limit set
+quota-hard-limit = (hard limit { $limit })
+quota-over = { $used } used of { $limit } { $kind ->
+        [soft] soft limit
+       *[hard] hard limit
+    }{ $hard }: delete at least { $excess } to get under quota
+quota-headroom = { $used } used of { $limit } { $kind ->
+        [soft] soft limit
+       *[hard] hard limit
+    }{ $hard }: { $headroom } headroom
+deleted-open-heading = Deleted but still open files:
+deleted-open-none = No deleted files are held open by readable processes
+deleted-open-file = { $path } (held by { $process }[{ $pid }] fd { $fd }): { $size }
+deleted-open-failed = Could not inspect /proc for deleted open files: { $error }
+usage-unknown = Could not query filesystem usage for { $path }: { $error }
+reconciliation-heading = Filesystem reconciliation for { $path } (df vs scan):
+reconcile-used = Used space reported by df:    { $size }
+reconcile-files = Regular files scanned:        { $size }
+reconcile-dirs = Directory metadata:           { $size }
+reconcile-deleted-open = Deleted but still open files: { $size } (see --deleted-open)
+reconcile-deleted-open-unknown = Deleted but still open files: unknown (/proc unavailable)
+reconcile-unexplained = Remaining unexplained:        { $size }
+reconcile-exceeds = Scan exceeds df by:           { $size } (hardlinked files are counted once per link)
+reconcile-unscanned = { $count } directories were excluded or unreadable and not scanned
+reconcile-snapshots = this filesystem supports snapshots, which hold space invisible to a scan
+reconcile-metadata = remaining space is filesystem metadata (journals, extended attributes, reserved blocks)
+
+## Actions
+
+protected = Blocked action on { $path }: protected by rule '{ $rule }'
+verb-move = Move
+verb-archive = Archive
+verb-compress = Compress
+confirm-action = { $verb } { $path } ({ $size })? [y/N]
+delete-script-written = Wrote deletion script for { $count } files to { $path } (nothing was deleted)
+moved-files = Moved { $count } files ({ $size }) to { $destination }
+archived-files = Archived { $count } files ({ $size }) to { $destination }
+compressed-files = Compressed { $count } files ({ $size }), reclaiming { $saved }
+action-failed = Failed for { $path }: { $error }
+journal-recorded = Recorded undo journal at { $path } (revert with `ferris-files undo`)
+journal-failed = Failed to record { $path } in the undo journal: { $error }
+journal-line-unreadable = Skipping unreadable journal line in { $path }: { $error }
+journal-prune-failed = Could not remove old undo journals: { $error }
+no-scan-record = no scan-time record of this file; refusing to act on it
+changed-since-scan = changed since the scan ({ $change }); skipped, rescan before acting on it
+change-replaced = it was replaced by a different file
+change-resized = its size changed from { $before } to { $after } bytes
+change-modified = it was modified
+nothing-to-undo = Nothing to undo
+restore-failed = Failed to restore { $path }: { $error }
+restored = Restored { $restored } of { $total } files from { $path }
+
+## Sessions and follow-up commands
+
+provenance-root = root { $root }
+provenance-host = host { $host }
+provenance-scanned = scanned { $started } for { $seconds }s
+provenance-config = config { $hash }
+clean-needs-action = clean needs an action: --emit-delete-script, --move-to, --archive-to or --compress
+clean-remote-entry = [{ $id }] { $path } was found on host { $host }; run clean there
+revealed = Revealed [{ $id }] { $path }
+reveal-failed = Could not reveal { $path }: { $error }
+file-manager-exit = file manager exited with { $status }
+clipboard-unavailable = no clipboard tool is available
+copied = Copied { $count } paths ({ $size }) to the clipboard
+copy-failed = Could not copy to the clipboard ({ $error }); printing the paths instead
+diff-no-changes = No changes between the sessions
+diff-settings-differ = The sessions were produced with other settings (root, exclusions, entry count or filters); some differences may come from the settings rather than the disk
+diff-version-differs = The files were written by different versions of ferris-files ({ $old } and { $new }); some differences may come from how the versions size files rather than the disk
+diff-mixed-files = { $old } and { $new } must both be sessions or both be snapshots
+diff-snapshot-needs-new = { $old } is a snapshot; give the later snapshot to compare it with: diff OLD NEW
+snapshot-diff-heading = Changes beneath { $root } from { $old } to { $new }:
+snapshot-diff-no-changes = No changes between the snapshots
+snapshot-diff-dirs = Directories that changed the most:
+snapshot-diff-files = Files that changed the most:
+snapshot-grew = grew
+snapshot-shrank = shrank
+snapshot-appeared = appeared
+snapshot-vanished = vanished
+merge-saved = Saved merged report to { $path }
+fleet-hosts = Scanning { $count } hosts
+fleet-host-ok = { $host }: { $files } files, { $failed } failed ({ $seconds }s)
+fleet-host-failed = { $host }: failed: { $error }
+fleet-timed-out = timed out after { $seconds }s
+fleet-failed = { $count } of { $total } hosts could not be scanned
+query-session = Session: { $session }
+query-no-match = No entries of the session match
+more-available = More results are available: run `ferris-files more` to see the next ones
+entry-siblings = { $dir }: { $size } in { $count } results
+entries-total = { $count ->
+        [one] This one takes { $size }, { $share }% of the { $total } scanned
+       *[other] These { $count } take { $size }, { $share }% of the { $total } scanned
+    }
+entry-modified = modified { $time }
+entry-accessed = accessed { $time }
+entry-path-length = { $length } characters
+entry-links = { $count } hard links
+entry-apparent-size = apparent size { $size }
+entry-disk-size = { $size } on disk
+entry-sparse = sparse
+entry-root = from { $root }
+more-none = No further results were kept for this session; rescan with a larger -n to see more
+
+## Desktop integration
+
+shell-menu-label = Find largest files here
+shell-installed = Added "{ $label }" to the Explorer context menu of folders
+shell-removed = Removed the entry from the Explorer context menu
+shell-not-installed = The Explorer context menu entry is not installed
+reg-failed = reg { $command } failed: { $error }
+quick-action-installed = Installed the "{ $name }" quick action at { $path }
+quick-action-removed = Removed the "{ $name }" quick action
+quick-action-not-installed = The "{ $name }" quick action is not installed
+summarizing = Summarizing space usage of { $root }:
+category-label = { $category ->
+        [user-media] User media
+        [caches] Caches
+        [logs] Logs
+        [packages] Packages/system
+        [vms] VMs/containers
+       *[other] Other
+    }
+category-files = ({ $files } files)
+category-total = Total
+drilling = Scanning { $root } to drill down:
+drill-descend = Descend into it? [Y/n]
+drill-leaf = This is a file; nothing further to drill into.
+drill-empty = { $dir } holds no sized files.
+drill-interactive = drill is interactive and cannot be combined with --porcelain or --format
+simulating = Simulating the retention policy on { $root } (nothing is deleted):
+report-retention = Reclaimable by directory:
+retention-total = The policy would reclaim { $size } in { $files } files, { $share }% of the { $scanned } scanned
+guard-too-deep = Pathological tree: { $path } is nested more than { $limit } directories below the scan root; { $action ->
+        [skip] skipping it
+       *[scan] still scanning it (use --skip-pathological to skip)
+    }
+guard-huge-dir = Pathological directory: { $path } holds more than { $limit } entries; { $action ->
+        [skip] skipping the rest of it
+       *[scan] still scanning it (use --skip-pathological to skip)
+    }
@@ -0,0 +1,373 @@
+# Mensajes de ferris-files en español. Los mensajes que falten se muestran en inglés.
+# Los tamaños, rutas y duraciones llegan ya formateados; los recuentos son números.
+
+## General
+
+error = Error: { $error }
+fatal-error = Error fatal: { $error }
+arguments-invalid = No se pudieron interpretar los argumentos: { $error }
+porcelain-conflict = --porcelain no se puede combinar con --preview, --attribute, --quota, --deleted-open ni con acciones
+format-conflict = --format { $format } no se puede combinar con --porcelain, --preview, --attribute, --quota, --deleted-open ni con acciones
+report-actions-need-top-files = Las acciones se aplican a los archivos más grandes; añada --report top-files para verlos
+dirs-no-actions = Las acciones se aplican a archivos y no se pueden combinar con --dirs
+report-by-tag-needs-tags = --report by-tag necesita reglas de etiquetas; añádalas a { $files }
+report-by-owner-needs-ownership = --report by-owner necesita una asignación de propietarios indicada con --ownership
+export-needs-scan = { $option } escribe los archivos de un escaneo y no se puede combinar con --import
+tree-needs-scan = --tree muestra los directorios de un escaneo y no se puede combinar con --import
+fan-out-needs-scan = --report fan-out cuenta las entradas de los directorios escaneados y no se puede combinar con --import
+repo-needs-scan = --repo compara el directorio .git de un repositorio escaneado y no se puede combinar con --import
+repo-not-found = { $dir } no está dentro de un repositorio git
+depth-needs-scan = --max-depth y --min-depth cuentan niveles por debajo de un directorio escaneado y no se pueden combinar con --import
+roots-conflict = { $option } trabaja sobre un solo directorio y no se puede combinar con varios directorios -d
+root-unreadable = No se puede escanear { $dir }: { $error }
+roots-apart = { $first } y { $second } no comparten ningún directorio común bajo el que clasificarlos
+priorities-need-roots = Las prioridades de { $file } ordenan varios directorios; indique más de un -d
+read-only-option = { $option } escribe archivos y no se puede combinar con --assert-read-only
+read-only-command = Este comando escribe o modifica archivos y no se puede ejecutar con --assert-read-only
+read-only-write = no se escribe { $path } en modo de solo lectura (--assert-read-only)
+scan-locked = otro escaneo de { $root } está en curso (PID { $pid }); espere a que termine o use --force
+scan-lock-forced = Tomando el bloqueo del escaneo en curso con PID { $pid } (--force)
+newest-needs-time = --newest necesita --metric mtime o atime
+metric-needs-file-scan = --metric { $metric } ordena archivos escaneados y no se puede combinar con --dirs ni --import
+output-unwritable = No se puede escribir { $option } en { $path }: { $error }
+parquet-unsupported = --export-parquet necesita un ferris-files compilado con soporte de Parquet (cargo install ferris-files --features parquet)
+portable-failed = No se pudo activar el modo portátil: { $error }
+completed = Programa completado en { $seconds } segundos
+# Respuestas afirmativas a las preguntas de sí/no, separadas por comas
+answer-yes = s, si, sí
+windows-only = { $command } solo es compatible con Windows
+macos-only = { $command } solo es compatible con macOS
+
+## Escaneo
+
+preparing = Preparando el escaneo con { $threads ->
+        [one] { $threads } hilo
+       *[other] { $threads } hilos
+    }
+open-file-limit = Limitando los descriptores de archivo abiertos a { $limit }
+own-files-only-unsupported = --own-files-only solo es compatible con plataformas Unix y se ignorará
+by-user-unsupported = --report by-user necesita los propietarios de los archivos, que solo existen en Unix; todos los archivos cuentan como de usuario desconocido
+ads-unsupported = --ads solo es compatible con Windows y se ignorará
+exclusion-line-unreadable = Error al leer la línea: { $error }
+exclusion-file-unreadable = No se puede leer el archivo de directorios excluidos { $file }: { $error }
+ignore-rules-invalid = Se omiten reglas no válidas en { $file }: { $error }
+searching = Buscando las { $count } entradas más grandes en { $root }:
+searching-oldest = Buscando los { $count } archivos modificados hace más tiempo en { $root }:
+searching-newest = Buscando los { $count } archivos modificados más recientemente en { $root }:
+searching-unread = Buscando los { $count } archivos accedidos hace más tiempo en { $root }:
+searching-read = Buscando los { $count } archivos accedidos más recientemente en { $root }:
+searching-longest = Buscando los { $count } archivos con las rutas más largas en { $root }:
+scanning = Escaneando: { $dir }
+scan-complete = Escaneo de directorios completado: { $dirs } directorios listados ({ $errors } errores encontrados: use -v para ver los detalles)
+processing = Procesando { $files } archivos, { $bytes } medidos hasta ahora (procesados correctamente: { $processed }, fallidos: { $failed })...
+processed = Procesados { $files } archivos, { $bytes } medidos ({ $processed } correctos, { $failed } fallidos)
+progress-feed-closed = Se dejó de transmitir el progreso: { $error }
+serve-listening = Sirviendo escaneos en http://{ $address }/api/ (progreso por WebSocket en /api/events)
+serve-connection-failed = Falló una conexión del servidor: { $error }
+serve-scan-running = Ya hay un escaneo en curso
+serve-busy = Demasiadas conexiones; inténtelo más tarde
+serve-no-results = Aún no hay resultados que servir: { $error }
+serve-not-found = No existe el endpoint { $path }
+serve-method-not-allowed = Este endpoint no admite { $method }
+serve-upgrade-required = /api/events es un WebSocket; conéctese con un cliente WebSocket
+serve-unauthorized = Falta el token o es incorrecto
+serve-foreign-origin = Las solicitudes deben dirigirse a este servidor y no venir de otro sitio
+serve-read-only = El servidor es de solo lectura; inícielo con --allow-actions para aplicar acciones
+serve-option-refused = El servidor no acepta --{ $option }
+serve-token-required = Escuchar en { $address } expone el sistema de archivos fuera de esta máquina; indique un --token (o defina FERRIS_FILES_TOKEN)
+serve-actions-token-required = --allow-actions permite a los clientes mover y comprimir archivos; indique también un --token (o defina FERRIS_FILES_TOKEN)
+serve-actions-refused = Los escaneos del servidor aplicarían acciones; inícielo con --allow-actions para permitirlo
+not-sized = Sin medir: { $summary }
+scan-errors = Errores: { $summary }
+scan-errors-permission-denied = { $count } permiso denegado
+scan-errors-not-found = { $count ->
+        [one] { $count } desaparecido durante el escaneo
+       *[other] { $count } desaparecidos durante el escaneo
+    }
+scan-errors-hardware = { $count ->
+        [one] { $count } error de E/S o tiempo de espera agotado
+       *[other] { $count } errores de E/S o tiempos de espera agotados
+    }
+scan-errors-other = { $count ->
+        [one] { $count } otro
+       *[other] { $count } otros
+    }
+possible-failing-disk = AVISO: posible disco defectuoso en torno a { $path } ({ $io_errors } errores de E/S, { $timeouts } tiempos de espera agotados al leer); haga una copia de seguridad de estos datos y compruebe el estado de la unidad (p. ej. SMART)
+apparent-size-fallback = Nota: { $mount } ({ $fs_type }) no informó bloques asignados para { $files } archivos no vacíos; se midieron por su tamaño aparente
+no-files-found = No se encontraron archivos; use -v para ver los errores
+stale-gone = ya no existe
+stale-replaced = reemplazado desde el escaneo
+stale-resized = ahora { $size }
+delta-new = nuevo
+previous-settings-differ = La sesión anterior de { $root } se produjo con otra configuración (exclusiones, número de entradas o filtros); los tamaños no se comparan con ella
+previous-version-differs = La sesión anterior de { $root } la escribió ferris-files { $version }, que puede medir los archivos de otra forma; los tamaños no se comparan con ella
+delta-unchanged = sin cambios
+recheck-incomplete = { $count } entradas no se pudieron volver a comprobar a tiempo y pueden estar desactualizadas
+full-scan-skipped = Escaneo completo omitido
+saved-session = Sesión guardada en { $path }
+session-not-saved = No se pudieron guardar los resultados para comandos posteriores: { $error }
+session-recovered = Se recuperaron resultados cuyo guardado se interrumpió: { $path }
+
+skipped-none = nada
+skipped-symlinks = { $count ->
+        [one] { $count } enlace simbólico
+       *[other] { $count } enlaces simbólicos
+    }
+skipped-sockets = { $count } sockets
+skipped-fifos = { $count } FIFO
+skipped-block-devices = { $count ->
+        [one] { $count } dispositivo de bloques
+       *[other] { $count } dispositivos de bloques
+    }
+skipped-char-devices = { $count ->
+        [one] { $count } dispositivo de caracteres
+       *[other] { $count } dispositivos de caracteres
+    }
+skipped-excluded-dirs = { $count ->
+        [one] { $count } directorio excluido
+       *[other] { $count } directorios excluidos
+    }
+skipped-unreadable-dirs = { $count ->
+        [one] { $count } directorio ilegible
+       *[other] { $count } directorios ilegibles
+    }
+skipped-foreign-owned = { $count ->
+        [one] { $count } entrada de otro usuario
+       *[other] { $count } entradas de otros usuarios
+    }
+skipped-too-deep-dirs = { $count ->
+        [one] { $count } directorio más allá de --depth-guard
+       *[other] { $count } directorios más allá de --depth-guard
+    }
+skipped-truncated-dirs = { $count ->
+        [one] { $count } directorio listado parcialmente por --max-dir-entries
+       *[other] { $count } directorios listados parcialmente por --max-dir-entries
+    }
+skipped-hard-links = { $count ->
+        [one] { $count } enlace duro más a un archivo ya medido
+       *[other] { $count } enlaces duros más a archivos ya medidos
+    }
+skipped-sparse = { $count ->
+        [one] { $count } archivo disperso (--skip-sparse)
+       *[other] { $count } archivos dispersos (--skip-sparse)
+    }
+skipped-not-included = { $count ->
+        [one] { $count } archivo que no coincide con --include
+       *[other] { $count } archivos que no coinciden con --include
+    }
+skipped-ignored = { $count ->
+        [one] { $count } entrada ignorada por reglas de .ferrisignore o .gitignore
+       *[other] { $count } entradas ignoradas por reglas de .ferrisignore o .gitignore
+    }
+skipped-depth-limited-dirs = { $count ->
+        [one] { $count } directorio en --max-depth, sin listar
+       *[other] { $count } directorios en --max-depth, sin listar
+    }
+skipped-shallow-files = { $count ->
+        [one] { $count } archivo por encima de --min-depth
+       *[other] { $count } archivos por encima de --min-depth
+    }
+
+## Vista previa
+
+preview-heading = Vista previa de { $root } (por muestreo, aproximada):
+preview-total = total
+preview-proceed = ¿Continuar con el escaneo completo? [s/N]
+
+## Importación
+
+import-format-unknown = formato de importación desconocido { $format }: use du, find, ncdu o wire
+importing = Importando las { $count } entradas más grandes de { $file }:
+imported = Importados { $files } archivos ({ $skipped } registros mal formados omitidos)
+import-empty = No se encontraron archivos en el inventario
+import-no-actions = Las acciones no se aplican a inventarios importados, cuyos archivos no fueron observados por un escaneo
+ncdu-exported = Árbol escaneado exportado a { $file } (explórelo con ncdu -f)
+treemap-exported = Árbol escaneado exportado a { $file } como JSON de treemap
+parquet-exported = { $rows } archivos exportados a { $file }
+parquet-export-halted = Se detuvo la exportación Parquet a { $file } tras { $rows } archivos porque quedaban menos de { $reserve } libres (--min-free); el archivo es válido pero incompleto
+snapshot-saved = Se guardó una instantánea de { $files } archivos en { $file } (compárala con una posterior con diff ANTERIOR NUEVA)
+
+## Informes
+
+report-top-dirs = Directorios más grandes:
+report-streams = Flujos de datos alternativos: { $count } con { $size }, los mayores:
+report-tree = Árbol por tamaño:
+report-by-ext = Espacio por extensión:
+report-histogram = Archivos por tamaño:
+report-stats = Estadísticas de tamaño:
+stats-line = { $files } archivos, { $total } en total, { $mean } de media, mediana de unos { $median }
+extension-none = (sin extensión)
+extension-largest = el mayor: { $path } ({ $size })
+report-by-tag = Espacio por etiqueta:
+tag-none = (sin etiqueta)
+report-by-owner = Espacio por propietario:
+owner-none = (sin propietario)
+report-by-user = Espacio por usuario:
+user-unknown = (usuario desconocido)
+report-age-by-dir = Directorios más grandes por antigüedad (modificado en 30 días / en un año / antes):
+report-fan-out = Directorios con más entradas:
+report-fan-out-deepest = Directorio más profundo, { $depth } niveles por debajo de la raíz: { $path }
+report-dup-names = Nombres de archivos grandes presentes en varios directorios:
+dup-name-copies = { $name } ({ $copies } copias)
+report-repo = Repositorio { $root }:
+repo-worktree = árbol de trabajo
+report-artifacts = Artefactos de compilación por proyecto:
+artifact-clean = se limpia con: { $command }
+cost-monthly = { $cost }/mes
+cost-total = Coste de almacenamiento estimado: { $cost } por { $size }
+alerts-heading = Alertas:
+alert-fired = Regla { $rule }: { $path } ({ $size })
+alert-action-failed = No se pudo ejecutar una acción de la regla de alerta '{ $rule }': { $error }
+alert-subject = ferris-files: { $count } alertas de almacenamiento en { $host }
+audit-heading = Auditoría de permisos:
+audit-clean = Ningún resultado es accesible por todos ni tiene setuid/setgid
+audit-skipped = Auditoría de permisos omitida: { $error }
+attribution-heading = Atribución por propietario y proceso:
+attribution-skipped = Atribución omitida: { $error }
+attribution-held-open = lo mantiene abierto
+attribution-working-dir = directorio de trabajo
+attribution-owner = propietario
+owner-unknown = No se pudo determinar el propietario de { $path }: { $error }
+quota = Cuota en { $mount } ({ $source }) para { $user }: { $usage }
+quota-unavailable = Cuota de { $user } no disponible: { $error }
+quota-unsupported = Los informes de cuota solo son compatibles con Linux
+quota-no-limit = { $used } en uso, sin límite establecido
+quota-hard-limit = (límite estricto { $limit })
+quota-over = { $used } en uso de { $limit } de { $kind ->
+        [soft] límite flexible
+       *[hard] límite estricto
+    }{ $hard }: elimine al menos { $excess } para quedar dentro de la cuota
+quota-headroom = { $used } en uso de { $limit } de { $kind ->
+        [soft] límite flexible
+       *[hard] límite estricto
+    }{ $hard }: { $headroom } disponibles
+deleted-open-heading = Archivos eliminados pero aún abiertos:
+deleted-open-none = Ningún proceso legible mantiene abiertos archivos eliminados
+deleted-open-file = { $path } (abierto por { $process }[{ $pid }] fd { $fd }): { $size }
+deleted-open-failed = No se pudo inspeccionar /proc en busca de archivos eliminados abiertos: { $error }
+usage-unknown = No se pudo consultar el uso del sistema de archivos de { $path }: { $error }
+reconciliation-heading = Conciliación del sistema de archivos de { $path } (df frente al escaneo):
+reconcile-used = Espacio usado según df:                { $size }
+reconcile-files = Archivos regulares escaneados:         { $size }
+reconcile-dirs = Metadatos de directorios:              { $size }
+reconcile-deleted-open = Archivos eliminados pero abiertos:     { $size } (vea --deleted-open)
+reconcile-deleted-open-unknown = Archivos eliminados pero abiertos:     desconocido (/proc no disponible)
+reconcile-unexplained = Sin explicar:                          { $size }
+reconcile-exceeds = El escaneo supera a df en:             { $size } (los archivos con enlaces duros se cuentan una vez por enlace)
+reconcile-unscanned = { $count } directorios se excluyeron o no se pudieron leer y no se escanearon
+reconcile-snapshots = este sistema de archivos admite instantáneas, que ocupan espacio invisible para un escaneo
+reconcile-metadata = el espacio restante son metadatos del sistema de archivos (diarios, atributos extendidos, bloques reservados)
+
+## Acciones
+
+protected = Acción bloqueada sobre { $path }: protegido por la regla '{ $rule }'
+verb-move = Mover
+verb-archive = Archivar
+verb-compress = Comprimir
+confirm-action = ¿{ $verb } { $path } ({ $size })? [s/N]
+delete-script-written = Script de borrado para { $count } archivos escrito en { $path } (no se eliminó nada)
+moved-files = Movidos { $count } archivos ({ $size }) a { $destination }
+archived-files = Archivados { $count } archivos ({ $size }) en { $destination }
+compressed-files = Comprimidos { $count } archivos ({ $size }), recuperando { $saved }
+action-failed = Falló para { $path }: { $error }
+journal-recorded = Registro para deshacer guardado en { $path } (revierta con `ferris-files undo`)
+journal-failed = No se pudo anotar { $path } en el registro para deshacer: { $error }
+journal-line-unreadable = Omitiendo una línea ilegible del registro { $path }: { $error }
+journal-prune-failed = No se pudieron eliminar registros de deshacer antiguos: { $error }
+no-scan-record = no hay constancia de este archivo en el escaneo; no se actuará sobre él
+changed-since-scan = cambió desde el escaneo ({ $change }); omitido, vuelva a escanear antes de actuar sobre él
+change-replaced = fue sustituido por otro archivo
+change-resized = su tamaño cambió de { $before } a { $after } bytes
+change-modified = fue modificado
+nothing-to-undo = No hay nada que deshacer
+restore-failed = No se pudo restaurar { $path }: { $error }
+restored = Restaurados { $restored } de { $total } archivos desde { $path }
+
+## Sesiones y comandos posteriores
+
+provenance-root = raíz { $root }
+provenance-host = equipo { $host }
+provenance-scanned = escaneado el { $started } durante { $seconds } s
+provenance-config = configuración { $hash }
+clean-needs-action = clean necesita una acción: --emit-delete-script, --move-to, --archive-to o --compress
+clean-remote-entry = [{ $id }] { $path } se encontró en el equipo { $host }; ejecute clean allí
+revealed = Mostrado [{ $id }] { $path }
+reveal-failed = No se pudo mostrar { $path }: { $error }
+file-manager-exit = el gestor de archivos terminó con { $status }
+clipboard-unavailable = no hay ninguna herramienta de portapapeles disponible
+copied = Copiadas { $count } rutas ({ $size }) al portapapeles
+copy-failed = No se pudo copiar al portapapeles ({ $error }); se muestran las rutas en su lugar
+diff-no-changes = No hay cambios entre las sesiones
+diff-settings-differ = Las sesiones se produjeron con otra configuración (raíz, exclusiones, número de entradas o filtros); algunas diferencias pueden deberse a la configuración y no al disco
+diff-version-differs = Los archivos los escribieron versiones distintas de ferris-files ({ $old } y { $new }); algunas diferencias pueden deberse a cómo miden los archivos y no al disco
+diff-mixed-files = { $old } y { $new } deben ser ambos sesiones o ambos instantáneas
+diff-snapshot-needs-new = { $old } es una instantánea; indica la instantánea posterior con la que compararla: diff ANTERIOR NUEVA
+snapshot-diff-heading = Cambios bajo { $root } desde { $old } hasta { $new }:
+snapshot-diff-no-changes = No hay cambios entre las instantáneas
+snapshot-diff-dirs = Directorios que más cambiaron:
+snapshot-diff-files = Archivos que más cambiaron:
+snapshot-grew = creció
+snapshot-shrank = encogió
+snapshot-appeared = apareció
+snapshot-vanished = desapareció
+merge-saved = Informe combinado guardado en { $path }
+fleet-hosts = Analizando { $count } equipos
+fleet-host-ok = { $host }: { $files } archivos, { $failed } fallidos ({ $seconds }s)
+fleet-host-failed = { $host }: falló: { $error }
+fleet-timed-out = se agotó el tiempo tras { $seconds }s
+fleet-failed = No se pudieron analizar { $count } de { $total } equipos
+query-session = Sesión: { $session }
+query-no-match = Ninguna entrada de la sesión coincide
+more-available = Hay más resultados: ejecuta `ferris-files more` para ver los siguientes
+entry-siblings = { $dir }: { $size } en { $count } resultados
+entries-total = { $count ->
+        [one] Este ocupa { $size }, el { $share }% de los { $total } escaneados
+       *[other] Estos { $count } ocupan { $size }, el { $share }% de los { $total } escaneados
+    }
+entry-modified = modificado { $time }
+entry-accessed = accedido { $time }
+entry-path-length = { $length } caracteres
+entry-links = { $count } enlaces duros
+entry-apparent-size = tamaño aparente { $size }
+entry-disk-size = { $size } en disco
+entry-sparse = disperso
+entry-root = de { $root }
+more-none = No se guardaron más resultados para esta sesión; vuelve a escanear con un -n mayor para ver más
+
+## Integración con el escritorio
+
+shell-menu-label = Buscar los archivos más grandes aquí
+shell-installed = Se añadió "{ $label }" al menú contextual de carpetas del Explorador
+shell-removed = Se quitó la entrada del menú contextual del Explorador
+shell-not-installed = La entrada del menú contextual del Explorador no está instalada
+reg-failed = reg { $command } falló: { $error }
+quick-action-installed = Acción rápida "{ $name }" instalada en { $path }
+quick-action-removed = Acción rápida "{ $name }" eliminada
+quick-action-not-installed = La acción rápida "{ $name }" no está instalada
+summarizing = Resumiendo el uso de espacio de { $root }:
+category-label = { $category ->
+        [user-media] Multimedia del usuario
+        [caches] Cachés
+        [logs] Registros
+        [packages] Paquetes/sistema
+        [vms] VMs/contenedores
+       *[other] Otros
+    }
+category-files = ({ $files } archivos)
+category-total = Total
+drilling = Escaneando { $root } para profundizar:
+drill-descend = ¿Entrar en él? [S/n]
+drill-leaf = Es un archivo; no hay nada más en lo que profundizar.
+drill-empty = { $dir } no contiene archivos con tamaño.
+drill-interactive = drill es interactivo y no se puede combinar con --porcelain ni con --format
+simulating = Simulando la política de retención en { $root } (no se elimina nada):
+report-retention = Espacio recuperable por directorio:
+retention-total = La política recuperaría { $size } en { $files } archivos, el { $share }% de los { $scanned } escaneados
+guard-too-deep = Árbol patológico: { $path } está anidado a más de { $limit } directorios por debajo de la raíz; { $action ->
+        [skip] se omite
+       *[scan] se sigue escaneando (use --skip-pathological para omitirlo)
+    }
+guard-huge-dir = Directorio patológico: { $path } contiene más de { $limit } entradas; { $action ->
+        [skip] se omite el resto
+       *[scan] se sigue escaneando (use --skip-pathological para omitirlo)
+    }
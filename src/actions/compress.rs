@@ -0,0 +1,152 @@
+use crate::actions::guard::ScanStamps;
+use crate::actions::journal::{ActionKind, Journal};
+use crate::actions::verify::{sha256_file, sha256_reader, HASH_READS};
+use crate::actions::ActionReport;
+use clap::ValueEnum;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Compression formats supported by the compress action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Codec {
+    Zstd,
+}
+
+impl Codec {
+    /// File extension appended to compressed files.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Codec::Zstd => "zst",
+        }
+    }
+}
+
+/// Compression level used for zstd; favours speed since the inputs are typically large.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Returns the on-disk size of the file at `path`.
+fn size_on_disk(path: &Path) -> io::Result<u64> {
+    use filesize::PathExt;
+    path.size_on_disk()
+}
+
+/// Compresses a single file next to itself, verifying the result before removing the original.
+///
+/// # Returns
+///
+/// * `io::Result<(PathBuf, u64, [u8; 32])>` - Path of the compressed file, the bytes saved on
+///   disk and the SHA-256 digest of the original content
+fn compress_file(codec: Codec, source: &Path) -> io::Result<(PathBuf, u64, [u8; 32])> {
+    crate::storage::check_write(source)?;
+    let mut destination = source.as_os_str().to_owned();
+    destination.push(".");
+    destination.push(codec.extension());
+    let destination = PathBuf::from(destination);
+
+    let original_size = size_on_disk(source)?;
+    let original_digest = sha256_file(source)?;
+
+    let result = (|| {
+        let output = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&destination)?;
+        match codec {
+            Codec::Zstd => {
+                let mut encoder = zstd::Encoder::new(output, ZSTD_LEVEL)?;
+                io::copy(&mut BufReader::new(File::open(source)?), &mut encoder)?;
+                encoder.finish()?.sync_all()?;
+            }
+        }
+
+        let _permit = HASH_READS.acquire();
+        let decoded_digest = match codec {
+            Codec::Zstd => sha256_reader(zstd::Decoder::new(File::open(&destination)?)?)?,
+        };
+        if decoded_digest != original_digest {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "compressed copy failed verification; original kept",
+            ));
+        }
+
+        // Carry over permissions and modification time so age based selection still works
+        let metadata = fs::metadata(source)?;
+        fs::set_permissions(&destination, metadata.permissions())?;
+        File::options()
+            .write(true)
+            .open(&destination)?
+            .set_modified(metadata.modified()?)?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        // Only clean up a destination this call created
+        if e.kind() != io::ErrorKind::AlreadyExists {
+            let _ = fs::remove_file(&destination);
+        }
+        return Err(e);
+    }
+
+    fs::remove_file(source)?;
+    let saved = original_size.saturating_sub(size_on_disk(&destination)?);
+    Ok((destination, saved, original_digest))
+}
+
+/// Outcome of compressing a set of entries.
+#[derive(Debug, Default)]
+pub struct CompressReport {
+    pub report: ActionReport,
+    pub bytes_saved: u64,
+}
+
+/// Compresses the given entries in place with `codec`.
+///
+/// Each file is written to `<name>.<ext>`, decompressed again and compared by SHA-256 with the
+/// original, and only then is the original removed. Files that are already compressed by the
+/// same codec or that fail are left untouched. Every compressed file is recorded in `journal`.
+/// Files that changed since the scan, according to `stamps`, are skipped.
+pub fn compress_entries(
+    codec: Codec,
+    entries: &[&(String, u64)],
+    stamps: &ScanStamps,
+    journal: &mut Journal,
+) -> CompressReport {
+    let mut outcome = CompressReport::default();
+
+    for (path, size) in entries {
+        if path.ends_with(&format!(".{}", codec.extension())) {
+            outcome.report.failed.push((
+                path.clone(),
+                io::Error::new(io::ErrorKind::InvalidInput, "already compressed"),
+            ));
+            continue;
+        }
+
+        match stamps
+            .verify_unchanged(path)
+            .and_then(|_| compress_file(codec, Path::new(path)))
+        {
+            Ok((destination, saved, digest)) => {
+                if let Err(e) =
+                    journal.record(ActionKind::Compress, path, &destination, None, &digest)
+                {
+                    log::error!(
+                        "{}",
+                        tr!(
+                            "journal-failed",
+                            path = path.as_str(),
+                            error = e.to_string()
+                        )
+                    );
+                }
+                outcome.bytes_saved += saved;
+                outcome.report.succeeded.push((path.clone(), *size));
+            }
+            Err(e) => outcome.report.failed.push((path.clone(), e)),
+        }
+    }
+
+    outcome
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Time spent at most re-checking the reported entries once a scan completes.
+pub const RECHECK_BUDGET: Duration = Duration::from_secs(2);
+
+/// Identity of a file as observed during the scan.
+///
+/// * `len` - Apparent size in bytes
+/// * `modified` - Last modification time, if the platform reports one
+/// * `inode` - Device and inode number (Unix only), which change when a path is replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    pub inode: Option<(u64, u64)>,
+}
+
+impl Fingerprint {
+    /// Captures the fingerprint of a file from its metadata.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        #[cfg(unix)]
+        let inode = {
+            use std::os::unix::fs::MetadataExt;
+            Some((metadata.dev(), metadata.ino()))
+        };
+        #[cfg(not(unix))]
+        let inode = None;
+
+        Fingerprint {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            inode,
+        }
+    }
+
+    /// Describes how `current` differs from this (scan-time) fingerprint.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - Description of the first difference, or `None` if unchanged
+    pub fn difference(&self, current: &Fingerprint) -> Option<String> {
+        if self.inode != current.inode {
+            Some(tr!("change-replaced"))
+        } else if self.len != current.len {
+            Some(tr!(
+                "change-resized",
+                before = self.len,
+                after = current.len
+            ))
+        } else if self.modified != current.modified {
+            Some(tr!("change-modified"))
+        } else {
+            None
+        }
+    }
+}
+
+/// How a reported entry differs from what the scan saw, found by re-checking it afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Staleness {
+    /// The path no longer exists, e.g. a log rotated away during a long scan
+    Gone,
+    /// The path now refers to a different file
+    Replaced,
+    /// The file now has this apparent size in bytes
+    Resized(u64),
+}
+
+impl Staleness {
+    /// Stable identifier of the state, used in porcelain output.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Staleness::Gone => "gone",
+            Staleness::Replaced => "replaced",
+            Staleness::Resized(_) => "resized",
+        }
+    }
+
+    /// Describes the state for annotating a reported entry.
+    pub fn describe(&self) -> String {
+        match self {
+            Staleness::Gone => tr!("stale-gone"),
+            Staleness::Replaced => tr!("stale-replaced"),
+            Staleness::Resized(len) => tr!(
+                "stale-resized",
+                size = crate::traits::ByteSize::format_size(len)
+            ),
+        }
+    }
+}
+
+/// Scan-time fingerprints of the reported entries, keyed by path.
+///
+/// Actions consult these right before touching a file so that a path which was modified or
+/// replaced since the scan is never deleted, moved, archived or compressed.
+#[derive(Debug, Clone, Default)]
+pub struct ScanStamps {
+    stamps: HashMap<String, Fingerprint>,
+}
+
+impl ScanStamps {
+    /// Records the scan-time fingerprint of `path`.
+    pub fn insert(&mut self, path: String, fingerprint: Fingerprint) {
+        self.stamps.insert(path, fingerprint);
+    }
+
+    /// Drops every fingerprint whose path is not accepted by `keep`.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.stamps.retain(|path, _| keep(path));
+    }
+
+    /// Returns the scan-time fingerprint of `path`, if one was recorded.
+    pub fn get(&self, path: &str) -> Option<&Fingerprint> {
+        self.stamps.get(path)
+    }
+
+    /// Checks that `path` is still the file that was observed during the scan.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the change if the file was modified, resized or replaced
+    /// since the scan, if it can no longer be read, or if no scan-time record exists for it.
+    pub fn verify_unchanged(&self, path: &str) -> io::Result<()> {
+        let expected = self
+            .get(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, tr!("no-scan-record")))?;
+        let current = Fingerprint::from_metadata(&std::fs::metadata(Path::new(path))?);
+
+        match expected.difference(&current) {
+            Some(change) => Err(io::Error::other(tr!("changed-since-scan", change = change))),
+            None => Ok(()),
+        }
+    }
+
+    /// Re-stats `paths` to find entries that vanished, were replaced or changed size since
+    /// they were sized, giving up once `budget` is spent.
+    ///
+    /// Paths without a scan-time record and paths that cannot be read for other reasons are
+    /// not reported; a file that was only modified keeps its size and is not reported either.
+    ///
+    /// # Returns
+    ///
+    /// * `(HashMap<String, Staleness>, usize)` - The stale paths, and the number of paths that
+    ///   were not checked because the budget ran out
+    pub fn recheck<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a str>,
+        budget: Duration,
+    ) -> (HashMap<String, Staleness>, usize) {
+        let deadline = Instant::now() + budget;
+        let mut stale = HashMap::new();
+        let mut unchecked = 0;
+
+        for path in paths {
+            if Instant::now() >= deadline {
+                unchecked += 1;
+                continue;
+            }
+            let Some(expected) = self.get(path) else {
+                continue;
+            };
+            let state = match std::fs::metadata(Path::new(path)) {
+                Ok(metadata) => {
+                    let current = Fingerprint::from_metadata(&metadata);
+                    if current.inode != expected.inode {
+                        Staleness::Replaced
+                    } else if current.len != expected.len {
+                        Staleness::Resized(current.len)
+                    } else {
+                        continue;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Staleness::Gone,
+                Err(_) => continue,
+            };
+            stale.insert(path.to_string(), state);
+        }
+        (stale, unchecked)
+    }
+}
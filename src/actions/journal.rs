@@ -0,0 +1,224 @@
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Kind of destructive action recorded in a journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    Move,
+    Archive,
+    Compress,
+}
+
+/// A single file operation that can be reverted by `undo`.
+///
+/// * `original` - Path the file had before the action
+/// * `destination` - Moved file, compressed file, or tarball the file was archived into
+/// * `member` - Name of the member inside the tarball (archive actions only)
+/// * `sha256` - Digest of the original content, used to verify the restored file
+/// * `timestamp` - Seconds since the Unix epoch at which the action completed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub action: ActionKind,
+    pub original: String,
+    pub destination: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    pub sha256: String,
+    pub timestamp: u64,
+}
+
+/// Extension of journals that have not been undone yet.
+const JOURNAL_EXTENSION: &str = "jsonl";
+/// Extension given to journals once they have been undone.
+const UNDONE_EXTENSION: &str = "undone";
+
+/// Number of batches whose journals are kept; older ones are pruned when a new batch starts.
+pub const JOURNAL_RETENTION: usize = 50;
+
+/// Returns the directory holding the undo journals.
+pub fn journal_dir() -> io::Result<PathBuf> {
+    let dir = storage::cache_dir()?.join("journal");
+    storage::check_write(&dir)?;
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Append-only record of the operations performed by one invocation (a batch).
+///
+/// Each operation is written and flushed as one JSON line as soon as it completes, so the
+/// journal stays usable even if the program is interrupted halfway through a batch.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    entries: usize,
+}
+
+impl Journal {
+    /// Creates a new, empty journal for a batch in the default journal directory.
+    ///
+    /// Journals beyond [`JOURNAL_RETENTION`] are pruned first; failing to prune is only logged.
+    pub fn create() -> io::Result<Journal> {
+        let dir = journal_dir()?;
+        if let Err(e) = prune(&dir, JOURNAL_RETENTION) {
+            log::warn!("{}", tr!("journal-prune-failed", error = e.to_string()));
+        }
+        Self::create_in(&dir)
+    }
+
+    /// Creates a new, empty journal for a batch in `dir`.
+    ///
+    /// Batch names start with a zero padded timestamp so they sort chronologically.
+    pub fn create_in(dir: &Path) -> io::Result<Journal> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let name = format!(
+            "{:020}-{}.{}",
+            now.as_nanos(),
+            std::process::id(),
+            JOURNAL_EXTENSION
+        );
+        let path = dir.join(name);
+        let file = File::options().append(true).create_new(true).open(&path)?;
+
+        Ok(Journal {
+            path,
+            file,
+            entries: 0,
+        })
+    }
+
+    /// Appends an operation to the journal and flushes it to disk.
+    pub fn record(
+        &mut self,
+        action: ActionKind,
+        original: &str,
+        destination: &Path,
+        member: Option<&Path>,
+        digest: &[u8],
+    ) -> io::Result<()> {
+        let entry = JournalEntry {
+            action,
+            original: original.to_string(),
+            destination: destination.to_string_lossy().into_owned(),
+            member: member.map(|member| member.to_string_lossy().into_owned()),
+            sha256: crate::actions::verify::to_hex(digest),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        };
+
+        let mut line = serde_json::to_string(&entry).map_err(io::Error::other)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Closes the journal, removing it if no operation was recorded.
+    pub fn finish(self) -> io::Result<Option<PathBuf>> {
+        if self.entries == 0 {
+            fs::remove_file(&self.path)?;
+            return Ok(None);
+        }
+        Ok(Some(self.path))
+    }
+}
+
+/// Reads every entry of a journal file, skipping a trailing partially written line.
+pub fn read_entries(path: &Path) -> io::Result<Vec<JournalEntry>> {
+    let mut entries = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!(
+                "{}",
+                tr!(
+                    "journal-line-unreadable",
+                    path = path.display().to_string(),
+                    error = e.to_string()
+                )
+            ),
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns the most recent journal in `dir` that has not been undone yet.
+pub fn latest_pending(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let mut journals: Vec<PathBuf> = fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(JOURNAL_EXTENSION))
+        .collect();
+    journals.sort();
+    Ok(journals.pop())
+}
+
+/// Removes undone journals and all but the `keep` most recent pending ones from `dir`.
+///
+/// Only the most recent batches can be undone in practice, since `undo` walks back from the
+/// latest one, so this bounds the journal directory of long-lived installations without losing
+/// anything `undo` would reach. Returns the number of journals removed.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::actions::journal::{latest_pending, mark_undone, prune, Journal};
+///
+/// let dir = std::env::temp_dir().join(format!("ff-prune-doc-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let journals: Vec<_> = (0..3)
+///     .map(|_| Journal::create_in(&dir).unwrap().path().to_path_buf())
+///     .collect();
+/// mark_undone(&journals[2]).unwrap();
+///
+/// // Keeps the newest pending journal, drops the older one and the undone one
+/// assert_eq!(prune(&dir, 1).unwrap(), 2);
+/// assert_eq!(latest_pending(&dir).unwrap(), Some(journals[1].clone()));
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn prune(dir: &Path, keep: usize) -> io::Result<usize> {
+    let mut pending = Vec::new();
+    let mut removed = 0;
+    for path in fs::read_dir(dir)?.flatten().map(|entry| entry.path()) {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(JOURNAL_EXTENSION) => pending.push(path),
+            Some(UNDONE_EXTENSION) => {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+            _ => {}
+        }
+    }
+    pending.sort();
+    let excess = pending.len().saturating_sub(keep);
+    for path in &pending[..excess] {
+        fs::remove_file(path)?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Marks a journal as undone so it is not picked up again.
+pub fn mark_undone(path: &Path) -> io::Result<()> {
+    storage::check_write(path)?;
+    fs::rename(path, path.with_extension(UNDONE_EXTENSION))
+}
@@ -0,0 +1,256 @@
+use crate::actions::guard::ScanStamps;
+use crate::config::Config;
+use crate::console;
+use crate::i18n::{self, FluentArgs};
+use crate::protect::ProtectList;
+use crate::traits::ByteSize;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+pub mod compress;
+pub mod guard;
+pub mod journal;
+pub mod relocate;
+pub mod script;
+pub mod undo;
+pub mod verify;
+
+/// Criteria deciding which of the reported entries an action applies to.
+///
+/// Actions never reach beyond the reported top entries; the selection only narrows them down.
+/// Entries covered by the protect list are always left out.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::actions::Selection;
+///
+/// let selection = Selection { min_size: Some(100), ..Default::default() };
+/// let entries = vec![("big".to_string(), 200), ("small".to_string(), 50)];
+/// let selected = selection.select(&entries);
+/// assert_eq!(selected.len(), 1);
+/// assert_eq!(selected[0].0, "big");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    pub min_size: Option<u64>,
+    pub older_than: Option<Duration>,
+    pub interactive: bool,
+    pub protect: ProtectList,
+}
+
+impl Selection {
+    /// Builds the selection criteria from the runtime configuration.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            min_size: config.action_min_size,
+            older_than: config.action_older_than,
+            interactive: config.interactive,
+            protect: config.protect.clone(),
+        }
+    }
+
+    /// Returns the entries matching every criterion, preserving their order.
+    ///
+    /// The age criterion is checked against each file's current modification time; files
+    /// whose modification time cannot be read are not selected by it. Protected entries are
+    /// logged and dropped.
+    pub fn select<'a>(&self, entries: &'a [(String, u64)]) -> Vec<&'a (String, u64)> {
+        let now = SystemTime::now();
+        entries
+            .iter()
+            .filter(|(path, _)| match self.protect.matching_rule(path) {
+                Some(rule) => {
+                    log::warn!("{}", tr!("protected", path = path.as_str(), rule = rule));
+                    false
+                }
+                None => true,
+            })
+            .filter(|(_, size)| self.min_size.is_none_or(|min| *size >= min))
+            .filter(|(path, _)| {
+                self.older_than.is_none_or(|age| {
+                    std::fs::metadata(path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| now.duration_since(modified).ok())
+                        .is_some_and(|elapsed| elapsed >= age)
+                })
+            })
+            .collect()
+    }
+
+    /// Describes the criteria in a single line, e.g. for script headers.
+    pub fn describe(&self) -> String {
+        let mut criteria = Vec::new();
+        if let Some(min) = self.min_size {
+            criteria.push(format!("of at least {}", min.format_size()));
+        }
+        if let Some(age) = self.older_than {
+            criteria.push(format!(
+                "unmodified for at least {} days",
+                age.as_secs() / 86_400
+            ));
+        }
+        if self.interactive {
+            criteria.push("confirmed interactively".to_string());
+        }
+
+        if criteria.is_empty() {
+            "all reported entries".to_string()
+        } else {
+            format!("reported entries {}", criteria.join(", "))
+        }
+    }
+
+    /// Selects the matching entries and, in interactive mode, asks for confirmation of each.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The reported entries to choose from
+    /// * `verb` - Action shown in the prompt, e.g. `"Move"` (already translated)
+    pub fn choose<'a>(
+        &self,
+        entries: &'a [(String, u64)],
+        verb: &str,
+    ) -> io::Result<Vec<&'a (String, u64)>> {
+        let selected = self.select(entries);
+        if !self.interactive {
+            return Ok(selected);
+        }
+
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        let mut confirmed = Vec::new();
+        for entry in selected {
+            print!(
+                "{} ",
+                tr!(
+                    "confirm-action",
+                    verb = verb,
+                    path = entry.0.as_str(),
+                    size = entry.1.format_size()
+                )
+            );
+            io::stdout().flush()?;
+            match lines.next() {
+                Some(answer) => {
+                    if i18n::is_yes(&answer?) {
+                        confirmed.push(entry);
+                    }
+                }
+                None => break,
+            }
+        }
+        Ok(confirmed)
+    }
+}
+
+/// Outcome of applying an action to a set of entries.
+#[derive(Debug, Default)]
+pub struct ActionReport {
+    pub succeeded: Vec<(String, u64)>,
+    pub failed: Vec<(String, io::Error)>,
+}
+
+impl ActionReport {
+    /// Total size of the entries the action succeeded on.
+    pub fn bytes(&self) -> u64 {
+        self.succeeded.iter().map(|(_, size)| size).sum()
+    }
+
+    /// Prints a one line summary (message `summary_id`) followed by every failure.
+    fn print(&self, summary_id: &str, destination: &Path) {
+        let mut args = FluentArgs::new();
+        args.set("count", self.succeeded.len());
+        args.set("size", self.bytes().format_size());
+        args.set("destination", destination.display().to_string());
+        println!("\n{}", i18n::message(summary_id, Some(&args)));
+        print_failures(&self.failed);
+    }
+}
+
+/// Prints every entry an action failed on, with the reason.
+fn print_failures(failed: &[(String, io::Error)]) {
+    for (path, err) in failed {
+        eprintln!(
+            "{}",
+            tr!(
+                "action-failed",
+                path = path.as_str(),
+                error = err.to_string()
+            )
+        );
+    }
+}
+
+/// Applies every action requested in the configuration to the reported entries.
+///
+/// Non-destructive actions (the deletion script) run first. The scan root is canonicalized
+/// so destinations mirror the structure of the reported (canonical) paths. Moves, archives
+/// and compressions are recorded in an undo journal which is created before anything is touched,
+/// and each file is compared with its scan-time fingerprint in `stamps` right before it is acted on.
+///
+/// # Errors
+///
+/// Returns an error if an output (script, archive or undo journal) cannot be written or if
+/// reading confirmations fails. Failures on individual files are reported but do not abort.
+pub fn run(config: &Config, entries: &[(String, u64)], stamps: &ScanStamps) -> io::Result<()> {
+    verify::HASH_READS.set_max(config.hash_concurrency);
+    let selection = Selection::from_config(config);
+    let root = console::canonicalize(&config.root_path)
+        .unwrap_or_else(|_| config.root_path.clone());
+
+    if let Some(script_path) = &config.delete_script {
+        let count = script::write_delete_script(script_path, &root, &selection, entries)?;
+        println!(
+            "\n{}",
+            tr!(
+                "delete-script-written",
+                count = count,
+                path = script_path.display().to_string()
+            )
+        );
+    }
+
+    if config.move_to.is_none() && config.archive_to.is_none() && config.compress.is_none() {
+        return Ok(());
+    }
+    let mut journal = journal::Journal::create()?;
+
+    if let Some(target_dir) = &config.move_to {
+        let chosen = selection.choose(entries, &tr!("verb-move"))?;
+        relocate::move_entries(&root, target_dir, &chosen, stamps, &mut journal)
+            .print("moved-files", target_dir);
+    }
+
+    if let Some(archive_path) = &config.archive_to {
+        let chosen = selection.choose(entries, &tr!("verb-archive"))?;
+        relocate::archive_entries(&root, archive_path, &chosen, stamps, &mut journal)?
+            .print("archived-files", archive_path);
+    }
+
+    if let Some(codec) = config.compress {
+        let chosen = selection.choose(entries, &tr!("verb-compress"))?;
+        let outcome = compress::compress_entries(codec, &chosen, stamps, &mut journal);
+        println!(
+            "\n{}",
+            tr!(
+                "compressed-files",
+                count = outcome.report.succeeded.len(),
+                size = outcome.report.bytes().format_size(),
+                saved = outcome.bytes_saved.format_size()
+            )
+        );
+        print_failures(&outcome.report.failed);
+    }
+
+    if let Some(path) = journal.finish()? {
+        println!(
+            "{}",
+            tr!("journal-recorded", path = path.display().to_string())
+        );
+    }
+
+    Ok(())
+}
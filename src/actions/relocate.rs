@@ -0,0 +1,258 @@
+use crate::actions::guard::ScanStamps;
+use crate::actions::journal::{ActionKind, Journal};
+use crate::actions::verify::{sha256_file, sha256_reader, verify_digest, HASH_READS};
+use crate::actions::ActionReport;
+use crate::console;
+use rayon::prelude::*;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Returns the path of `path` relative to `root`, falling back to the path without its
+/// prefix and root components when it does not live beneath `root`.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::actions::relocate::relative_to_root;
+/// use std::path::{Path, PathBuf};
+///
+/// assert_eq!(
+///     relative_to_root(Path::new("/data/logs/a.log"), Path::new("/data")),
+///     PathBuf::from("logs/a.log")
+/// );
+/// assert_eq!(
+///     relative_to_root(Path::new("/other/a.log"), Path::new("/data")),
+///     PathBuf::from("other/a.log")
+/// );
+/// ```
+pub fn relative_to_root(path: &Path, root: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect(),
+    }
+}
+
+/// Moves a single file to `destination`, verifying the copy when it cannot be linked there.
+///
+/// On the same filesystem the file is hard linked at `destination` and then unlinked from
+/// `source`; unlike a rename, linking fails instead of replacing a file created at
+/// `destination` in the meantime. Across filesystems, or where hard links are not supported,
+/// the file is copied into a newly created destination, its permissions and modification time
+/// restored, the copy synced to disk and verified by SHA-256, and only then is the original
+/// removed.
+///
+/// # Returns
+///
+/// * `io::Result<[u8; 32]>` - SHA-256 digest of the moved content
+///
+/// # Errors
+///
+/// Returns an `AlreadyExists` error, leaving both files alone, if `destination` exists.
+pub(crate) fn move_file(source: &Path, destination: &Path) -> io::Result<[u8; 32]> {
+    crate::storage::check_write(source)?;
+    let exists = || {
+        io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", destination.display()),
+        )
+    };
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let digest = sha256_file(source)?;
+    match fs::hard_link(source, destination) {
+        Ok(()) => {
+            if let Err(e) = fs::remove_file(source) {
+                let _ = fs::remove_file(destination);
+                return Err(e);
+            }
+            return Ok(digest);
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(exists()),
+        Err(_) => {}
+    }
+
+    let copied = (|| {
+        let mut input = File::open(source)?;
+        let mut output = File::options()
+            .write(true)
+            .create_new(true)
+            .open(destination)?;
+        io::copy(&mut input, &mut output)?;
+        let metadata = fs::metadata(source)?;
+        if let Ok(modified) = metadata.modified() {
+            output.set_modified(modified)?;
+        }
+        output.set_permissions(metadata.permissions())?;
+        // Synced before the original goes, so a crash cannot lose both
+        output.sync_all()?;
+        verify_digest(destination, &digest)?;
+        crate::results::sync_parent(destination)
+    })();
+    match copied {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(exists()),
+        Err(e) => {
+            // Only clean up a destination this call created
+            let _ = fs::remove_file(destination);
+            return Err(e);
+        }
+    }
+    fs::remove_file(source)?;
+    Ok(digest)
+}
+
+/// Moves the given entries beneath `target_dir`, preserving their structure relative to `root`.
+///
+/// Each file is handled independently: a failure is recorded in the report and the
+/// remaining files are still processed. Originals are only removed once their copy has
+/// been verified, and every completed move is recorded in `journal` so it can be undone.
+/// Files that changed since the scan, according to `stamps`, are skipped.
+pub fn move_entries(
+    root: &Path,
+    target_dir: &Path,
+    entries: &[&(String, u64)],
+    stamps: &ScanStamps,
+    journal: &mut Journal,
+) -> ActionReport {
+    let mut report = ActionReport::default();
+
+    for (path, size) in entries {
+        let source = Path::new(path);
+        let destination = target_dir.join(relative_to_root(source, root));
+        match stamps
+            .verify_unchanged(path)
+            .and_then(|_| move_file(source, &destination))
+        {
+            Ok(digest) => {
+                if let Err(e) = journal.record(ActionKind::Move, path, &destination, None, &digest)
+                {
+                    log::error!(
+                        "{}",
+                        tr!(
+                            "journal-failed",
+                            path = path.as_str(),
+                            error = e.to_string()
+                        )
+                    );
+                }
+                report.succeeded.push((path.clone(), *size));
+            }
+            Err(e) => report.failed.push((path.clone(), e)),
+        }
+    }
+
+    report
+}
+
+/// Archives the given entries into a tarball at `archive_path` and removes the originals.
+///
+/// Entries are stored under their path relative to `root`. Once the archive is written it is
+/// read back and every member is compared by SHA-256 against its original; only originals
+/// whose archived copy verified are removed. Every removed original is recorded in `journal`.
+/// Files that changed since the scan, according to `stamps`, are neither archived nor removed.
+///
+/// # Errors
+///
+/// Returns an error (without removing anything) if the archive already exists or cannot be
+/// created, written or read back. Failures of individual files are recorded in the report.
+pub fn archive_entries(
+    root: &Path,
+    archive_path: &Path,
+    entries: &[&(String, u64)],
+    stamps: &ScanStamps,
+    journal: &mut Journal,
+) -> io::Result<ActionReport> {
+    let mut report = ActionReport::default();
+    let mut archived = Vec::new();
+
+    crate::storage::check_write(archive_path)?;
+    let file = File::options()
+        .write(true)
+        .create_new(true)
+        .open(archive_path)?;
+    let mut builder = tar::Builder::new(file);
+
+    // Hash the originals up front, in parallel within the configured read limit
+    let digests: Vec<io::Result<[u8; 32]>> = entries
+        .par_iter()
+        .map(|(path, _)| {
+            stamps
+                .verify_unchanged(path)
+                .and_then(|_| sha256_file(Path::new(path)))
+        })
+        .collect();
+
+    for ((path, size), digest) in entries.iter().zip(digests) {
+        let relative = relative_to_root(Path::new(path), root);
+        match digest.and_then(|digest| {
+            builder
+                .append_path_with_name(path, &relative)
+                .map(|_| digest)
+        }) {
+            Ok(digest) => archived.push((path, *size, relative, digest)),
+            Err(e) => report.failed.push((path.clone(), e)),
+        }
+    }
+    builder.into_inner()?.sync_all()?;
+
+    // Read the archive back and hash each member before touching any original
+    let mut verified = std::collections::HashMap::new();
+    let permit = HASH_READS.acquire();
+    let mut archive = tar::Archive::new(File::open(archive_path)?);
+    for member in archive.entries()? {
+        let member = member?;
+        let name = member.path()?.into_owned();
+        verified.insert(name, sha256_reader(member)?);
+    }
+    drop(permit);
+
+    for (path, size, relative, digest) in archived {
+        if verified.get(&relative) != Some(&digest) {
+            report.failed.push((
+                path.clone(),
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "archived copy failed verification; original kept",
+                ),
+            ));
+            continue;
+        }
+        // The file may have changed while the archive was being written and verified
+        if let Err(e) = stamps.verify_unchanged(path) {
+            report.failed.push((path.clone(), e));
+            continue;
+        }
+        match fs::remove_file(path) {
+            Ok(()) => {
+                let archive = console::canonicalize(archive_path)
+                    .unwrap_or_else(|_| archive_path.to_path_buf());
+                if let Err(e) = journal.record(
+                    ActionKind::Archive,
+                    path,
+                    &archive,
+                    Some(&relative),
+                    &digest,
+                ) {
+                    log::error!(
+                        "{}",
+                        tr!(
+                            "journal-failed",
+                            path = path.as_str(),
+                            error = e.to_string()
+                        )
+                    );
+                }
+                report.succeeded.push((path.clone(), size));
+            }
+            Err(e) => report.failed.push((path.clone(), e)),
+        }
+    }
+
+    Ok(report)
+}
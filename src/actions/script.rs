@@ -0,0 +1,136 @@
+use crate::actions::Selection;
+use crate::traits::ByteSize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Flavour of deletion script to generate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    /// POSIX `sh` script using `rm`
+    Shell,
+    /// PowerShell script using `Remove-Item`
+    PowerShell,
+}
+
+impl ScriptKind {
+    /// Picks the script flavour from the output file extension (`.ps1` selects PowerShell).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::actions::script::ScriptKind;
+    /// use std::path::Path;
+    ///
+    /// assert_eq!(ScriptKind::for_path(Path::new("cleanup.ps1")), ScriptKind::PowerShell);
+    /// assert_eq!(ScriptKind::for_path(Path::new("cleanup.sh")), ScriptKind::Shell);
+    /// ```
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("ps1") => ScriptKind::PowerShell,
+            _ => ScriptKind::Shell,
+        }
+    }
+
+    /// Quotes a path so it is passed verbatim as a single argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::actions::script::ScriptKind;
+    ///
+    /// assert_eq!(ScriptKind::Shell.quote("it's"), r#"'it'\''s'"#);
+    /// assert_eq!(ScriptKind::PowerShell.quote("it's"), "'it''s'");
+    /// ```
+    pub fn quote(&self, path: &str) -> String {
+        match self {
+            ScriptKind::Shell => format!("'{}'", path.replace('\'', r"'\''")),
+            ScriptKind::PowerShell => format!("'{}'", path.replace('\'', "''")),
+        }
+    }
+}
+
+/// Renders a commented deletion script for the given entries.
+///
+/// The script starts with a header describing where the entries came from and how they were
+/// selected, and lists one delete command per entry preceded by a comment with its size.
+/// It stops on the first failure so a partially reviewed script never silently continues.
+pub fn render(
+    kind: ScriptKind,
+    root: &Path,
+    selection: &Selection,
+    entries: &[&(String, u64)],
+) -> String {
+    let total: u64 = entries.iter().map(|(_, size)| size).sum();
+    let mut script = String::new();
+
+    if kind == ScriptKind::Shell {
+        script.push_str("#!/bin/sh\n");
+    }
+    script.push_str(&format!(
+        "# Deletion script generated by ferris-files {}\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    script.push_str(&format!("# Scan root: {}\n", root.display()));
+    script.push_str(&format!("# Selection: {}\n", selection.describe()));
+    script.push_str(&format!(
+        "# {} files, {} in total\n",
+        entries.len(),
+        total.format_size()
+    ));
+    script.push_str("#\n# Nothing has been deleted yet. Review (and remove lines from) this script\n");
+    script.push_str("# before running it.\n\n");
+
+    match kind {
+        ScriptKind::Shell => script.push_str("set -eu\n"),
+        ScriptKind::PowerShell => script.push_str("$ErrorActionPreference = 'Stop'\n"),
+    }
+
+    for (path, size) in entries {
+        script.push_str(&format!("\n# {}\n", size.format_size()));
+        match kind {
+            ScriptKind::Shell => script.push_str(&format!("rm -f -- {}\n", kind.quote(path))),
+            ScriptKind::PowerShell => script.push_str(&format!(
+                "Remove-Item -LiteralPath {} -Force\n",
+                kind.quote(path)
+            )),
+        }
+    }
+
+    script
+}
+
+/// Writes a deletion script for the selected entries to `path`.
+///
+/// Interactive confirmation is not used here since every line of the script is reviewed anyway.
+///
+/// The script flavour is chosen from the file extension. Shell scripts are marked executable
+/// on Unix. PowerShell scripts start with a byte order mark, without which Windows PowerShell
+/// reads them in the ANSI code page and garbles paths outside it.
+///
+/// # Returns
+///
+/// * `io::Result<usize>` - Number of files listed in the script
+pub fn write_delete_script(
+    path: &Path,
+    root: &Path,
+    selection: &Selection,
+    entries: &[(String, u64)],
+) -> io::Result<usize> {
+    crate::storage::check_write(path)?;
+    let kind = ScriptKind::for_path(path);
+    let selected = selection.select(entries);
+    let mut script = render(kind, root, selection, &selected);
+    if kind == ScriptKind::PowerShell {
+        script.insert(0, '\u{feff}');
+    }
+    fs::write(path, script)?;
+
+    #[cfg(unix)]
+    if kind == ScriptKind::Shell {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(selected.len())
+}
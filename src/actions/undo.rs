@@ -0,0 +1,150 @@
+use crate::actions::journal::{self, ActionKind, JournalEntry};
+use crate::actions::relocate::move_file;
+use crate::actions::verify::{from_hex, verify_digest};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Restores a single journaled operation, verifying the restored content against its digest.
+///
+/// An existing file at the original path is never overwritten.
+fn restore(entry: &JournalEntry) -> io::Result<()> {
+    let original = Path::new(&entry.original);
+    let destination = Path::new(&entry.destination);
+    crate::storage::check_write(original)?;
+    let digest = from_hex(&entry.sha256).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "journal entry has an invalid digest",
+        )
+    })?;
+    if original.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", original.display()),
+        ));
+    }
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match entry.action {
+        ActionKind::Move => {
+            verify_digest(destination, &digest)?;
+            move_file(destination, original)?;
+        }
+        ActionKind::Archive => {
+            let member = entry.member.as_deref().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "journal entry has no archive member",
+                )
+            })?;
+            let mut archive = tar::Archive::new(File::open(destination)?);
+            let mut found = false;
+            for item in archive.entries()? {
+                let mut item = item?;
+                if item.path()?.as_ref() == Path::new(member) {
+                    item.unpack(original)?;
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not in {}", member, destination.display()),
+                ));
+            }
+            if let Err(e) = verify_digest(original, &digest) {
+                let _ = fs::remove_file(original);
+                return Err(e);
+            }
+        }
+        ActionKind::Compress => {
+            let result = (|| {
+                let mut output = File::options()
+                    .write(true)
+                    .create_new(true)
+                    .open(original)?;
+                io::copy(
+                    &mut zstd::Decoder::new(File::open(destination)?)?,
+                    &mut output,
+                )?;
+                output.sync_all()?;
+                verify_digest(original, &digest)?;
+
+                let metadata = fs::metadata(destination)?;
+                fs::set_permissions(original, metadata.permissions())?;
+                output.set_modified(metadata.modified()?)
+            })();
+            if let Err(e) = result {
+                let _ = fs::remove_file(original);
+                return Err(e);
+            }
+            fs::remove_file(destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reverts the most recent batch of move, archive and compress operations.
+///
+/// Operations are restored in reverse order. Once every operation has been restored the
+/// journal is marked as undone; otherwise it is rewritten to hold only the operations that
+/// failed, so `undo` can be retried after the cause has been fixed. Archives are left in
+/// place since they may hold files from other batches.
+///
+/// # Errors
+///
+/// Returns an error if the journal directory or journal cannot be read or updated.
+/// Failures to restore individual files are reported but do not abort.
+pub fn undo_last() -> io::Result<()> {
+    let dir = journal::journal_dir()?;
+    let Some(path) = journal::latest_pending(&dir)? else {
+        println!("{}", tr!("nothing-to-undo"));
+        return Ok(());
+    };
+
+    let entries = journal::read_entries(&path)?;
+    let mut failed = Vec::new();
+    let mut restored = 0;
+    for entry in entries.iter().rev() {
+        match restore(entry) {
+            Ok(()) => restored += 1,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    tr!(
+                        "restore-failed",
+                        path = entry.original.as_str(),
+                        error = e.to_string()
+                    )
+                );
+                failed.push(entry);
+            }
+        }
+    }
+
+    println!(
+        "{}",
+        tr!(
+            "restored",
+            restored = restored,
+            total = entries.len(),
+            path = path.display().to_string()
+        )
+    );
+
+    if failed.is_empty() {
+        return journal::mark_undone(&path);
+    }
+
+    // Keep the failed operations, in their original order, for another attempt
+    let mut file = File::create(&path)?;
+    for entry in failed.iter().rev() {
+        let line = serde_json::to_string(entry).map_err(io::Error::other)?;
+        writeln!(file, "{}", line)?;
+    }
+    file.sync_data()
+}
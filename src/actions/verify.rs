@@ -0,0 +1,142 @@
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::{Condvar, Mutex, PoisonError};
+
+/// Default number of files that may be read in full for hashing at the same time.
+pub const DEFAULT_HASH_READS: usize = 4;
+
+/// Limits how many files are read in full for hashing at the same time.
+///
+/// Full-content reads saturate disk bandwidth rather than file handles, so they are throttled
+/// independently of the open file limit used while scanning.
+#[derive(Debug)]
+pub struct ReadLimiter {
+    state: Mutex<(usize, usize)>,
+    freed: Condvar,
+}
+
+/// Held while a full-content read is in progress; frees its slot when dropped.
+pub struct ReadPermit<'a> {
+    limiter: &'a ReadLimiter,
+}
+
+impl ReadLimiter {
+    /// Creates a limiter allowing `max` concurrent reads (at least one).
+    pub const fn new(max: usize) -> Self {
+        ReadLimiter {
+            state: Mutex::new((0, if max == 0 { 1 } else { max })),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Changes the number of concurrent reads allowed (at least one).
+    pub fn set_max(&self, max: usize) {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).1 = max.max(1);
+        self.freed.notify_all();
+    }
+
+    /// Blocks until a read slot is available.
+    pub fn acquire(&self) -> ReadPermit<'_> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        while state.0 >= state.1 {
+            state = self
+                .freed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        state.0 += 1;
+        ReadPermit { limiter: self }
+    }
+}
+
+impl Drop for ReadPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .0 -= 1;
+        self.limiter.freed.notify_one();
+    }
+}
+
+/// Limiter shared by every full-content read of the actions.
+pub static HASH_READS: ReadLimiter = ReadLimiter::new(DEFAULT_HASH_READS);
+
+/// Computes the SHA-256 digest of everything read from `reader`.
+pub fn sha256_reader(mut reader: impl Read) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+/// Computes the SHA-256 digest of the file at `path`, waiting for a slot of [`HASH_READS`].
+pub fn sha256_file(path: &Path) -> io::Result<[u8; 32]> {
+    let _permit = HASH_READS.acquire();
+    sha256_reader(File::open(path)?)
+}
+
+/// Formats a digest as lowercase hexadecimal.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::actions::verify::{sha256_reader, to_hex};
+///
+/// let digest = sha256_reader(&b"abc"[..]).unwrap();
+/// assert_eq!(
+///     to_hex(&digest),
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+/// );
+/// ```
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Verifies that the file at `path` has the given SHA-256 digest.
+///
+/// # Errors
+///
+/// Returns an `InvalidData` error if the digests differ, or any error from reading the file.
+pub fn verify_digest(path: &Path, expected: &[u8; 32]) -> io::Result<()> {
+    if &sha256_file(path)? != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("verification failed: content of {} differs", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a lowercase or uppercase hexadecimal SHA-256 digest.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::actions::verify::{from_hex, to_hex};
+///
+/// let digest = [0xab_u8; 32];
+/// assert_eq!(from_hex(&to_hex(&digest)), Some(digest));
+/// assert_eq!(from_hex("abc"), None);
+/// ```
+pub fn from_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(digest)
+}
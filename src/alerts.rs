@@ -0,0 +1,444 @@
+use crate::actions::guard::ScanStamps;
+use crate::host::hostname;
+use crate::porcelain;
+use crate::protect::rule_globs;
+use crate::storage;
+use crate::traits::ByteSize;
+use crate::units::{parse_duration, parse_size};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Name of the alert rules file inside the system and user configuration directories.
+const ALERTS_FILE: &str = "alerts";
+
+/// Exit status of a scan in which a rule with the `exit` action fired.
+pub const ALERT_EXIT_CODE: i32 = 3;
+
+/// Set once a rule with the `exit` action fired.
+static EXIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if a rule with the `exit` action fired during this run.
+pub fn exit_requested() -> bool {
+    EXIT_REQUESTED.load(Ordering::Relaxed)
+}
+
+/// What happens when an alert rule fires, besides listing the alert in the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertAction {
+    /// POST the alerts of the rule as JSON to this URL (with `curl`)
+    Webhook(String),
+    /// Mail the alerts of the rule to this address (with `sendmail`)
+    Email(String),
+    /// Exit with [`ALERT_EXIT_CODE`] once the scan is done
+    Exit,
+    /// Log every alert to the system log (Unix only)
+    Syslog,
+}
+
+/// One rule of an alerts file: which reported entries fire it and what to do then.
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    /// The rule as written, to tell the user which one fired
+    pub text: String,
+    pub min_size: Option<u64>,
+    pub min_growth: Option<u64>,
+    pub within: Option<Duration>,
+    pub actions: Vec<AlertAction>,
+    matcher: GlobSet,
+}
+
+impl AlertRule {
+    /// Parses a rule: a path glob followed by any of `size>=SIZE`, `growth>=SIZE`,
+    /// `within=DURATION`, `exit`, `syslog`, `webhook=URL` and `email=ADDRESS`.
+    ///
+    /// The path follows the protect list syntax (see [`rule_globs`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the glob, a size, a duration or a word of
+    /// the rule is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::alerts::{AlertAction, AlertRule};
+    /// use std::time::Duration;
+    ///
+    /// let rule = AlertRule::parse("/var/log size>=1GB within=24h syslog exit").unwrap();
+    /// assert_eq!(rule.min_size, Some(1_000_000_000));
+    /// assert_eq!(rule.within, Some(Duration::from_secs(24 * 3600)));
+    /// assert_eq!(rule.actions, [AlertAction::Syslog, AlertAction::Exit]);
+    /// assert!(rule.matches("/var/log/app/debug.log"));
+    /// assert!(!rule.matches("/var/lib/app.db"));
+    ///
+    /// assert!(AlertRule::parse("/var/log size>=lots").is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<AlertRule, String> {
+        let invalid = |detail: String| format!("invalid alert rule '{}': {}", text, detail);
+        let mut words = text.split_whitespace();
+        let path = words.next().ok_or_else(|| invalid("no path".to_string()))?;
+
+        let mut builder = GlobSetBuilder::new();
+        for glob in rule_globs(path) {
+            builder.add(Glob::new(&glob).map_err(|e| invalid(e.to_string()))?);
+        }
+        let mut rule = AlertRule {
+            text: text.to_string(),
+            min_size: None,
+            min_growth: None,
+            within: None,
+            actions: Vec::new(),
+            matcher: builder.build().map_err(|e| invalid(e.to_string()))?,
+        };
+
+        for word in words {
+            if let Some(size) = word.strip_prefix("size>=") {
+                rule.min_size = Some(parse_size(size).map_err(invalid)?);
+            } else if let Some(growth) = word.strip_prefix("growth>=") {
+                rule.min_growth = Some(parse_size(growth).map_err(invalid)?);
+            } else if let Some(within) = word.strip_prefix("within=") {
+                rule.within = Some(parse_duration(within).map_err(invalid)?);
+            } else if let Some(url) = word.strip_prefix("webhook=") {
+                rule.actions.push(AlertAction::Webhook(url.to_string()));
+            } else if let Some(address) = word.strip_prefix("email=") {
+                rule.actions.push(AlertAction::Email(address.to_string()));
+            } else if word == "exit" {
+                rule.actions.push(AlertAction::Exit);
+            } else if word == "syslog" {
+                rule.actions.push(AlertAction::Syslog);
+            } else {
+                return Err(invalid(format!("unknown condition or action '{}'", word)));
+            }
+        }
+        Ok(rule)
+    }
+
+    /// Whether `path` is covered by the rule's path glob.
+    pub fn matches(&self, path: &str) -> bool {
+        self.matcher.is_match(path)
+    }
+}
+
+/// A reported entry that fired a rule.
+///
+/// * `rule` - Index of the rule in [`AlertRules::rules`]
+/// * `growth` - Growth since the previous session of the root (the whole size for new entries),
+///   or `None` without a previous session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub rule: usize,
+    pub path: String,
+    pub size: u64,
+    pub growth: Option<u64>,
+}
+
+/// Alert rules evaluated against the reported entries at the end of every scan.
+///
+/// A rule fires for every reported entry matching its path whose size, growth since the
+/// previous session of the same root, and modification time before the end of the scan meet
+/// all of its conditions. Only the reported entries are checked, so `-n` bounds what can fire.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::actions::guard::ScanStamps;
+/// use ferris_files::alerts::AlertRules;
+/// use std::collections::HashMap;
+/// use std::time::SystemTime;
+///
+/// let rules = AlertRules::parse("
+///     /var/log  size>=1GB
+///     /srv/db   growth>=5GB  exit
+/// ").unwrap();
+///
+/// let entries = [
+///     ("/var/log/app.log".to_string(), 3_000_000_000),
+///     ("/srv/db/wal.tar".to_string(), 9_000_000_000),
+/// ];
+/// let previous = HashMap::from([("/srv/db/wal.tar".to_string(), 8_000_000_000)]);
+/// let alerts = rules.evaluate(&entries, Some(&previous), &ScanStamps::default(), SystemTime::now());
+/// assert_eq!(alerts.len(), 1);
+/// assert_eq!(alerts[0].path, "/var/log/app.log");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AlertRules {
+    pub rules: Vec<AlertRule>,
+}
+
+impl AlertRules {
+    /// Parses an alerts file: one rule per line, blank lines and `#` comments ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid rule.
+    pub fn parse(contents: &str) -> Result<AlertRules, String> {
+        let rules = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(AlertRule::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(AlertRules { rules })
+    }
+
+    /// Loads and combines the system wide and per-user alerts files.
+    ///
+    /// The rules are read from `alerts` files in [`storage::system_config_dir`] and
+    /// [`storage::config_dir`]; missing files are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an alerts file exists but cannot be read or contains an invalid
+    /// rule, since silently ignoring it would leave the storage unwatched.
+    pub fn load() -> io::Result<AlertRules> {
+        let mut contents = String::new();
+        for file in Self::files() {
+            match fs::read_to_string(&file) {
+                Ok(text) => {
+                    contents.push_str(&text);
+                    contents.push('\n');
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("could not read alert rules {}: {}", file.display(), e),
+                    ))
+                }
+            }
+        }
+        Self::parse(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Locations of the system wide and per-user alerts files.
+    pub fn files() -> Vec<PathBuf> {
+        [storage::system_config_dir(), storage::config_dir()]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(ALERTS_FILE))
+            .collect()
+    }
+
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns the alerts fired by the reported `entries`, in rule order.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The reported `(path, size)` pairs
+    /// * `previous` - Sizes in the previous session of the same root, needed by `growth>=`
+    /// * `stamps` - Scan-time fingerprints, whose modification times `within=` looks at
+    /// * `scan_end` - When the scan finished, the end of every `within=` window
+    pub fn evaluate(
+        &self,
+        entries: &[(String, u64)],
+        previous: Option<&HashMap<String, u64>>,
+        stamps: &ScanStamps,
+        scan_end: SystemTime,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            for (path, size) in entries {
+                if !rule.matches(path) || rule.min_size.is_some_and(|min| *size < min) {
+                    continue;
+                }
+                let growth = previous
+                    .map(|sizes| size.saturating_sub(sizes.get(path).copied().unwrap_or_default()));
+                if let Some(min) = rule.min_growth {
+                    if growth.is_none_or(|growth| growth < min) {
+                        continue;
+                    }
+                }
+                if let Some(window) = rule.within {
+                    let modified = stamps.get(path).and_then(|stamp| stamp.modified);
+                    let recent = modified.is_some_and(|modified| {
+                        scan_end
+                            .duration_since(modified)
+                            .map_or(true, |age| age <= window)
+                    });
+                    if !recent {
+                        continue;
+                    }
+                }
+                alerts.push(Alert {
+                    rule: index,
+                    path: path.clone(),
+                    size: *size,
+                    growth,
+                });
+            }
+        }
+        alerts
+    }
+
+    /// Lists the fired `alerts`, as `alert` records with `porcelain`.
+    pub fn print(alerts: &[Alert], porcelain: bool) {
+        if alerts.is_empty() {
+            return;
+        }
+        if !porcelain {
+            println!("\n{}", tr!("alerts-heading"));
+        }
+        for alert in alerts {
+            if porcelain {
+                println!(
+                    "{}",
+                    porcelain::record(
+                        "alert",
+                        &[
+                            &(alert.rule + 1).to_string(),
+                            &alert.size.to_string(),
+                            &alert.growth.map(|g| g.to_string()).unwrap_or_default(),
+                            &alert.path
+                        ]
+                    )
+                );
+            } else {
+                println!("{}", describe(alert));
+            }
+        }
+    }
+
+    /// Runs the actions of the rules that fired `alerts`, once per rule for webhooks and mails.
+    ///
+    /// A failing action is logged as a warning and does not stop the others.
+    pub fn dispatch(&self, alerts: &[Alert]) {
+        for (index, rule) in self.rules.iter().enumerate() {
+            let fired: Vec<&Alert> = alerts.iter().filter(|alert| alert.rule == index).collect();
+            if fired.is_empty() {
+                continue;
+            }
+            for action in &rule.actions {
+                let result = match action {
+                    AlertAction::Webhook(url) => post_webhook(url, rule, &fired),
+                    AlertAction::Email(address) => send_mail(address, rule, &fired),
+                    AlertAction::Syslog => fired.iter().try_for_each(|alert| {
+                        syslog(&format!("{} ({})", describe(alert), rule.text))
+                    }),
+                    AlertAction::Exit => {
+                        EXIT_REQUESTED.store(true, Ordering::Relaxed);
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    log::warn!(
+                        "{}",
+                        tr!(
+                            "alert-action-failed",
+                            rule = rule.text.as_str(),
+                            error = e.to_string()
+                        )
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// One line describing a fired alert.
+fn describe(alert: &Alert) -> String {
+    tr!(
+        "alert-fired",
+        rule = alert.rule + 1,
+        path = alert.path.as_str(),
+        size = alert.size.format_size()
+    )
+}
+
+/// POSTs the alerts of `rule` as a JSON document to `url`.
+fn post_webhook(url: &str, rule: &AlertRule, alerts: &[&Alert]) -> io::Result<()> {
+    let body = serde_json::json!({
+        "host": hostname(),
+        "rule": rule.text,
+        "alerts": alerts
+            .iter()
+            .map(|alert| serde_json::json!({
+                "path": alert.path,
+                "size": alert.size,
+                "growth": alert.growth,
+            }))
+            .collect::<Vec<_>>(),
+    });
+    pipe_to(
+        &[
+            "curl",
+            "-fsS",
+            "-m",
+            "10",
+            "-H",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+            url,
+        ],
+        &body.to_string(),
+    )
+}
+
+/// Mails the alerts of `rule` to `address`.
+fn send_mail(address: &str, rule: &AlertRule, alerts: &[&Alert]) -> io::Result<()> {
+    let host = hostname().unwrap_or_default();
+    let mut message = format!(
+        "To: {}\nSubject: {}\n\n{}\n\n",
+        address,
+        tr!("alert-subject", count = alerts.len(), host = host.as_str()),
+        rule.text
+    );
+    for alert in alerts {
+        message.push_str(&describe(alert));
+        message.push('\n');
+    }
+    pipe_to(&["sendmail", "-t"], &message)
+}
+
+/// Runs `command`, writing `input` to its stdin, and fails unless it exits successfully.
+fn pipe_to(command: &[&str], input: &str) -> io::Result<()> {
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", command[0], e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} exited with {}",
+            command[0], status
+        )))
+    }
+}
+
+/// Logs `message` as a warning to the system log.
+#[cfg(unix)]
+fn syslog(message: &str) -> io::Result<()> {
+    let message = std::ffi::CString::new(message)?;
+    // SAFETY: both format and message are NUL terminated, and the identifier is static
+    unsafe {
+        libc::openlog(c"ferris-files".as_ptr(), libc::LOG_PID, libc::LOG_USER);
+        libc::syslog(libc::LOG_WARNING, c"%s".as_ptr(), message.as_ptr());
+    }
+    Ok(())
+}
+
+/// Logs `message` as a warning to the system log.
+#[cfg(not(unix))]
+fn syslog(_message: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "syslog is only available on Unix",
+    ))
+}
@@ -1,7 +1,21 @@
-use clap::Parser;
+use crate::actions::compress::Codec;
+use crate::actions::verify::DEFAULT_HASH_READS;
+use crate::cost::parse_rate;
+use crate::output::OutputFormat;
+use crate::report::{Metric, ReportKind, ThenBy};
+use crate::units::{parse_duration, parse_size};
+use clap::parser::ValueSource;
+use clap::{ArgGroup, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::time::Duration;
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// (optional) Number of largest entries to output
     #[arg(short = 'n', long = "num_entries", default_value_t = 10)]
     pub num_entries: usize,
@@ -10,14 +24,480 @@ pub struct Args {
     #[arg(short = 'b', long = "batch_Size", default_value_t = 1000)]
     pub batch_size: usize,
 
-    /// (optional) defaults to attempting to detect current working directory
+    /// (optional) Number of directories listed at once regardless of thread count (at least 1), e.g. to spare a network filesystem server
+    #[arg(long = "max-concurrent-dirs", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_concurrent_dirs: Option<usize>,
+
+    /// (optional) Warn about directories nested deeper than this below the scan root (0 disables the guard)
+    #[arg(long = "depth-guard", default_value_t = 256)]
+    pub depth_guard: usize,
+
+    /// (optional) Warn about directories holding more entries than this (0 disables the guard)
+    #[arg(long = "max-dir-entries", default_value_t = 1_000_000)]
+    pub max_dir_entries: usize,
+
+    /// (optional) Only descend this many levels below the scan root, sizing files down to that depth (0 for no limit)
+    #[arg(long = "max-depth", default_value_t = 0)]
+    pub max_depth: usize,
+
+    /// (optional) Only size files at least this many levels below the scan root (1 for those directly in it)
+    #[arg(long = "min-depth", default_value_t = 0)]
+    pub min_depth: usize,
+
+    /// (optional) Skip directories beyond --depth-guard and the rest of directories beyond --max-dir-entries instead of only warning
+    #[arg(long = "skip-pathological")]
+    pub skip_pathological: bool,
+
+    /// (optional) defaults to attempting to detect current working directory; repeat to scan several directories into one ranking
     #[arg(short = 'd', long = "directory")]
-    pub target_dir: Option<String>,
+    pub target_dir: Vec<String>,
+
+    /// (optional) File weighting several -d directories, one `PATH WEIGHT` per line; heavier ones are scanned first
+    #[arg(long = "priorities", value_name = "FILE")]
+    pub priorities: Option<String>,
 
     /// (optional) Path to a file where each line specifies a directory to ignore
-    #[arg(short = 'x', long = "excluded-dirs-file")]
+    #[arg(long = "excluded-dirs-file")]
     pub exclusion_file: Option<String>,
 
+    /// (optional) Skip directories matching this glob, e.g. 'node_modules' or '/var/cache/**' (repeatable; see the README)
+    #[arg(short = 'x', long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// (optional) Skip files and directories ignored by the .gitignore and .git/info/exclude files found in the tree
+    #[arg(long = "respect-gitignore", overrides_with = "no_respect_gitignore")]
+    pub respect_gitignore: bool,
+
+    /// (optional) Count what .gitignore files ignore, also with --repo
+    #[arg(long = "no-respect-gitignore", overrides_with = "respect_gitignore")]
+    pub no_respect_gitignore: bool,
+
+    /// (optional) Also count what .ferrisignore files found in the tree ignore
+    #[arg(long = "no-ferrisignore")]
+    pub no_ferrisignore: bool,
+
+    /// (optional) Scan the whole git repository enclosing the directory, honoring its .gitignore files, and compare the size of .git with the working tree
+    #[arg(long = "repo")]
+    pub repo: bool,
+
+    /// (optional) Only size files matching this glob, e.g. '*.log' (repeatable; see the README)
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// (optional) Skip files not owned by the invoking user (Unix only)
+    #[arg(long = "own-files-only")]
+    pub own_files_only: bool,
+
+    /// (optional) Size files by their length instead of the space allocated to them on disk
+    #[arg(long = "apparent-size")]
+    pub apparent_size: bool,
+
+    /// (optional) Only rank files (or directories with --dirs) of at least this size (e.g. 100MB, 2GiB); smaller ones still count in the totals and reports
+    #[arg(long = "min-size", value_name = "SIZE", value_parser = parse_size, default_value = "0")]
+    pub min_size: u64,
+
+    /// (optional) Leave sparse files (less than half allocated) out of the results
+    #[arg(long = "skip-sparse")]
+    pub skip_sparse: bool,
+
+    /// (optional) Size every hard link of a file instead of only the first one found (Unix only)
+    #[arg(short = 'l', long = "count-links")]
+    pub count_links: bool,
+
+    /// (optional) Flag results that are world readable/writable or setuid/setgid (Unix only)
+    #[arg(long = "audit")]
+    pub audit: bool,
+
+    /// (optional) List large files that were deleted but are still held open by a process (Linux only)
+    #[arg(long = "deleted-open")]
+    pub deleted_open: bool,
+
+    /// (optional) Include named alternate data streams in file sizes and list the largest (Windows only)
+    #[arg(long = "ads")]
+    pub ads: bool,
+
+    /// (optional) Group results by owning user and the processes likely responsible (Unix only)
+    #[arg(long = "attribute")]
+    pub attribute: bool,
+
+    /// (optional) Show quota usage and headroom of the scanned directory's owner (Linux only)
+    #[arg(long = "quota")]
+    pub quota: bool,
+
+    /// (optional) Report to print, repeatable to get several from one scan (default: top-files)
+    #[arg(long = "report", value_enum)]
+    pub reports: Vec<ReportKind>,
+
+    /// (optional) Print the number, total, mean and median size of all files with a size histogram
+    #[arg(long = "stats")]
+    pub stats: bool,
+
+    /// (optional) Rank directories by the total size of everything beneath them instead of ranking files
+    #[arg(long = "dirs")]
+    pub dirs: bool,
+
+    /// (optional) Rank files by size, by last modification or access (the oldest first) or by path length
+    #[arg(long = "metric", value_enum, default_value_t = Metric::Size)]
+    pub metric: Metric,
+
+    /// (optional) With --metric mtime or atime, rank the most recently modified or accessed files first
+    #[arg(long = "newest")]
+    pub newest: bool,
+
+    /// (optional) Print the scanned hierarchy with the total size of every directory, largest first
+    #[arg(long = "tree")]
+    pub tree: bool,
+
+    /// (optional) Limit --tree to this many levels below the scanned directory
+    #[arg(long = "tree-depth", value_name = "LEVELS", requires = "tree")]
+    pub tree_depth: Option<usize>,
+
+    /// (optional) List results as one line per directory when more than K of them share it
+    #[arg(long = "collapse-siblings", value_name = "K", value_parser = clap::value_parser!(u64).range(1..))]
+    pub collapse_siblings: Option<u64>,
+
+    /// (optional) Order results of equal size by path, modification time (most recent first) or file name
+    #[arg(long = "then-by", value_enum)]
+    pub then_by: Option<ThenBy>,
+
+    /// (optional) Print the final results as text, as one JSON document for jq and other tools, or as wire frames for --import wire
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// (optional) CSV or JSON file mapping path prefixes to an owner, team and cost center, joined into the results and --report by-owner
+    #[arg(long = "ownership", value_name = "FILE")]
+    pub ownership: Option<String>,
+
+    /// (optional) Estimate the monthly storage cost of the results and reports at this rate per GB (tier rates come from the costs file)
+    #[arg(long = "cost-per-gb", value_name = "RATE", value_parser = parse_rate)]
+    pub cost_per_gb: Option<f64>,
+
+    #[command(flatten)]
+    pub actions: ActionArgs,
+
+    /// (optional) Show a quick sampled estimate of each top-level entry and ask before running the full scan
+    #[arg(long = "preview")]
+    pub preview: bool,
+
+    /// (optional) Print the effective settings and where each came from (options, defaults, rule files) as TOML, or as JSON with --format json, and exit without scanning
+    #[arg(long = "print-config")]
+    pub print_config: bool,
+
+    /// (optional) Also save the results to this session file (they are always saved as the last scan)
+    #[arg(long = "save-session")]
+    pub save_session: Option<String>,
+
+    /// (optional) Scan even if another scan of the same root is running, taking over its lock
+    #[arg(long = "force")]
+    pub force: bool,
+
+    /// (optional) Save the size of every scanned file to FILE (e.g. srv.ffs), to compare two scans with `diff OLD NEW`
+    #[arg(long = "save-snapshot", value_name = "FILE")]
+    pub save_snapshot: Option<String>,
+
+    /// (optional) Stream live progress and top file updates as JSON lines to FILE (may be a pipe)
+    #[arg(long = "progress-json", value_name = "FILE")]
+    pub progress_json: Option<String>,
+
+    /// (optional) Write the whole scanned tree to FILE in the ncdu JSON export format, to browse it with `ncdu -f FILE`
+    #[arg(long = "export-ncdu", value_name = "FILE")]
+    pub export_ncdu: Option<String>,
+
+    /// (optional) Write the whole scanned tree to FILE as nested JSON (name, value, children) for d3 and Plotly treemaps
+    #[arg(long = "export-treemap", value_name = "FILE")]
+    pub export_treemap: Option<String>,
+
+    /// (optional) Write every sized file to FILE as Parquet, for DuckDB, Spark and other analytics tools (needs the parquet feature)
+    #[arg(long = "export-parquet", value_name = "FILE")]
+    pub export_parquet: Option<String>,
+
+    /// (optional) Stop --export-parquet cleanly, keeping the rows written so far, once its destination has less than SIZE free
+    #[arg(long = "min-free", value_name = "SIZE", value_parser = parse_size, default_value = "256MiB")]
+    pub min_free: u64,
+
+    /// (optional) Analyze an inventory from `du -ab`, `find -printf '%s\t%p\n'`, `ncdu -o` or `ferris-files --format wire` instead of scanning (FILE may be - for stdin) [formats: du, find, ncdu, wire]
+    #[arg(long = "import", num_args = 2, value_names = ["FORMAT", "FILE"])]
+    pub import: Option<Vec<String>>,
+
+    /// (optional) Keep config, cache, sessions and undo journals in ferris-files-data next to the executable
+    #[arg(long = "portable", global = true)]
+    pub portable: bool,
+
+    /// (optional) Print results in a stable tab-separated format for scripts (see the README)
+    #[arg(long = "porcelain", global = true)]
+    pub porcelain: bool,
+
+    /// (optional) Language of the output (en, es); defaults to the system locale
+    #[arg(long = "lang", global = true)]
+    pub lang: Option<String>,
+
+    /// (optional) Show timestamps as ISO-8601 in UTC instead of in the local timezone
+    #[arg(long = "utc", global = true)]
+    pub utc: bool,
+
+    /// (optional) Refuse every option and command that writes files, and fail any write attempted anyway
+    #[arg(long = "assert-read-only", global = true)]
+    pub assert_read_only: bool,
+
     #[arg(short, long)]
     pub verbose: bool,
+
+    /// Options given on the command line rather than left to their defaults, as recorded by
+    /// [`Args::try_parse_with_sources`]
+    #[arg(skip)]
+    pub given: HashSet<String>,
+}
+
+impl Args {
+    /// Parses `argv` like [`Parser::try_parse_from`], also recording in `given` the options
+    /// that were given, so `--print-config` can tell them apart from defaults.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::args::Args;
+    ///
+    /// let args = Args::try_parse_with_sources(["ferris-files", "-n", "10", "--tree"]).unwrap();
+    /// assert!(args.given.contains("num_entries"));
+    /// assert!(args.given.contains("tree"));
+    /// assert!(!args.given.contains("batch_size"));
+    /// ```
+    pub fn try_parse_with_sources<I, T>(argv: I) -> Result<Args, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = Args::command().try_get_matches_from(argv)?;
+        let mut args =
+            Args::from_arg_matches(&matches).map_err(|e| e.format(&mut Args::command()))?;
+        args.given = Args::command()
+            .get_arguments()
+            .map(|arg| arg.get_id().as_str())
+            .filter(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+            .map(str::to_string)
+            .collect();
+        Ok(args)
+    }
+}
+
+/// Actions applied to reported entries, shared by the scan and the `clean` subcommand.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct ActionArgs {
+    /// (optional) Write a reviewable deletion script for the results instead of deleting (.ps1 for PowerShell)
+    #[arg(long = "emit-delete-script")]
+    pub delete_script: Option<String>,
+
+    /// (optional) Move the results beneath this directory, keeping their structure relative to the scan root
+    #[arg(long = "move-to", conflicts_with = "archive_to")]
+    pub move_to: Option<String>,
+
+    /// (optional) Archive the results into this (new) tarball and remove the verified originals
+    #[arg(long = "archive-to")]
+    pub archive_to: Option<String>,
+
+    /// (optional) Compress the results in place, removing originals once the compressed copy is verified
+    #[arg(long = "compress", value_enum, conflicts_with_all = ["move_to", "archive_to"])]
+    pub compress: Option<Codec>,
+
+    /// (optional) Only apply actions such as --emit-delete-script to results of at least this size (e.g. 500MB, 2GiB)
+    #[arg(long = "action-min-size", value_parser = parse_size)]
+    pub action_min_size: Option<u64>,
+
+    /// (optional) Only apply actions to results not modified for this long (e.g. 90d, 12w, 1y)
+    #[arg(long = "action-older-than", value_parser = parse_duration)]
+    pub action_older_than: Option<Duration>,
+
+    /// (optional) Ask for confirmation of each result before moving, archiving or compressing it
+    #[arg(short = 'i', long = "interactive")]
+    pub interactive: bool,
+
+    /// (optional) Number of files read in full at once to hash or verify them, independent of the open file limit
+    #[arg(long = "hash-concurrency", default_value_t = DEFAULT_HASH_READS)]
+    pub hash_concurrency: usize,
+}
+
+/// Session file holding the results a follow-up command operates on.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct SessionArgs {
+    /// (optional) Session file to operate on; defaults to the results of the last scan
+    #[arg(long = "session")]
+    pub session: Option<String>,
+}
+
+/// Short IDs of entries reported by a scan.
+#[derive(clap::Args, Debug, Clone)]
+pub struct IdArgs {
+    /// Comma separated IDs (or unique ID prefixes) shown next to each reported entry
+    #[arg(long = "ids", value_delimiter = ',', required = true)]
+    pub ids: Vec<String>,
+
+    #[command(flatten)]
+    pub session: SessionArgs,
+}
+
+/// Subcommands run instead of a scan.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Revert the most recent batch of --move-to, --archive-to or --compress operations
+    Undo,
+    /// Apply an action to entries of the last scan, addressed by their IDs
+    Clean {
+        #[command(flatten)]
+        ids: IdArgs,
+        #[command(flatten)]
+        actions: ActionArgs,
+    },
+    /// Show entries of the last scan in the platform file manager
+    Reveal {
+        #[command(flatten)]
+        ids: IdArgs,
+    },
+    /// Copy the full paths of entries of the last scan to the clipboard
+    Copy {
+        #[command(flatten)]
+        ids: IdArgs,
+    },
+    /// Compare an earlier session with the last scan (or another session), or two snapshots
+    Diff {
+        /// Session or snapshot file of the earlier scan
+        old: String,
+        /// (optional) Session or snapshot file of the later scan; required for snapshots
+        #[arg(conflicts_with = "session")]
+        new: Option<String>,
+        /// (optional) Number of largest changed directories and files to show for snapshots
+        #[arg(short = 'n', long = "num_entries", default_value_t = 10)]
+        num_entries: usize,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Combine sessions saved on several hosts into one ranked report with a host column
+    Merge {
+        /// Session files to combine (e.g. saved with --save-session on each host)
+        #[arg(required = true)]
+        sessions: Vec<String>,
+        /// (optional) Number of largest entries to keep
+        #[arg(short = 'n', long = "num_entries", default_value_t = 10)]
+        num_entries: usize,
+        /// (optional) Save the merged report as a session file
+        #[arg(short = 'o', long = "output")]
+        output: Option<String>,
+    },
+    /// Scan many hosts over ssh at once and rank their largest files together
+    Fleet {
+        /// File listing the hosts, one `[user@]host[:/directory]` per line (`#` starts a comment)
+        hosts: String,
+        /// (optional) Directory to scan on hosts that do not name one
+        #[arg(short = 'd', long = "directory", default_value = "/")]
+        directory: String,
+        /// (optional) Number of largest entries to report, per host and across the fleet
+        #[arg(short = 'n', long = "num_entries", default_value_t = 10)]
+        num_entries: usize,
+        /// (optional) Number of hosts scanned at the same time
+        #[arg(short = 'j', long = "jobs", default_value_t = 8)]
+        jobs: usize,
+        /// (optional) Give up on a host whose scan takes longer than this (e.g. 30m)
+        #[arg(long = "timeout", value_parser = parse_duration)]
+        timeout: Option<Duration>,
+        /// (optional) Command that runs ferris-files on the hosts
+        #[arg(long = "remote-command", default_value = "ferris-files")]
+        remote_command: String,
+        /// (optional) Save the combined report as a session file
+        #[arg(short = 'o', long = "output")]
+        output: Option<String>,
+    },
+    /// List entries of the last scan, optionally filtered
+    Query {
+        /// (optional) Only list entries of at least this size (e.g. 500MB, 2GiB)
+        #[arg(long = "min-size", value_parser = parse_size)]
+        min_size: Option<u64>,
+        /// (optional) Only list entries whose path matches this glob (e.g. '**/*.iso')
+        #[arg(long = "glob")]
+        glob: Option<String>,
+        /// (optional) Only list entries modified within this long before the scan ended (e.g. 24h, 7d)
+        #[arg(long = "modified-within", value_parser = parse_duration)]
+        modified_within: Option<Duration>,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Show the next largest entries of the last scan without rescanning
+    More {
+        /// (optional) Number of further entries to show
+        #[arg(short = 'n', long = "num_entries", default_value_t = 10)]
+        num_entries: usize,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Show how the space of a directory divides into media, caches, logs, packages, VMs and other files
+    Summary {
+        /// (optional) Directory to summarize; defaults to -d or the current working directory
+        directory: Option<String>,
+    },
+    /// Estimate how much space a retention policy would reclaim, per directory, without deleting anything
+    #[command(group(ArgGroup::new("policy").required(true).multiple(true)))]
+    Simulate {
+        /// (optional) Directory to simulate the policy on; defaults to -d or the current working directory
+        directory: Option<String>,
+        /// Count files not modified for this long (e.g. 180d, 1y)
+        #[arg(long = "delete-older-than", value_name = "AGE", value_parser = parse_duration, group = "policy")]
+        delete_older_than: Option<Duration>,
+        /// Only count files matching this glob, e.g. '*.bak' (may be repeated; a file matching any counts)
+        #[arg(long = "match", value_name = "GLOB", group = "policy")]
+        matches: Vec<String>,
+    },
+    /// Walk down from a directory to its largest child, one prompt per level, until a file is reached
+    Drill {
+        /// (optional) Directory to start from; defaults to -d or the current working directory
+        directory: Option<String>,
+        /// (optional) Stop after descending this many levels
+        #[arg(long = "levels", value_name = "N")]
+        levels: Option<usize>,
+    },
+    /// Run scans on request over HTTP, pushing their live progress and largest files to WebSocket clients
+    Serve {
+        /// (optional) Address to listen on; anyone who can reach it can start scans and read their results
+        #[arg(long = "listen", value_name = "ADDRESS", default_value = "127.0.0.1:7878")]
+        listen: SocketAddr,
+        /// (optional) Token clients must send as `Authorization: Bearer TOKEN`, also read from FERRIS_FILES_TOKEN; required beyond localhost
+        #[arg(long = "token")]
+        token: Option<String>,
+        /// (optional) Let clients apply actions to results (POST /api/clean) and scans run with actions, which requires a token; the server is read-only otherwise
+        #[arg(long = "allow-actions")]
+        allow_actions: bool,
+    },
+    /// Add "Find largest files here" to the Explorer context menu of folders (Windows only)
+    InstallShellIntegration,
+    /// Remove the Explorer context menu entry added by install-shell-integration (Windows only)
+    UninstallShellIntegration,
+    /// Create a "Find Largest Files" Finder quick action for selected folders (macOS only)
+    InstallQuickAction,
+    /// Remove the Finder quick action added by install-quick-action (macOS only)
+    UninstallQuickAction,
+}
+
+impl Command {
+    /// Whether the command changes files or the system, or is asked to save its results, which
+    /// `--assert-read-only` refuses outright.
+    pub fn writes(&self) -> bool {
+        matches!(
+            self,
+            Command::Undo
+                | Command::Clean { .. }
+                | Command::Merge {
+                    output: Some(_),
+                    ..
+                }
+                | Command::Fleet {
+                    output: Some(_),
+                    ..
+                }
+                | Command::Serve {
+                    allow_actions: true,
+                    ..
+                }
+                | Command::InstallShellIntegration
+                | Command::UninstallShellIntegration
+                | Command::InstallQuickAction
+                | Command::UninstallQuickAction
+        )
+    }
 }
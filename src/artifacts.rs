@@ -0,0 +1,237 @@
+use crate::actions::script::ScriptKind;
+use crate::report::{Aggregator, FileInfo, ReportRow, ReportSection};
+use crate::traits::ByteSize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Kinds of projects whose build output the `artifacts` report recognizes.
+///
+/// * `Cargo` - Rust crates and workspaces: `target` next to a `Cargo.toml`
+/// * `Node` - npm, yarn and pnpm packages: `node_modules` next to a `package.json`
+/// * `Gradle` - Gradle builds: `build` next to a `build.gradle(.kts)` or `settings.gradle(.kts)`
+/// * `CMake` - CMake builds: `build` next to a `CMakeLists.txt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProjectKind {
+    Cargo,
+    Node,
+    Gradle,
+    CMake,
+}
+
+impl ProjectKind {
+    /// Every kind, in the order they are tried; Gradle comes before CMake as both build into
+    /// `build`.
+    pub const ALL: [ProjectKind; 4] = [
+        ProjectKind::Cargo,
+        ProjectKind::Node,
+        ProjectKind::Gradle,
+        ProjectKind::CMake,
+    ];
+
+    /// Name of the kind in reports and porcelain records.
+    pub fn name(self) -> &'static str {
+        match self {
+            ProjectKind::Cargo => "cargo",
+            ProjectKind::Node => "node",
+            ProjectKind::Gradle => "gradle",
+            ProjectKind::CMake => "cmake",
+        }
+    }
+
+    /// Name of the directory projects of this kind build into, next to their marker file.
+    pub fn artifact_dir(self) -> &'static str {
+        match self {
+            ProjectKind::Cargo => "target",
+            ProjectKind::Node => "node_modules",
+            ProjectKind::Gradle | ProjectKind::CMake => "build",
+        }
+    }
+
+    /// Files marking the root of a project of this kind, any one of which suffices.
+    pub fn markers(self) -> &'static [&'static str] {
+        match self {
+            ProjectKind::Cargo => &["Cargo.toml"],
+            ProjectKind::Node => &["package.json"],
+            ProjectKind::Gradle => &[
+                "build.gradle",
+                "build.gradle.kts",
+                "settings.gradle",
+                "settings.gradle.kts",
+            ],
+            ProjectKind::CMake => &["CMakeLists.txt"],
+        }
+    }
+
+    /// Detects the kind of project `dir` is the build output of, from its name and the marker
+    /// files next to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::artifacts::ProjectKind;
+    ///
+    /// let project = std::env::temp_dir().join(format!("ferris-files-doc-project-{}", std::process::id()));
+    /// std::fs::create_dir_all(project.join("target")).unwrap();
+    /// std::fs::write(project.join("Cargo.toml"), "[package]\n").unwrap();
+    /// assert_eq!(ProjectKind::detect(&project.join("target")), Some(ProjectKind::Cargo));
+    /// assert_eq!(ProjectKind::detect(&project.join("build")), None);
+    /// std::fs::remove_dir_all(&project).unwrap();
+    /// ```
+    pub fn detect(dir: &Path) -> Option<ProjectKind> {
+        let name = dir.file_name()?.to_str()?;
+        let project = dir.parent()?;
+        ProjectKind::ALL.into_iter().find(|kind| {
+            kind.artifact_dir() == name
+                && kind
+                    .markers()
+                    .iter()
+                    .any(|marker| project.join(marker).is_file())
+        })
+    }
+
+    /// Returns a one-line command removing `dir`, the build output of a project of this kind,
+    /// through the project's own tooling where it has a clean step.
+    pub fn clean_command(self, dir: &Path) -> String {
+        let shell = if cfg!(windows) {
+            ScriptKind::PowerShell
+        } else {
+            ScriptKind::Shell
+        };
+        let quote = |path: &Path| shell.quote(&path.to_string_lossy());
+        let project = dir.parent().unwrap_or(dir);
+        match (self, shell) {
+            (ProjectKind::Cargo, _) => format!(
+                "cargo clean --manifest-path {}",
+                quote(&project.join("Cargo.toml"))
+            ),
+            (ProjectKind::Node, ScriptKind::Shell) => format!("rm -rf {}", quote(dir)),
+            (ProjectKind::Node, ScriptKind::PowerShell) => {
+                format!("Remove-Item -Recurse -Force {}", quote(dir))
+            }
+            (ProjectKind::Gradle, _) => format!("gradle -p {} clean", quote(project)),
+            (ProjectKind::CMake, _) => format!("cmake --build {} --target clean", quote(dir)),
+        }
+    }
+}
+
+/// Build output directories of the projects found in a scan, for the `artifacts` report, each
+/// with the command cleaning it.
+///
+/// A file counts towards the outermost of its ancestors that is the build output of a project
+/// (see [`ProjectKind::detect`]), so the packages nested in a `node_modules` count towards it.
+/// Marker files are looked up on disk once per candidate directory, the first time a file
+/// beneath it is observed.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::artifacts::{BuildArtifacts, ProjectKind};
+/// use ferris_files::report::{Aggregator, FileInfo};
+///
+/// let app = std::env::temp_dir().join(format!("ferris-files-doc-artifacts-{}", std::process::id()));
+/// std::fs::create_dir_all(&app).unwrap();
+/// std::fs::write(app.join("package.json"), "{}").unwrap();
+///
+/// let mut artifacts = BuildArtifacts::new(10);
+/// for (path, size) in [("node_modules/left-pad/index.js", 300), ("node_modules/.bin/tsc", 200), ("src/index.js", 50)] {
+///     artifacts.observe(&FileInfo { path: app.join(path).to_string_lossy().to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(artifacts.top(), [(app.join("node_modules"), ProjectKind::Node, 500, 2)]);
+/// std::fs::remove_dir_all(&app).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct BuildArtifacts {
+    limit: usize,
+    // Every candidate directory looked up so far, with the kind of project it belongs to
+    detected: HashMap<PathBuf, Option<ProjectKind>>,
+    totals: HashMap<PathBuf, (ProjectKind, u64, usize)>,
+}
+
+impl BuildArtifacts {
+    /// Creates empty totals, reporting the `limit` largest build output directories.
+    pub fn new(limit: usize) -> Self {
+        BuildArtifacts {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the build output directories holding the most bytes as `(directory, kind of
+    /// project, bytes, files)`, largest first.
+    pub fn top(&self) -> Vec<(PathBuf, ProjectKind, u64, usize)> {
+        let mut top: Vec<(PathBuf, ProjectKind, u64, usize)> = self
+            .totals
+            .iter()
+            .map(|(dir, (kind, bytes, files))| (dir.clone(), *kind, *bytes, *files))
+            .collect();
+        top.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(self.limit);
+        top
+    }
+}
+
+impl Aggregator for BuildArtifacts {
+    fn observe(&mut self, file: &FileInfo) {
+        let mut outermost = None;
+        for dir in Path::new(&file.path).ancestors().skip(1) {
+            let is_candidate = dir.file_name().is_some_and(|name| {
+                ProjectKind::ALL
+                    .iter()
+                    .any(|kind| name == kind.artifact_dir())
+            });
+            if !is_candidate {
+                continue;
+            }
+            let kind = match self.detected.get(dir) {
+                Some(kind) => *kind,
+                None => *self
+                    .detected
+                    .entry(dir.to_path_buf())
+                    .or_insert_with(|| ProjectKind::detect(dir)),
+            };
+            if let Some(kind) = kind {
+                outermost = Some((dir, kind));
+            }
+        }
+        if let Some((dir, kind)) = outermost {
+            let (_, bytes, files) = match self.totals.get_mut(dir) {
+                Some(totals) => totals,
+                None => self.totals.entry(dir.to_path_buf()).or_insert((kind, 0, 0)),
+            };
+            *bytes += file.size;
+            *files += 1;
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        let rows = self
+            .top()
+            .into_iter()
+            .map(|(dir, kind, bytes, files)| {
+                let command = kind.clean_command(&dir);
+                let dir = dir.to_string_lossy().to_string();
+                ReportRow {
+                    text: format!(
+                        "{:>10}  {} ({})  {}",
+                        bytes.format_size(),
+                        dir,
+                        kind.name(),
+                        tr!("artifact-clean", command = command.as_str())
+                    ),
+                    kind: "artifact".to_string(),
+                    fields: vec![
+                        bytes.to_string(),
+                        files.to_string(),
+                        kind.name().to_string(),
+                        command,
+                        dir,
+                    ],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-artifacts")),
+            rows,
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::procfs::ProcessRef;
+use std::io;
+
+/// How a file was linked to the processes listed in its attribution group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributionSource {
+    /// The processes currently hold the file open.
+    HeldOpen,
+    /// The processes run as the file's owner with the file's directory as working directory.
+    WorkingDirectory,
+    /// No process could be linked, only the owner is known.
+    OwnerOnly,
+}
+
+/// A set of top entries attributed to the same owner and processes.
+#[derive(Debug)]
+pub struct AttributionGroup {
+    pub owner: String,
+    pub processes: Vec<ProcessRef>,
+    pub source: AttributionSource,
+    pub total: u64,
+    pub files: Vec<(String, u64)>,
+}
+
+impl AttributionGroup {
+    /// Returns a human readable label such as `alice - python3[123] (holds open)`.
+    pub fn label(&self) -> String {
+        if self.processes.is_empty() {
+            return self.owner.clone();
+        }
+
+        let processes: Vec<String> = self
+            .processes
+            .iter()
+            .map(|process| format!("{}[{}]", process.name, process.pid))
+            .collect();
+        let how = match self.source {
+            AttributionSource::HeldOpen => tr!("attribution-held-open"),
+            AttributionSource::WorkingDirectory => tr!("attribution-working-dir"),
+            AttributionSource::OwnerOnly => tr!("attribution-owner"),
+        };
+        format!("{} - {} ({})", self.owner, processes.join(", "), how)
+    }
+}
+
+/// Attributes the given top entries to the users and processes most likely responsible.
+///
+/// Every file is attributed to its owner. On Linux, `/proc` is consulted as well: processes
+/// holding the file open are preferred, otherwise processes running as the owner whose
+/// working directory is the file's directory are used as a heuristic. This is aimed at
+/// scratch space such as `/tmp` where the owner alone is often a shared service account.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<AttributionGroup>>` - Groups sorted by total size in descending order,
+///   or an `Unsupported` error on platforms without Unix file ownership
+pub fn attribute_entries(entries: &[(String, u64)]) -> io::Result<Vec<AttributionGroup>> {
+    #[cfg(unix)]
+    {
+        use crate::procfs;
+        use crate::users::UserNames;
+        use std::collections::HashMap;
+        use std::os::unix::fs::MetadataExt;
+        use std::path::Path;
+
+        // /proc is only available on Linux; elsewhere attribution falls back to owners
+        let holders = procfs::open_file_holders().unwrap_or_default();
+        let cwds = procfs::process_cwds().unwrap_or_default();
+        let names = UserNames::default();
+
+        let mut groups: HashMap<(String, Vec<u32>, AttributionSource), AttributionGroup> =
+            HashMap::new();
+
+        for (path, size) in entries {
+            let Ok(metadata) = std::fs::symlink_metadata(path) else {
+                continue;
+            };
+            let owner = names.name(metadata.uid());
+
+            let (processes, source) = match holders.get(&(metadata.dev(), metadata.ino())) {
+                Some(processes) => (processes.clone(), AttributionSource::HeldOpen),
+                None => {
+                    let parent = Path::new(path).parent();
+                    let processes: Vec<ProcessRef> = cwds
+                        .iter()
+                        .filter(|p| p.uid == Some(metadata.uid()))
+                        .filter(|p| Some(p.cwd.as_path()) == parent)
+                        .map(|p| p.process.clone())
+                        .collect();
+                    if processes.is_empty() {
+                        (processes, AttributionSource::OwnerOnly)
+                    } else {
+                        (processes, AttributionSource::WorkingDirectory)
+                    }
+                }
+            };
+
+            let pids = processes.iter().map(|process| process.pid).collect();
+            let group = groups
+                .entry((owner.clone(), pids, source))
+                .or_insert_with(|| AttributionGroup {
+                    owner,
+                    processes,
+                    source,
+                    total: 0,
+                    files: Vec::new(),
+                });
+            group.total += size;
+            group.files.push((path.clone(), *size));
+        }
+
+        let mut groups: Vec<AttributionGroup> = groups.into_values().collect();
+        groups.sort_by_key(|group| std::cmp::Reverse(group.total));
+        Ok(groups)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = entries;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "attribution is only supported on Unix platforms",
+        ))
+    }
+}
@@ -0,0 +1,120 @@
+use std::io;
+
+/// Permission bits of interest to a security hygiene sweep.
+///
+/// Each flag corresponds to a property of a file's mode that is commonly
+/// worth reviewing when a file is also among the largest files on a system.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::audit::PermissionFlags;
+///
+/// let flags = PermissionFlags::from_mode(0o4755);
+/// assert!(flags.setuid);
+/// assert!(flags.world_readable);
+/// assert!(!flags.world_writable);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PermissionFlags {
+    pub world_readable: bool,
+    pub world_writable: bool,
+    pub setuid: bool,
+    pub setgid: bool,
+}
+
+impl PermissionFlags {
+    /// Extracts the audited flags from a Unix mode value.
+    pub fn from_mode(mode: u32) -> Self {
+        Self {
+            world_readable: mode & 0o004 != 0,
+            world_writable: mode & 0o002 != 0,
+            setuid: mode & 0o4000 != 0,
+            setgid: mode & 0o2000 != 0,
+        }
+    }
+
+    /// Returns true if any audited flag is set.
+    pub fn any(&self) -> bool {
+        self.world_readable || self.world_writable || self.setuid || self.setgid
+    }
+
+    /// Returns short labels for every flag that is set, in order of severity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::audit::PermissionFlags;
+    ///
+    /// let flags = PermissionFlags::from_mode(0o666);
+    /// assert_eq!(flags.labels(), vec!["world-writable", "world-readable"]);
+    /// ```
+    pub fn labels(&self) -> Vec<&'static str> {
+        let mut labels = Vec::new();
+        if self.setuid {
+            labels.push("setuid");
+        }
+        if self.setgid {
+            labels.push("setgid");
+        }
+        if self.world_writable {
+            labels.push("world-writable");
+        }
+        if self.world_readable {
+            labels.push("world-readable");
+        }
+        labels
+    }
+}
+
+/// A single top entry whose permissions were flagged by the audit.
+#[derive(Debug)]
+pub struct AuditFinding {
+    pub path: String,
+    pub size: u64,
+    pub flags: PermissionFlags,
+}
+
+/// Audits the permissions of the given top entries.
+///
+/// Only the final top entries are inspected, so the additional cost is a single
+/// `lstat` per reported file. Entries that cannot be inspected (for example because
+/// they were removed after the scan) are silently omitted.
+///
+/// # Arguments
+///
+/// * `entries` - Slice of `(path, size)` pairs as produced by `TopEntries`
+///
+/// # Returns
+///
+/// * `io::Result<Vec<AuditFinding>>` - The flagged entries in their original order, or an
+///   `Unsupported` error on platforms without Unix permission bits
+pub fn audit_entries(entries: &[(String, u64)]) -> io::Result<Vec<AuditFinding>> {
+    #[cfg(unix)]
+    {
+        use std::fs;
+        use std::os::unix::fs::MetadataExt;
+
+        Ok(entries
+            .iter()
+            .filter_map(|(path, size)| {
+                let metadata = fs::symlink_metadata(path).ok()?;
+                let flags = PermissionFlags::from_mode(metadata.mode());
+                flags.any().then(|| AuditFinding {
+                    path: path.clone(),
+                    size: *size,
+                    flags,
+                })
+            })
+            .collect())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = entries;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "permission audit is only supported on Unix platforms",
+        ))
+    }
+}
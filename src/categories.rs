@@ -0,0 +1,218 @@
+use std::path::{Component, Path};
+
+/// Broad kinds of space usage, for a one-screen overview of where the space went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    UserMedia,
+    Caches,
+    Logs,
+    Packages,
+    VirtualMachines,
+    Other,
+}
+
+impl Category {
+    /// All categories in report order.
+    pub const ALL: [Category; 6] = [
+        Category::UserMedia,
+        Category::Caches,
+        Category::Logs,
+        Category::Packages,
+        Category::VirtualMachines,
+        Category::Other,
+    ];
+
+    /// Stable identifier of the category, used in porcelain output and message ids.
+    pub fn key(self) -> &'static str {
+        match self {
+            Category::UserMedia => "user-media",
+            Category::Caches => "caches",
+            Category::Logs => "logs",
+            Category::Packages => "packages",
+            Category::VirtualMachines => "vms",
+            Category::Other => "other",
+        }
+    }
+
+    /// Returns the translated name of the category.
+    pub fn label(self) -> String {
+        tr!("category-label", category = self.key())
+    }
+}
+
+/// Extensions of disk images and container layers.
+const VM_EXTENSIONS: [&str; 10] = [
+    "vmdk", "vdi", "qcow2", "qcow", "vhd", "vhdx", "ova", "hdd", "hds", "utm",
+];
+
+/// Directories holding virtual machines or container storage.
+const VM_DIRS: [&str; 11] = [
+    "docker",
+    "containerd",
+    "com.docker.docker",
+    "podman",
+    "virtual machines",
+    "virtual machines.localized",
+    "virtualbox vms",
+    ".vagrant.d",
+    ".lima",
+    "libvirt",
+    "parallels",
+];
+
+/// Directories holding caches and temporary files.
+const CACHE_DIRS: [&str; 9] = [
+    ".cache",
+    "cache",
+    "caches",
+    "temp",
+    "tmp",
+    ".npm",
+    ".gradle",
+    "deriveddata",
+    "inetcache",
+];
+
+/// Directories holding logs.
+const LOG_DIRS: [&str; 3] = ["log", "logs", "journal"];
+
+/// Extensions of log files.
+const LOG_EXTENSIONS: [&str; 2] = ["log", "trace"];
+
+/// Directories holding installed software, toolchains and package managers' stores.
+const PACKAGE_DIRS: [&str; 19] = [
+    "usr",
+    "opt",
+    "nix",
+    "snap",
+    "flatpak",
+    "applications",
+    "system",
+    "program files",
+    "program files (x86)",
+    "programdata",
+    "windows",
+    "node_modules",
+    "site-packages",
+    ".cargo",
+    ".rustup",
+    ".m2",
+    ".gem",
+    "sdk",
+    "homebrew",
+];
+
+/// Extensions of installers and package archives.
+const PACKAGE_EXTENSIONS: [&str; 9] = [
+    "deb", "rpm", "pkg", "dmg", "msi", "msix", "apk", "whl", "jar",
+];
+
+/// Directories holding a user's own media.
+const MEDIA_DIRS: [&str; 7] = [
+    "pictures",
+    "photos",
+    "movies",
+    "music",
+    "videos",
+    "dcim",
+    "recordings",
+];
+
+/// Extensions of photos, videos and audio.
+const MEDIA_EXTENSIONS: [&str; 24] = [
+    "jpg", "jpeg", "png", "gif", "heic", "heif", "tif", "tiff", "cr2", "nef", "arw", "dng", "mp4",
+    "m4v", "mov", "mkv", "avi", "wmv", "webm", "mp3", "m4a", "flac", "wav", "aac",
+];
+
+/// Classifies a file by its path alone, without touching the filesystem.
+///
+/// The first matching category wins, checked in the order virtual machines and containers,
+/// caches, logs, packages and system files, user media. So a video inside a browser cache
+/// counts as cache, which is what matters when deciding what can be freed.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::categories::{classify, Category};
+/// use std::path::Path;
+///
+/// assert_eq!(classify(Path::new("/home/ann/Videos/trip.mp4")), Category::UserMedia);
+/// assert_eq!(classify(Path::new("/home/ann/.cache/yarn/v6/x.zip")), Category::Caches);
+/// assert_eq!(classify(Path::new("/var/log/syslog.1")), Category::Logs);
+/// assert_eq!(classify(Path::new("/usr/lib/libLLVM.so")), Category::Packages);
+/// assert_eq!(classify(Path::new("/var/lib/docker/overlay2/a/b")), Category::VirtualMachines);
+/// assert_eq!(classify(Path::new("/home/ann/report.pdf")), Category::Other);
+/// ```
+pub fn classify(path: &Path) -> Category {
+    let dirs: Vec<String> = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let in_any = |names: &[&str]| dirs.iter().any(|dir| names.contains(&dir.as_str()));
+
+    if VM_EXTENSIONS.contains(&extension.as_str()) || in_any(&VM_DIRS) {
+        Category::VirtualMachines
+    } else if in_any(&CACHE_DIRS) {
+        Category::Caches
+    } else if LOG_EXTENSIONS.contains(&extension.as_str()) || in_any(&LOG_DIRS) {
+        Category::Logs
+    } else if PACKAGE_EXTENSIONS.contains(&extension.as_str()) || in_any(&PACKAGE_DIRS) {
+        Category::Packages
+    } else if MEDIA_EXTENSIONS.contains(&extension.as_str()) || in_any(&MEDIA_DIRS) {
+        Category::UserMedia
+    } else {
+        Category::Other
+    }
+}
+
+/// Bytes and number of files per category.
+#[derive(Debug, Default, Clone)]
+pub struct CategoryTotals {
+    bytes: [u64; Category::ALL.len()],
+    files: [usize; Category::ALL.len()],
+    costs: [f64; Category::ALL.len()],
+}
+
+impl CategoryTotals {
+    /// Adds a file of `size` bytes to `category`.
+    pub fn add(&mut self, category: Category, size: u64) {
+        let index = category as usize;
+        self.bytes[index] += size;
+        self.files[index] += 1;
+    }
+
+    /// Adds the estimated monthly storage `cost` of a file to `category`.
+    pub fn add_cost(&mut self, category: Category, cost: f64) {
+        self.costs[category as usize] += cost;
+    }
+
+    /// Returns the estimated monthly storage cost of `category`.
+    pub fn cost(&self, category: Category) -> f64 {
+        self.costs[category as usize]
+    }
+
+    /// Returns the bytes and number of files of `category`.
+    pub fn get(&self, category: Category) -> (u64, usize) {
+        let index = category as usize;
+        (self.bytes[index], self.files[index])
+    }
+
+    /// Returns the bytes of all categories together.
+    pub fn total_bytes(&self) -> u64 {
+        self.bytes.iter().sum()
+    }
+
+    /// Returns the estimated monthly storage cost of all categories together.
+    pub fn total_cost(&self) -> f64 {
+        self.costs.iter().sum()
+    }
+}
@@ -0,0 +1,672 @@
+use crate::actions;
+use crate::args::{ActionArgs, IdArgs, SessionArgs};
+use crate::config::Config;
+use crate::fleet::{self, FleetOptions};
+use crate::porcelain;
+use crate::results::{self, SavedEntry, SavedScan};
+use crate::snapshot::{self, Snapshot, SnapshotChange};
+use crate::storage;
+use crate::traits::ByteSize;
+use crate::units::format_unix_time;
+use globset::Glob;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Returns the path of the requested session, defaulting to the results of the last scan.
+fn session_path(session: &SessionArgs) -> io::Result<PathBuf> {
+    match &session.session {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => SavedScan::last_scan_path(),
+    }
+}
+
+/// Loads the requested session, defaulting to the results of the last scan.
+fn load_session(session: &SessionArgs) -> io::Result<SavedScan> {
+    SavedScan::load(&session_path(session)?)
+}
+
+/// Loads the session and looks up the requested entries.
+fn resolve_last_scan(ids: &IdArgs) -> Result<(SavedScan, Vec<SavedEntry>), Box<dyn Error>> {
+    let scan = load_session(&ids.session)?;
+    let entries = scan.resolve(&ids.ids)?.into_iter().cloned().collect();
+    Ok((scan, entries))
+}
+
+/// Applies the requested actions to entries of the last scan, addressed by ID.
+///
+/// Actions behave exactly as when given to a scan: the protect list, selection criteria,
+/// undo journal and the scan-time change check all apply.
+///
+/// # Errors
+///
+/// Returns an error if no action was requested, the last scan cannot be loaded, an ID
+/// cannot be resolved, or the actions fail as described in [`actions::run`].
+pub fn clean(ids: &IdArgs, action_args: &ActionArgs) -> Result<(), Box<dyn Error>> {
+    let (scan, chosen) = resolve_last_scan(ids)?;
+    let config = Config::for_actions(scan.provenance.root.clone(), action_args)?;
+    if !config.has_actions() {
+        return Err(tr!("clean-needs-action").into());
+    }
+
+    // Entries of merged sessions may live on other machines
+    let local = crate::host::hostname();
+    if let Some(remote) = chosen
+        .iter()
+        .find(|entry| entry.host.is_some() && entry.host != local)
+    {
+        return Err(tr!(
+            "clean-remote-entry",
+            id = remote.id.as_str(),
+            path = remote.path.as_str(),
+            host = remote.host.as_deref().unwrap_or_default()
+        )
+        .into());
+    }
+
+    let entries: Vec<(String, u64)> = chosen
+        .into_iter()
+        .map(|entry| (entry.path, entry.size))
+        .collect();
+    actions::run(&config, &entries, &scan.stamps())?;
+    Ok(())
+}
+
+/// Shows entries of the last scan in the platform file manager.
+///
+/// On macOS and Windows the file itself is selected; elsewhere its directory is opened
+/// with `xdg-open`.
+pub fn reveal(ids: &IdArgs) -> Result<(), Box<dyn Error>> {
+    let (_, chosen) = resolve_last_scan(ids)?;
+    for entry in chosen {
+        let path = Path::new(&entry.path);
+        let status = if cfg!(target_os = "macos") {
+            Command::new("open").arg("-R").arg(path).status()
+        } else if cfg!(windows) {
+            // Explorer reports a failure exit code even when it succeeds
+            let mut select = std::ffi::OsString::from("/select,");
+            select.push(path);
+            Command::new("explorer")
+                .arg(select)
+                .status()
+                .map(|_| Default::default())
+        } else {
+            Command::new("xdg-open")
+                .arg(path.parent().unwrap_or(path))
+                .status()
+        };
+
+        match status {
+            Ok(status) if status.success() => println!(
+                "{}",
+                tr!(
+                    "revealed",
+                    id = entry.id.as_str(),
+                    path = entry.path.as_str()
+                )
+            ),
+            Ok(status) => eprintln!(
+                "{}",
+                tr!(
+                    "reveal-failed",
+                    path = entry.path.as_str(),
+                    error = tr!("file-manager-exit", status = status.to_string())
+                )
+            ),
+            Err(e) => eprintln!(
+                "{}",
+                tr!(
+                    "reveal-failed",
+                    path = entry.path.as_str(),
+                    error = e.to_string()
+                )
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Clipboard commands to try, in order, reading the text to copy from stdin.
+fn clipboard_commands() -> &'static [&'static [&'static str]] {
+    if cfg!(target_os = "macos") {
+        &[&["pbcopy"]]
+    } else if cfg!(windows) {
+        &[&["clip"]]
+    } else {
+        &[
+            &["wl-copy"],
+            &["xclip", "-selection", "clipboard"],
+            &["xsel", "--clipboard", "--input"],
+        ]
+    }
+}
+
+/// Pipes `text` into the first clipboard command that succeeds.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    for command in clipboard_commands() {
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        tr!("clipboard-unavailable"),
+    ))
+}
+
+/// Copies the full paths of entries of the last scan to the clipboard, one per line.
+///
+/// When no clipboard tool is available the paths are printed instead.
+pub fn copy(ids: &IdArgs) -> Result<(), Box<dyn Error>> {
+    let (_, chosen) = resolve_last_scan(ids)?;
+    let text = chosen
+        .iter()
+        .map(|entry| entry.path.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    match copy_to_clipboard(&text) {
+        Ok(()) => {
+            let total: u64 = chosen.iter().map(|entry| entry.size).sum();
+            println!(
+                "{}",
+                tr!("copied", count = chosen.len(), size = total.format_size())
+            );
+        }
+        Err(e) => {
+            log::warn!("{}", tr!("copy-failed", error = e.to_string()));
+            println!("{}", text);
+        }
+    }
+    Ok(())
+}
+
+/// Prints the next `count` candidates of the session that were ranked below the entries shown
+/// so far, and saves them as shown so their IDs can be used and the following call continues
+/// after them.
+///
+/// With `porcelain` the page is printed as `entry` records.
+///
+/// # Errors
+///
+/// Returns an error if the session cannot be loaded or saved.
+pub fn more(count: usize, session: &SessionArgs, porcelain: bool) -> Result<(), Box<dyn Error>> {
+    let path = session_path(session)?;
+    let mut scan = SavedScan::load(&path)?;
+    if porcelain {
+        porcelain::print_version();
+    }
+    let page = scan.next_page(count);
+    if page.is_empty() {
+        if !porcelain {
+            println!("{}", tr!("more-none"));
+        }
+        return Ok(());
+    }
+    for entry in page {
+        if porcelain {
+            porcelain::print_saved("entry", entry);
+        } else {
+            println!(
+                "[{}] {}: {}",
+                entry.id,
+                entry.location(),
+                entry.size.format_size()
+            );
+        }
+    }
+    // Read-only runs show the same page again next time
+    if !storage::read_only() {
+        scan.save(&path)?;
+    }
+    if !scan.more.is_empty() && !porcelain {
+        println!("{}", tr!("more-available"));
+    }
+    Ok(())
+}
+
+/// Prints the entries that appeared, disappeared or changed size between two sessions, or the
+/// directories and files that did between two snapshots (see [`diff_snapshots`]).
+///
+/// The newer session is `new`, defaulting to the given `--session` and then to the results of
+/// the last scan. With `porcelain` the changes are printed as `added`, `resized` and `removed`
+/// records.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be loaded, a snapshot is compared with a session, or the
+/// newer snapshot is missing.
+pub fn diff(
+    old: &str,
+    new: Option<&str>,
+    session: &SessionArgs,
+    limit: usize,
+    porcelain: bool,
+) -> Result<(), Box<dyn Error>> {
+    let old_is_snapshot = Snapshot::is_snapshot(Path::new(old));
+    if let Some(new) = new {
+        if Snapshot::is_snapshot(Path::new(new)) != old_is_snapshot {
+            return Err(tr!("diff-mixed-files", old = old, new = new).into());
+        }
+    }
+    if old_is_snapshot {
+        let Some(new) = new else {
+            return Err(tr!("diff-snapshot-needs-new", old = old).into());
+        };
+        let old = Snapshot::load(Path::new(old))?;
+        let new = Snapshot::load(Path::new(new))?;
+        if old.settings_differ(&new) {
+            log::warn!("{}", tr!("diff-settings-differ"));
+        }
+        if old.version_differs(&new) {
+            log::warn!(
+                "{}",
+                tr!(
+                    "diff-version-differs",
+                    old = old.tool_version.as_str(),
+                    new = new.tool_version.as_str()
+                )
+            );
+        }
+        diff_snapshots(&old, &new, limit, porcelain);
+        return Ok(());
+    }
+
+    let old = SavedScan::load(Path::new(old))?;
+    let new = match new {
+        Some(new) => SavedScan::load(Path::new(new))?,
+        None => load_session(session)?,
+    };
+    if old.provenance.settings_differ(&new.provenance) {
+        log::warn!("{}", tr!("diff-settings-differ"));
+    }
+    if old.provenance.version_differs(&new.provenance) {
+        log::warn!(
+            "{}",
+            tr!(
+                "diff-version-differs",
+                old = old.provenance.tool_version.as_str(),
+                new = new.provenance.tool_version.as_str()
+            )
+        );
+    }
+    let changes = results::diff(&old, &new);
+
+    if porcelain {
+        porcelain::print_version();
+        for entry in &changes.added {
+            porcelain::print_saved("added", entry);
+        }
+        for (before, after) in &changes.resized {
+            println!(
+                "{}",
+                porcelain::record(
+                    "resized",
+                    &[
+                        &after.id,
+                        &before.size.to_string(),
+                        &after.size.to_string(),
+                        after.host.as_deref().unwrap_or_default(),
+                        &after.path
+                    ]
+                )
+            );
+        }
+        for entry in &changes.removed {
+            porcelain::print_saved("removed", entry);
+        }
+        return Ok(());
+    }
+    if changes.added.is_empty() && changes.removed.is_empty() && changes.resized.is_empty() {
+        println!("{}", tr!("diff-no-changes"));
+        return Ok(());
+    }
+    for entry in &changes.added {
+        println!(
+            "+ [{}] {}: {}",
+            entry.id,
+            entry.location(),
+            entry.size.format_size()
+        );
+    }
+    for (before, after) in &changes.resized {
+        let sign = if after.size > before.size { '+' } else { '-' };
+        println!(
+            "~ [{}] {}: {} -> {} ({}{})",
+            after.id,
+            after.location(),
+            before.size.format_size(),
+            after.size.format_size(),
+            sign,
+            after.size.abs_diff(before.size).format_size()
+        );
+    }
+    for entry in &changes.removed {
+        println!(
+            "- [{}] {}: {}",
+            entry.id,
+            entry.location(),
+            entry.size.format_size()
+        );
+    }
+    Ok(())
+}
+
+/// Prints the `limit` directories and files of two snapshots that grew, shrank, appeared or
+/// vanished by the most bytes.
+///
+/// With `porcelain` the changes are printed as `grew`, `shrank`, `appeared` and `vanished`
+/// records.
+pub fn diff_snapshots(old: &Snapshot, new: &Snapshot, limit: usize, porcelain: bool) {
+    let (directories, files) = snapshot::diff(old, new);
+    let directories = &directories[..directories.len().min(limit)];
+    let files = &files[..files.len().min(limit)];
+
+    if porcelain {
+        porcelain::print_version();
+        for change in directories.iter().chain(files) {
+            let size = |size: Option<u64>| size.map(|size| size.to_string()).unwrap_or_default();
+            println!(
+                "{}",
+                porcelain::record(
+                    change.kind(),
+                    &[
+                        if change.is_dir { "dir" } else { "file" },
+                        &size(change.before),
+                        &size(change.after),
+                        &change.path
+                    ]
+                )
+            );
+        }
+        return;
+    }
+    println!(
+        "{}",
+        tr!(
+            "snapshot-diff-heading",
+            root = new.root.as_str(),
+            old = format_unix_time(old.taken_at),
+            new = format_unix_time(new.taken_at)
+        )
+    );
+    if directories.is_empty() && files.is_empty() {
+        println!("{}", tr!("snapshot-diff-no-changes"));
+        return;
+    }
+    for (heading, changes) in [
+        (tr!("snapshot-diff-dirs"), directories),
+        (tr!("snapshot-diff-files"), files),
+    ] {
+        if changes.is_empty() {
+            continue;
+        }
+        println!("\n{}", heading);
+        for change in changes {
+            println!("{}", describe_change(change));
+        }
+    }
+}
+
+/// Formats a change between two snapshots as a line of the `diff` output.
+fn describe_change(change: &SnapshotChange) -> String {
+    let size = |size: Option<u64>| {
+        size.map(|size| size.format_size())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let delta = change.delta();
+    let kind = match change.kind() {
+        "grew" => tr!("snapshot-grew"),
+        "shrank" => tr!("snapshot-shrank"),
+        "appeared" => tr!("snapshot-appeared"),
+        _ => tr!("snapshot-vanished"),
+    };
+    format!(
+        "{:>11}  {:<9} {}{} ({} -> {})",
+        format!(
+            "{}{}",
+            if delta < 0 { '-' } else { '+' },
+            (delta.unsigned_abs() as u64).format_size()
+        ),
+        kind,
+        change.path,
+        if change.is_dir { "/" } else { "" },
+        size(change.before),
+        size(change.after)
+    )
+}
+
+/// Prints a ranked report combining sessions saved on several hosts.
+///
+/// Sessions without a recorded host are labelled with their file name. The merged report is
+/// also saved to `output`, if given, so it can be queried or diffed like any other session.
+/// With `porcelain` the report is printed as `entry` records.
+pub fn merge(
+    sessions: &[String],
+    limit: usize,
+    output: Option<&str>,
+    porcelain: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut loaded = Vec::new();
+    for path in sessions {
+        let scan = SavedScan::load(Path::new(path))?;
+        let fallback = Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.clone());
+        loaded.push((scan, fallback));
+    }
+    let merged = results::merge(&loaded, limit);
+    if porcelain {
+        porcelain::print_version();
+    }
+    print_merged(&merged, output, porcelain)
+}
+
+/// Prints the entries of a merged report with their hosts and saves it to `output`, if given.
+fn print_merged(
+    merged: &SavedScan,
+    output: Option<&str>,
+    porcelain: bool,
+) -> Result<(), Box<dyn Error>> {
+    if porcelain {
+        for entry in &merged.entries {
+            porcelain::print_saved("entry", entry);
+        }
+        if let Some(output) = output {
+            merged.save(Path::new(output))?;
+        }
+        return Ok(());
+    }
+    let width = merged
+        .entries
+        .iter()
+        .filter_map(|entry| entry.host.as_deref().map(str::len))
+        .max()
+        .unwrap_or(0);
+    for entry in &merged.entries {
+        println!(
+            "[{}] {:<width$}  {}: {}",
+            entry.id,
+            entry.host.as_deref().unwrap_or_default(),
+            entry.path,
+            entry.size.format_size(),
+        );
+    }
+
+    if let Some(output) = output {
+        merged.save(Path::new(output))?;
+        println!("\n{}", tr!("merge-saved", path = output));
+    }
+    Ok(())
+}
+
+/// Scans the hosts listed in `hosts` over ssh and prints how each fared, followed by one ranked
+/// report of their largest files with a host column, like `merge`.
+///
+/// Hosts that fail do not stop the others; their error is printed in place of their counts.
+/// The combined report is saved to `output`, if given. With `porcelain` each host is printed as
+/// a `host` record before the `entry` records.
+///
+/// # Errors
+///
+/// Returns an error if the host list cannot be read, or after printing the report if any host
+/// could not be scanned.
+pub fn fleet(
+    hosts: &str,
+    options: &FleetOptions,
+    output: Option<&str>,
+    porcelain: bool,
+) -> Result<(), Box<dyn Error>> {
+    let hosts = fleet::load_hosts(Path::new(hosts))?;
+    if !porcelain {
+        println!("{}", tr!("fleet-hosts", count = hosts.len()));
+    }
+    let outcomes = fleet::scan_fleet(&hosts, options)?;
+
+    if porcelain {
+        porcelain::print_version();
+    }
+    let mut scans = Vec::new();
+    let mut failures = 0;
+    for outcome in outcomes {
+        let destination = outcome.host.destination;
+        match outcome.scan {
+            Ok(mut scan) => {
+                // label entries as the host list names them, which is how they are reached
+                for entry in &mut scan.session.entries {
+                    entry.host = Some(destination.clone());
+                }
+                if porcelain {
+                    println!(
+                        "{}",
+                        porcelain::record(
+                            "host",
+                            &[
+                                "ok",
+                                &scan.files.to_string(),
+                                &scan.failed.to_string(),
+                                &destination
+                            ]
+                        )
+                    );
+                } else {
+                    println!(
+                        "{}",
+                        tr!(
+                            "fleet-host-ok",
+                            host = destination.as_str(),
+                            files = scan.files,
+                            failed = scan.failed,
+                            seconds = outcome.elapsed.as_secs()
+                        )
+                    );
+                }
+                scans.push((scan.session, destination));
+            }
+            Err(error) => {
+                failures += 1;
+                if porcelain {
+                    println!(
+                        "{}",
+                        porcelain::record("host", &["failed", "", "", &destination])
+                    );
+                } else {
+                    println!(
+                        "{}",
+                        tr!(
+                            "fleet-host-failed",
+                            host = destination.as_str(),
+                            error = error
+                        )
+                    );
+                }
+            }
+        }
+    }
+    if !porcelain {
+        println!();
+    }
+
+    let merged = results::merge(&scans, options.num_entries);
+    print_merged(&merged, output, porcelain)?;
+    if failures > 0 {
+        return Err(tr!("fleet-failed", count = failures, total = hosts.len()).into());
+    }
+    Ok(())
+}
+
+/// Lists entries of a session that match every given filter.
+///
+/// `modified_within` is measured back from the end of the scan, so a session answers the
+/// same way however long after the scan it is queried, and sudden large writes (runaway logs,
+/// dumps) can be told apart from files that have been large for a long time.
+///
+/// With `porcelain` the matches are printed as `entry` records, without the session header.
+pub fn query(
+    min_size: Option<u64>,
+    glob: Option<&str>,
+    modified_within: Option<Duration>,
+    session: &SessionArgs,
+    porcelain: bool,
+) -> Result<(), Box<dyn Error>> {
+    let scan = load_session(session)?;
+    if porcelain {
+        porcelain::print_version();
+    } else {
+        println!(
+            "{}",
+            tr!("query-session", session = scan.provenance.describe())
+        );
+    }
+    let matcher = glob
+        .map(Glob::new)
+        .transpose()?
+        .map(|glob| glob.compile_matcher());
+    let scan_end = match scan.provenance.finished_at {
+        0 => SystemTime::now(),
+        finished_at => UNIX_EPOCH + Duration::from_secs(finished_at),
+    };
+
+    let mut shown = 0;
+    for entry in &scan.entries {
+        if min_size.is_some_and(|min| entry.size < min) {
+            continue;
+        }
+        if matcher.as_ref().is_some_and(|m| !m.is_match(&entry.path)) {
+            continue;
+        }
+        if modified_within.is_some_and(|window| !entry.modified_within(window, scan_end)) {
+            continue;
+        }
+        shown += 1;
+        if porcelain {
+            porcelain::print_saved("entry", entry);
+            continue;
+        }
+        println!(
+            "[{}] {}: {}",
+            entry.id,
+            entry.location(),
+            entry.size.format_size()
+        );
+    }
+    if shown == 0 && !porcelain {
+        println!("{}", tr!("query-no-match"));
+    }
+    Ok(())
+}
@@ -1,11 +1,30 @@
-use crate::args::Args;
+use crate::actions::compress::Codec;
+use crate::actions::verify::to_hex;
+use crate::alerts::AlertRules;
+use crate::args::{ActionArgs, Args};
+use crate::cost::CostModel;
+use crate::console;
+use crate::filter::{self, GlobFilter};
 use crate::get_fd_limit;
+use crate::import::ImportFormat;
+use crate::output::OutputFormat;
+use crate::ownership::OwnershipMap;
+use crate::priorities::RootPriorities;
+use crate::protect::ProtectList;
+use crate::report::{Metric, ReportKind, ThenBy};
+use crate::storage;
+use crate::tags::TagMap;
+use clap::ValueEnum;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::env;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Configuration structure containing runtime settings.
 ///
@@ -15,19 +34,173 @@ use std::path::PathBuf;
 /// * `num_entries` - Number of entries to output at program completion
 /// * `batch_size` - Size of batches for processing file metadata
 /// * `root_path` - Base directory path to recursively find and size files
+/// * `roots` - The directories scanned when several were given, whose closest common ancestor
+///   `root_path` then is (empty when only `root_path` is scanned), heaviest first
+/// * `priorities` - Weights ordering the `roots` and the listing of their trees, from `--priorities`
 /// * `skip_dirs` - Set of directory names to exclude from the search
+/// * `exclude` - Globs of directories left out of the search, matched against names and full paths
+/// * `respect_gitignore` - Bool to leave out what the `.gitignore` files of the tree ignore (see
+///   [`crate::filter::IgnoreRules`])
+/// * `no_ferrisignore` - Bool to also scan what the `.ferrisignore` files of the tree ignore
+/// * `repo` - Bool set by `--repo`: `root_path` is the root of a git repository, whose `.git`
+///   is compared with the working tree (see [`crate::report::RepoSizes`])
+/// * `include` - Globs limiting the scan to the files matching any of them; all files if empty
 /// * `max_open_files` - Maximum number of open file handles used by this program
 /// * `verbose` - Bool to determine if errors collected during runtime will be printed
+/// * `own_files_only` - Bool to skip files (and unreadable directories) owned by other users
+/// * `apparent_size` - Bool to size files by their length rather than by their allocated blocks
+/// * `min_size` - Size in bytes below which files (or directories with `dirs`) are not ranked
+/// * `skip_sparse` - Bool to leave sparse files out of the scan (see [`crate::netfs::is_sparse`])
+/// * `count_links` - Bool to size every hard link of a file rather than only the first one found
+/// * `audit` - Bool to print a permission audit of the final results
+/// * `deleted_open` - Bool to list deleted files still held open by processes
+/// * `ads` - Bool to size named alternate data streams with their files and list the largest (Windows only)
+/// * `attribute` - Bool to group results by owning user and responsible processes
+/// * `quota` - Bool to show quota usage and headroom for the owner of the root directory
+/// * `reports` - Reports to print, computed in one traversal (empty for only the largest files)
+/// * `stats` - Bool to print the size histogram and the number, total, mean and median size of all files
+/// * `dirs` - Bool to rank directories by the total size beneath them instead of files
+/// * `metric` - What files are ranked by: size, last modification or access, or path length (see [`Metric::value`])
+/// * `newest` - Bool to rank the most recently modified or accessed files first with a time `metric`
+/// * `tree` - Bool to print the scanned hierarchy with the size of every node (see [`crate::export::ScanTree::tree_section`])
+/// * `tree_depth` - Optional number of levels below the root `tree` is limited to
+/// * `collapse_siblings` - Optional number of results sharing a directory above which the text
+///   output lists the directory instead (see [`crate::results::collapse_siblings`])
+/// * `then_by` - Optional secondary key ordering results of equal size
+/// * `delete_script` - Optional path of a deletion script to generate for the results
+/// * `move_to` - Optional directory to move the results to
+/// * `archive_to` - Optional tarball to archive the results into
+/// * `compress` - Optional codec used to compress the results in place
+/// * `action_min_size` - Optional minimum size of results that actions apply to
+/// * `action_older_than` - Optional minimum age (since last modification) of results that actions apply to
+/// * `interactive` - Bool to confirm each result before an action is applied to it
+/// * `hash_concurrency` - Number of files actions may read in full at the same time to hash them
+/// * `protect` - Paths and globs that actions must never touch or suggest
+/// * `alerts` - Alert rules evaluated against the reported entries after the scan
+/// * `tags` - Labels of known paths, shown with the results and totalled by `--report by-tag`
+/// * `ownership` - Owners, teams and cost centers of path prefixes, shown with the results and totalled by `--report by-owner`
+/// * `costs` - Monthly storage rates per GB, to estimate the cost of the results and report rows (see [`crate::cost`])
+/// * `save_session` - Optional extra session file the results are saved to
+/// * `force` - Whether to scan even while another scan of the root holds its lock (see [`crate::storage::ScanLock`])
+/// * `save_snapshot` - Optional file the size of every scanned file is saved to, for `diff` (see [`crate::snapshot`])
+/// * `progress_json` - Optional file or pipe the live progress of a scan is streamed to (see [`crate::progress`])
+/// * `export_ncdu` - Optional file the whole scanned tree is written to in the ncdu export format (see [`crate::export`])
+/// * `export_treemap` - Optional file the whole scanned tree is written to as nested treemap JSON (see [`crate::export`])
+/// * `export_parquet` - Optional file every sized file is written to as Parquet, with the `parquet` feature
+/// * `min_free` - Free bytes kept at the destination of `export_parquet`, below which the export stops (0 to never stop)
+/// * `preview` - Bool to show a sampled estimate of the top-level entries and confirm before scanning
+/// * `import` - Optional format and file of a foreign inventory analyzed instead of scanning
+/// * `max_concurrent_dirs` - Optional number of directories that may be listed at the same time (at least 1)
+/// * `depth_guard` - Depth below the root from which directories are reported as pathological (0 for no limit)
+/// * `max_dir_entries` - Number of entries from which a directory is reported as pathological (0 for no limit)
+/// * `max_depth` - Depth below the root beyond which nothing is listed or sized (0 for no limit)
+/// * `min_depth` - Depth below the root from which files are sized, the files directly in the
+///   root lying at depth 1
+/// * `skip_pathological` - Bool to skip what exceeds `depth_guard` or `max_dir_entries` instead of only warning
+/// * `porcelain` - Bool to print results in the stable, tab separated format of [`crate::porcelain`]
+/// * `format` - Whether the final results are printed as text or as one JSON document (see [`crate::output`])
+/// * `read_only` - Bool set by `--assert-read-only`: nothing is written, not even the last scan (see
+///   [`crate::storage::enforce_read_only`])
+/// * `given` - Options given on the command line (see [`Args::given`]), which
+///   `--print-config` tells apart from defaults
 ///
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Config {
     pub num_threads: usize,
     pub num_entries: usize,
     pub batch_size: usize,
     pub root_path: PathBuf,
+    pub roots: Vec<PathBuf>,
+    pub priorities: RootPriorities,
     pub skip_dirs: HashSet<String>,
+    pub exclude: GlobFilter,
+    pub respect_gitignore: bool,
+    pub no_ferrisignore: bool,
+    pub repo: bool,
+    pub include: GlobFilter,
     pub max_open_files: usize,
     pub verbose: bool,
+    pub own_files_only: bool,
+    pub apparent_size: bool,
+    pub min_size: u64,
+    pub skip_sparse: bool,
+    pub count_links: bool,
+    pub audit: bool,
+    pub deleted_open: bool,
+    pub ads: bool,
+    pub attribute: bool,
+    pub quota: bool,
+    pub reports: Vec<ReportKind>,
+    pub stats: bool,
+    pub dirs: bool,
+    pub metric: Metric,
+    pub newest: bool,
+    pub tree: bool,
+    pub tree_depth: Option<usize>,
+    pub collapse_siblings: Option<usize>,
+    pub then_by: Option<ThenBy>,
+    pub delete_script: Option<PathBuf>,
+    pub move_to: Option<PathBuf>,
+    pub archive_to: Option<PathBuf>,
+    pub compress: Option<Codec>,
+    pub action_min_size: Option<u64>,
+    pub action_older_than: Option<Duration>,
+    pub interactive: bool,
+    pub hash_concurrency: usize,
+    pub protect: ProtectList,
+    pub alerts: AlertRules,
+    pub tags: TagMap,
+    pub ownership: OwnershipMap,
+    pub costs: CostModel,
+    pub save_session: Option<PathBuf>,
+    pub force: bool,
+    pub save_snapshot: Option<PathBuf>,
+    pub progress_json: Option<PathBuf>,
+    pub export_ncdu: Option<PathBuf>,
+    pub export_treemap: Option<PathBuf>,
+    pub export_parquet: Option<PathBuf>,
+    pub min_free: u64,
+    pub preview: bool,
+    pub import: Option<(ImportFormat, PathBuf)>,
+    pub max_concurrent_dirs: Option<usize>,
+    pub depth_guard: usize,
+    pub max_depth: usize,
+    pub min_depth: usize,
+    pub max_dir_entries: usize,
+    pub skip_pathological: bool,
+    pub porcelain: bool,
+    pub format: OutputFormat,
+    pub read_only: bool,
+    pub given: HashSet<String>,
+}
+
+/// Where the value of a setting printed by `--print-config` came from.
+///
+/// No environment variable or settings file sets the options of a scan: besides the command
+/// line, values come from the rule files found in the config directories, from the system, or
+/// are defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingSource {
+    /// Given as an option
+    CommandLine,
+    /// Read from the protect, alert, tag or cost files in the config directories
+    RuleFile,
+    /// Detected on the system (threads, open file limit) or computed from other settings
+    System,
+    /// Left to its default
+    Default,
+}
+
+impl SettingSource {
+    /// Name of the source, as printed next to each setting.
+    pub fn name(self) -> &'static str {
+        match self {
+            SettingSource::CommandLine => "command line",
+            SettingSource::RuleFile => "rule file",
+            SettingSource::System => "system",
+            SettingSource::Default => "default",
+        }
+    }
 }
 
 impl Config {
@@ -47,16 +220,55 @@ impl Config {
     /// 1. Configures parallel processing based on available CPU cores
     /// 2. Calls a library function to determine platform specific cap on open file descriptors
     /// 3. Sets number of entries to output equal to provided command line arg or default of 10
-    /// 4. Sets batch size to match command line arg if specified or else default to 1000
-    /// 5. Sets verbose bool to match command line arg
-    /// 6. Sets up the root directory path for operations
-    /// 7. Loads directory exclusion rules if file containing dirs was supplied
+    /// 4. Sets batch size to match command line arg if specified or else default to 1000, and
+    ///    the optional cap on directories listed at once
+    /// 5. Sets the guards against pathological trees (depth, entries per directory) and whether
+    ///    what exceeds them is skipped
+    /// 6. Sets verbose, preview and report related bools (own-files-only, audit, deleted-open, attribute, quota)
+    ///    and the requested reports and ordering of equal-size results to match command line args
+    /// 7. Sets up the root directory path for operations, the enclosing repository's with --repo,
+    ///    or the directories given and their common ancestor if several were, ordered by the
+    ///    weights of the priorities file, if given
+    /// 8. Loads directory exclusion rules if file containing dirs was supplied
+    /// 9. Sets up optional actions (deletion script, move, archive, compress) and how results are selected for them
+    /// 10. Loads the system wide and per-user protect lists consulted by every action, the
+    ///     alert rules evaluated after the scan, the tag map labelling the results and the
+    ///     storage tier rates, and reads the ownership mapping, if given
+    /// 11. Parses the format and file of an inventory to import instead of scanning, if given
+    /// 12. Selects porcelain or JSON output, which suppress the human oriented messages
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// * Current directory cannot be determined when no target directory is specified
+    /// * --repo is given outside of a git repository, or --repo, --max-depth or --min-depth is
+    ///   combined with --import
+    /// * One of several directories cannot be accessed, they share no common ancestor (different
+    ///   drives), or they are combined with --repo or --import
+    /// * A priorities file is given without several directories, or cannot be read or contains
+    ///   an invalid rule
     /// * Exclusion file cannot be opened or read
+    /// * A protect list exists but cannot be read or contains an invalid rule
+    /// * An alerts file exists but cannot be read or contains an invalid rule
+    /// * A costs file exists but cannot be read or contains an invalid rule
+    /// * A tags file exists but cannot be read or contains an invalid rule, or --report by-tag
+    ///   is requested without any tag rules
+    /// * The ownership mapping cannot be read or is invalid, or --report by-owner is requested
+    ///   without one
+    /// * A file written once the scan is done cannot be written (not checked with
+    ///   --print-config, which writes nothing)
+    /// * The format given to --import is not one of du, find, ncdu or wire, or --export-ncdu,
+    ///   --export-treemap, --save-snapshot, --export-parquet, --tree or --report fan-out is
+    ///   combined with --import
+    /// * --export-parquet is given to a build without the `parquet` feature
+    /// * --dirs is combined with an action, which only applies to files
+    /// * --newest is given without --metric mtime or atime, or --metric other than size is
+    ///   combined with --dirs or --import
+    /// * --porcelain is combined with an option whose output has no porcelain form (--preview,
+    ///   --attribute, --quota, --deleted-open or an action)
+    /// * --format json or wire is combined with --porcelain or one of the options above
+    /// * --assert-read-only is combined with an option that writes a file or an action
+    /// * An action is requested while --report leaves out the largest files
     /// * Thread pool configuration fails (logged as error but doesn't halt execution)
     /// 
     pub fn build(args: &Args) -> Result<Config, Box<dyn Error>> {
@@ -64,43 +276,751 @@ impl Config {
             .map(|n| n.get())
             .unwrap_or(1);
 
-        println!("Preparing to scan using {} threads", num_threads);
-
         let max_open_files = get_fd_limit();
-        println!("Limiting open file handles to {}", max_open_files);
+        if !args.porcelain && args.format == OutputFormat::Text && !args.print_config {
+            println!("{}", tr!("preparing", threads = num_threads));
+            println!("{}", tr!("open-file-limit", limit = max_open_files));
+        }
 
         let num_entries = args.num_entries;
         let batch_size = args.batch_size;
         let verbose = args.verbose;
 
-        let root_path = if let Some(target_dir) = &args.target_dir {
+        let own_files_only = args.own_files_only;
+        if own_files_only && cfg!(not(unix)) {
+            log::warn!("{}", tr!("own-files-only-unsupported"));
+        }
+        if args.reports.contains(&ReportKind::ByUser) && cfg!(not(unix)) {
+            log::warn!("{}", tr!("by-user-unsupported"));
+        }
+        let ads = args.ads && cfg!(windows);
+        if args.ads && !ads {
+            log::warn!("{}", tr!("ads-unsupported"));
+        }
+
+        let mut root_path = if let Some(target_dir) = args.target_dir.first() {
             PathBuf::from(target_dir)
         } else {
             env::current_dir()?
         };
+        let mut roots = Vec::new();
+        if args.target_dir.len() > 1 {
+            roots = distinct_roots(&args.target_dir)?;
+            root_path = common_ancestor(&roots)?;
+            // Roots nested in another one may leave a single root
+            if roots.len() == 1 {
+                roots.clear();
+            }
+        }
+        let priorities = match &args.priorities {
+            Some(file) if roots.is_empty() => {
+                return Err(tr!("priorities-need-roots", file = file.as_str()).into())
+            }
+            Some(file) => RootPriorities::load(Path::new(file))?,
+            None => RootPriorities::default(),
+        };
+        priorities.order(&mut roots);
+        if args.repo {
+            let dir = console::canonicalize(&root_path)?;
+            root_path = filter::repo_root(&dir)
+                .ok_or_else(|| tr!("repo-not-found", dir = dir.display().to_string()))?
+                .to_path_buf();
+        }
 
         let mut skip_dirs: HashSet<String> = HashSet::new();
         if let Some(exclusion_file) = &args.exclusion_file {
-            let file = File::open(exclusion_file)
-                .expect("A path to an excluded directories file was provided but the file could not be read");
+            let file = File::open(exclusion_file).map_err(|e| {
+                tr!(
+                    "exclusion-file-unreadable",
+                    file = exclusion_file.as_str(),
+                    error = e.to_string()
+                )
+            })?;
 
             let reader = BufReader::new(file);
             reader.lines().for_each(|line| match line {
                 Ok(dir) => {
                     skip_dirs.insert(dir);
                 }
-                Err(e) => log::error!("Error reading line: {}", e),
+                Err(e) => log::error!(
+                    "{}",
+                    tr!("exclusion-line-unreadable", error = e.to_string())
+                ),
             });
         }
 
-        Ok(Config {
+        let protect = ProtectList::load()?;
+        let alerts = AlertRules::load()?;
+        let tags = TagMap::load()?;
+        let costs = CostModel::load(args.cost_per_gb)?;
+        let ownership = match &args.ownership {
+            Some(file) => OwnershipMap::load(Path::new(file))?,
+            None => OwnershipMap::default(),
+        };
+
+        let import = match args.import.as_deref() {
+            Some([format, file]) => Some((
+                ImportFormat::from_str(format, true)
+                    .map_err(|_| tr!("import-format-unknown", format = format.as_str()))?,
+                PathBuf::from(file),
+            )),
+            _ => None,
+        };
+
+        let mut config = Config {
             num_threads,
             num_entries,
             batch_size,
+            max_concurrent_dirs: args.max_concurrent_dirs,
+            depth_guard: args.depth_guard,
+            max_depth: args.max_depth,
+            min_depth: args.min_depth,
+            max_dir_entries: args.max_dir_entries,
+            skip_pathological: args.skip_pathological,
             root_path,
+            roots,
+            priorities,
             skip_dirs,
+            exclude: GlobFilter::new(&args.exclude)?,
+            respect_gitignore: args.respect_gitignore || (args.repo && !args.no_respect_gitignore),
+            no_ferrisignore: args.no_ferrisignore,
+            repo: args.repo,
+            include: GlobFilter::new(&args.include)?,
             max_open_files,
-            verbose
+            verbose,
+            own_files_only,
+            apparent_size: args.apparent_size,
+            min_size: args.min_size,
+            skip_sparse: args.skip_sparse,
+            count_links: args.count_links,
+            audit: args.audit,
+            deleted_open: args.deleted_open,
+            ads,
+            attribute: args.attribute,
+            quota: args.quota,
+            reports: dedup_reports(&args.reports),
+            stats: args.stats,
+            dirs: args.dirs,
+            metric: args.metric,
+            newest: args.newest,
+            tree: args.tree,
+            tree_depth: args.tree_depth,
+            collapse_siblings: args.collapse_siblings.map(|limit| limit as usize),
+            then_by: args.then_by,
+            protect,
+            alerts,
+            tags,
+            ownership,
+            costs,
+            save_session: args.save_session.as_ref().map(PathBuf::from),
+            force: args.force,
+            save_snapshot: args.save_snapshot.as_ref().map(PathBuf::from),
+            progress_json: args.progress_json.as_ref().map(PathBuf::from),
+            export_ncdu: args.export_ncdu.as_ref().map(PathBuf::from),
+            export_treemap: args.export_treemap.as_ref().map(PathBuf::from),
+            export_parquet: args.export_parquet.as_ref().map(PathBuf::from),
+            min_free: args.min_free,
+            preview: args.preview,
+            import,
+            porcelain: args.porcelain,
+            format: args.format,
+            given: args.given.clone(),
+            ..Default::default()
+        }
+        .with_actions(&args.actions);
+
+        if args.assert_read_only {
+            let writers = [
+                ("--save-session", config.save_session.is_some()),
+                ("--save-snapshot", config.save_snapshot.is_some()),
+                ("--progress-json", config.progress_json.is_some()),
+                ("--export-ncdu", config.export_ncdu.is_some()),
+                ("--export-treemap", config.export_treemap.is_some()),
+                ("--export-parquet", config.export_parquet.is_some()),
+                ("--emit-delete-script", config.delete_script.is_some()),
+                ("--move-to", config.move_to.is_some()),
+                ("--archive-to", config.archive_to.is_some()),
+                ("--compress", config.compress.is_some()),
+            ];
+            if let Some((option, _)) = writers.iter().find(|(_, given)| *given) {
+                return Err(tr!("read-only-option", option = *option).into());
+            }
+            config.read_only = true;
+        }
+        if config.porcelain
+            && (config.preview
+                || config.attribute
+                || config.quota
+                || config.deleted_open
+                || config.has_actions())
+        {
+            return Err(tr!("porcelain-conflict").into());
+        }
+        if config.format != OutputFormat::Text
+            && (config.porcelain
+                || config.preview
+                || config.attribute
+                || config.quota
+                || config.deleted_open
+                || config.has_actions())
+        {
+            let format = value_name(&config.format);
+            return Err(tr!("format-conflict", format = format).into());
+        }
+        if config.has_actions() && !config.wants_report(ReportKind::TopFiles) {
+            return Err(tr!("report-actions-need-top-files").into());
+        }
+        if config.has_actions() && config.dirs {
+            return Err(tr!("dirs-no-actions").into());
+        }
+        if config.tags.is_empty() && config.wants_report(ReportKind::ByTag) {
+            let files: Vec<String> = TagMap::files()
+                .iter()
+                .map(|file| file.display().to_string())
+                .collect();
+            return Err(tr!("report-by-tag-needs-tags", files = files.join(", ")).into());
+        }
+        if config.ownership.is_empty() && config.wants_report(ReportKind::ByOwner) {
+            return Err(tr!("report-by-owner-needs-ownership").into());
+        }
+        if config.import.is_some() && config.tree {
+            return Err(tr!("tree-needs-scan").into());
+        }
+        if config.import.is_some() && config.wants_report(ReportKind::FanOut) {
+            return Err(tr!("fan-out-needs-scan").into());
+        }
+        if config.import.is_some() && config.repo {
+            return Err(tr!("repo-needs-scan").into());
+        }
+        if !config.roots.is_empty() {
+            let option = if config.repo {
+                Some("--repo")
+            } else if config.import.is_some() {
+                Some("--import")
+            } else {
+                None
+            };
+            if let Some(option) = option {
+                return Err(tr!("roots-conflict", option = option).into());
+            }
+        }
+        if config.import.is_some() && (config.max_depth > 0 || config.min_depth > 0) {
+            return Err(tr!("depth-needs-scan").into());
+        }
+        if config.newest && !config.metric.is_time() {
+            return Err(tr!("newest-needs-time").into());
+        }
+        if config.metric != Metric::Size && (config.dirs || config.import.is_some()) {
+            return Err(tr!("metric-needs-file-scan", metric = config.metric.name()).into());
+        }
+        if config.import.is_some() {
+            let option = if config.export_ncdu.is_some() {
+                Some("--export-ncdu")
+            } else if config.export_treemap.is_some() {
+                Some("--export-treemap")
+            } else if config.export_parquet.is_some() {
+                Some("--export-parquet")
+            } else if config.save_snapshot.is_some() {
+                Some("--save-snapshot")
+            } else {
+                None
+            };
+            if let Some(option) = option {
+                return Err(tr!("export-needs-scan", option = option).into());
+            }
+        }
+        if config.export_parquet.is_some() && cfg!(not(feature = "parquet")) {
+            return Err(tr!("parquet-unsupported").into());
+        }
+        // Printing the settings writes nothing, not even the probes of the outputs
+        if !args.print_config {
+            config.check_outputs()?;
+        }
+        Ok(config)
+    }
+
+    /// Builds a Config for applying actions to the results of an earlier scan of `root_path`.
+    ///
+    /// Only the action related fields and the protect list are set; scan settings keep their
+    /// defaults since no scan takes place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a protect list exists but cannot be read or contains an invalid rule.
+    pub fn for_actions(root_path: PathBuf, actions: &ActionArgs) -> Result<Config, Box<dyn Error>> {
+        Ok(Config {
+            root_path,
+            protect: ProtectList::load()?,
+            ..Default::default()
+        }
+        .with_actions(actions))
+    }
+
+    /// Copies the action settings (deletion script, move, archive, compress and selection) from `actions`.
+    fn with_actions(self, actions: &ActionArgs) -> Config {
+        Config {
+            delete_script: actions.delete_script.as_ref().map(PathBuf::from),
+            move_to: actions.move_to.as_ref().map(PathBuf::from),
+            archive_to: actions.archive_to.as_ref().map(PathBuf::from),
+            compress: actions.compress,
+            action_min_size: actions.action_min_size,
+            action_older_than: actions.action_older_than,
+            interactive: actions.interactive,
+            hash_concurrency: actions.hash_concurrency,
+            ..self
+        }
+    }
+
+    /// Checks that every file written once the scan is done can be written, so a missing or
+    /// read only destination fails the run before the scan instead of after it.
+    ///
+    /// The archive must not exist yet, as it is never overwritten. The progress feed is left
+    /// out: it is opened as the scan starts and may be a pipe nobody reads yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the option and the path of the first destination that cannot be
+    /// written.
+    pub fn check_outputs(&self) -> Result<(), Box<dyn Error>> {
+        let outputs = [
+            ("--save-session", &self.save_session, false),
+            ("--save-snapshot", &self.save_snapshot, false),
+            ("--export-ncdu", &self.export_ncdu, false),
+            ("--export-treemap", &self.export_treemap, false),
+            ("--export-parquet", &self.export_parquet, false),
+            ("--emit-delete-script", &self.delete_script, false),
+            ("--archive-to", &self.archive_to, true),
+        ];
+        for (option, path, must_be_new) in outputs {
+            if let Some(path) = path {
+                storage::check_writable(path, must_be_new).map_err(|e| {
+                    tr!(
+                        "output-unwritable",
+                        option = option,
+                        path = path.display().to_string(),
+                        error = e.to_string()
+                    )
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a short hash of the settings that determine which entries a scan reports.
+    ///
+    /// Two sessions with the same hash were produced with the same root, entry count,
+    /// exclusions and ownership filter, so their results can be compared like for like.
+    pub fn settings_hash(&self) -> String {
+        let mut skip_dirs: Vec<&String> = self.skip_dirs.iter().collect();
+        skip_dirs.sort();
+        let mut settings = format!(
+            "root={}\nnum_entries={}\nskip_dirs={:?}\nown_files_only={}",
+            self.root_path.display(),
+            self.num_entries,
+            skip_dirs,
+            self.own_files_only
+        );
+        if !self.roots.is_empty() {
+            // Priorities only change the order the roots are scanned in
+            let mut roots = self.roots.clone();
+            roots.sort();
+            settings.push_str(&format!("\nroots={:?}", roots));
+        }
+        // Only skipping changes the results; the hash of other scans stays as it was
+        if self.dirs {
+            settings.push_str("\ndirs=true");
+        }
+        if self.metric != Metric::Size {
+            settings.push_str(&format!("\nmetric={}\nnewest={}", self.metric.name(), self.newest));
+        }
+        if self.ads {
+            settings.push_str("\nads=true");
+        }
+        if self.count_links {
+            settings.push_str("\ncount_links=true");
+        }
+        if self.apparent_size {
+            settings.push_str("\napparent_size=true");
+        }
+        if !self.exclude.is_empty() {
+            settings.push_str(&format!("\nexclude={:?}", self.exclude.patterns()));
+        }
+        if self.respect_gitignore {
+            settings.push_str("\nrespect_gitignore=true");
+        }
+        if self.no_ferrisignore {
+            settings.push_str("\nno_ferrisignore=true");
+        }
+        if !self.include.is_empty() {
+            settings.push_str(&format!("\ninclude={:?}", self.include.patterns()));
+        }
+        if self.min_size > 0 {
+            settings.push_str(&format!("\nmin_size={}", self.min_size));
+        }
+        if self.skip_sparse {
+            settings.push_str("\nskip_sparse=true");
+        }
+        // Named as before the depth options were renamed, so saved settings hashes still match
+        if self.max_depth > 0 {
+            settings.push_str(&format!("\ndepth_limit={}", self.max_depth));
+        }
+        if self.min_depth > 0 {
+            settings.push_str(&format!("\nmin_depth={}", self.min_depth));
+        }
+        if self.skip_pathological {
+            settings.push_str(&format!(
+                "\nmax_depth={}\nmax_dir_entries={}",
+                self.depth_guard, self.max_dir_entries
+            ));
+        }
+        let mut hash = to_hex(&Sha256::digest(settings.as_bytes()));
+        hash.truncate(16);
+        hash
+    }
+
+    /// Returns the directories a scan starts from: the `roots` if several were given, or else
+    /// `root_path`.
+    pub fn scan_roots(&self) -> Vec<PathBuf> {
+        if self.roots.is_empty() {
+            vec![self.root_path.clone()]
+        } else {
+            self.roots.clone()
+        }
+    }
+
+    /// Returns the root a scanned `path` comes from when several were given; `None` with a
+    /// single root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::config::Config;
+    /// use std::path::{Path, PathBuf};
+    ///
+    /// let config = Config {
+    ///     root_path: PathBuf::from("/srv"),
+    ///     roots: vec![PathBuf::from("/srv/db"), PathBuf::from("/srv/media")],
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(config.root_of("/srv/media/film.m4v"), Some(Path::new("/srv/media")));
+    /// assert_eq!(Config::default().root_of("/srv/media/film.m4v"), None);
+    /// ```
+    pub fn root_of(&self, path: &str) -> Option<&Path> {
+        self.roots
+            .iter()
+            .find(|root| Path::new(path).starts_with(root))
+            .map(PathBuf::as_path)
+    }
+
+    /// Whether `report` is printed; without any --report only the largest files are.
+    pub fn wants_report(&self, report: ReportKind) -> bool {
+        if self.reports.is_empty() {
+            report == ReportKind::TopFiles
+        } else {
+            self.reports.contains(&report)
+        }
+    }
+
+    /// Whether the output is meant for programs (porcelain, JSON or wire), so human oriented messages
+    /// and progress are left out.
+    pub fn machine_readable(&self) -> bool {
+        self.porcelain || self.format != OutputFormat::Text
+    }
+
+    /// Whether any action that acts on, or suggests acting on, the results was requested.
+    pub fn has_actions(&self) -> bool {
+        self.delete_script.is_some()
+            || self.move_to.is_some()
+            || self.archive_to.is_some()
+            || self.compress.is_some()
+    }
+
+    /// Returns every setting as resolved from the command line, the rule files and the
+    /// defaults, named like the fields and in their order, for `--print-config`.
+    ///
+    /// Unset options are `null`. The protect list, alerts, tag map and storage rates show as the
+    /// rule files found in the config directories and what was read from them; the
+    /// [`settings_hash`](Config::settings_hash) sessions are compared by comes last.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::config::Config;
+    /// use serde_json::json;
+    ///
+    /// let config = Config { num_entries: 5, batch_size: 1000, ..Default::default() };
+    /// let settings = config.effective_settings();
+    /// assert_eq!(settings[1], ("num_entries", json!(5)));
+    /// assert!(settings.contains(&("move_to", json!(null))));
+    /// assert_eq!(settings.last().unwrap().0, "settings_hash");
+    /// ```
+    pub fn effective_settings(&self) -> Vec<(&'static str, Value)> {
+        let path = |path: &Option<PathBuf>| match path {
+            Some(path) => json!(path.to_string_lossy()),
+            None => Value::Null,
+        };
+        let found = |files: Vec<PathBuf>| {
+            files
+                .into_iter()
+                .filter(|file| file.is_file())
+                .map(|file| file.to_string_lossy().into_owned())
+                .collect::<Vec<String>>()
+        };
+        let mut skip_dirs: Vec<&String> = self.skip_dirs.iter().collect();
+        skip_dirs.sort();
+        vec![
+            ("num_threads", json!(self.num_threads)),
+            ("num_entries", json!(self.num_entries)),
+            ("batch_size", json!(self.batch_size)),
+            ("root_path", json!(self.root_path.to_string_lossy())),
+            (
+                "roots",
+                json!(self.roots.iter().map(|root| root.to_string_lossy()).collect::<Vec<_>>()),
+            ),
+            (
+                "priorities",
+                json!(self
+                    .roots
+                    .iter()
+                    .map(|root| self.priorities.weight(root))
+                    .collect::<Vec<u32>>()),
+            ),
+            ("skip_dirs", json!(skip_dirs)),
+            ("exclude", json!(self.exclude.patterns())),
+            ("respect_gitignore", json!(self.respect_gitignore)),
+            ("no_ferrisignore", json!(self.no_ferrisignore)),
+            ("repo", json!(self.repo)),
+            ("include", json!(self.include.patterns())),
+            ("max_open_files", json!(self.max_open_files)),
+            ("verbose", json!(self.verbose)),
+            ("own_files_only", json!(self.own_files_only)),
+            ("apparent_size", json!(self.apparent_size)),
+            ("min_size", json!(self.min_size)),
+            ("skip_sparse", json!(self.skip_sparse)),
+            ("count_links", json!(self.count_links)),
+            ("audit", json!(self.audit)),
+            ("deleted_open", json!(self.deleted_open)),
+            ("ads", json!(self.ads)),
+            ("attribute", json!(self.attribute)),
+            ("quota", json!(self.quota)),
+            (
+                "reports",
+                json!(self.reports.iter().map(value_name).collect::<Vec<String>>()),
+            ),
+            ("stats", json!(self.stats)),
+            ("dirs", json!(self.dirs)),
+            ("metric", json!(self.metric.name())),
+            ("newest", json!(self.newest)),
+            ("tree", json!(self.tree)),
+            ("tree_depth", json!(self.tree_depth)),
+            ("collapse_siblings", json!(self.collapse_siblings)),
+            ("then_by", json!(self.then_by.as_ref().map(value_name))),
+            ("delete_script", path(&self.delete_script)),
+            ("move_to", path(&self.move_to)),
+            ("archive_to", path(&self.archive_to)),
+            ("compress", json!(self.compress.as_ref().map(value_name))),
+            ("action_min_size", json!(self.action_min_size)),
+            (
+                "action_older_than",
+                json!(self.action_older_than.map(|age| age.as_secs())),
+            ),
+            ("interactive", json!(self.interactive)),
+            ("hash_concurrency", json!(self.hash_concurrency)),
+            ("protect_files", json!(found(ProtectList::files()))),
+            ("protect", json!(self.protect.rules())),
+            ("alert_files", json!(found(AlertRules::files()))),
+            ("alerts", json!(self.alerts.rules.len())),
+            ("tag_files", json!(found(TagMap::files()))),
+            ("tags", json!(!self.tags.is_empty())),
+            ("ownership", json!(!self.ownership.is_empty())),
+            ("cost_files", json!(found(CostModel::files()))),
+            ("costs", json!(self.costs.is_enabled())),
+            ("save_session", path(&self.save_session)),
+            ("force", json!(self.force)),
+            ("save_snapshot", path(&self.save_snapshot)),
+            ("progress_json", path(&self.progress_json)),
+            ("export_ncdu", path(&self.export_ncdu)),
+            ("export_treemap", path(&self.export_treemap)),
+            ("export_parquet", path(&self.export_parquet)),
+            ("min_free", json!(self.min_free)),
+            ("preview", json!(self.preview)),
+            (
+                "import_format",
+                json!(self.import.as_ref().map(|(format, _)| value_name(format))),
+            ),
+            (
+                "import_file",
+                path(&self.import.as_ref().map(|(_, file)| file.clone())),
+            ),
+            ("max_concurrent_dirs", json!(self.max_concurrent_dirs)),
+            ("depth_guard", json!(self.depth_guard)),
+            ("max_depth", json!(self.max_depth)),
+            ("min_depth", json!(self.min_depth)),
+            ("max_dir_entries", json!(self.max_dir_entries)),
+            ("skip_pathological", json!(self.skip_pathological)),
+            ("porcelain", json!(self.porcelain)),
+            ("format", json!(value_name(&self.format))),
+            ("read_only", json!(self.read_only)),
+            ("settings_hash", json!(self.settings_hash())),
+        ]
+    }
+
+    /// Returns where the value of the setting named `key` in the
+    /// [`effective_settings`](Config::effective_settings) came from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::args::Args;
+    /// use ferris_files::config::{Config, SettingSource};
+    ///
+    /// let args = Args::try_parse_with_sources(["ferris-files", "-d", "/tmp", "-n", "5"]).unwrap();
+    /// let config = Config::build(&args).unwrap();
+    /// assert_eq!(config.setting_source("num_entries"), SettingSource::CommandLine);
+    /// assert_eq!(config.setting_source("root_path"), SettingSource::CommandLine);
+    /// assert_eq!(config.setting_source("batch_size"), SettingSource::Default);
+    /// assert_eq!(config.setting_source("num_threads"), SettingSource::System);
+    /// ```
+    pub fn setting_source(&self, key: &str) -> SettingSource {
+        let found = |files: Vec<PathBuf>| files.iter().any(|file| file.is_file());
+        let options: &[&str] = match key {
+            "num_threads" | "max_open_files" | "settings_hash" => return SettingSource::System,
+            "protect_files" | "protect" if found(ProtectList::files()) => {
+                return SettingSource::RuleFile
+            }
+            "alert_files" | "alerts" if found(AlertRules::files()) => return SettingSource::RuleFile,
+            "tag_files" | "tags" if found(TagMap::files()) => return SettingSource::RuleFile,
+            "cost_files" if found(CostModel::files()) => return SettingSource::RuleFile,
+            "costs" if !self.given.contains("cost_per_gb") && found(CostModel::files()) => {
+                return SettingSource::RuleFile
+            }
+            "root_path" | "roots" => &["target_dir", "repo"],
+            "skip_dirs" => &["exclusion_file"],
+            "respect_gitignore" => &["respect_gitignore", "no_respect_gitignore", "repo"],
+            "costs" => &["cost_per_gb"],
+            "import_format" | "import_file" => &["import"],
+            "read_only" => &["assert_read_only"],
+            key => &[key],
+        };
+        if options.iter().any(|option| self.given.contains(*option)) {
+            SettingSource::CommandLine
+        } else {
+            SettingSource::Default
+        }
+    }
+
+    /// Formats the [`effective_settings`](Config::effective_settings) as TOML, one `key = value`
+    /// line each followed by its [source](Config::setting_source); unset options, which TOML has
+    /// no value for, are commented out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::config::Config;
+    ///
+    /// let config = Config { num_entries: 5, ..Default::default() };
+    /// let toml = config.settings_toml();
+    /// assert!(toml.contains("\nnum_entries = 5  # default\n"));
+    /// assert!(toml.contains("\nexclude = []  # default\n"));
+    /// assert!(toml.contains("\n# move_to =  (default)\n"));
+    /// ```
+    pub fn settings_toml(&self) -> String {
+        self.effective_settings()
+            .into_iter()
+            .map(|(key, value)| {
+                let source = self.setting_source(key).name();
+                match value {
+                    Value::Null => format!("# {} =  ({})\n", key, source),
+                    value => format!("{} = {}  # {}\n", key, value, source),
+                }
+            })
+            .collect()
+    }
+
+    /// Formats the [`effective_settings`](Config::effective_settings) as one JSON object, in the
+    /// order of the fields, with the value and [source](Config::setting_source) of each.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::config::Config;
+    /// use serde_json::Value;
+    ///
+    /// let config = Config { num_entries: 5, ..Default::default() };
+    /// let json: Value = serde_json::from_str(&config.settings_json()).unwrap();
+    /// assert_eq!(json["num_entries"]["value"], 5);
+    /// assert_eq!(json["num_entries"]["source"], "default");
+    /// ```
+    pub fn settings_json(&self) -> String {
+        let settings: Vec<(&str, Value)> = self
+            .effective_settings()
+            .into_iter()
+            .map(|(key, value)| (key, json!({ "value": value, "source": self.setting_source(key).name() })))
+            .collect();
+        serde_json::to_string_pretty(&InOrder(&settings)).unwrap_or_default()
+    }
+}
+
+/// Serializes `(key, value)` pairs as one JSON object, keeping their order where a
+/// [`serde_json::Map`] would sort the keys.
+struct InOrder<'a>(&'a [(&'a str, Value)]);
+
+impl Serialize for InOrder<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+/// Returns the name a value of a command line enum is given as.
+fn value_name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Canonicalizes the directories given to repeated `-d` options, sorted, leaving out those
+/// nested in (or equal to) another one, whose files would otherwise count twice.
+fn distinct_roots(dirs: &[String]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut roots = dirs
+        .iter()
+        .map(|dir| {
+            console::canonicalize(Path::new(dir))
+                .map_err(|e| tr!("root-unreadable", dir = dir.as_str(), error = e.to_string()))
         })
+        .collect::<Result<Vec<PathBuf>, String>>()?;
+    // Parents sort right before what lies beneath them
+    roots.sort();
+    let mut distinct: Vec<PathBuf> = Vec::with_capacity(roots.len());
+    for root in roots {
+        if !distinct.last().is_some_and(|parent| root.starts_with(parent)) {
+            distinct.push(root);
+        }
+    }
+    Ok(distinct)
+}
+
+/// Returns the deepest directory all `roots` lie beneath (or are).
+fn common_ancestor(roots: &[PathBuf]) -> Result<PathBuf, Box<dyn Error>> {
+    let mut common = roots[0].clone();
+    for root in &roots[1..] {
+        while !root.starts_with(&common) {
+            if !common.pop() {
+                let first = roots[0].display().to_string();
+                return Err(tr!("roots-apart", first = first, second = root.display().to_string()).into());
+            }
+        }
+    }
+    Ok(common)
+}
+
+/// Drops repeated reports, keeping the order they were first requested in.
+fn dedup_reports(reports: &[ReportKind]) -> Vec<ReportKind> {
+    let mut unique = Vec::with_capacity(reports.len());
+    for report in reports {
+        if !unique.contains(report) {
+            unique.push(*report);
+        }
     }
+    unique
 }
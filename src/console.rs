@@ -0,0 +1,120 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Code page the Windows console used before [`enable_utf8`], restored by [`restore`].
+#[cfg(windows)]
+static PREVIOUS_CODE_PAGE: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+/// Switches the output code page of the Windows console to UTF-8 until [`restore`] is called.
+///
+/// Text written straight to the console is already converted to UTF-16 by the standard
+/// library, but output piped to another console program (`| more`, `| findstr`) reaches the
+/// screen through the console code page, which garbles every path outside the ANSI code page
+/// unless it is UTF-8. Elsewhere this does nothing.
+pub fn enable_utf8() {
+    #[cfg(windows)]
+    {
+        use std::sync::atomic::Ordering;
+        use windows_sys::Win32::System::Console::{GetConsoleOutputCP, SetConsoleOutputCP};
+
+        const CP_UTF8: u32 = 65001;
+        // SAFETY: neither call takes pointers; both fail harmlessly without a console
+        let previous = unsafe { GetConsoleOutputCP() };
+        if previous != 0 && previous != CP_UTF8 && unsafe { SetConsoleOutputCP(CP_UTF8) } != 0 {
+            PREVIOUS_CODE_PAGE.store(previous, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Gives the console back the code page it had before [`enable_utf8`], as the setting outlives
+/// the process.
+pub fn restore() {
+    #[cfg(windows)]
+    {
+        use std::sync::atomic::Ordering;
+        use windows_sys::Win32::System::Console::SetConsoleOutputCP;
+
+        let previous = PREVIOUS_CODE_PAGE.swap(0, Ordering::Relaxed);
+        if previous != 0 {
+            // SAFETY: no pointers are involved
+            unsafe { SetConsoleOutputCP(previous) };
+        }
+    }
+}
+
+/// Restores the console (see [`restore`]) and exits with `code`.
+pub fn exit(code: i32) -> ! {
+    restore();
+    std::process::exit(code)
+}
+
+/// Returns the canonical form of `path` as people write it.
+///
+/// On Windows, [`Path::canonicalize`] returns verbatim paths (`\\?\C:\Users\...`), and every
+/// path printed or saved would carry the prefix. It is dropped wherever the path means the same
+/// without it (see [`strip_verbatim`]); the standard library adds it back by itself for paths
+/// too long for the legacy APIs. Elsewhere this is [`Path::canonicalize`].
+///
+/// # Errors
+///
+/// Returns an error if `path` does not exist or cannot be resolved.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let canonical = path.canonicalize()?;
+    #[cfg(windows)]
+    if let Some(simple) = canonical.to_str().and_then(strip_verbatim) {
+        return Ok(PathBuf::from(simple));
+    }
+    Ok(canonical)
+}
+
+/// Removes the `\\?\` prefix of a verbatim Windows path to a drive or a UNC share, returning
+/// `None` if `path` is not one or would change its meaning without the prefix.
+///
+/// Verbatim paths skip the normalization of other paths, so components ending in a dot or a
+/// space, reserved device names such as `NUL` and forward slashes only keep their meaning with
+/// the prefix.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::console::strip_verbatim;
+///
+/// assert_eq!(strip_verbatim(r"\\?\C:\Users\Zoë\Música").as_deref(), Some(r"C:\Users\Zoë\Música"));
+/// assert_eq!(strip_verbatim(r"\\?\UNC\nas\share\data").as_deref(), Some(r"\\nas\share\data"));
+/// assert_eq!(strip_verbatim(r"\\?\C:\logs\aux.txt"), None);
+/// assert_eq!(strip_verbatim(r"\\?\Volume{8e2a}\data"), None);
+/// assert_eq!(strip_verbatim(r"C:\Users"), None);
+/// ```
+pub fn strip_verbatim(path: &str) -> Option<String> {
+    let (prefix, rest) = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        (r"\\", rest)
+    } else {
+        let rest = path.strip_prefix(r"\\?\")?;
+        let drive = rest.as_bytes();
+        if drive.len() < 3 || !drive[0].is_ascii_alphabetic() || &drive[1..3] != b":\\" {
+            return None;
+        }
+        ("", rest)
+    };
+    let altered = |component: &str| {
+        let stem = component.split('.').next().unwrap_or(component);
+        component.ends_with(['.', ' ']) || is_reserved_name(stem)
+    };
+    if rest.contains('/') || rest.split('\\').any(altered) {
+        return None;
+    }
+    Some(format!("{}{}", prefix, rest))
+}
+
+/// Whether `stem` names a DOS device, which Windows resolves in any directory.
+fn is_reserved_name(stem: &str) -> bool {
+    matches!(
+        stem.trim_end().to_ascii_uppercase().as_bytes(),
+        b"CON"
+            | b"PRN"
+            | b"AUX"
+            | b"NUL"
+            | [b'C', b'O', b'M', b'1'..=b'9']
+            | [b'L', b'P', b'T', b'1'..=b'9']
+    )
+}
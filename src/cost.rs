@@ -0,0 +1,272 @@
+use crate::protect::rule_globs;
+use crate::report::{Aggregator, FileInfo, ReportRow, ReportSection};
+use crate::storage;
+use crate::traits::ByteSize;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::path::PathBuf;
+
+/// Name of the storage tier rates file inside the system and user configuration directories.
+const COSTS_FILE: &str = "costs";
+
+/// Bytes in the GB that rates are given per, matching the sizes printed by ferris-files.
+pub const BYTES_PER_GB: f64 = (1u64 << 30) as f64;
+
+/// Monthly storage rates per GB, to estimate what the reported files cost.
+///
+/// Tier rules give the rate of the paths they match, e.g. a cheaper archive mount, and use the
+/// protect list syntax (see [`rule_globs`]) followed by the rate. The first matching rule wins;
+/// other paths are charged the default rate of `--cost-per-gb`, or nothing without one.
+/// Amounts are in whatever currency the rates are given in.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::cost::CostModel;
+///
+/// let costs = CostModel::parse("/mnt/archive 0.004", Some(0.023)).unwrap();
+/// assert_eq!(costs.rate("/mnt/archive/2019.tar"), Some(0.004));
+/// assert_eq!(costs.rate("/srv/db/base.dat"), Some(0.023));
+/// assert!((costs.monthly("/srv/db/base.dat", 100 << 30).unwrap() - 2.3).abs() < 1e-9);
+///
+/// let tiers_only = CostModel::parse("/mnt/archive 0.004", None).unwrap();
+/// assert_eq!(tiers_only.monthly("/srv/db/base.dat", 100 << 30), None);
+/// assert!(CostModel::parse("/mnt/archive cheap", None).is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CostModel {
+    default_rate: Option<f64>,
+    rates: Vec<f64>,
+    // Every rule is compiled to two globs: the rule itself and everything beneath it
+    set: GlobSet,
+}
+
+impl CostModel {
+    /// Parses a rates file: one `PATH RATE` rule per line, blank lines and `#` comments
+    /// ignored, with `default_rate` for the paths no rule matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first rule without a valid, non-negative rate or with an
+    /// invalid glob.
+    pub fn parse(contents: &str, default_rate: Option<f64>) -> Result<CostModel, String> {
+        let mut builder = GlobSetBuilder::new();
+        let mut rates = Vec::new();
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let invalid = |detail: String| format!("invalid cost rule '{}': {}", line, detail);
+            let mut words = line.split_whitespace();
+            let (Some(path), Some(rate), None) = (words.next(), words.next(), words.next()) else {
+                return Err(invalid("expected a path and a rate per GB".to_string()));
+            };
+            for glob in rule_globs(path) {
+                builder.add(Glob::new(&glob).map_err(|e| invalid(e.to_string()))?);
+            }
+            rates.push(parse_rate(rate).map_err(invalid)?);
+        }
+        let set = builder.build().map_err(|e| e.to_string())?;
+        Ok(CostModel {
+            default_rate,
+            rates,
+            set,
+        })
+    }
+
+    /// Loads and combines the system wide and per-user rates files, system rules first.
+    ///
+    /// The rules are read from `costs` files in [`storage::system_config_dir`] and
+    /// [`storage::config_dir`]; missing files are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a costs file exists but cannot be read or contains an invalid rule.
+    pub fn load(default_rate: Option<f64>) -> io::Result<CostModel> {
+        let mut contents = String::new();
+        for file in Self::files() {
+            match fs::read_to_string(&file) {
+                Ok(text) => {
+                    contents.push_str(&text);
+                    contents.push('\n');
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("could not read storage rates {}: {}", file.display(), e),
+                    ))
+                }
+            }
+        }
+        Self::parse(&contents, default_rate)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Locations of the system wide and per-user rates files.
+    pub fn files() -> Vec<PathBuf> {
+        [storage::system_config_dir(), storage::config_dir()]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(COSTS_FILE))
+            .collect()
+    }
+
+    /// Returns the monthly rate per GB of `path`, if any.
+    pub fn rate(&self, path: &str) -> Option<f64> {
+        self.set
+            .matches(path)
+            .first()
+            .map(|index| self.rates[index / 2])
+            .or(self.default_rate)
+    }
+
+    /// Returns the estimated monthly cost of storing `bytes` at `path`, if it has a rate.
+    pub fn monthly(&self, path: &str, bytes: u64) -> Option<f64> {
+        self.rate(path)
+            .map(|rate| bytes as f64 / BYTES_PER_GB * rate)
+    }
+
+    /// Whether any rate is known, i.e. costs are estimated at all.
+    pub fn is_enabled(&self) -> bool {
+        self.default_rate.is_some() || !self.rates.is_empty()
+    }
+}
+
+/// Parses a non-negative rate per GB, e.g. `0.023`.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::cost::parse_rate;
+///
+/// assert_eq!(parse_rate("0.023"), Ok(0.023));
+/// assert!(parse_rate("-1").is_err());
+/// assert!(parse_rate("$0.02").is_err());
+/// ```
+pub fn parse_rate(rate: &str) -> Result<f64, String> {
+    match rate.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate >= 0.0 => Ok(rate),
+        _ => Err(format!(
+            "invalid rate '{}': expected a non-negative number per GB, e.g. 0.023",
+            rate
+        )),
+    }
+}
+
+/// Estimated monthly costs of the groups of a report, e.g. per extension.
+///
+/// Costs are summed per file, so groups spanning several tiers are charged at each file's
+/// rate.
+#[derive(Debug)]
+pub struct GroupCosts<K> {
+    costs: CostModel,
+    totals: HashMap<K, f64>,
+}
+
+impl<K> Default for GroupCosts<K> {
+    fn default() -> Self {
+        GroupCosts {
+            costs: CostModel::default(),
+            totals: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq> GroupCosts<K> {
+    /// Creates empty group costs charged at the rates of `costs`.
+    pub fn new(costs: CostModel) -> Self {
+        GroupCosts {
+            costs,
+            totals: HashMap::new(),
+        }
+    }
+
+    /// Returns the monthly cost of `file`, if it has a rate.
+    pub fn monthly(&self, file: &FileInfo) -> Option<f64> {
+        self.costs.monthly(&file.path, file.size)
+    }
+
+    /// Adds `cost` to the group `key`.
+    pub fn add(&mut self, key: K, cost: f64) {
+        *self.totals.entry(key).or_default() += cost;
+    }
+
+    /// Returns the cost of the group `key`, or `None` if costs are not estimated.
+    pub fn get(&self, key: &K) -> Option<f64> {
+        self.costs
+            .is_enabled()
+            .then(|| self.totals.get(key).copied().unwrap_or_default())
+    }
+}
+
+/// Formats a monthly cost for people, e.g. `12.34/month`.
+pub fn format_monthly(cost: f64) -> String {
+    tr!("cost-monthly", cost = format!("{:.2}", cost))
+}
+
+/// Estimated monthly cost of every file of a scan, added to the reports whenever a rate is
+/// known.
+///
+/// Files without a rate (outside every tier, without `--cost-per-gb`) count as free.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::cost::{CostModel, CostTotal};
+/// use ferris_files::report::{Aggregator, FileInfo};
+///
+/// let mut total = CostTotal::new(CostModel::parse("/archive 0.004", Some(0.023)).unwrap());
+/// for (path, size) in [("/srv/db", 100 << 30), ("/archive/old.tar", 1000 << 30)] {
+///     total.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(total.finish().rows[0].fields, ["6.3000", "1181116006400"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct CostTotal {
+    costs: CostModel,
+    bytes: u64,
+    monthly: f64,
+}
+
+impl CostTotal {
+    /// Creates an empty total charged at the rates of `costs`.
+    pub fn new(costs: CostModel) -> Self {
+        CostTotal {
+            costs,
+            bytes: 0,
+            monthly: 0.0,
+        }
+    }
+}
+
+impl Aggregator for CostTotal {
+    fn observe(&mut self, file: &FileInfo) {
+        self.bytes += file.size;
+        if let Some(cost) = self.costs.monthly(&file.path, file.size) {
+            self.monthly += cost;
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        ReportSection {
+            heading: None,
+            rows: vec![ReportRow {
+                text: format!(
+                    "\n{}",
+                    tr!(
+                        "cost-total",
+                        cost = format_monthly(self.monthly),
+                        size = self.bytes.format_size()
+                    )
+                ),
+                kind: "cost-total".to_string(),
+                fields: vec![format!("{:.4}", self.monthly), self.bytes.to_string()],
+            }],
+        }
+    }
+}
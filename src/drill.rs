@@ -0,0 +1,224 @@
+use crate::i18n;
+use crate::report::{Aggregator, FileInfo, ReportSection};
+use crate::traits::ByteSize;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// The largest entry directly inside a directory.
+///
+/// * `path` - Path of the entry
+/// * `size` - Bytes of the file, or of everything beneath the directory
+/// * `is_dir` - Whether the entry is a directory that can be descended into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Child {
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// What the `drill` command needs of a scan: the size of every directory below the root and
+/// the largest file directly inside each, so the largest child of any directory is known
+/// without scanning again.
+///
+/// Unlike [`crate::export::ScanTree`] only one file per directory is kept, which bounds the
+/// memory by the number of directories.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::drill::{Child, DrillTree};
+/// use ferris_files::report::{Aggregator, FileInfo};
+/// use std::path::{Path, PathBuf};
+///
+/// let mut tree = DrillTree::new(Path::new("/srv"));
+/// for (path, size) in [("/srv/db/wal/1", 300), ("/srv/db/base", 200), ("/srv/big.iso", 400)] {
+///     tree.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(tree.size(Path::new("/srv")), 900);
+/// assert_eq!(
+///     tree.largest_child(Path::new("/srv")),
+///     Some(Child { path: PathBuf::from("/srv/db"), size: 500, is_dir: true })
+/// );
+/// assert_eq!(
+///     tree.largest_child(Path::new("/srv/db")),
+///     Some(Child { path: PathBuf::from("/srv/db/wal"), size: 300, is_dir: true })
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct DrillTree {
+    root: PathBuf,
+    sizes: HashMap<PathBuf, u64>,
+    subdirs: HashMap<PathBuf, Vec<PathBuf>>,
+    largest_files: HashMap<PathBuf, (PathBuf, u64)>,
+}
+
+impl DrillTree {
+    /// Creates an empty tree of the scan of `root`.
+    pub fn new(root: &Path) -> Self {
+        DrillTree {
+            root: root.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the bytes beneath `dir`, or 0 if it holds no sized file.
+    pub fn size(&self, dir: &Path) -> u64 {
+        self.sizes.get(dir).copied().unwrap_or_default()
+    }
+
+    /// Returns the largest file or directory directly inside `dir`, directories first among
+    /// equals, or `None` if it holds no sized file.
+    pub fn largest_child(&self, dir: &Path) -> Option<Child> {
+        let largest_dir = self
+            .subdirs
+            .get(dir)
+            .into_iter()
+            .flatten()
+            .map(|subdir| (subdir, self.size(subdir)))
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(path, size)| Child {
+                path: path.clone(),
+                size,
+                is_dir: true,
+            });
+        let largest_file = self.largest_files.get(dir).map(|(path, size)| Child {
+            path: path.clone(),
+            size: *size,
+            is_dir: false,
+        });
+        match (largest_dir, largest_file) {
+            (Some(dir), Some(file)) if file.size > dir.size => Some(file),
+            (Some(dir), _) => Some(dir),
+            (None, file) => file,
+        }
+    }
+}
+
+impl Aggregator for DrillTree {
+    fn observe(&mut self, file: &FileInfo) {
+        let path = Path::new(&file.path);
+        let Some(parent) = path
+            .parent()
+            .filter(|parent| parent.starts_with(&self.root))
+        else {
+            return;
+        };
+        match self.largest_files.get_mut(parent) {
+            Some((_, largest)) if *largest >= file.size => {}
+            Some(largest) => *largest = (path.to_path_buf(), file.size),
+            None => {
+                self.largest_files
+                    .insert(parent.to_path_buf(), (path.to_path_buf(), file.size));
+            }
+        }
+        for dir in path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+        {
+            match self.sizes.get_mut(dir) {
+                Some(size) => *size += file.size,
+                None => {
+                    self.sizes.insert(dir.to_path_buf(), file.size);
+                    if let Some(parent) = dir.parent().filter(|_| *dir != self.root) {
+                        self.subdirs
+                            .entry(parent.to_path_buf())
+                            .or_default()
+                            .push(dir.to_path_buf());
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        ReportSection {
+            heading: None,
+            rows: Vec::new(),
+        }
+    }
+}
+
+/// Walks down `tree` from its root: prints the largest child of the current directory to
+/// `out` and asks on `input` whether to descend into it, until a file, an empty directory, a
+/// declined prompt, the end of `input` or `levels` descents are reached.
+///
+/// An empty answer descends, so Enter can be held down the tree.
+///
+/// # Returns
+///
+/// * `io::Result<PathBuf>` - The directory the walk ended in
+///
+/// # Errors
+///
+/// Returns an error if writing to `out` or reading from `input` fails.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::drill::{drill_down, DrillTree};
+/// use ferris_files::report::{Aggregator, FileInfo};
+/// use std::path::Path;
+///
+/// let mut tree = DrillTree::new(Path::new("/srv"));
+/// for (path, size) in [("/srv/db/wal/1", 300), ("/srv/db/base", 200), ("/srv/www/index", 5)] {
+///     tree.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// let mut out = Vec::new();
+/// let end = drill_down(&tree, "\nn\n".as_bytes(), &mut out, None).unwrap();
+/// assert_eq!(end, Path::new("/srv/db"));
+/// ```
+pub fn drill_down(
+    tree: &DrillTree,
+    mut input: impl BufRead,
+    mut out: impl Write,
+    levels: Option<usize>,
+) -> io::Result<PathBuf> {
+    let mut current = tree.root.clone();
+    let mut depth = 0;
+    loop {
+        let Some(child) = tree.largest_child(&current) else {
+            writeln!(
+                out,
+                "{}",
+                tr!("drill-empty", dir = current.display().to_string())
+            )?;
+            return Ok(current);
+        };
+        let parent_size = tree.size(&current);
+        let share = if parent_size == 0 {
+            0.0
+        } else {
+            child.size as f64 * 100.0 / parent_size as f64
+        };
+        writeln!(
+            out,
+            "{:>10} {:>6.1}%  {}{}",
+            child.size.format_size(),
+            share,
+            child.path.display(),
+            if child.is_dir { "/" } else { "" }
+        )?;
+        if !child.is_dir {
+            writeln!(out, "{}", tr!("drill-leaf"))?;
+            return Ok(current);
+        }
+        if levels.is_some_and(|levels| depth >= levels) {
+            return Ok(current);
+        }
+
+        write!(out, "{} ", tr!("drill-descend"))?;
+        out.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            writeln!(out)?;
+            return Ok(current);
+        }
+        if !answer.trim().is_empty() && !i18n::is_yes(&answer) {
+            return Ok(current);
+        }
+        current = child.path;
+        depth += 1;
+    }
+}
@@ -89,3 +89,92 @@ impl std::error::Error for SearchError {
         }
     }
 }
+
+/// Error returned by [`crate::run`], telling apart which stage of a run failed so the command
+/// line and embedders can react to it precisely.
+///
+/// Every variant wraps the underlying error, whose message is displayed unchanged; the stage
+/// shows in the [exit code](FerrisError::exit_code) rather than in the text.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::errors::FerrisError;
+/// use std::io;
+///
+/// let err = FerrisError::action(io::Error::other("archive.tar already exists"));
+/// assert_eq!(err.to_string(), "archive.tar already exists");
+/// assert_eq!(err.exit_code(), 6);
+/// assert!(matches!(err, FerrisError::Action(_)));
+/// ```
+#[derive(Debug)]
+pub enum FerrisError {
+    /// The arguments or a configuration file (protect list, alerts, tags, ...) are invalid.
+    Config(Box<dyn std::error::Error>),
+
+    /// The tree could not be walked, or the inventory given to `--import` could not be read.
+    Scan(Box<dyn std::error::Error>),
+
+    /// The results could not be written: the session, a snapshot, an export or the output.
+    Report(Box<dyn std::error::Error>),
+
+    /// An action on the results, such as moving or archiving them, failed.
+    Action(Box<dyn std::error::Error>),
+}
+
+impl FerrisError {
+    /// Wraps `err` as a [`FerrisError::Config`] error.
+    pub fn config(err: impl Into<Box<dyn std::error::Error>>) -> Self {
+        FerrisError::Config(err.into())
+    }
+
+    /// Wraps `err` as a [`FerrisError::Scan`] error.
+    pub fn scan(err: impl Into<Box<dyn std::error::Error>>) -> Self {
+        FerrisError::Scan(err.into())
+    }
+
+    /// Wraps `err` as a [`FerrisError::Report`] error.
+    pub fn report(err: impl Into<Box<dyn std::error::Error>>) -> Self {
+        FerrisError::Report(err.into())
+    }
+
+    /// Wraps `err` as a [`FerrisError::Action`] error.
+    pub fn action(err: impl Into<Box<dyn std::error::Error>>) -> Self {
+        FerrisError::Action(err.into())
+    }
+
+    /// Returns the exit status the command line ends with: 2 for configuration errors, 4 for
+    /// scan errors, 5 for report errors and 6 for action errors. 1 stays the status of other
+    /// failures and 3 that of [alerts](crate::alerts::ALERT_EXIT_CODE).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FerrisError::Config(_) => 2,
+            FerrisError::Scan(_) => 4,
+            FerrisError::Report(_) => 5,
+            FerrisError::Action(_) => 6,
+        }
+    }
+}
+
+impl std::fmt::Display for FerrisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FerrisError::Config(e)
+            | FerrisError::Scan(e)
+            | FerrisError::Report(e)
+            | FerrisError::Action(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FerrisError {
+    /// Returns the wrapped error.
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FerrisError::Config(e)
+            | FerrisError::Scan(e)
+            | FerrisError::Report(e)
+            | FerrisError::Action(e) => Some(e.as_ref()),
+        }
+    }
+}
@@ -0,0 +1,627 @@
+use crate::console;
+use crate::storage;
+use crate::report::{Aggregator, FileInfo, ReportRow, ReportSection};
+use crate::traits::ByteSize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Major and minor version of the ncdu JSON export format that is written.
+const NCDU_VERSION: (u32, u32) = (1, 2);
+
+/// A directory of the scanned tree, with its files by name.
+#[derive(Debug, Default)]
+struct Directory {
+    directories: BTreeMap<String, Directory>,
+    files: BTreeMap<String, u64>,
+}
+
+/// The whole scanned tree below the root, for the outputs that need its hierarchy: the JSON
+/// export format of ncdu, so the scan can be browsed with `ncdu -f FILE`, nested treemap JSON
+/// for d3 and Plotly, and the tree printed by `--tree`.
+///
+/// Unlike the reports, which keep at most the top entries, this retains every sized file and
+/// the directories leading to it. Directories without any sized file, such as empty ones or
+/// those whose files all failed to be read, are left out. Files carry the disk usage
+/// ferris-files measured; their apparent size is not known. Paths outside the root are ignored.
+///
+/// The tree has no report of its own; [`Aggregator::finish`] returns an empty section.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::export::ScanTree;
+/// use ferris_files::import::{import_entries, ImportFormat};
+/// use std::path::Path;
+///
+/// let mut tree = ScanTree::new(Path::new("/data"));
+/// tree.add("/data/sub/b.iso", 20);
+/// tree.add("/data/a.txt", 10);
+///
+/// let mut export = Vec::new();
+/// tree.write_ncdu(&mut export, 1700000000).unwrap();
+/// let mut files = Vec::new();
+/// import_entries(ImportFormat::Ncdu, &export[..], |path, size| files.push((path, size))).unwrap();
+/// assert_eq!(
+///     files,
+///     [("/data/sub/b.iso".to_string(), 20), ("/data/a.txt".to_string(), 10)]
+/// );
+/// ```
+#[derive(Debug)]
+pub struct ScanTree {
+    root: PathBuf,
+    name: String,
+    tree: Directory,
+}
+
+impl ScanTree {
+    /// Creates an empty tree of the scan of `root`, named by its canonical path if it exists,
+    /// as the paths of the scanned files are.
+    pub fn new(root: &Path) -> Self {
+        let root = console::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        ScanTree {
+            name: root.to_string_lossy().into_owned(),
+            root,
+            tree: Directory::default(),
+        }
+    }
+
+    /// Adds the file at `path` with `size` bytes, creating the directories leading to it.
+    pub fn add(&mut self, path: &str, size: u64) {
+        let Ok(relative) = Path::new(path).strip_prefix(&self.root) else {
+            return;
+        };
+        let mut names: Vec<String> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let Some(file) = names.pop() else {
+            return;
+        };
+        let mut directory = &mut self.tree;
+        for name in names {
+            directory = directory.directories.entry(name).or_default();
+        }
+        directory.files.insert(file, size);
+    }
+
+    /// Writes the tree as an ncdu export to `out`, stamped with the Unix time `timestamp`.
+    ///
+    /// Files are given their size as `dsize`, the disk usage ncdu shows by default.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `out` fails.
+    pub fn write_ncdu(&self, mut out: impl Write, timestamp: u64) -> io::Result<()> {
+        let metadata = serde_json::json!({
+            "progname": env!("CARGO_PKG_NAME"),
+            "progver": env!("CARGO_PKG_VERSION"),
+            "timestamp": timestamp,
+        });
+        write!(out, "[{},{},{},", NCDU_VERSION.0, NCDU_VERSION.1, metadata)?;
+        write_directory(&mut out, &self.name, &self.tree)?;
+        writeln!(out, "]")
+    }
+
+    /// Writes the tree as an ncdu export to the file at `path`, replacing it if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn save_ncdu(&self, path: &Path, timestamp: u64) -> io::Result<()> {
+        storage::check_write(path)?;
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_ncdu(&mut out, timestamp)?;
+        out.flush()
+    }
+
+    /// Writes the tree to `out` as nested treemap JSON: every node has a `name` and a `value`
+    /// in bytes, and directories also have `children`, their subdirectories first.
+    ///
+    /// A directory's value is the total of everything beneath it, as Plotly expects with
+    /// `branchvalues: "total"`; d3 recomputes the same totals from the files with
+    /// `d3.hierarchy(data).sum(d => d.children ? 0 : d.value)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `out` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::export::ScanTree;
+    /// use std::path::Path;
+    ///
+    /// let mut tree = ScanTree::new(Path::new("/data"));
+    /// tree.add("/data/sub/b.iso", 20);
+    /// tree.add("/data/a.txt", 10);
+    ///
+    /// let mut export = Vec::new();
+    /// tree.write_treemap(&mut export).unwrap();
+    /// let root: serde_json::Value = serde_json::from_slice(&export).unwrap();
+    /// assert_eq!(root["value"], 30);
+    /// assert_eq!(root["children"][0], serde_json::json!({
+    ///     "name": "sub",
+    ///     "children": [{"name": "b.iso", "value": 20}],
+    ///     "value": 20
+    /// }));
+    /// assert_eq!(root["children"][1], serde_json::json!({"name": "a.txt", "value": 10}));
+    /// ```
+    pub fn write_treemap(&self, mut out: impl Write) -> io::Result<()> {
+        write_treemap_node(&mut out, &self.name, &self.tree)?;
+        writeln!(out)
+    }
+
+    /// Writes the tree as treemap JSON to the file at `path`, replacing it if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn save_treemap(&self, path: &Path) -> io::Result<()> {
+        storage::check_write(path)?;
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_treemap(&mut out)?;
+        out.flush()
+    }
+
+    /// Returns the section printed by `--tree`: the hierarchy below the root with the total
+    /// size of every node, children largest first, like `dust` or `tree --du`.
+    ///
+    /// Nodes deeper than `depth` levels below the root are left out, their size still counted
+    /// in their ancestors. Porcelain rows hold the size, the depth (0 for the root) and the
+    /// full path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::export::ScanTree;
+    /// use std::path::Path;
+    ///
+    /// let mut tree = ScanTree::new(Path::new("/srv"));
+    /// tree.add("/srv/db/base/1", 700);
+    /// tree.add("/srv/db/wal", 200);
+    /// tree.add("/srv/README", 1);
+    ///
+    /// let rows = tree.tree_section(Some(1)).rows;
+    /// let fields: Vec<_> = rows.iter().map(|row| row.fields.join(" ")).collect();
+    /// assert_eq!(fields, ["901 0 /srv", "900 1 /srv/db", "1 1 /srv/README"]);
+    /// assert!(rows[1].text.ends_with("├── db"));
+    /// assert!(rows[2].text.ends_with("└── README"));
+    /// ```
+    pub fn tree_section(&self, depth: Option<usize>) -> ReportSection {
+        let root = sized_node(&self.name, &self.tree);
+        let mut rows = vec![ReportRow {
+            text: format!("{:>10}  {}", root.size.format_size(), root.name),
+            kind: "tree".to_string(),
+            fields: vec![root.size.to_string(), "0".to_string(), root.name.clone()],
+        }];
+        tree_rows(&root, &self.root, "", 1, depth, &mut rows);
+        ReportSection {
+            heading: Some(tr!("report-tree")),
+            rows,
+        }
+    }
+}
+
+/// A node of the tree printed by `--tree`, with the total size beneath it.
+struct SizedNode {
+    name: String,
+    size: u64,
+    children: Vec<SizedNode>,
+}
+
+/// Sizes `directory` and everything beneath it, children ordered largest first and then by name.
+fn sized_node(name: &str, directory: &Directory) -> SizedNode {
+    let mut children: Vec<SizedNode> = directory
+        .directories
+        .iter()
+        .map(|(name, child)| sized_node(name, child))
+        .chain(directory.files.iter().map(|(name, &size)| SizedNode {
+            name: name.clone(),
+            size,
+            children: Vec::new(),
+        }))
+        .collect();
+    children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    SizedNode {
+        name: name.to_string(),
+        size: children.iter().map(|child| child.size).sum(),
+        children,
+    }
+}
+
+/// Adds a row for every child of `node` at `level` and below, down to `depth` levels, drawing
+/// the branches after `prefix`.
+fn tree_rows(
+    node: &SizedNode,
+    path: &Path,
+    prefix: &str,
+    level: usize,
+    depth: Option<usize>,
+    rows: &mut Vec<ReportRow>,
+) {
+    if depth.is_some_and(|depth| level > depth) {
+        return;
+    }
+    for (i, child) in node.children.iter().enumerate() {
+        let last = i + 1 == node.children.len();
+        let child_path = path.join(&child.name);
+        rows.push(ReportRow {
+            text: format!(
+                "{:>10}  {}{}{}",
+                child.size.format_size(),
+                prefix,
+                if last { "└── " } else { "├── " },
+                child.name
+            ),
+            kind: "tree".to_string(),
+            fields: vec![
+                child.size.to_string(),
+                level.to_string(),
+                child_path.to_string_lossy().into_owned(),
+            ],
+        });
+        let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        tree_rows(child, &child_path, &prefix, level + 1, depth, rows);
+    }
+}
+
+/// Writes `directory` as a treemap node, its value after its children so the totals can be
+/// summed while writing, and returns that value.
+fn write_treemap_node(out: &mut impl Write, name: &str, directory: &Directory) -> io::Result<u64> {
+    write!(out, "{{\"name\":{},\"children\":[", serde_json::json!(name))?;
+    let mut total = 0;
+    let mut first = true;
+    for (name, child) in &directory.directories {
+        if !std::mem::take(&mut first) {
+            write!(out, ",")?;
+        }
+        total += write_treemap_node(out, name, child)?;
+    }
+    for (name, size) in &directory.files {
+        if !std::mem::take(&mut first) {
+            write!(out, ",")?;
+        }
+        write!(
+            out,
+            "{}",
+            serde_json::json!({ "name": name, "value": size })
+        )?;
+        total += size;
+    }
+    write!(out, "],\"value\":{}}}", total)?;
+    Ok(total)
+}
+
+/// Writes `directory` as an ncdu directory array: `[{"name":...}, child, child, ...]`.
+fn write_directory(out: &mut impl Write, name: &str, directory: &Directory) -> io::Result<()> {
+    write!(out, "[{}", serde_json::json!({ "name": name }))?;
+    for (name, child) in &directory.directories {
+        write!(out, ",")?;
+        write_directory(out, name, child)?;
+    }
+    for (name, size) in &directory.files {
+        let file = serde_json::json!({ "name": name, "dsize": size });
+        write!(out, ",{}", file)?;
+    }
+    write!(out, "]")
+}
+
+impl Aggregator for ScanTree {
+    fn observe(&mut self, file: &FileInfo) {
+        self.add(&file.path, file.size);
+    }
+
+    fn finish(&self) -> ReportSection {
+        ReportSection {
+            heading: None,
+            rows: Vec::new(),
+        }
+    }
+}
+
+/// Rows buffered before they are written out as a Parquet row group.
+#[cfg(feature = "parquet")]
+pub const PARQUET_ROW_GROUP: usize = 131_072;
+
+/// Schema of [`ParquetExport`] files: one row per sized file.
+#[cfg(feature = "parquet")]
+const PARQUET_SCHEMA: &str = "
+message ferris_files_scan {
+    OPTIONAL BYTE_ARRAY host (STRING);
+    REQUIRED BYTE_ARRAY root (STRING);
+    REQUIRED BYTE_ARRAY path (STRING);
+    REQUIRED INT64 size (INTEGER(64,false));
+    OPTIONAL INT64 modified (TIMESTAMP(MILLIS,true));
+    OPTIONAL BYTE_ARRAY mount (STRING);
+}
+";
+
+/// Every sized file of a scan written to a Parquet file, for DuckDB, Spark and other analytics
+/// tools. Only available when ferris-files is built with the `parquet` feature.
+///
+/// Each row holds the host and root of the scan, the path, its size in bytes and its last
+/// modification as a UTC timestamp in milliseconds (null if unknown), so the exports of many
+/// hosts can be queried together. The mount point the file lives on partitions the rows of a
+/// root further (null unless the mounts are given with [`ParquetExport::with_mounts`]). Rows are written in row groups of [`PARQUET_ROW_GROUP`] as
+/// the scan goes, which keeps memory bounded on huge scans, and compressed with zstd.
+///
+/// Write errors cannot interrupt a scan; the first one stops the export and is returned by
+/// [`ParquetExport::close`]. With [`ParquetExport::with_reserve`] the free space at the
+/// destination is checked before each row group, and the export stops short of filling the
+/// disk instead: the rows written so far are closed into a valid file and
+/// [`ParquetExport::is_halted`] reports the cut. The export has no report of its own;
+/// [`Aggregator::finish`] returns an empty section.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::export::ParquetExport;
+/// use ferris_files::report::{Aggregator, FileInfo};
+///
+/// let mut export = ParquetExport::new(Vec::new(), Some("db-01"), "/srv").unwrap();
+/// export.observe(&FileInfo { path: "/srv/base.dat".to_string(), size: 200, modified: None, uid: None, accessed: None });
+/// let (parquet, rows) = export.close().unwrap();
+/// assert_eq!(rows, 1);
+/// assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+/// ```
+#[cfg(feature = "parquet")]
+pub struct ParquetExport<W: Write + Send> {
+    writer: parquet::file::writer::SerializedFileWriter<W>,
+    host: Option<parquet::data_type::ByteArray>,
+    root: parquet::data_type::ByteArray,
+    paths: Vec<parquet::data_type::ByteArray>,
+    sizes: Vec<i64>,
+    modified: Vec<i64>,
+    modified_levels: Vec<i16>,
+    mounts: Vec<crate::quota::MountInfo>,
+    // Files mostly arrive directory by directory, so the mount of the last one is kept
+    last_mount: Option<(PathBuf, Option<parquet::data_type::ByteArray>)>,
+    mount_points: Vec<parquet::data_type::ByteArray>,
+    mount_levels: Vec<i16>,
+    rows: u64,
+    error: Option<parquet::errors::ParquetError>,
+    reserve: Option<(std::path::PathBuf, u64)>,
+    halted: bool,
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetExport<BufWriter<File>> {
+    /// Creates the export of a scan of `root` on this host at `path`, replacing the file if it
+    /// exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created.
+    pub fn create(path: &Path, root: &str) -> io::Result<Self> {
+        crate::storage::check_write(path)?;
+        let file = BufWriter::new(File::create(path)?);
+        ParquetExport::new(file, crate::host::hostname().as_deref(), root)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl<W: Write + Send> ParquetExport<W> {
+    /// Starts an export of a scan of `root` on `host` to `out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Parquet header cannot be written.
+    pub fn new(
+        out: W,
+        host: Option<&str>,
+        root: &str,
+    ) -> Result<Self, parquet::errors::ParquetError> {
+        use parquet::basic::{Compression, ZstdLevel};
+        use parquet::file::properties::WriterProperties;
+        use std::sync::Arc;
+
+        let schema = Arc::new(parquet::schema::parser::parse_message_type(PARQUET_SCHEMA)?);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_created_by(format!(
+                "{} {}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build();
+        Ok(ParquetExport {
+            writer: parquet::file::writer::SerializedFileWriter::new(
+                out,
+                schema,
+                Arc::new(properties),
+            )?,
+            host: host.map(Into::into),
+            root: root.into(),
+            paths: Vec::with_capacity(PARQUET_ROW_GROUP),
+            sizes: Vec::with_capacity(PARQUET_ROW_GROUP),
+            modified: Vec::new(),
+            modified_levels: Vec::with_capacity(PARQUET_ROW_GROUP),
+            mounts: Vec::new(),
+            last_mount: None,
+            mount_points: Vec::new(),
+            mount_levels: Vec::with_capacity(PARQUET_ROW_GROUP),
+            rows: 0,
+            error: None,
+            reserve: None,
+            halted: false,
+        })
+    }
+
+    /// Stops the export once the filesystem holding `dir` has less than `min_free` bytes
+    /// available, checked before each row group is written. Where free space cannot be
+    /// queried the export carries on.
+    pub fn with_reserve(mut self, dir: &Path, min_free: u64) -> Self {
+        self.reserve = Some((dir.to_path_buf(), min_free));
+        self
+    }
+
+    /// Records the mount point of every file among `mounts` (see
+    /// [`crate::quota::system_mounts`]).
+    pub fn with_mounts(mut self, mounts: Vec<crate::quota::MountInfo>) -> Self {
+        self.mounts = mounts;
+        self
+    }
+
+    /// Returns the mount point of the directory `dir`, if known.
+    fn mount_of(&mut self, dir: &Path) -> Option<parquet::data_type::ByteArray> {
+        if let Some((last, mount)) = &self.last_mount {
+            if last == dir {
+                return mount.clone();
+            }
+        }
+        let mount = crate::quota::mount_for_path(&self.mounts, dir)
+            .map(|mount| mount.mount_point.to_string_lossy().as_ref().into());
+        self.last_mount = Some((dir.to_path_buf(), mount.clone()));
+        mount
+    }
+
+    /// Returns true if the export stopped early because free space ran low.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Returns true if the destination has dropped below the reserve given to
+    /// [`ParquetExport::with_reserve`].
+    fn space_low(&self) -> bool {
+        self.reserve.as_ref().is_some_and(|(dir, min_free)| {
+            crate::reconcile::fs_usage(dir).is_ok_and(|usage| usage.available_bytes < *min_free)
+        })
+    }
+
+    /// Writes the buffered rows as a row group, or drops them and halts the export if free
+    /// space at the destination ran low.
+    fn write_row_group(&mut self) -> Result<(), parquet::errors::ParquetError> {
+        use parquet::data_type::{ByteArrayType, Int64Type};
+
+        let rows = self.paths.len();
+        if rows == 0 || self.halted {
+            return Ok(());
+        }
+        if self.space_low() {
+            self.halted = true;
+            self.paths = Vec::new();
+            self.sizes = Vec::new();
+            self.modified = Vec::new();
+            self.modified_levels = Vec::new();
+            self.mount_points = Vec::new();
+            self.mount_levels = Vec::new();
+            return Ok(());
+        }
+        let hosts = vec![self.host.clone().unwrap_or_default(); rows];
+        let host_levels = vec![i16::from(self.host.is_some()); rows];
+        let roots = vec![self.root.clone(); rows];
+        let mut row_group = self.writer.next_row_group()?;
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            match index {
+                0 => {
+                    let hosts = if self.host.is_some() { &hosts[..] } else { &[] };
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(hosts, Some(&host_levels), None)?;
+                }
+                1 => {
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&roots, None, None)?;
+                }
+                2 => {
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&self.paths, None, None)?;
+                }
+                3 => {
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&self.sizes, None, None)?;
+                }
+                4 => {
+                    column.typed::<Int64Type>().write_batch(
+                        &self.modified,
+                        Some(&self.modified_levels),
+                        None,
+                    )?;
+                }
+                _ => {
+                    column.typed::<ByteArrayType>().write_batch(
+                        &self.mount_points,
+                        Some(&self.mount_levels),
+                        None,
+                    )?;
+                }
+            }
+            column.close()?;
+            index += 1;
+        }
+        row_group.close()?;
+        self.rows += rows as u64;
+        self.paths.clear();
+        self.sizes.clear();
+        self.modified.clear();
+        self.modified_levels.clear();
+        self.mount_points.clear();
+        self.mount_levels.clear();
+        Ok(())
+    }
+
+    /// Writes the remaining rows and the Parquet footer, and returns the output with the
+    /// number of rows written. A halted export only gets its footer, so the file stays
+    /// readable.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error writing the export, during the scan or now.
+    pub fn close(mut self) -> io::Result<(W, u64)> {
+        let to_io = |e: parquet::errors::ParquetError| io::Error::other(e.to_string());
+        if let Some(error) = self.error.take() {
+            return Err(to_io(error));
+        }
+        self.write_row_group().map_err(to_io)?;
+        let mut out = self.writer.into_inner().map_err(to_io)?;
+        out.flush()?;
+        Ok((out, self.rows))
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl<W: Write + Send> Aggregator for ParquetExport<W> {
+    fn observe(&mut self, file: &FileInfo) {
+        if self.error.is_some() || self.halted {
+            return;
+        }
+        self.paths.push(file.path.as_str().into());
+        // Sizes beyond i64::MAX do not occur; the column is annotated as unsigned anyway
+        self.sizes.push(file.size as i64);
+        let modified = file.modified.and_then(|modified| {
+            let millis = match modified.duration_since(std::time::UNIX_EPOCH) {
+                Ok(after) => i64::try_from(after.as_millis()).ok()?,
+                Err(before) => -i64::try_from(before.duration().as_millis()).ok()?,
+            };
+            Some(millis)
+        });
+        self.modified_levels.push(i16::from(modified.is_some()));
+        self.modified.extend(modified);
+        let dir = Path::new(&file.path).parent().unwrap_or(Path::new(""));
+        let mount = self.mount_of(dir);
+        self.mount_levels.push(i16::from(mount.is_some()));
+        self.mount_points.extend(mount);
+        if self.paths.len() >= PARQUET_ROW_GROUP {
+            if let Err(e) = self.write_row_group() {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        ReportSection {
+            heading: None,
+            rows: Vec::new(),
+        }
+    }
+}
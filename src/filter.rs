@@ -0,0 +1,237 @@
+use crate::protect::rule_globs;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Path globs given on the command line, such as the files a scan is limited to with
+/// `--include` or the directories it leaves out with `--exclude`.
+///
+/// Each pattern follows the protect list syntax (see [`rule_globs`]): patterns without a `/`
+/// match a name anywhere (e.g. `*.log`), others the path, and a matching directory matches
+/// everything beneath it. Alternatives such as `*.{mp4,mkv}` cover a category of files at once.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::filter::GlobFilter;
+///
+/// let filter = GlobFilter::new(["*.log", "*.{mp4,mkv}", "/srv/dumps"]).unwrap();
+/// assert!(filter.is_match("/var/log/syslog.log"));
+/// assert!(filter.is_match("/home/ann/Videos/trip.mkv"));
+/// assert!(filter.is_match("/srv/dumps/2026/db.sql"));
+/// assert!(!filter.is_match("/home/ann/notes.txt"));
+/// assert!(GlobFilter::new(["*.{log"]).is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GlobFilter {
+    patterns: Vec<String>,
+    // Every pattern is compiled to two globs: the pattern itself and everything beneath it
+    set: GlobSet,
+}
+
+impl GlobFilter {
+    /// Compiles the `patterns`; without any, the filter matches nothing and is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first pattern that is not a valid glob.
+    pub fn new<I, S>(patterns: I) -> Result<GlobFilter, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = GlobSetBuilder::new();
+        let mut kept = Vec::new();
+        for pattern in patterns {
+            let pattern = pattern.as_ref().trim();
+            for glob in rule_globs(pattern) {
+                builder.add(
+                    Glob::new(&glob).map_err(|e| format!("invalid glob '{}': {}", pattern, e))?,
+                );
+            }
+            kept.push(pattern.to_string());
+        }
+        let set = builder.build().map_err(|e| e.to_string())?;
+        Ok(GlobFilter {
+            patterns: kept,
+            set,
+        })
+    }
+
+    /// Whether no pattern was given.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// The patterns as given, in order.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether any pattern matches `path`.
+    pub fn is_match(&self, path: impl AsRef<Path>) -> bool {
+        self.set.is_match(path)
+    }
+}
+
+/// Returns the root of the git repository enclosing `dir`: the nearest directory at or above it
+/// holding a `.git` directory (or a `.git` file, as in worktrees and submodules).
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::filter::repo_root;
+///
+/// let repo = std::env::temp_dir().join(format!("ferris-files-doc-repo-{}", std::process::id()));
+/// std::fs::create_dir_all(repo.join(".git")).unwrap();
+/// std::fs::create_dir_all(repo.join("src/bin")).unwrap();
+/// assert_eq!(repo_root(&repo.join("src/bin")), Some(repo.as_path()));
+/// std::fs::remove_dir_all(&repo).unwrap();
+/// ```
+pub fn repo_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|dir| dir.join(".git").exists())
+}
+
+/// Files holding the ignore rules of git, in increasing precedence, honored with
+/// `--respect-gitignore`.
+pub const GITIGNORE_FILES: [&str; 2] = [".git/info/exclude", ".gitignore"];
+
+/// File marking parts of a tree as uninteresting to every scan, in the syntax of `.gitignore`.
+pub const FERRISIGNORE_FILE: &str = ".ferrisignore";
+
+/// The ignore rules of a tree: the `.ferrisignore` files every scan honors (see
+/// [`FERRISIGNORE_FILE`]) and, with `--respect-gitignore`, those of git.
+///
+/// Any directory from a root down may hold rule files, which apply to everything beneath it
+/// in the syntax of `.gitignore` (a repository's `.git/info/exclude` applies to the directory
+/// holding `.git`). As in git, the rules of deeper directories take precedence, as do later
+/// files of the same directory, and the last rule matching a path decides, so `!` patterns
+/// include paths again. Rules above the roots are not read, and each directory's files are
+/// read once, the first time rules are asked for in or below it.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::filter::{IgnoreRules, FERRISIGNORE_FILE};
+///
+/// let root = std::env::temp_dir().join(format!("ferris-files-doc-ignore-{}", std::process::id()));
+/// std::fs::create_dir_all(root.join("app/vendor")).unwrap();
+/// std::fs::write(root.join(".ferrisignore"), "target/\n*.log\n").unwrap();
+/// std::fs::write(root.join("app/.ferrisignore"), "vendor\n!keep.log\n").unwrap();
+///
+/// let rules = IgnoreRules::new(std::slice::from_ref(&root), &[FERRISIGNORE_FILE]);
+/// assert!(rules.for_dir(&root).is_ignored(&root.join("target"), true));
+/// assert!(!rules.for_dir(&root).is_ignored(&root.join("target"), false));
+/// let app = rules.for_dir(&root.join("app"));
+/// assert!(app.is_ignored(&root.join("app/vendor"), true));
+/// assert!(app.is_ignored(&root.join("app/debug.log"), false));
+/// assert!(!app.is_ignored(&root.join("app/keep.log"), false));
+/// assert!(!app.is_ignored(&root.join("app/main.rs"), false));
+/// std::fs::remove_dir_all(&root).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct IgnoreRules {
+    roots: Vec<PathBuf>,
+    files: Vec<&'static str>,
+    // The rules of every directory seen so far, `None` for those without any
+    dirs: Mutex<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl IgnoreRules {
+    /// Creates the rules of the trees below the scan `roots` read from the rule `files` of their
+    /// directories (relative paths, in increasing precedence), reading nothing yet.
+    pub fn new(roots: &[PathBuf], files: &[&'static str]) -> Self {
+        IgnoreRules {
+            roots: roots.to_vec(),
+            files: files.to_vec(),
+            dirs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the rules applying to the entries of `dir`, a directory at or below a root.
+    pub fn for_dir(&self, dir: &Path) -> DirRules {
+        DirRules(
+            dir.ancestors()
+                .take_while(|dir| self.roots.iter().any(|root| dir.starts_with(root)))
+                .filter_map(|dir| self.load(dir))
+                .collect(),
+        )
+    }
+
+    /// Returns the rules of `dir` itself, reading them the first time.
+    fn load(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        if let Some(rules) = self.dirs.lock().unwrap_or_else(PoisonError::into_inner).get(dir) {
+            return rules.clone();
+        }
+        // Read without the lock; two threads reading the same files agree anyway
+        let rules = self.read(dir).map(Arc::new);
+        self.dirs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(dir.to_path_buf(), rules.clone());
+        rules
+    }
+
+    /// Compiles the rule files of `dir`, if it has any.
+    ///
+    /// Invalid lines are logged and left out, keeping the rest of the file.
+    fn read(&self, dir: &Path) -> Option<Gitignore> {
+        let files: Vec<PathBuf> = self
+            .files
+            .iter()
+            .map(|file| dir.join(file))
+            .filter(|file| file.is_file())
+            .collect();
+        if files.is_empty() {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        for file in &files {
+            if let Some(err) = builder.add(file) {
+                log::warn!(
+                    "{}",
+                    tr!(
+                        "ignore-rules-invalid",
+                        file = file.display().to_string(),
+                        error = err.to_string()
+                    )
+                );
+            }
+        }
+        builder
+            .build()
+            .map_err(|err| {
+                log::warn!(
+                    "{}",
+                    tr!(
+                        "ignore-rules-invalid",
+                        file = files[files.len() - 1].display().to_string(),
+                        error = err.to_string()
+                    )
+                )
+            })
+            .ok()
+    }
+}
+
+/// The ignore rules applying to the entries of one directory, deepest first (see
+/// [`IgnoreRules::for_dir`]).
+#[derive(Debug, Clone, Default)]
+pub struct DirRules(Vec<Arc<Gitignore>>);
+
+impl DirRules {
+    /// Whether the rules ignore `path`, an entry of the directory, and a directory if `is_dir`.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for rules in &self.0 {
+            match rules.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,289 @@
+use crate::actions::guard::Fingerprint;
+use crate::integration::shell_quote;
+use crate::results::{entry_id, Provenance, SavedEntry, SavedScan, SCHEMA_VERSION};
+use crate::wire::{FrameReader, Message};
+use rayon::prelude::*;
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// How often a running remote scan is checked for completion or timeout.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A host of a fleet: an ssh destination and, optionally, the directory to scan on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FleetHost {
+    pub destination: String,
+    pub directory: Option<String>,
+}
+
+/// Parses a host list: one `[user@]host[:/directory]` per line, blank lines and `#` comments
+/// ignored.
+///
+/// # Errors
+///
+/// Returns an error if the list names no host.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::fleet::parse_hosts;
+///
+/// let hosts = parse_hosts("# databases\ndb-01\nops@db-02:/srv/pg\n").unwrap();
+/// assert_eq!(hosts[0].destination, "db-01");
+/// assert_eq!(hosts[0].directory, None);
+/// assert_eq!(hosts[1].destination, "ops@db-02");
+/// assert_eq!(hosts[1].directory.as_deref(), Some("/srv/pg"));
+///
+/// assert!(parse_hosts("# nothing yet\n").is_err());
+/// ```
+pub fn parse_hosts(contents: &str) -> Result<Vec<FleetHost>, String> {
+    let hosts: Vec<FleetHost> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.split_once(":/") {
+            Some((destination, directory)) => FleetHost {
+                destination: destination.to_string(),
+                directory: Some(format!("/{}", directory)),
+            },
+            None => FleetHost {
+                destination: line.to_string(),
+                directory: None,
+            },
+        })
+        .collect();
+    if hosts.is_empty() {
+        return Err("the host list names no host".to_string());
+    }
+    Ok(hosts)
+}
+
+/// Reads a host list file (see [`parse_hosts`]).
+pub fn load_hosts(path: &Path) -> io::Result<Vec<FleetHost>> {
+    parse_hosts(&fs::read_to_string(path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+/// How the hosts of a fleet are scanned.
+///
+/// * `directory` - Directory scanned on hosts that do not name one
+/// * `num_entries` - Number of largest files each host reports
+/// * `jobs` - Number of hosts scanned at the same time
+/// * `timeout` - Optional time after which a host's scan is abandoned
+/// * `remote_command` - Command that runs ferris-files on the hosts, e.g. a full path or `sudo ferris-files`
+#[derive(Debug, Clone)]
+pub struct FleetOptions {
+    pub directory: String,
+    pub num_entries: usize,
+    pub jobs: usize,
+    pub timeout: Option<Duration>,
+    pub remote_command: String,
+}
+
+impl FleetOptions {
+    /// Returns the shell command run on `host` to scan it and stream the results back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::fleet::{FleetHost, FleetOptions};
+    ///
+    /// let options = FleetOptions {
+    ///     directory: "/".to_string(),
+    ///     num_entries: 50,
+    ///     jobs: 8,
+    ///     timeout: None,
+    ///     remote_command: "ferris-files".to_string(),
+    /// };
+    /// let host = FleetHost { destination: "db-01".to_string(), directory: Some("/srv/it's".to_string()) };
+    /// assert_eq!(
+    ///     options.remote_command(&host),
+    ///     r"ferris-files -d '/srv/it'\''s' -n 50 --format wire"
+    /// );
+    /// ```
+    pub fn remote_command(&self, host: &FleetHost) -> String {
+        format!(
+            "{} -d {} -n {} --format wire",
+            self.remote_command,
+            shell_quote(host.directory.as_deref().unwrap_or(&self.directory)),
+            self.num_entries
+        )
+    }
+}
+
+/// The results a remote agent streamed back.
+///
+/// * `session` - The host's ranked files, as a session
+/// * `files` / `failed` - Files the agent came across and could not size
+#[derive(Debug)]
+pub struct RemoteScan {
+    pub session: SavedScan,
+    pub files: u64,
+    pub failed: u64,
+}
+
+/// The result of scanning one host.
+///
+/// * `scan` - What the host reported, or why it could not be obtained
+/// * `elapsed` - Time from connecting until the scan finished or failed
+#[derive(Debug)]
+pub struct HostOutcome {
+    pub host: FleetHost,
+    pub scan: Result<RemoteScan, String>,
+    pub elapsed: Duration,
+}
+
+/// Scans every host over ssh, up to `options.jobs` at the same time, and returns their
+/// outcomes in the order of `hosts`.
+///
+/// A host that cannot be reached, fails, times out or sends an incomplete stream is reported
+/// as failed without affecting the others. ssh runs in batch mode, so hosts that would prompt
+/// for a password fail instead of hanging.
+pub fn scan_fleet(hosts: &[FleetHost], options: &FleetOptions) -> io::Result<Vec<HostOutcome>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs.max(1))
+        .build()
+        .map_err(io::Error::other)?;
+    Ok(pool.install(|| {
+        hosts
+            .par_iter()
+            .map(|host| {
+                let started = Instant::now();
+                HostOutcome {
+                    host: host.clone(),
+                    scan: scan_host(host, options),
+                    elapsed: started.elapsed(),
+                }
+            })
+            .collect()
+    }))
+}
+
+/// Runs the remote scan of `host` and reads its wire stream.
+fn scan_host(host: &FleetHost, options: &FleetOptions) -> Result<RemoteScan, String> {
+    let mut child = Command::new("ssh")
+        .args(["-o", "BatchMode=yes", "--", &host.destination])
+        .arg(options.remote_command(host))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("ssh: {}", e))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let reader = thread::spawn(move || read_scan(BufReader::new(stdout)));
+    let errors = thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break Some(status),
+            None if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            None => thread::sleep(POLL_INTERVAL),
+        }
+    };
+    let scan = reader
+        .join()
+        .map_err(|_| "reading the results panicked".to_string())?;
+    let errors = errors.join().unwrap_or_default();
+
+    match status {
+        None => Err(tr!(
+            "fleet-timed-out",
+            seconds = options.timeout.unwrap_or_default().as_secs()
+        )),
+        Some(status) if !status.success() => Err(errors
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("ssh exited with {}", status))),
+        Some(_) => scan.map_err(|e| e.to_string()),
+    }
+}
+
+/// Reads a complete wire stream (see [`crate::wire`]) into a session and the agent's counts.
+///
+/// The session records the host, root and times announced by the agent. Entries keep their
+/// modification time, so `query --modified-within` works on them, but no scan-time identity,
+/// and entries of other hosts are refused by `clean` anyway.
+///
+/// # Errors
+///
+/// Returns an `InvalidData` error if the stream is malformed, and an `UnexpectedEof` error if
+/// it ended before the agent's `done` frame.
+pub fn read_scan(input: impl Read) -> io::Result<RemoteScan> {
+    let mut frames = FrameReader::new(input)?;
+    let mut scan = SavedScan {
+        schema_version: SCHEMA_VERSION,
+        provenance: Provenance::default(),
+        sources: Vec::new(),
+        entries: Vec::new(),
+        more: Vec::new(),
+        dirs: false,
+    };
+    while let Some(message) = frames.read_message()? {
+        match message {
+            Message::Hello {
+                host,
+                root,
+                tool_version,
+                started_at,
+            } => {
+                scan.provenance.host = host;
+                scan.provenance.root = root.into();
+                scan.provenance.tool_version = tool_version;
+                scan.provenance.started_at = started_at;
+            }
+            Message::Entries(entries) => {
+                scan.entries
+                    .extend(entries.into_iter().map(|entry| SavedEntry {
+                        id: entry_id(&entry.path),
+                        fingerprint: entry.modified.map(|secs| Fingerprint {
+                            len: entry.size,
+                            modified: Some(UNIX_EPOCH + Duration::from_secs(secs)),
+                            inode: None,
+                        }),
+                        path: entry.path,
+                        size: entry.size,
+                        host: None,
+                    }));
+            }
+            // Sessions keep no mounts; the entries carry their full paths
+            Message::Mounts(_) => {}
+            Message::Done {
+                files,
+                failed,
+                finished_at,
+                ..
+            } => {
+                scan.provenance.finished_at = finished_at;
+                return Ok(RemoteScan {
+                    session: scan,
+                    files,
+                    failed,
+                });
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "the wire stream ended before the scan finished",
+    ))
+}
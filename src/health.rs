@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Number of timeouts within one directory from which they are reported as a cluster.
+///
+/// A single timeout is usually a slow network or a busy disk; several in the same place point
+/// at sectors the drive keeps retrying.
+pub const TIMEOUT_CLUSTER: usize = 3;
+
+/// Kinds of scan errors that suggest a hardware problem rather than permissions or races.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HardwareError {
+    /// The device reported an I/O error (`EIO`, or a CRC / sector error on Windows)
+    Io,
+    /// The read timed out
+    Timeout,
+}
+
+/// Classifies an error of a scan read, returning `None` for errors unrelated to the hardware.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::health::{classify, HardwareError};
+/// use std::io;
+///
+/// let timeout = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+/// assert_eq!(classify(&timeout), Some(HardwareError::Timeout));
+/// let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+/// assert_eq!(classify(&denied), None);
+/// ```
+pub fn classify(err: &io::Error) -> Option<HardwareError> {
+    if err.kind() == io::ErrorKind::TimedOut {
+        return Some(HardwareError::Timeout);
+    }
+    let code = err.raw_os_error()?;
+
+    #[cfg(unix)]
+    {
+        if code == libc::EIO {
+            Some(HardwareError::Io)
+        } else if code == libc::ETIMEDOUT {
+            Some(HardwareError::Timeout)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        // ERROR_CRC, ERROR_SEEK, ERROR_SECTOR_NOT_FOUND, ERROR_READ_FAULT, ERROR_IO_DEVICE
+        const IO_ERRORS: [i32; 5] = [23, 25, 27, 30, 1117];
+        // ERROR_SEM_TIMEOUT
+        const TIMEOUT: i32 = 121;
+        if IO_ERRORS.contains(&code) {
+            Some(HardwareError::Io)
+        } else if code == TIMEOUT {
+            Some(HardwareError::Timeout)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = code;
+        None
+    }
+}
+
+/// Hardware-like errors counted in one directory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCounts {
+    pub io_errors: usize,
+    pub timeouts: usize,
+}
+
+/// A directory the scan had hardware-like trouble reading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suspect {
+    pub path: PathBuf,
+    pub counts: ErrorCounts,
+}
+
+/// Thread-safe record of the scan errors that hint at a failing disk, grouped by directory.
+///
+/// Errors on files count towards their parent directory, errors listing a directory towards
+/// the directory itself, so damage confined to one area of the disk shows up as a cluster.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::health::StorageHealth;
+/// use std::io;
+/// use std::path::Path;
+///
+/// let health = StorageHealth::default();
+/// let timeout = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+/// health.record(Path::new("/data/music"), true, &timeout);
+/// for name in ["a.jpg", "b.jpg", "c.jpg"] {
+///     health.record(&Path::new("/data/photos").join(name), false, &timeout);
+/// }
+///
+/// // A lone timeout is not worth a warning, three in one directory are
+/// let suspects = health.suspects();
+/// assert_eq!(suspects.len(), 1);
+/// assert_eq!(suspects[0].path, Path::new("/data/photos"));
+/// assert_eq!(suspects[0].counts.timeouts, 3);
+/// ```
+#[derive(Debug, Default)]
+pub struct StorageHealth {
+    errors: Mutex<HashMap<PathBuf, ErrorCounts>>,
+}
+
+impl StorageHealth {
+    /// Records `err`, raised while reading `path`, if it suggests a hardware problem.
+    pub fn record(&self, path: &Path, is_dir: bool, err: &io::Error) {
+        let Some(kind) = classify(err) else {
+            return;
+        };
+        let area = if is_dir {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        let mut errors = self.errors.lock().unwrap_or_else(PoisonError::into_inner);
+        let counts = errors.entry(area.to_path_buf()).or_default();
+        match kind {
+            HardwareError::Io => counts.io_errors += 1,
+            HardwareError::Timeout => counts.timeouts += 1,
+        }
+    }
+
+    /// Returns the directories worth a warning, those with the most errors first.
+    ///
+    /// Any I/O error qualifies, while timeouts only do once [`TIMEOUT_CLUSTER`] of them
+    /// occurred in the same directory.
+    pub fn suspects(&self) -> Vec<Suspect> {
+        let mut suspects: Vec<Suspect> = self
+            .errors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|(_, counts)| counts.io_errors > 0 || counts.timeouts >= TIMEOUT_CLUSTER)
+            .map(|(path, counts)| Suspect {
+                path: path.clone(),
+                counts: *counts,
+            })
+            .collect();
+        suspects.sort_by(|a, b| {
+            (b.counts.io_errors + b.counts.timeouts)
+                .cmp(&(a.counts.io_errors + a.counts.timeouts))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        suspects
+    }
+}
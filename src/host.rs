@@ -0,0 +1,32 @@
+/// Returns the name of the machine running the scan, if it can be determined.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::host::hostname;
+///
+/// if let Some(name) = hostname() {
+///     assert!(!name.is_empty());
+/// }
+/// ```
+pub fn hostname() -> Option<String> {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        // SAFETY: the buffer is valid for its full length and gethostname NUL terminates
+        // the name when it fits
+        let rc = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+        if rc != 0 {
+            return None;
+        }
+        let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+        let name = String::from_utf8_lossy(&buffer[..end]).into_owned();
+        (!name.is_empty()).then_some(name)
+    }
+    #[cfg(not(unix))]
+    {
+        std::env::var("COMPUTERNAME")
+            .ok()
+            .filter(|name| !name.is_empty())
+    }
+}
@@ -0,0 +1,154 @@
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::FluentResource;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentArgs;
+
+/// Message catalogs compiled into the binary, keyed by language. English comes first and is
+/// the fallback for messages missing from another catalog.
+const CATALOGS: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en/ferris-files.ftl")),
+    ("es", include_str!("../locales/es/ferris-files.ftl")),
+];
+
+/// Catalog used to render messages, chosen once by [`init`] (English until then).
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// Bundles consulted in order: the chosen language, then English.
+struct Catalog {
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Catalog {
+    /// Loads the catalog of `language`, falling back to English.
+    fn new(language: &str) -> Self {
+        let mut bundles = Vec::new();
+        for (code, source) in CATALOGS {
+            if code != language && code != "en" {
+                continue;
+            }
+            let id: LanguageIdentifier = code.parse().expect("catalog language codes are valid");
+            let resource = FluentResource::try_new(source.to_string())
+                .expect("bundled message catalogs are valid Fluent");
+            let mut bundle = FluentBundle::new_concurrent(vec![id]);
+            // Unicode isolation marks around arguments show up as garbage in many terminals
+            bundle.set_use_isolating(false);
+            bundle
+                .add_resource(resource)
+                .expect("bundled message catalogs have no duplicate messages");
+            if code == language {
+                bundles.insert(0, bundle);
+            } else {
+                bundles.push(bundle);
+            }
+        }
+        Catalog { bundles }
+    }
+
+    /// Formats message `id`, or returns the id itself if no catalog has it.
+    fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        for bundle in &self.bundles {
+            let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else {
+                continue;
+            };
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, args, &mut errors);
+            if !errors.is_empty() {
+                log::debug!("Errors formatting message {}: {:?}", id, errors);
+            }
+            return text.into_owned();
+        }
+        id.to_string()
+    }
+}
+
+/// Returns the primary language subtag of a locale such as `es-MX`, `es_ES.UTF-8` or `en`.
+fn primary_language(locale: &str) -> String {
+    locale
+        .split(['-', '_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Returns the supported language matching `locale`, if any.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::i18n::supported_language;
+///
+/// assert_eq!(supported_language("es_AR.UTF-8"), Some("es"));
+/// assert_eq!(supported_language("en-GB"), Some("en"));
+/// assert_eq!(supported_language("fr-FR"), None);
+/// ```
+pub fn supported_language(locale: &str) -> Option<&'static str> {
+    let language = primary_language(locale);
+    CATALOGS
+        .iter()
+        .map(|(code, _)| *code)
+        .find(|code| *code == language)
+}
+
+/// Chooses the language of all further output.
+///
+/// An explicit `lang` (from `--lang`) wins; otherwise the first supported language among the
+/// user's preferred locales (`LC_ALL`, `LC_MESSAGES`, `LANG` on Unix, the user's display
+/// languages on Windows and macOS) is used, and English if none is supported. Only the first
+/// call has an effect.
+pub fn init(lang: Option<&str>) {
+    let language = match lang {
+        Some(lang) => supported_language(lang).unwrap_or_else(|| {
+            log::warn!("No messages are available in {}; using English", lang);
+            "en"
+        }),
+        None => sys_locale::get_locales()
+            .find_map(|locale| supported_language(&locale))
+            .unwrap_or("en"),
+    };
+    CATALOG.get_or_init(|| Catalog::new(language));
+}
+
+/// Formats message `id` of the current catalog with `args`.
+///
+/// Prefer the [`tr!`](crate::tr) macro, which builds the arguments.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    CATALOG.get_or_init(|| Catalog::new("en")).format(id, args)
+}
+
+/// Returns true if `answer` to a yes/no prompt means yes in the current language.
+///
+/// English answers are accepted in every language.
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    message("answer-yes", None)
+        .split(',')
+        .chain(["y", "yes"])
+        .any(|yes| yes.trim() == answer)
+}
+
+/// Formats a message of the current catalog, with optional `name = value` arguments.
+///
+/// Values may be anything convertible into a Fluent value (strings and numbers); paths and
+/// sizes are passed pre-formatted.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::tr;
+///
+/// assert_eq!(tr!("full-scan-skipped"), "Full scan skipped");
+/// assert_eq!(tr!("saved-session", path = "srv.json"), "Saved session to srv.json");
+/// ```
+#[macro_export]
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
@@ -0,0 +1,304 @@
+use crate::wire::{FrameReader, Message};
+use clap::ValueEnum;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Formats of foreign file inventories that can be imported instead of scanning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// Output of `du -ab`: apparent size in bytes, a tab, and the path (files and directories)
+    Du,
+    /// Output of `find ROOT -type f -printf '%s\t%p\n'`: size in bytes, a tab, and the path
+    Find,
+    /// JSON export written by `ncdu -o FILE`
+    Ncdu,
+    /// Stream written by `ferris-files --format wire`, e.g. on another host over ssh
+    Wire,
+}
+
+/// Opens an inventory file, with `-` meaning standard input.
+pub fn open_inventory(path: &Path) -> io::Result<Box<dyn Read>> {
+    if path.as_os_str() == "-" {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+/// Splits a `<size>\t<path>` line.
+fn parse_size_line(line: &str) -> Option<(u64, &str)> {
+    let (size, path) = line.split_once('\t')?;
+    Some((size.trim().parse().ok()?, path))
+}
+
+/// Returns the parent of a `/` or `\` separated path as listed by du, if it has one.
+fn parent_of(path: &str) -> Option<&str> {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    let cut = trimmed.rfind(['/', '\\'])?;
+    Some(if cut == 0 {
+        &trimmed[..1]
+    } else {
+        &trimmed[..cut]
+    })
+}
+
+/// Streams the files of an inventory to `visit` as `(path, size)` without holding the
+/// whole inventory in memory.
+///
+/// For `du -ab` output, which also lists directories with their cumulative size, directories
+/// are recognized because du lists every directory after its contents; empty directories
+/// cannot be told apart from files. Lines that cannot be parsed are counted and skipped.
+///
+/// # Returns
+///
+/// * `io::Result<usize>` - Number of malformed lines or records that were skipped
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::import::{import_entries, ImportFormat};
+///
+/// let du = "10\t/data/a\n20\t/data/sub/b\n4116\t/data/sub\n4146\t/data\n";
+/// let mut files = Vec::new();
+/// let skipped = import_entries(ImportFormat::Du, du.as_bytes(), |path, size| {
+///     files.push((path, size))
+/// })
+/// .unwrap();
+/// assert_eq!(skipped, 0);
+/// assert_eq!(files, vec![("/data/a".to_string(), 10), ("/data/sub/b".to_string(), 20)]);
+/// ```
+pub fn import_entries(
+    format: ImportFormat,
+    reader: impl Read,
+    mut visit: impl FnMut(String, u64),
+) -> io::Result<usize> {
+    match format {
+        ImportFormat::Du | ImportFormat::Find => {
+            let mut skipped = 0;
+            let mut directories: HashSet<String> = HashSet::new();
+            for line in BufReader::new(reader).lines() {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+                let Some((size, path)) = parse_size_line(&line) else {
+                    skipped += 1;
+                    continue;
+                };
+                if format == ImportFormat::Du {
+                    if directories.remove(path) {
+                        // Its contents were listed already, so this is a directory total
+                        if let Some(parent) = parent_of(path) {
+                            directories.insert(parent.to_string());
+                        }
+                        continue;
+                    }
+                    if let Some(parent) = parent_of(path) {
+                        directories.insert(parent.to_string());
+                    }
+                }
+                visit(path.to_string(), size);
+            }
+            Ok(skipped)
+        }
+        ImportFormat::Ncdu => {
+            let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+            let mut skipped = 0;
+            NcduExport {
+                visit: &mut visit,
+                skipped: &mut skipped,
+            }
+            .deserialize(&mut deserializer)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Ok(skipped)
+        }
+        ImportFormat::Wire => {
+            let mut frames = FrameReader::new(BufReader::new(reader))?;
+            while let Some(message) = frames.read_message()? {
+                match message {
+                    Message::Entries(entries) => {
+                        for entry in entries {
+                            visit(entry.path, entry.size);
+                        }
+                    }
+                    Message::Done { .. } => return Ok(0),
+                    Message::Hello { .. } | Message::Mounts(_) => {}
+                }
+            }
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the wire stream ended before the scan finished",
+            ))
+        }
+    }
+}
+
+/// Receives the files found while walking an ncdu export.
+struct NcduSink<'a, F: FnMut(String, u64)> {
+    visit: &'a mut F,
+    skipped: &'a mut usize,
+}
+
+/// Top level of an ncdu export: `[major, minor, {metadata}, [root directory]]`.
+struct NcduExport<'a, F: FnMut(String, u64)> {
+    visit: &'a mut F,
+    skipped: &'a mut usize,
+}
+
+impl<'de, F: FnMut(String, u64)> DeserializeSeed<'de> for NcduExport<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(String, u64)> Visitor<'de> for NcduExport<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an ncdu export array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let major: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::custom("missing ncdu export version"))?;
+        if major != 1 {
+            return Err(de::Error::custom(format!(
+                "unsupported ncdu export version {}",
+                major
+            )));
+        }
+        // Minor version and metadata
+        seq.next_element::<IgnoredAny>()?;
+        seq.next_element::<IgnoredAny>()?;
+
+        let mut sink = NcduSink {
+            visit: self.visit,
+            skipped: self.skipped,
+        };
+        seq.next_element_seed(NcduDirectory {
+            sink: &mut sink,
+            parent: None,
+        })?
+        .ok_or_else(|| de::Error::custom("ncdu export has no root directory"))?;
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(())
+    }
+}
+
+/// Information object of an ncdu file or directory; only the fields used are read.
+#[derive(Deserialize)]
+struct NcduInfo {
+    name: String,
+    #[serde(default)]
+    asize: Option<u64>,
+    #[serde(default)]
+    dsize: Option<u64>,
+    #[serde(default)]
+    excluded: Option<String>,
+    #[serde(default)]
+    notreg: bool,
+}
+
+/// A directory of an ncdu export: `[{info}, child, child, ...]`.
+struct NcduDirectory<'s, 'a, F: FnMut(String, u64)> {
+    sink: &'s mut NcduSink<'a, F>,
+    parent: Option<&'s str>,
+}
+
+/// Joins an ncdu name onto its parent directory path.
+fn join_name(parent: Option<&str>, name: &str) -> String {
+    match parent {
+        None => name.to_string(),
+        Some(parent) if parent.ends_with('/') => format!("{}{}", parent, name),
+        Some(parent) => format!("{}/{}", parent, name),
+    }
+}
+
+impl<'de, F: FnMut(String, u64)> DeserializeSeed<'de> for NcduDirectory<'_, '_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(String, u64)> Visitor<'de> for NcduDirectory<'_, '_, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an ncdu directory array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let info: NcduInfo = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::custom("ncdu directory without information"))?;
+        let path = join_name(self.parent, &info.name);
+
+        while seq
+            .next_element_seed(NcduChild {
+                sink: &mut *self.sink,
+                parent: &path,
+            })?
+            .is_some()
+        {}
+        Ok(())
+    }
+}
+
+/// A child of an ncdu directory: a file object or a nested directory array.
+struct NcduChild<'s, 'a, F: FnMut(String, u64)> {
+    sink: &'s mut NcduSink<'a, F>,
+    parent: &'s str,
+}
+
+impl<'de, F: FnMut(String, u64)> DeserializeSeed<'de> for NcduChild<'_, '_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, F: FnMut(String, u64)> Visitor<'de> for NcduChild<'_, '_, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an ncdu file object or directory array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<(), A::Error> {
+        NcduDirectory {
+            sink: self.sink,
+            parent: Some(self.parent),
+        }
+        .visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<(), A::Error> {
+        let info = match NcduInfo::deserialize(de::value::MapAccessDeserializer::new(map)) {
+            Ok(info) => info,
+            Err(_) => {
+                *self.sink.skipped += 1;
+                return Ok(());
+            }
+        };
+        // Excluded entries and non-regular files carry no meaningful size
+        if info.excluded.is_some() || info.notreg {
+            return Ok(());
+        }
+        match info.dsize.or(info.asize) {
+            Some(size) => (self.sink.visit)(join_name(Some(self.parent), &info.name), size),
+            None => *self.sink.skipped += 1,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+use std::error::Error;
+
+/// Name of the registry keys (and menu verb) of the Explorer context-menu entry.
+#[cfg(windows)]
+const SHELL_VERB: &str = "FerrisFiles";
+
+/// Registry locations of the context-menu entry, for right-clicking a folder and the
+/// background of an open folder, with the placeholder Explorer substitutes for each.
+#[cfg(windows)]
+const SHELL_KEYS: [(&str, &str); 2] = [
+    (r"HKCU\Software\Classes\Directory\shell", "%1"),
+    (r"HKCU\Software\Classes\Directory\Background\shell", "%V"),
+];
+
+/// Command line run by the context-menu entry for the folder Explorer substitutes for
+/// `placeholder`, keeping the console open on the results.
+#[cfg(any(windows, test))]
+pub(crate) fn menu_command(exe: &str, placeholder: &str) -> String {
+    // cmd strips the outer pair of quotes from the /k command line
+    format!(r#"cmd.exe /k ""{}" -d "{}"""#, exe, placeholder)
+}
+
+/// Runs `reg.exe` with `args`, turning a failure exit code into an error.
+#[cfg(windows)]
+fn reg(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let output = std::process::Command::new("reg").args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(tr!(
+            "reg-failed",
+            command = args[0],
+            error = String::from_utf8_lossy(&output.stderr).trim().to_string()
+        )
+        .into())
+    }
+}
+
+/// Registers a "Find largest files here" entry in the Explorer context menu of folders.
+///
+/// The entry opens a console running a scan of the folder, which stays open so the results
+/// can be read. It is registered for the current user only, so no administrator rights are
+/// needed; running the command again updates the entry, e.g. after moving the executable.
+///
+/// # Errors
+///
+/// Returns an error on platforms other than Windows, if the path of the running executable
+/// cannot be determined, or if the registry cannot be written.
+pub fn install_shell_integration() -> Result<(), Box<dyn Error>> {
+    #[cfg(windows)]
+    {
+        let exe = std::env::current_exe()?;
+        let exe = exe.to_string_lossy();
+        let label = tr!("shell-menu-label");
+        for (parent, placeholder) in SHELL_KEYS {
+            let key = format!(r"{}\{}", parent, SHELL_VERB);
+            let command = menu_command(&exe, placeholder);
+            reg(&["add", &key, "/ve", "/d", &label, "/f"])?;
+            reg(&["add", &key, "/v", "Icon", "/d", &exe, "/f"])?;
+            reg(&[
+                "add",
+                &format!(r"{}\command", key),
+                "/ve",
+                "/d",
+                &command,
+                "/f",
+            ])?;
+        }
+        println!("{}", tr!("shell-installed", label = label));
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    {
+        Err(tr!("windows-only", command = "install-shell-integration").into())
+    }
+}
+
+/// Removes the context-menu entry added by [`install_shell_integration`].
+///
+/// # Errors
+///
+/// Returns an error on platforms other than Windows, or if the registry cannot be written.
+pub fn uninstall_shell_integration() -> Result<(), Box<dyn Error>> {
+    #[cfg(windows)]
+    {
+        let mut removed = false;
+        for (parent, _) in SHELL_KEYS {
+            let key = format!(r"{}\{}", parent, SHELL_VERB);
+            // A missing key means the entry was never installed there
+            if reg(&["query", &key]).is_ok() {
+                reg(&["delete", &key, "/f"])?;
+                removed = true;
+            }
+        }
+        if removed {
+            println!("{}", tr!("shell-removed"));
+        } else {
+            println!("{}", tr!("shell-not-installed"));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    {
+        Err(tr!("windows-only", command = "uninstall-shell-integration").into())
+    }
+}
+
+/// Name of the Finder quick action, as shown in the Quick Actions and Services menus.
+#[cfg(target_os = "macos")]
+const QUICK_ACTION_NAME: &str = "Find Largest Files";
+
+/// Info.plist of the quick action bundle: a service for folders selected in Finder.
+#[cfg(target_os = "macos")]
+const QUICK_ACTION_INFO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSMenuItem</key>
+			<dict>
+				<key>default</key>
+				<string>{name}</string>
+			</dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict>
+				<key>NSApplicationIdentifier</key>
+				<string>com.apple.finder</string>
+			</dict>
+			<key>NSSendFileTypes</key>
+			<array>
+				<string>public.folder</string>
+			</array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#;
+
+/// Automator document of the quick action: a single "Run Shell Script" action receiving the
+/// selected folders as arguments.
+#[cfg(target_os = "macos")]
+const QUICK_ACTION_WORKFLOW: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>523</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Optional</key>
+					<true/>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.string</string>
+					</array>
+				</dict>
+				<key>AMActionVersion</key>
+				<string>2.0.3</string>
+				<key>AMApplication</key>
+				<array>
+					<string>Automator</string>
+				</array>
+				<key>AMProvides</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.string</string>
+					</array>
+				</dict>
+				<key>ActionBundlePath</key>
+				<string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key>
+				<string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<string>{script}</string>
+					<key>CheckedForUserDefaultShell</key>
+					<true/>
+					<key>inputMethod</key>
+					<integer>1</integer>
+					<key>shell</key>
+					<string>/bin/bash</string>
+					<key>source</key>
+					<string></string>
+				</dict>
+				<key>BundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+				<key>CFBundleVersion</key>
+				<string>2.0.3</string>
+				<key>CanShowSelectedItemsWhenRun</key>
+				<false/>
+				<key>CanShowWhenRun</key>
+				<true/>
+				<key>Category</key>
+				<array>
+					<string>AMCategoryUtilities</string>
+				</array>
+				<key>Class Name</key>
+				<string>RunShellScriptAction</string>
+				<key>isViewVisible</key>
+				<integer>1</integer>
+			</dict>
+			<key>isViewVisible</key>
+			<integer>1</integer>
+		</dict>
+	</array>
+	<key>connectors</key>
+	<dict/>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceInputTypeIdentifier</key>
+		<string>com.apple.Automator.fileSystemObject.folder</string>
+		<key>serviceOutputTypeIdentifier</key>
+		<string>com.apple.Automator.nothing</string>
+		<key>serviceProcessesInput</key>
+		<integer>0</integer>
+		<key>workflowTypeIdentifier</key>
+		<string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#;
+
+/// Escapes text for use inside a plist `<string>` element.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Quotes text as a single argument for a POSIX shell.
+pub(crate) fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Shell script of the quick action: scans every folder it is given with `exe`, writes the
+/// combined report to `report` and opens it.
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn quick_action_script(exe: &str, report: &str) -> String {
+    format!(
+        "{{ for folder in \"$@\"; do {} -d \"$folder\"; echo; done; }} > {report} 2>&1\nopen {report}\n",
+        shell_quote(exe),
+        report = shell_quote(report),
+    )
+}
+
+/// Location of the quick action bundle in the user's Services folder.
+#[cfg(target_os = "macos")]
+fn quick_action_path() -> Result<std::path::PathBuf, Box<dyn Error>> {
+    let home = std::env::var_os("HOME").ok_or("could not determine the home directory")?;
+    Ok(std::path::PathBuf::from(home)
+        .join("Library")
+        .join("Services")
+        .join(format!("{}.workflow", QUICK_ACTION_NAME)))
+}
+
+/// Asks macOS to rescan the Services folders so menu changes show up without logging out.
+#[cfg(target_os = "macos")]
+fn refresh_services() {
+    let _ = std::process::Command::new("/System/Library/CoreServices/pbs")
+        .arg("-update")
+        .status();
+}
+
+/// Creates a "Find Largest Files" quick action for folders selected in Finder.
+///
+/// The quick action scans each selected folder with this executable and opens the combined
+/// text report (saved in the cache directory) in the default text editor. It is installed
+/// into `~/Library/Services`, where Finder lists it under Quick Actions in the context menu;
+/// running the command again updates it, e.g. after moving the executable.
+///
+/// # Errors
+///
+/// Returns an error on platforms other than macOS, if the path of the running executable or
+/// the home directory cannot be determined, or if the bundle cannot be written.
+pub fn install_quick_action() -> Result<(), Box<dyn Error>> {
+    #[cfg(target_os = "macos")]
+    {
+        let exe = std::env::current_exe()?;
+        let report = crate::storage::cache_dir()?.join("quick-action-report.txt");
+        let script = quick_action_script(&exe.to_string_lossy(), &report.to_string_lossy());
+
+        let bundle = quick_action_path()?;
+        let contents = bundle.join("Contents");
+        std::fs::create_dir_all(&contents)?;
+        std::fs::write(
+            contents.join("Info.plist"),
+            QUICK_ACTION_INFO.replace("{name}", QUICK_ACTION_NAME),
+        )?;
+        std::fs::write(
+            contents.join("document.wflow"),
+            QUICK_ACTION_WORKFLOW.replace("{script}", &xml_escape(&script)),
+        )?;
+        refresh_services();
+
+        println!(
+            "{}",
+            tr!(
+                "quick-action-installed",
+                name = QUICK_ACTION_NAME,
+                path = bundle.display().to_string()
+            )
+        );
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err(tr!("macos-only", command = "install-quick-action").into())
+    }
+}
+
+/// Removes the quick action created by [`install_quick_action`].
+///
+/// # Errors
+///
+/// Returns an error on platforms other than macOS, or if the bundle cannot be removed.
+pub fn uninstall_quick_action() -> Result<(), Box<dyn Error>> {
+    #[cfg(target_os = "macos")]
+    {
+        let bundle = quick_action_path()?;
+        match std::fs::remove_dir_all(&bundle) {
+            Ok(()) => {
+                refresh_services();
+                println!("{}", tr!("quick-action-removed", name = QUICK_ACTION_NAME));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                println!(
+                    "{}",
+                    tr!("quick-action-not-installed", name = QUICK_ACTION_NAME)
+                );
+            }
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err(tr!("macos-only", command = "uninstall-quick-action").into())
+    }
+}
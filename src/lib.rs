@@ -1,28 +1,139 @@
 use filesize::PathExt;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use rayon::prelude::*;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{self, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fs, io, thread};
 
+#[macro_use]
+pub mod i18n;
+
 pub mod traits;
 use crate::traits::ByteSize;
 
 pub mod errors;
-use crate::errors::SearchError;
+use crate::errors::{FerrisError, SearchError};
 
 pub mod config;
 use crate::config::Config;
 
 pub mod top_entries;
-use crate::top_entries::TopEntries;
+use crate::top_entries::{ConcurrentTopEntries, TopEntries};
+
+pub mod report;
+use crate::report::{Aggregator, DirTotals, FileInfo, Metric, ReportKind, ReportSection};
 
 pub mod args;
 
+pub mod audit;
+
+pub mod tally;
+use crate::tally::{ByteTotals, HardLinks, SkipTally, TreeShape};
+
+pub mod stats;
+use crate::stats::ScanStats;
+
+pub mod health;
+use crate::health::StorageHealth;
+
+pub mod netfs;
+use crate::netfs::SizeFallbacks;
+
+pub mod streams;
+use crate::streams::StreamTally;
+
+pub mod categories;
+use crate::categories::{Category, CategoryTotals};
+
+pub mod procfs;
+
+pub mod reconcile;
+
+pub mod users;
+
+pub mod artifacts;
+
+pub mod attribution;
+
+pub mod quota;
+
+pub mod units;
+use crate::units::format_unix_time;
+
+pub mod storage;
+use crate::storage::ScanLock;
+
+pub mod console;
+
+pub mod protect;
+pub mod filter;
+use crate::filter::{IgnoreRules, FERRISIGNORE_FILE, GITIGNORE_FILES};
+pub mod priorities;
+
+pub mod alerts;
+use crate::alerts::AlertRules;
+
+pub mod tags;
+pub mod ownership;
+use crate::ownership::Owner;
+
+pub mod cost;
+use crate::cost::format_monthly;
+
+pub mod retention;
+use crate::retention::{RetentionPolicy, RetentionSimulation};
+
+pub mod drill;
+use crate::drill::DrillTree;
+
+pub mod snapshot;
+use crate::snapshot::Snapshot;
+
+pub mod host;
+
+pub mod results;
+use crate::results::{
+    collapse_siblings, entry_id, partition_by_mount, unix_now, Listed, MountPartition, Provenance,
+    SavedScan, MORE_CANDIDATES,
+};
+
+pub mod commands;
+
+pub mod preview;
+
+pub mod import;
+use crate::import::ImportFormat;
+
+pub mod export;
+use crate::export::ScanTree;
+#[cfg(feature = "parquet")]
+use crate::export::ParquetExport;
+
+pub mod integration;
+
+pub mod porcelain;
+
+pub mod progress;
+use crate::progress::ProgressFeed;
+
+pub mod output;
+use crate::output::{JsonReport, OutputFormat};
+
+pub mod wire;
+use crate::wire::{FrameWriter, Message, WireEntry, WireMount};
+pub mod fleet;
+
+pub mod serve;
+
+pub mod actions;
+use crate::actions::guard::{Fingerprint, ScanStamps, Staleness, RECHECK_BUDGET};
+use crate::actions::verify::ReadLimiter;
+
 pub mod tests;
 
 /// Represents a file system entry with its path and processing result.
@@ -50,7 +161,10 @@ fn get_fd_limit() -> usize {
             return limit / 2;
         } else {
             // Print the error if getrlimit fails
-            println!("Error: {}", std::io::Error::last_os_error());
+            println!(
+                "{}",
+                tr!("error", error = std::io::Error::last_os_error().to_string())
+            );
         }
     }
 
@@ -72,24 +186,145 @@ fn get_fd_limit() -> usize {
     100
 }
 
+/// Determines whether an entry should be skipped because it belongs to another user.
+///
+/// Files are skipped whenever their owner differs from `uid`. Directories owned by another
+/// user are only skipped when they are not world readable and searchable, since those are
+/// the ones that would otherwise produce permission errors (e.g. other users' home directories)
+/// while shared directories may still contain files owned by `uid`.
+///
+/// Only the metadata already collected during traversal is consulted.
+#[cfg(unix)]
+fn is_foreign_owned(metadata: &fs::Metadata, uid: u32) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    if metadata.uid() == uid {
+        return false;
+    }
+
+    !metadata.is_dir() || metadata.mode() & 0o005 != 0o005
+}
+
+/// Returns the style of the progress spinners, falling back to the plain default spinner should
+/// the template ever be rejected.
+fn spinner_style() -> ProgressStyle {
+    ProgressStyle::default_spinner()
+        .template("{spinner:.green} [{elapsed_precise}] {msg}")
+        .unwrap_or_else(|_| ProgressStyle::default_spinner())
+}
+
+/// Returns the id of the user owning the entry described by `metadata`, if the platform has
+/// numeric owners.
+fn owner_of(metadata: &fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some(metadata.uid())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Creates the byte totals of a scan of `root`, counting the root directory's own size along
+/// with those of the directories the scanner finds beneath it.
+fn root_byte_totals(root: &Path) -> ByteTotals {
+    let Ok(metadata) = fs::metadata(root) else {
+        return ByteTotals::default();
+    };
+    let totals = ByteTotals::new(device_of(&metadata));
+    if let Ok(size) = root.size_on_disk_fast(&metadata) {
+        totals.add_dir(totals.root_device, size);
+    }
+    totals
+}
+
+/// Returns the id of the device containing the entry described by `metadata`, if the
+/// platform exposes one.
+fn device_of(metadata: &fs::Metadata) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some(metadata.dev())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Records a non-regular, non-directory entry (socket, FIFO or device node) in the skip tally.
+fn count_special_file(metadata: &fs::Metadata, tally: &SkipTally) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        let file_type = metadata.file_type();
+        let counter = if file_type.is_socket() {
+            &tally.sockets
+        } else if file_type.is_fifo() {
+            &tally.fifos
+        } else if file_type.is_block_device() {
+            &tally.block_devices
+        } else if file_type.is_char_device() {
+            &tally.char_devices
+        } else {
+            return;
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (metadata, tally);
+    }
+}
+
+/// Size and measure (see [`Metric::measure`]) of the files kept by a ranking other than by
+/// size, whose values are not sizes.
+type RankedFiles = HashMap<String, (u64, u64)>;
+
+/// Replaces the ranking values of `entries` by the sizes of their files.
+fn with_sizes(entries: &mut [(String, u64)], ranked: &RankedFiles) {
+    for (path, value) in entries.iter_mut() {
+        if let Some((size, _)) = ranked.get(path) {
+            *value = *size;
+        }
+    }
+}
+
 /// Processes a batch of file entries and updates the top_entries collection.
 ///
 /// This function processes each file entry in parallel, collecting metadata and file sizes.
-/// It handles various error conditions (IO errors, invalid paths, mutex lock failures)
+/// It handles various error conditions (IO errors, invalid paths)
 /// while maintaining a count of successful and failed operations.
 ///
 /// # Arguments
 ///
 /// * `batch` - Vector of file entries to process. Each entry contains a path and its current processing status
-/// * `top_entries` - Thread-safe collection that maintains the N largest files found so far
+/// * `top_entries` - Thread-safe collection that maintains the N highest ranked files found so far
 /// * `error_log` - Thread-safe collection that maintains a record of any errors that occurr
+/// * `byte_totals` - Thread-safe running totals of bytes sized so far
+/// * `scan_stamps` - Thread-safe scan-time fingerprints of the entries kept in top_entries
+/// * `health` - Thread-safe record of errors hinting at failing storage
+/// * `size_fallbacks` - Sizes files, using apparent sizes where network mounts report no blocks
+/// * `streams` - With `--ads`, adds the named alternate data streams of each file to its size
+/// * `aggregators` - Built-in reports other than the largest files and custom ones, each handed every sized file
+/// * `ranking` - Metric files are ranked by, whether the newest come first (see [`Metric::value`])
+///   and the size below which files are not ranked (`--min-size`)
+/// * `ranked` - Unless ranking by size, receives the size and measure of the files kept
 /// * `is_verbose` - A bool used to log error messages if true
 ///
-/// # Returns
+/// # Counts
 ///
-/// Returns a tuple of `(processed, total)` where:
-/// * `processed` - Number of files successfully processed and added to top_entries
-/// * `total` - Total number of files attempted to process
+/// Adds to `stats` the files sized (or deliberately left out) as `processed`, the others as
+/// `failed`, and the bytes sized. Errors reading a file's metadata or size are counted by class,
+/// except for entries the scanner already failed to read.
 ///
 /// # Error Handling
 ///
@@ -97,94 +332,178 @@ fn get_fd_limit() -> usize {
 /// * File metadata access failures
 /// * File size calculation failures
 /// * Invalid UTF-8 in path names
-/// * Mutex lock failures
 ///
 /// # Implementation Details
 ///
 /// * Uses parallel iteration for metadata collection
+/// * Skips files below the current threshold, then ranks the rest of the batch at once with
+///   `ConcurrentTopEntries::insert_batch`
 /// * Metadata collection is skipped on entry.result Err variant
-/// * Maintains a thread-safe ordering of largest files
+/// * Only the first link found of a file with several hard links is sized, unless `hard_links`
+///   counts them all; the others are counted in the skipped counts of `stats`
+/// * Sparse files are counted in the skipped counts of `stats` instead of being sized if `size_fallbacks`
+///   skips them
+/// * Maintains a thread-safe ordering of largest files in a sharded `ConcurrentTopEntries`
+#[allow(clippy::too_many_arguments)]
 fn process_batch(
     batch: Vec<FileEntry>,
-    top_entries: &Arc<Mutex<TopEntries>>,
+    top_entries: &ConcurrentTopEntries,
     error_log: Arc<Mutex<Vec<String>>>,
+    byte_totals: &ByteTotals,
+    scan_stamps: &Mutex<ScanStamps>,
+    health: &StorageHealth,
+    size_fallbacks: &SizeFallbacks,
+    streams: Option<&StreamTally>,
+    hard_links: &HardLinks,
+    stats: &ScanStats,
+    aggregators: &mut [&mut dyn Aggregator],
+    ranking: (Metric, bool, u64),
+    mut ranked: Option<&mut RankedFiles>,
     is_verbose: bool,
-) -> (usize, usize) {
-    let metadata_results: Vec<_> = batch
+) {
+    let results: Vec<Result<Option<(FileInfo, Fingerprint)>, String>> = batch
         .into_par_iter()
-        .map(|entry| match entry.result {
-            Ok(()) => match fs::metadata(&entry.path) {
-                Ok(metadata) => Some((entry.path, Ok(metadata))),
-                Err(err) => Some((entry.path, Err(err))),
-            },
-            Err(err) => Some((
-                entry.path,
-                Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Previous error: {:?}", err),
-                )),
-            )),
+        .map(|entry| {
+            let path = entry.path;
+            let metadata = match entry.result {
+                Ok(()) => fs::metadata(&path).inspect_err(|err| stats.record_error(err)),
+                Err(err) => Err(io::Error::other(format!("Previous error: {:?}", err))),
+            };
+            let metadata = metadata.map_err(|err| {
+                health.record(&path, false, &err);
+                format!("Error processing {}: {}", path.display(), err)
+            })?;
+            // Further links to a file take no space of their own
+            if !hard_links.claim(&metadata) {
+                stats.skipped.hard_links.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
+            }
+            if size_fallbacks.skips(&path, &metadata) {
+                stats.skipped.sparse.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
+            }
+            let size = size_fallbacks.size(&path, &metadata).map_err(|err| {
+                stats.record_error(&err);
+                health.record(&path, false, &err);
+                format!("Failed to get size for {}: {}", path.display(), err)
+            })?;
+            let size = size + streams.map_or(0, |streams| streams.record(&path));
+            byte_totals.add_file(device_of(&metadata), size);
+            stats.bytes.fetch_add(size, Ordering::Relaxed);
+            let path_str = path
+                .into_os_string()
+                .into_string()
+                .map_err(|path| format!("Invalid UTF-8 in path: {}", Path::new(&path).display()))?;
+            let file = FileInfo {
+                path: path_str,
+                size,
+                modified: metadata.modified().ok(),
+                uid: owner_of(&metadata),
+                accessed: metadata.accessed().ok(),
+            };
+            Ok(Some((file, Fingerprint::from_metadata(&metadata))))
         })
         .collect();
 
-    let total = metadata_results.len();
-    let mut processed = 0;
+    let total = results.len();
     let mut errors = Vec::new();
+    let mut items = Vec::with_capacity(total);
+    let mut fingerprints = Vec::with_capacity(total);
+    let mut details = Vec::with_capacity(total);
+    // Late in a scan most files are too small to rank, and need not touch any shard. A scan
+    // sizes every path once, so no kept entry has to be replaced by a smaller size.
+    let threshold = top_entries.threshold();
+    for result in results {
+        match result {
+            Ok(None) => {}
+            Ok(Some((file, fingerprint))) => {
+                for aggregator in aggregators.iter_mut() {
+                    aggregator.observe(&file);
+                }
+                if file.size < ranking.2 {
+                    continue;
+                }
+                let Some(value) = ranking.0.value(&file, ranking.1) else {
+                    continue;
+                };
+                // Ties at the cutoff are left to `--then-by` to break
+                if threshold
+                    .is_none_or(|min| value > min || (top_entries.keeps_ties() && value == min))
+                {
+                    details.push((file.size, ranking.0.measure(&file).unwrap_or(0)));
+                    items.push((file.path, value));
+                    fingerprints.push(fingerprint);
+                }
+            }
+            Err(err) => errors.push(err),
+        }
+    }
 
-    for result in metadata_results {
-        if let Some((path, metadata_result)) = result {
-            match metadata_result {
-                Ok(metadata) => match path.size_on_disk_fast(&metadata) {
-                    Ok(size) => {
-                        if let Some(path_str) = path.to_str() {
-                            match top_entries.lock() {
-                                Ok(mut top) => {
-                                    top.insert(path_str.to_string(), size);
-                                    processed += 1;
-                                }
-                                Err(err) => {
-                                    errors.push(format!(
-                                        "Failed to lock top_entries for {}: {}",
-                                        path.display(),
-                                        err
-                                    ));
-                                }
-                            }
-                        } else {
-                            errors.push(format!("Invalid UTF-8 in path: {}", path.display()));
-                        }
-                    }
-                    Err(err) => {
-                        errors.push(format!(
-                            "Failed to get size for {}: {}",
-                            path.display(),
-                            err
-                        ));
-                    }
-                },
-                Err(err) => {
-                    errors.push(format!("Error processing {}: {}", path.display(), err));
+    stats.processed.fetch_add(total - errors.len(), Ordering::Relaxed);
+    stats.failed.fetch_add(errors.len(), Ordering::Relaxed);
+    top_entries.insert_batch(&items);
+    // Remember what was sized so actions can detect later changes
+    if let Ok(mut stamps) = scan_stamps.lock() {
+        let kept = items.into_iter().zip(fingerprints).zip(details);
+        for (((path, _), fingerprint), detail) in kept {
+            if top_entries.contains(&path) {
+                if let Some(ranked) = ranked.as_deref_mut() {
+                    ranked.insert(path.clone(), detail);
                 }
+                stamps.insert(path, fingerprint);
             }
         }
     }
 
     // Log errors if any occurred
     if !errors.is_empty() && is_verbose {
-        error_log.lock().unwrap().extend(errors);
+        error_log.lock().unwrap_or_else(PoisonError::into_inner).extend(errors);
+    }
+}
+
+/// Directories waiting to be listed, each with its depth below its root, queued in tiers by the
+/// weight of their root (see [`priorities::RootPriorities::tiers`]).
+///
+/// The first tier holding any directory is served first, in the order they were queued, so the
+/// trees of heavier roots are listed before the others; threads only take up a lighter tree while
+/// the heavier ones have nothing queued.
+#[derive(Debug, Default)]
+struct WorkQueue(Vec<VecDeque<(PathBuf, usize)>>);
+
+impl WorkQueue {
+    fn push(&mut self, tier: usize, dir: PathBuf, depth: usize) {
+        if self.0.len() <= tier {
+            self.0.resize_with(tier + 1, VecDeque::new);
+        }
+        self.0[tier].push_back((dir, depth));
+    }
+
+    /// Takes the next directory with its depth and tier.
+    fn pop(&mut self) -> Option<(PathBuf, usize, usize)> {
+        self.0.iter_mut().enumerate().find_map(|(tier, queue)| {
+            let (dir, depth) = queue.pop_front()?;
+            Some((dir, depth, tier))
+        })
     }
 
-    (processed, total)
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(VecDeque::is_empty)
+    }
 }
 
 /// Performs a parallel search of files in a directory tree, sending batches of file paths to a channel.
 ///
 /// # Arguments
 ///
-/// * `root_dir` - The root directory to start the search from
+/// * `root_dirs` - The root directories to start the search from, together
 /// * `tx` - A channel sender to transmit batches of discovered file paths
 /// * `config` - Arc reference to a config instance
 /// * `error_log` - Thread safe collection of errors ocurring during runtime
+/// * `stats` - Thread safe counts of the scan: directories listed, errors by class and entries
+///   that were intentionally not sized
+/// * `byte_totals` - Thread safe byte totals, used here to account for directory metadata
+/// * `health` - Thread safe record of errors hinting at failing storage
+/// * `shape` - Thread safe record of the directories with the most entries and the deepest one
 ///
 /// # Returns
 ///
@@ -195,31 +514,41 @@ fn process_batch(
 ///
 /// This function performs parallel directory traversal that:
 /// - Uses multiple threads (based on available CPU cores) to search directories recursively
-/// - Manages a shared work queue for distributing directory scanning work
+/// - Manages a shared work queue for distributing directory scanning work, seeded with every root
 /// - Limits the number of simultaneously open file handles to a platofrm specific limit or default of 100
-/// - Skips symbolic links and non-existent paths
+/// - Skips (and counts) symbolic links, sockets, FIFOs, device nodes and non-existent paths
 /// - Respects a set of directories to exclude from scanning
+/// - Optionally skips files and unreadable directories owned by other users
+/// - Warns about directories nested too deep or holding too many entries, optionally skipping them
+/// - Optionally limits the depth it descends to and the depth from which files are sized
 /// - Batches results to reduce channel communication overhead
+/// - Records read errors that hint at failing storage (I/O errors, timeouts) by directory
+/// - Records the number of entries and the depth of every directory it lists
 ///
+#[allow(clippy::too_many_arguments)]
 fn parallel_search(
-    root_dir: &Path,
+    root_dirs: &[PathBuf],
     tx: Sender<Vec<FileEntry>>,
     progress: ProgressBar,
     config: Arc<Config>,
     error_log: Arc<Mutex<Vec<String>>>,
+    stats: Arc<ScanStats>,
+    byte_totals: Arc<ByteTotals>,
+    health: Arc<StorageHealth>,
+    shape: Arc<TreeShape>,
 ) -> Result<(), SearchError> {
-    let work_queue = Arc::new(Mutex::new(VecDeque::new()));
+    let work_queue = Arc::new(Mutex::new(WorkQueue::default()));
     let is_scanning = Arc::new(AtomicBool::new(true));
 
     // Canonicalize directories to ignore
     let skip_dirs: HashSet<PathBuf> = config
         .skip_dirs
         .iter()
-        .filter_map(|dir| match PathBuf::from(dir).canonicalize() {
+        .filter_map(|dir| match console::canonicalize(Path::new(dir)) {
             Ok(path) => Some(path),
             Err(err) => {
                 if config.verbose {
-                    error_log.lock().unwrap().push(format!(
+                    error_log.lock().unwrap_or_else(PoisonError::into_inner).push(format!(
                         "Warning: Could not canonicalize skip directory '{}': {}",
                         dir, err
                     ));
@@ -230,33 +559,69 @@ fn parallel_search(
         })
         .collect();
 
-    // Initialize work queue with root directory
-    match root_dir.canonicalize() {
-        Ok(root) => work_queue.lock().unwrap().push_back(root),
-        Err(err) => {
-            if config.verbose {
-                error_log
-                    .lock()
-                    .unwrap()
-                    .push(format!("Failed to canonicalize root directory: {}", err));
+    // Initialize work queue with the root directories
+    let tiers = config.priorities.tiers(root_dirs);
+    let mut roots = Vec::with_capacity(root_dirs.len());
+    let mut root_tiers = Vec::with_capacity(root_dirs.len());
+    for (root_dir, tier) in root_dirs.iter().zip(tiers) {
+        match console::canonicalize(root_dir) {
+            Ok(root) => {
+                roots.push(root);
+                root_tiers.push(tier);
+            }
+            Err(err) => {
+                if config.verbose {
+                    error_log
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(format!("Failed to canonicalize root directory: {}", err));
+                }
             }
         }
     }
+    let mut files = Vec::new();
+    if config.respect_gitignore {
+        files.extend(GITIGNORE_FILES);
+    }
+    if !config.no_ferrisignore {
+        files.push(FERRISIGNORE_FILE);
+    }
+    let ignore_rules = (!files.is_empty()).then(|| Arc::new(IgnoreRules::new(&roots, &files)));
+    // Directories are queued with their depth below their root
+    let mut queue = work_queue.lock().unwrap_or_else(PoisonError::into_inner);
+    for (root, tier) in roots.into_iter().zip(root_tiers) {
+        queue.push(tier, root, 0);
+    }
+    drop(queue);
+
+    #[cfg(unix)]
+    let owner_uid = if config.own_files_only {
+        Some(unsafe { libc::geteuid() })
+    } else {
+        None
+    };
 
     let mut handles = vec![];
     let open_files = Arc::new(AtomicUsize::new(0));
-    let errors_count = Arc::new(AtomicUsize::new(0));
+    let dir_listings = config
+        .max_concurrent_dirs
+        .map(|max| Arc::new(ReadLimiter::new(max)));
 
     for _ in 0..config.num_threads {
         let work_queue = Arc::clone(&work_queue);
         let tx = tx.clone();
         let progress = progress.clone();
         let open_files = Arc::clone(&open_files);
+        let dir_listings = dir_listings.clone();
         let is_scanning = Arc::clone(&is_scanning);
         let skip_dirs = skip_dirs.clone();
-        let errors_count = Arc::clone(&errors_count);
+        let ignore_rules = ignore_rules.clone();
         let config_clone = config.clone();
         let error_log = error_log.clone();
+        let stats = Arc::clone(&stats);
+        let byte_totals = Arc::clone(&byte_totals);
+        let health = Arc::clone(&health);
+        let shape = Arc::clone(&shape);
 
         handles.push(thread::spawn(move || -> Result<(), SearchError> {
             let mut batch = Vec::with_capacity(config_clone.batch_size);
@@ -265,13 +630,13 @@ fn parallel_search(
                 let dir = {
                     match work_queue.lock() {
                         Ok( mut q) => {
-                            q.pop_front()
+                            q.pop()
                         }
                         Err(e) => {
                             if config_clone.verbose {
                                 error_log
                                     .lock()
-                                    .unwrap()
+                                    .unwrap_or_else(PoisonError::into_inner)
                                     .push(format!("Failed to lock work queue: {}", e));
                             }
                             None
@@ -280,26 +645,33 @@ fn parallel_search(
                 };
 
                 match dir {
-                    Some(dir) => {
-                        progress.set_message(format!("Scanning: {}", dir.display()));
+                    Some((dir, depth, tier)) => {
+                        progress.set_message(tr!("scanning", dir = dir.display().to_string()));
 
                         // Check if directory should be skipped
-                        match dir.canonicalize() {
+                        match console::canonicalize(&dir) {
                             Ok(canonical_dir) => {
                                 if skip_dirs
                                     .iter()
                                     .any(|skip_dir| canonical_dir.starts_with(skip_dir))
+                                    // The root itself is scanned even if a glob matches it
+                                    || (depth > 0 && config_clone.exclude.is_match(&canonical_dir))
                                 {
+                                    stats.skipped.excluded_dirs.fetch_add(1, Ordering::Relaxed);
                                     continue;
                                 }
                             }
                             Err(e) => {
                                 if config_clone.verbose {
-                                    error_log.lock().unwrap().push(format!("Failed to canonicalize directory {:#?} : {}", dir, e));
+                                    error_log.lock().unwrap_or_else(PoisonError::into_inner).push(format!("Failed to canonicalize directory {:#?} : {}", dir, e));
                                 }
                             }
                         }
 
+                        // On network filesystems the server's readdir concurrency, not local
+                        // file handles, is the constraint; the slot is held until the listing ends
+                        let _listing = dir_listings.as_ref().map(|limiter| limiter.acquire());
+
                         // Wait for available file handle with timeout
                         let mut wait_time = 1;
                         while open_files.load(Ordering::Relaxed) >= config_clone.max_open_files {
@@ -310,34 +682,106 @@ fn parallel_search(
 
                         match fs::read_dir(&dir) {
                             Ok(entries) => {
-                                for entry in entries.flatten() {
+                                let mut listed = 0;
+                                let ignored = ignore_rules.as_ref().map(|rules| rules.for_dir(&dir));
+                                for entry in entries {
+                                    let entry = match entry {
+                                        Ok(entry) => entry,
+                                        Err(err) => {
+                                            stats.record_error(&err);
+                                            health.record(&dir, true, &err);
+                                            continue;
+                                        }
+                                    };
+                                    listed += 1;
+                                    if config_clone.max_dir_entries > 0 && listed == config_clone.max_dir_entries + 1 {
+                                        log::warn!("{}", tr!(
+                                            "guard-huge-dir",
+                                            path = dir.display().to_string(),
+                                            limit = config_clone.max_dir_entries,
+                                            action = if config_clone.skip_pathological { "skip" } else { "scan" }
+                                        ));
+                                        if config_clone.skip_pathological {
+                                            stats.skipped.truncated_dirs.fetch_add(1, Ordering::Relaxed);
+                                            break;
+                                        }
+                                    }
                                     let path = entry.path();
-                                    if path.is_symlink() {
+                                    if entry.file_type().is_ok_and(|t| t.is_symlink()) {
+                                        stats.skipped.symlinks.fetch_add(1, Ordering::Relaxed);
                                         continue;
                                     }
 
                                     let file_entry = match path.metadata() {
                                         Ok(metadata) => {
+                                            #[cfg(unix)]
+                                            if let Some(uid) = owner_uid {
+                                                if is_foreign_owned(&metadata, uid) {
+                                                    stats.skipped.foreign_owned.fetch_add(1, Ordering::Relaxed);
+                                                    continue;
+                                                }
+                                            }
+
+                                            if ignored.as_ref().is_some_and(|rules| rules.is_ignored(&path, metadata.is_dir())) {
+                                                stats.skipped.ignored.fetch_add(1, Ordering::Relaxed);
+                                                continue;
+                                            }
+
                                             if metadata.is_dir() {
+                                                // Warn once where a branch crosses the depth limit; when
+                                                // skipping, nothing deeper is ever queued
+                                                if config_clone.depth_guard > 0 && depth == config_clone.depth_guard {
+                                                    log::warn!("{}", tr!(
+                                                        "guard-too-deep",
+                                                        path = path.display().to_string(),
+                                                        limit = config_clone.depth_guard,
+                                                        action = if config_clone.skip_pathological { "skip" } else { "scan" }
+                                                    ));
+                                                    if config_clone.skip_pathological {
+                                                        stats.skipped.too_deep_dirs.fetch_add(1, Ordering::Relaxed);
+                                                        continue;
+                                                    }
+                                                }
+                                                if let Ok(size) = path.size_on_disk_fast(&metadata) {
+                                                    byte_totals.add_dir(device_of(&metadata), size);
+                                                }
+                                                // Its entries would lie beyond the limit
+                                                if config_clone.max_depth > 0 && depth + 1 >= config_clone.max_depth {
+                                                    stats.skipped.depth_limited_dirs.fetch_add(1, Ordering::Relaxed);
+                                                    continue;
+                                                }
                                                 match work_queue.lock() {
                                                     Ok(mut q) => {
-                                                        q.push_back(path);
+                                                        q.push(tier, path, depth + 1);
                                                     }
                                                     Err(e) => {
                                                         if config_clone.verbose {
-                                                            error_log.lock().unwrap().push(format!("Error obtaining lock on work queue: {}", e));
+                                                            error_log.lock().unwrap_or_else(PoisonError::into_inner).push(format!("Error obtaining lock on work queue: {}", e));
                                                         }
                                                     }
                                                 }
                                                 continue;
                                             }
+                                            if !metadata.is_file() {
+                                                count_special_file(&metadata, &stats.skipped);
+                                                continue;
+                                            }
+                                            if depth + 1 < config_clone.min_depth {
+                                                stats.skipped.shallow_files.fetch_add(1, Ordering::Relaxed);
+                                                continue;
+                                            }
+                                            if !config_clone.include.is_empty() && !config_clone.include.is_match(&path) {
+                                                stats.skipped.not_included.fetch_add(1, Ordering::Relaxed);
+                                                continue;
+                                            }
                                             FileEntry {
                                                 path,
                                                 result: Ok(()),
                                             }
                                         }
                                         Err(err) => {
-                                            errors_count.fetch_add(1, Ordering::Relaxed);
+                                            stats.record_error(&err);
+                                            health.record(&path, false, &err);
                                             FileEntry {
                                                 path,
                                                 result: Err(SearchError::IoError(err)),
@@ -356,11 +800,15 @@ fn parallel_search(
                                         batch = Vec::with_capacity(config_clone.batch_size);
                                     }
                                 }
+                                stats.dirs.fetch_add(1, Ordering::Relaxed);
+                                shape.record(&dir, listed, depth);
                             }
                             Err(err) => {
-                                errors_count.fetch_add(1, Ordering::Relaxed);
+                                stats.record_error(&err);
+                                stats.skipped.unreadable_dirs.fetch_add(1, Ordering::Relaxed);
+                                health.record(&dir, true, &err);
                                 if config_clone.verbose {
-                                    error_log.lock().unwrap().push(format!("Error reading directory {}: {}", dir.display(), err));
+                                    error_log.lock().unwrap_or_else(PoisonError::into_inner).push(format!("Error reading directory {}: {}", dir.display(), err));
                                 }
                             }
                         }
@@ -368,8 +816,8 @@ fn parallel_search(
                         open_files.fetch_sub(1, Ordering::SeqCst);
                     }
                     None => {
-                        if !is_scanning.load(Ordering::SeqCst) {
-                            if work_queue
+                        if !is_scanning.load(Ordering::SeqCst)
+                            && work_queue
                                 .lock()
                                 .map_err(|e| {
                                     SearchError::ThreadError(format!(
@@ -378,9 +826,8 @@ fn parallel_search(
                                     ))
                                 })?
                                 .is_empty()
-                            {
-                                break 'outer;
-                            }
+                        {
+                            break 'outer;
                         }
                         thread::sleep(std::time::Duration::from_millis(10));
                     }
@@ -415,157 +862,1847 @@ fn parallel_search(
             if config.verbose {
                 error_log
                     .lock()
-                    .unwrap()
+                    .unwrap_or_else(PoisonError::into_inner)
                     .push(format!("Thread error: {:?}", err));
             }
         }
     }
 
-    let error_count = errors_count.load(Ordering::Relaxed);
-    progress.finish_with_message(format!(
-        "Directory scan complete ({} errors encountered: run with -v for details)",
-        error_count
+    progress.finish_with_message(tr!(
+        "scan-complete",
+        dirs = stats.dirs.load(Ordering::Relaxed),
+        errors = stats.total_errors()
     ));
 
     Ok(())
 }
 
-/// Responsible for initiating the directory traversdal and analyzing files as they are discovered
-///
-/// # Arguments
-///
-/// * `config` - An instance of a `Config` struct
-///
-/// # Returns
-///
-/// * `Result<(), Box<dyn Error>>` - Ok(()) if successful, or an Error if something fails
-///
-/// # Progress Display
-///
-/// The function shows two progress indicators:
-/// 1. A spinner showing the directory scanning progress
-/// 2. A spinner showing file processing progress with counts of total and successfully processed files
-///
-/// # Output
+/// Saves the reported entries as the last scan (and to `config.save_session`, if given) so
+/// follow-up commands can operate on them.
 ///
-/// Upon completion, prints a list of the largest files found, with their paths and sizes.
-/// If verbsoity was enabled, errors will be printed before file size results.
+/// Failing to save the last scan is not fatal for the scan and is only logged. Read-only runs
+/// save nothing, so follow-up commands see the scan before.
+fn save_session(config: &Config, session: &SavedScan) -> io::Result<()> {
+    if config.read_only {
+        return Ok(());
+    }
+    if let Err(e) = SavedScan::last_scan_path().and_then(|path| session.save(&path)) {
+        log::warn!("{}", tr!("session-not-saved", error = e.to_string()));
+    }
+    if let Some(path) = &config.save_session {
+        session.save(path)?;
+        if config.machine_readable() {
+            return Ok(());
+        }
+        println!(
+            "\n{}",
+            tr!("saved-session", path = path.display().to_string())
+        );
+    }
+    Ok(())
+}
+
+/// Ranks the files of a foreign inventory (see [`import::ImportFormat`]) instead of scanning,
+/// and saves them as a session so `query`, `diff` and `merge` work on them like on a scan.
 ///
-/// # Implementation Details
+/// The session records the inventory file as its root and no host, since the listing may
+/// come from another machine. No scan-time fingerprints exist for imported entries, so
+/// actions are not offered for them and `clean` refuses to touch them.
 ///
-/// - Uses a channel (`mpsc`) for communication between scanner and processor threads
-/// - Maintains thread-safe access to the top entries using `Arc<Mutex<TopEntries>>`
-/// - Processes files in batches for better performance
-/// - Shows real-time progress using the `indicatif` crate's progress bars
+/// # Errors
 ///
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let is_verbose = config.verbose;
-    let error_log: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-    let error_log_clone = error_log.clone();
-    let config_arc: Arc<Config> = Arc::new(config.clone());
-
-    print!(
-        "Searching for {0} largest entries in {1}:\n",
-        config.num_entries,
-        config.root_path.display()
-    );
-
-    let multi_progress = MultiProgress::new();
-    let scan_progress = multi_progress.add(ProgressBar::new_spinner());
-    scan_progress.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .unwrap(),
-    );
-
-    let process_progress = multi_progress.add(ProgressBar::new_spinner());
-    process_progress.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .unwrap(),
-    );
+/// Returns an error if the inventory cannot be opened or read, or is not valid for its format.
+fn run_import(
+    config: &Config,
+    format: ImportFormat,
+    file: &Path,
+    custom: &mut [&mut dyn Aggregator],
+    stats: &ScanStats,
+) -> Result<(), FerrisError> {
+    let started_at = unix_now();
+    if config.porcelain {
+        porcelain::print_version();
+    } else if !config.machine_readable() {
+        println!(
+            "{}",
+            tr!(
+                "importing",
+                count = config.num_entries,
+                file = file.display().to_string()
+            )
+        );
+    }
 
-    let (tx, rx) = mpsc::channel();
-    let top_entries = Arc::new(Mutex::new(TopEntries::new(config.num_entries)));
+    let mut top = TopEntries::new(config.num_entries.saturating_add(MORE_CANDIDATES));
+    if config.then_by.is_some() {
+        top = top.with_ties();
+    }
+    // Inventories list paths from another root, so directories are counted up to the filesystem's
+    let mut builtins = report::aggregators(config, Path::new(""));
+    let mut aggregators = with_custom(&mut builtins, custom);
+    let mut dir_totals = dir_ranking(config, Path::new(""));
+    let inventory = import::open_inventory(file).map_err(FerrisError::scan)?;
+    let skipped = import::import_entries(format, inventory, |path, size| {
+        if !config.include.is_empty() && !config.include.is_match(&path) {
+            return;
+        }
+        // A matching directory also matches everything beneath it, so the parent suffices
+        if Path::new(&path).parent().is_some_and(|dir| config.exclude.is_match(dir)) {
+            return;
+        }
+        // Every listed file counts as sized
+        stats.files.fetch_add(1, Ordering::Relaxed);
+        stats.processed.fetch_add(1, Ordering::Relaxed);
+        stats.bytes.fetch_add(size, Ordering::Relaxed);
+        let file = FileInfo {
+            path,
+            size,
+            modified: None,
+            uid: None,
+            accessed: None,
+        };
+        for aggregator in aggregators.iter_mut() {
+            aggregator.observe(&file);
+        }
+        match dir_totals.as_mut() {
+            Some(totals) => totals.observe(&file),
+            None if size < config.min_size => {}
+            None => {
+                top.insert(file.path, size);
+            }
+        }
+    })
+    .map_err(FerrisError::scan)?;
+    let counts = stats.snapshot();
+    if config.porcelain {
+        counts.print_porcelain();
+    } else if !config.machine_readable() {
+        println!(
+            "{}\n",
+            tr!("imported", files = counts.files, skipped = skipped)
+        );
+    }
 
-    // Directory scanner thread
-    let root_path = config.root_path.clone();
-    let scan_handle = thread::spawn(move || {
-        parallel_search(
-            &root_path,
-            tx,
-            scan_progress,
-            config_arc.clone(),
-            error_log_clone.clone(),
-        )
+    let root = console::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+    let mut json = (config.format == OutputFormat::Json).then(|| {
+        let mut report = JsonReport::new(&root.to_string_lossy());
+        report.set_stats(stats);
+        if skipped > 0 {
+            report.summary.skipped.insert("invalid-lines".to_string(), skipped);
+        }
+        report.summary.started_at = started_at;
+        report.summary.finished_at = unix_now();
+        report
     });
 
-    // Process files as received
-    let mut total_files = 0;
-    let mut total_processed = 0;
-    let mut total_attempts = 0;
-
-    while let Ok(batch) = rx.recv() {
-        total_files += batch.len();
-        let (processed, attempted) =
-            process_batch(batch, &top_entries, error_log.clone(), is_verbose);
-        total_processed += processed;
-        total_attempts += attempted;
-
-        process_progress.set_message(format!(
-            "Processing {} files (successfully processed: {}, failed: {})...",
-            total_files,
-            total_processed,
-            total_attempts - total_processed
-        ));
+    let mut entries = match &dir_totals {
+        Some(totals) => ranked_dirs(totals, config.min_size),
+        None => top.get_entries().to_vec(),
+    };
+    // An inventory records no modification times, and its paths need not exist here
+    report::order_entries(&mut entries, config.then_by, |_| None);
+    let mut more = entries.split_off(config.num_entries.min(entries.len()));
+    // Ties past the candidates were only kept to be ordered
+    more.truncate(MORE_CANDIDATES);
+    if entries.is_empty() {
+        match json {
+            Some(json) => println!("{}", json.to_json()),
+            None if !config.machine_readable() => println!("{}", tr!("import-empty")),
+            None => {}
+        }
+        if config.format == OutputFormat::Wire {
+            send_wire(&root, &entries, &ScanStamps::default(), &[], started_at, (0, 0, 0))
+                .map_err(FerrisError::report)?;
+        }
+        return Ok(());
     }
-
-    // Handle scanner thread result
-    match scan_handle.join() {
-        Ok(result) => result.map_err(|e| Box::new(e))?,
-        Err(e) => {
-            if is_verbose {
-                error_log
-                    .lock()
-                    .unwrap()
-                    .push(format!("Scanner thread panicked: {:?}", e));
+    // The mounts of this system say nothing about where the inventory's files live
+    let partitions = partition_by_mount(&entries, &[], &root);
+    if config.wants_report(ReportKind::TopFiles) {
+        match json.as_mut() {
+            Some(json) => {
+                json.add_entries(&entries, &HashMap::new(), None);
+                json.set_partitions(&partitions);
+                json.set_tags(&config.tags);
+                json.set_owners(&config.ownership);
+                json.set_costs(&config.costs);
+            }
+            None if config.format == OutputFormat::Text => {
+                let stale = HashMap::new();
+                let facts = EntryFacts::default();
+                print_entries(config, &entries, counts.bytes, &stale, None, None, &facts);
+                if config.porcelain {
+                    print_partitions(&root, &partitions);
+                }
+                if !more.is_empty() && !config.porcelain {
+                    println!("{}", tr!("more-available"));
+                }
             }
+            None => {}
         }
     }
+    for aggregator in &aggregators {
+        print_section(aggregator.finish(), config, json.as_mut());
+    }
 
-    process_progress.finish_with_message(format!(
-        "Processed {} files ({} successful, {} failed)",
-        total_attempts,
-        total_processed,
-        total_attempts - total_processed
-    ));
-
-    if is_verbose {
-        println!();
-        error_log.lock().unwrap().iter().for_each(|e| {
-            eprintln!("{}", e);
-        });
+    let stamps = ScanStamps::default();
+    let mut session = SavedScan::new(&root, &entries, &stamps).with_more(&more, &stamps);
+    session.dirs = config.dirs;
+    session.provenance.host = None;
+    session.provenance.started_at = started_at;
+    save_session(config, &session).map_err(FerrisError::report)?;
+    if let Some(json) = json {
+        println!("{}", json.to_json());
+    }
+    if config.format == OutputFormat::Wire {
+        let counts = (counts.files, counts.processed, counts.failed);
+        send_wire(&root, &entries, &ScanStamps::default(), &partitions, started_at, counts)
+            .map_err(FerrisError::report)?;
     }
 
-    println!("\n");
+    if config.has_actions() {
+        log::warn!("{}", tr!("import-no-actions"));
+    }
+    Ok(())
+}
 
-    match top_entries.lock() {
-        Ok(top) => {
-            if top.entries.is_empty() {
-                println!("No files found - run with -v flag for error output");
-            } else {
-                for (path, size) in top.entries.iter() {
-                    println!("{}: {}", path, size.format_size());
-                }
-            }
+/// Warns about directories where reads failed in ways that suggest a failing disk.
+///
+/// Scans read far more of a disk than everyday use, so they are often the first to run into
+/// bad sectors. With `porcelain` every suspect directory is printed as a `disk-errors` record.
+fn print_storage_warnings(health: &StorageHealth, porcelain: bool) {
+    for suspect in health.suspects() {
+        if porcelain {
+            println!(
+                "{}",
+                porcelain::record(
+                    "disk-errors",
+                    &[
+                        &suspect.counts.io_errors.to_string(),
+                        &suspect.counts.timeouts.to_string(),
+                        &suspect.path.to_string_lossy()
+                    ]
+                )
+            );
+        } else {
+            println!(
+                "\n{}",
+                tr!(
+                    "possible-failing-disk",
+                    path = suspect.path.display().to_string(),
+                    io_errors = suspect.counts.io_errors,
+                    timeouts = suspect.counts.timeouts
+                )
+            );
         }
-        Err(e) => {
-            return Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Failed to lock top entries for final output: {}", e),
-            )));
+    }
+}
+
+/// Notes the network mounts where files were sized by their apparent size because the server
+/// reported no allocated blocks for them. With `porcelain` each mount is printed as an
+/// `apparent-sizes` record.
+fn print_size_fallbacks(size_fallbacks: &SizeFallbacks, porcelain: bool) {
+    for note in size_fallbacks.notes() {
+        if porcelain {
+            println!(
+                "{}",
+                porcelain::record(
+                    "apparent-sizes",
+                    &[
+                        &note.files.to_string(),
+                        &note.mount.fs_type,
+                        &note.mount.mount_point.to_string_lossy()
+                    ]
+                )
+            );
+        } else {
+            println!(
+                "{}",
+                tr!(
+                    "apparent-size-fallback",
+                    mount = note.mount.mount_point.display().to_string(),
+                    fs_type = note.mount.fs_type.as_str(),
+                    files = note.files
+                )
+            );
         }
     }
+}
+
+/// Returns `root` as the paths of its scanned files start, i.e. canonical if it exists.
+fn canonical_root(root: &Path) -> PathBuf {
+    console::canonicalize(root).unwrap_or_else(|_| root.to_path_buf())
+}
+
+/// Ranks the directories of a `--dirs` run by everything beneath them, keeping as many as the
+/// files it replaces, including the candidates for `more`.
+fn dir_ranking(config: &Config, root: &Path) -> Option<DirTotals> {
+    config
+        .dirs
+        .then(|| DirTotals::new(root, config.num_entries.saturating_add(MORE_CANDIDATES)))
+}
+
+/// Returns the ranked directories of a `--dirs` run of at least `min_size` as entries.
+fn ranked_dirs(totals: &DirTotals, min_size: u64) -> Vec<(String, u64)> {
+    totals
+        .top()
+        .into_iter()
+        .filter(|(_, size)| *size >= min_size)
+        .map(|(path, size)| (path.to_string_lossy().into_owned(), size))
+        .collect()
+}
+
+/// Returns the entry sizes of the previous session of the same root on this host, if one exists.
+///
+/// The session about to be overwritten by `--save-session` is preferred over the last scan,
+/// which may have been of another root. A previous session produced with other settings, e.g.
+/// other exclusions or entry count, or by another version of ferris-files, which may size files
+/// differently, is not compared with, as its sizes would show changes the disk never went
+/// through; a warning says so instead.
+fn previous_sizes(config: &Config, current: &Provenance) -> Option<HashMap<String, u64>> {
+    let candidates = [
+        config.save_session.clone(),
+        SavedScan::last_scan_path().ok(),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .filter(|path| path.exists())
+        .filter_map(|path| SavedScan::load(&path).ok())
+        .find(|previous| {
+            previous.provenance.root == current.root
+                && previous.provenance.host == current.host
+                && previous.dirs == config.dirs
+        })
+        .filter(|previous| {
+            let differ = previous.provenance.settings_differ(current);
+            if differ {
+                log::warn!(
+                    "{}",
+                    tr!(
+                        "previous-settings-differ",
+                        root = current.root.display().to_string()
+                    )
+                );
+            }
+            !differ
+        })
+        .filter(|previous| {
+            let differs = previous.provenance.version_differs(current);
+            if differs {
+                log::warn!(
+                    "{}",
+                    tr!(
+                        "previous-version-differs",
+                        root = current.root.display().to_string(),
+                        version = previous.provenance.tool_version.as_str()
+                    )
+                );
+            }
+            !differs
+        })
+        .map(|previous| {
+            previous
+                .entries
+                .into_iter()
+                .map(|entry| (entry.path, entry.size))
+                .collect()
+        })
+}
+
+/// Describes how an entry's size changed since the previous session, e.g. `+1.20 GB` or `new`.
+fn size_delta(size: u64, previous: Option<u64>) -> String {
+    match previous {
+        None => tr!("delta-new"),
+        Some(before) if before == size => tr!("delta-unchanged"),
+        Some(before) if size > before => format!("+{}", (size - before).format_size()),
+        Some(before) => format!("-{}", (before - size).format_size()),
+    }
+}
+
+/// Joins the built-in report aggregators with the custom ones of a library user.
+fn with_custom<'a, 'b: 'a>(
+    builtins: &'a mut [Box<dyn Aggregator>],
+    custom: &'a mut [&'b mut dyn Aggregator],
+) -> Vec<&'a mut dyn Aggregator> {
+    builtins
+        .iter_mut()
+        .map(|aggregator| aggregator.as_mut() as &mut dyn Aggregator)
+        .chain(custom.iter_mut().map(|aggregator| &mut **aggregator as &mut dyn Aggregator))
+        .collect()
+}
+
+/// Prints a report section as text or porcelain records, or adds it to the `json` document.
+///
+/// Wire output carries only the ranked files, so sections are dropped for it.
+fn print_section(section: ReportSection, config: &Config, json: Option<&mut JsonReport>) {
+    match json {
+        Some(json) => json.add_section(section),
+        None if config.format == OutputFormat::Text => section.print(config.porcelain),
+        None => {}
+    }
+}
+
+/// Streams the ranked `entries` of a scan of `root`, the mounts of their `partitions` and its
+/// `(files, processed, failed)` counts to stdout as wire frames (see [`wire`]), for `--import
+/// wire` on another machine.
+fn send_wire(
+    root: &Path,
+    entries: &[(String, u64)],
+    stamps: &ScanStamps,
+    partitions: &[MountPartition],
+    started_at: u64,
+    (files, processed, failed): (usize, usize, usize),
+) -> io::Result<()> {
+    let mut frames = FrameWriter::new(io::stdout().lock())?;
+    frames.send(&Message::Hello {
+        host: host::hostname(),
+        root: root.to_string_lossy().into_owned(),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        started_at,
+    })?;
+    frames.send_entries(entries.iter().map(|(path, size)| WireEntry {
+        path: path.clone(),
+        size: *size,
+        modified: stamps
+            .get(path)
+            .and_then(|stamp| stamp.modified)
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|age| age.as_secs()),
+    }))?;
+    let mounts: Vec<WireMount> = partitions
+        .iter()
+        .filter(|partition| !partition.fs_type.is_empty())
+        .map(|partition| WireMount {
+            mount_point: partition.mount_point.to_string_lossy().into_owned(),
+            fs_type: partition.fs_type.clone(),
+        })
+        .collect();
+    if !mounts.is_empty() {
+        frames.send(&Message::Mounts(mounts))?;
+    }
+    frames.send(&Message::Done {
+        files: files as u64,
+        processed: processed as u64,
+        failed: failed as u64,
+        finished_at: unix_now(),
+    })
+}
+
+/// Prints the `partitions` of the entries of `root` as porcelain records: a `partition` record
+/// per mount, followed by a `mount` record for each of its entries.
+fn print_partitions(root: &Path, partitions: &[MountPartition]) {
+    let root = root.to_string_lossy();
+    for partition in partitions {
+        let mount_point = partition.mount_point.to_string_lossy();
+        println!(
+            "{}",
+            porcelain::record(
+                "partition",
+                &[
+                    &partition.size.to_string(),
+                    &partition.entries.len().to_string(),
+                    &partition.fs_type,
+                    &root,
+                    &mount_point,
+                ]
+            )
+        );
+        for path in &partition.entries {
+            println!("{}", porcelain::record("mount", &[&entry_id(path), &mount_point]));
+        }
+    }
+}
+
+/// Details about the reported files that are looked up once the scan is done.
+///
+/// * `links` - Number of hard links of files with several (see [`HardLinks`])
+/// * `other_sizes` - The apparent size of files, or their size on disk with `--apparent-size`,
+///   where it differs by more than a tenth from the size they were ranked by, as for sparse
+///   files or files on compressing filesystems
+/// * `sparse` - Files that are sparse (see [`netfs::is_sparse`])
+#[derive(Debug, Default)]
+struct EntryFacts {
+    links: HashMap<String, u64>,
+    other_sizes: HashMap<String, u64>,
+    sparse: HashSet<String>,
+}
+
+impl EntryFacts {
+    /// Gathers the facts about the ranked files of `entries`; directories have none.
+    fn gather(
+        config: &Config,
+        entries: &[(String, u64)],
+        stamps: &ScanStamps,
+        hard_links: &HardLinks,
+        size_fallbacks: &SizeFallbacks,
+    ) -> Self {
+        let mut facts = EntryFacts::default();
+        if config.dirs {
+            return facts;
+        }
+        for (path, size) in entries {
+            if let Some(links) = stamps
+                .get(path)
+                .and_then(|stamp| stamp.inode)
+                .and_then(|inode| hard_links.links(inode))
+            {
+                facts.links.insert(path.clone(), links);
+            }
+            let Ok(metadata) = fs::metadata(path) else {
+                continue;
+            };
+            if size_fallbacks.sparse(Path::new(path), &metadata) {
+                facts.sparse.insert(path.clone());
+            }
+            let other = if config.apparent_size {
+                Path::new(path).size_on_disk_fast(&metadata).ok()
+            } else {
+                Some(metadata.len())
+            };
+            if let Some(other) = other.filter(|other| other.abs_diff(*size) * 10 > *other.max(size)) {
+                facts.other_sizes.insert(path.clone(), other);
+            }
+        }
+        facts
+    }
+}
+
+/// Prints the ranked entries, as `[id] path: size` lines or as porcelain `entry` records,
+/// followed by their total and its share of the `total_bytes` scanned (a `total` record),
+/// unless they are directories.
+///
+/// Every line also gives the entry's share of `total_bytes`, unless nothing was sized.
+/// With the sizes of a `previous` session of the same root, every entry is annotated with its
+/// change since then (or followed by a `previous` record). Entries found `stale` when
+/// re-checked are annotated too (or followed by a `stale` record), and so are entries labelled
+/// by the tag map (or followed by a `tag` record), entries with an owner in the ownership
+/// mapping (or followed by an `owner` record) and entries with a storage rate (or followed by a
+/// `cost` record). Entries ranked by another metric than size start with their `measures` (or
+/// are followed by a record named after the metric, see [`measure_note`]), and the `facts`
+/// gathered about them are noted too (or follow as `links`, `apparent-size`, `disk-size` or
+/// `sparse` records).
+fn print_entries(
+    config: &Config,
+    entries: &[(String, u64)],
+    total_bytes: u64,
+    stale: &HashMap<String, Staleness>,
+    previous: Option<&HashMap<String, u64>>,
+    measures: Option<&HashMap<String, u64>>,
+    facts: &EntryFacts,
+) {
+    let share = |size: u64| share_of(size, total_bytes);
+    let listed = match config.collapse_siblings {
+        Some(limit) if !config.porcelain => collapse_siblings(entries, limit),
+        _ => entries
+            .iter()
+            .map(|(path, size)| Listed::Entry(path, *size))
+            .collect(),
+    };
+    for line in listed {
+        let (path, size) = match line {
+            Listed::Entry(path, size) => (path, size),
+            Listed::Siblings {
+                parent,
+                count,
+                size,
+            } => {
+                let siblings = tr!(
+                    "entry-siblings",
+                    dir = parent.display().to_string(),
+                    count = count,
+                    size = size.format_size()
+                );
+                match share(size) {
+                    Some(share) => println!("{} ({:.1}%)", siblings, share),
+                    None => println!("{}", siblings),
+                }
+                continue;
+            }
+        };
+        let measure = measures
+            .and_then(|measures| measures.get(path))
+            .map(|measure| measure_note(config.metric, *measure));
+        let state = stale.get(path);
+        let linked = facts.links.get(path);
+        let other_size = facts.other_sizes.get(path);
+        let sparse = facts.sparse.contains(path);
+        let before = previous.map(|sizes| sizes.get(path).copied());
+        let root = config.root_of(path);
+        let tag = config.tags.tag(path);
+        let owner = config.ownership.owner(path);
+        let cost = config.costs.monthly(path, size);
+        if config.porcelain {
+            porcelain::print_entry("entry", &entry_id(path), size, None, path);
+            if let Some((kind, measure, _)) = &measure {
+                println!(
+                    "{}",
+                    porcelain::record(kind, &[&entry_id(path), measure, path])
+                );
+            }
+            if let Some(linked) = linked {
+                println!(
+                    "{}",
+                    porcelain::record("links", &[&entry_id(path), &linked.to_string(), path])
+                );
+            }
+            if let Some(other_size) = other_size {
+                let kind = if config.apparent_size {
+                    "disk-size"
+                } else {
+                    "apparent-size"
+                };
+                println!(
+                    "{}",
+                    porcelain::record(kind, &[&entry_id(path), &other_size.to_string(), path])
+                );
+            }
+            if sparse {
+                println!("{}", porcelain::record("sparse", &[&entry_id(path), path]));
+            }
+            if let Some(before) = before {
+                let before = before.map(|size| size.to_string()).unwrap_or_default();
+                println!(
+                    "{}",
+                    porcelain::record("previous", &[&entry_id(path), &before, path])
+                );
+            }
+            if let Some(state) = state {
+                let current = match state {
+                    Staleness::Resized(len) => len.to_string(),
+                    _ => String::new(),
+                };
+                println!(
+                    "{}",
+                    porcelain::record("stale", &[&entry_id(path), state.key(), &current, path])
+                );
+            }
+            if let Some(root) = root {
+                println!(
+                    "{}",
+                    porcelain::record("root", &[&entry_id(path), &root.to_string_lossy(), path])
+                );
+            }
+            if let Some(tag) = tag {
+                println!(
+                    "{}",
+                    porcelain::record("tag", &[&entry_id(path), tag, path])
+                );
+            }
+            if let Some(owner) = owner {
+                let field = |value: &Option<String>| value.clone().unwrap_or_default();
+                println!(
+                    "{}",
+                    porcelain::record(
+                        "owner",
+                        &[
+                            &entry_id(path),
+                            &field(&owner.cost_center),
+                            &field(&owner.team),
+                            &field(&owner.owner),
+                            path
+                        ]
+                    )
+                );
+            }
+            if let Some(cost) = cost {
+                println!(
+                    "{}",
+                    porcelain::record("cost", &[&entry_id(path), &format!("{:.4}", cost), path])
+                );
+            }
+        } else {
+            let notes: Vec<String> = measure
+                .map(|(_, _, note)| note)
+                .into_iter()
+                .chain(share(size).map(|share| format!("{:.1}%", share)))
+                .chain(linked.map(|&count| tr!("entry-links", count = count)))
+                .chain(other_size.map(|other| {
+                    let size = other.format_size();
+                    if config.apparent_size {
+                        tr!("entry-disk-size", size = size)
+                    } else {
+                        tr!("entry-apparent-size", size = size)
+                    }
+                }))
+                .chain(sparse.then(|| tr!("entry-sparse")))
+                .chain(root.map(|root| tr!("entry-root", root = root.display().to_string())))
+                .chain(tag.map(str::to_string))
+                .chain(owner.map(Owner::label))
+                .chain(cost.map(format_monthly))
+                .chain(before.map(|before| size_delta(size, before)))
+                .chain(state.map(Staleness::describe))
+                .collect();
+            if notes.is_empty() {
+                println!("[{}] {}: {}", entry_id(path), path, size.format_size());
+            } else {
+                println!(
+                    "[{}] {}: {} ({})",
+                    entry_id(path),
+                    path,
+                    size.format_size(),
+                    notes.join(", ")
+                );
+            }
+        }
+    }
+
+    if let Some(total) = entries_total(config, entries, total_bytes) {
+        println!("{}", total);
+    }
+}
+
+/// Percentage of `total_bytes` that `size` takes, or `None` if nothing was sized.
+fn share_of(size: u64, total_bytes: u64) -> Option<f64> {
+    (total_bytes > 0).then(|| size as f64 * 100.0 / total_bytes as f64)
+}
+
+/// Line closing the listing of `entries`: their total and its share of the `total_bytes` scanned,
+/// or a porcelain `total` record.
+///
+/// Ranked directories nest, so their sizes do not add up and they get none; without porcelain
+/// neither do entries of a scan that sized nothing.
+fn entries_total(config: &Config, entries: &[(String, u64)], total_bytes: u64) -> Option<String> {
+    if config.dirs {
+        return None;
+    }
+    let listed: u64 = entries.iter().map(|(_, size)| size).sum();
+    if config.porcelain {
+        return Some(porcelain::record(
+            "total",
+            &[&listed.to_string(), &total_bytes.to_string()],
+        ));
+    }
+    share_of(listed, total_bytes).map(|share| {
+        tr!(
+            "entries-total",
+            count = entries.len(),
+            size = listed.format_size(),
+            share = format!("{:.1}", share),
+            total = total_bytes.format_size()
+        )
+    })
+}
+
+/// Porcelain record type, field and note of an entry's `measure` by `metric`, which ranked it.
+fn measure_note(metric: Metric, measure: u64) -> (&'static str, String, String) {
+    match metric {
+        Metric::Size => ("size", measure.to_string(), measure.format_size()),
+        Metric::Mtime => (
+            "modified",
+            measure.to_string(),
+            tr!("entry-modified", time = format_unix_time(measure)),
+        ),
+        Metric::Atime => (
+            "accessed",
+            measure.to_string(),
+            tr!("entry-accessed", time = format_unix_time(measure)),
+        ),
+        Metric::PathLength => (
+            "path-length",
+            measure.to_string(),
+            tr!("entry-path-length", length = measure),
+        ),
+    }
+}
+
+/// Prints the permission audit section for the final top entries.
+///
+/// Entries that are world readable/writable or carry setuid/setgid bits are listed
+/// together with their size so they can be reviewed during periodic hygiene sweeps.
+/// With `porcelain` every finding is printed as an `audit` record instead.
+fn print_audit(entries: &[(String, u64)], porcelain: bool) {
+    if !porcelain {
+        println!("\n{}", tr!("audit-heading"));
+    }
+    match audit::audit_entries(entries) {
+        Ok(findings) if findings.is_empty() && !porcelain => {
+            println!("{}", tr!("audit-clean"));
+        }
+        Ok(findings) if porcelain => {
+            for finding in findings {
+                println!(
+                    "{}",
+                    porcelain::record(
+                        "audit",
+                        &[
+                            &finding.flags.labels().join(","),
+                            &finding.size.to_string(),
+                            &finding.path
+                        ]
+                    )
+                );
+            }
+        }
+        Ok(findings) => {
+            for finding in findings {
+                println!(
+                    "[{}] {}: {}",
+                    finding.flags.labels().join(", "),
+                    finding.path,
+                    finding.size.format_size()
+                );
+            }
+        }
+        Err(e) => log::warn!("{}", tr!("audit-skipped", error = e.to_string())),
+    }
+}
+
+/// Prints the final top entries grouped by the users and processes they are attributed to.
+fn print_attribution(entries: &[(String, u64)]) {
+    println!("\n{}", tr!("attribution-heading"));
+    match attribution::attribute_entries(entries) {
+        Ok(groups) => {
+            for group in groups {
+                println!("{}: {}", group.label(), group.total.format_size());
+                for (path, size) in group.files {
+                    println!("  {}: {}", path, size.format_size());
+                }
+            }
+        }
+        Err(e) => log::warn!("{}", tr!("attribution-skipped", error = e.to_string())),
+    }
+}
+
+/// Prints the quota usage and headroom of the user owning the scan root.
+///
+/// The owner of the root (rather than the invoking user) is used so that an administrator
+/// scanning `/home/alice` sees alice's quota.
+fn print_quota(root: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let (root, uid) = match console::canonicalize(root).and_then(|root| {
+            let uid = fs::metadata(&root)?.uid();
+            Ok((root, uid))
+        }) {
+            Ok(found) => found,
+            Err(e) => {
+                log::warn!(
+                    "{}",
+                    tr!(
+                        "owner-unknown",
+                        path = root.display().to_string(),
+                        error = e.to_string()
+                    )
+                );
+                return;
+            }
+        };
+
+        let user = users::UserNames::default().name(uid);
+        match quota::user_quota(&root, uid) {
+            Ok((mount, usage)) => println!(
+                "\n{}",
+                tr!(
+                    "quota",
+                    mount = mount.mount_point.display().to_string(),
+                    source = mount.source,
+                    user = user,
+                    usage = usage.describe()
+                )
+            ),
+            Err(e) => println!(
+                "\n{}",
+                tr!("quota-unavailable", user = user, error = e.to_string())
+            ),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = root;
+        log::warn!("{}", tr!("quota-unsupported"));
+    }
+}
+
+/// Prints the largest files that were deleted while still held open by a process.
+///
+/// These files still occupy disk space but can no longer be found by a directory scan,
+/// which frequently explains a full disk with nothing left to delete. The space is
+/// released once the listed process closes the descriptor (or is restarted).
+fn print_deleted_open(limit: usize) {
+    println!("\n{}", tr!("deleted-open-heading"));
+    match procfs::deleted_open_files() {
+        Ok(files) if files.is_empty() => {
+            println!("{}", tr!("deleted-open-none"));
+        }
+        Ok(files) => {
+            for file in files.iter().take(limit) {
+                println!(
+                    "{}",
+                    tr!(
+                        "deleted-open-file",
+                        path = file.path.as_str(),
+                        process = file.process_name.as_str(),
+                        pid = file.pid,
+                        fd = file.fd,
+                        size = file.size.format_size()
+                    )
+                );
+            }
+        }
+        Err(e) => log::warn!("{}", tr!("deleted-open-failed", error = e.to_string())),
+    }
+}
+
+/// Prints a comparison of the scanned total against the filesystem's used space.
+///
+/// Only called when the scan root is a mount point, since otherwise the filesystem usage
+/// includes space outside of the scanned tree and the comparison is meaningless.
+fn print_reconciliation(root: &Path, tally: &SkipTally, byte_totals: &ByteTotals) {
+    let usage = match reconcile::fs_usage(root) {
+        Ok(usage) => usage,
+        Err(e) => {
+            log::warn!(
+                "{}",
+                tr!(
+                    "usage-unknown",
+                    path = root.display().to_string(),
+                    error = e.to_string()
+                )
+            );
+            return;
+        }
+    };
+
+    let deleted_open_bytes = procfs::deleted_open_files().ok().map(|files| {
+        let mut seen = HashSet::new();
+        files
+            .iter()
+            .filter(|file| Some(file.device) == byte_totals.root_device)
+            .filter(|file| seen.insert((file.device, file.inode)))
+            .map(|file| file.size)
+            .sum()
+    });
+
+    let reconciliation = reconcile::Reconciliation {
+        used_bytes: usage.used_bytes,
+        file_bytes: byte_totals.root_device_file_bytes.load(Ordering::Relaxed),
+        dir_bytes: byte_totals.root_device_dir_bytes.load(Ordering::Relaxed),
+        deleted_open_bytes,
+        unscanned_dirs: tally.excluded_dirs.load(Ordering::Relaxed)
+            + tally.unreadable_dirs.load(Ordering::Relaxed),
+        supports_snapshots: usage.supports_snapshots,
+    };
+
+    println!(
+        "\n{}",
+        tr!("reconciliation-heading", path = root.display().to_string())
+    );
+    for line in reconciliation.lines() {
+        println!("{}", line);
+    }
+}
+
+/// Responsible for initiating the directory traversdal and analyzing files as they are discovered
+///
+/// # Arguments
+///
+/// * `config` - An instance of a `Config` struct
+///
+/// # Returns
+///
+/// * `Result<(), FerrisError>` - Ok(()) if successful, or the error of the stage that failed
+///
+/// # Errors
+///
+/// Returns a [`FerrisError`] naming the stage that failed: [`FerrisError::Scan`] if the tree
+/// or the imported inventory cannot be read, [`FerrisError::Report`] if the session, an export
+/// or the output cannot be written and [`FerrisError::Action`] if an action fails.
+///
+/// # Progress Display
+///
+/// The function shows two progress indicators:
+/// 1. A spinner showing the directory scanning progress
+/// 2. A spinner showing file processing progress with counts of total and successfully processed files
+///
+/// # Output
+///
+/// Upon completion, prints a tally of entries that were intentionally skipped (symlinks,
+/// special files, excluded directories) followed by a list of the largest files found,
+/// with their paths and sizes. When the scan root is a mount point, the scanned total is
+/// reconciled against the filesystem's used space and the gap is broken down by known causes.
+/// If verbsoity was enabled, errors will be printed before file size results.
+/// With `config.format` set to JSON, all of this is printed as one [`JsonReport`] instead.
+///
+/// # Implementation Details
+///
+/// - Uses a channel (`mpsc`) for communication between scanner and processor threads
+/// - Maintains thread-safe access to the top entries using a sharded `ConcurrentTopEntries`
+/// - Processes files in batches for better performance
+/// - Shows real-time progress using the `indicatif` crate's progress bars
+///
+pub fn run(config: Config) -> Result<(), FerrisError> {
+    run_with_aggregators(config, &mut [])
+}
+
+/// Runs a scan like [`run`], additionally handing every sized file to the `custom` aggregators,
+/// whose sections are printed after the requested reports.
+///
+/// Custom analytics thus ride the same single traversal as the built-in reports; the
+/// aggregators stay with the caller, who can inspect them once the scan is done.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ferris_files::config::Config;
+/// use ferris_files::report::{Aggregator, FileInfo, ReportSection};
+///
+/// #[derive(Default)]
+/// struct TotalBytes(u64);
+///
+/// impl Aggregator for TotalBytes {
+///     fn observe(&mut self, file: &FileInfo) {
+///         self.0 += file.size;
+///     }
+///
+///     fn finish(&self) -> ReportSection {
+///         ReportSection::default()
+///     }
+/// }
+///
+/// let mut total = TotalBytes::default();
+/// ferris_files::run_with_aggregators(Config::default(), &mut [&mut total]).unwrap();
+/// println!("{} bytes scanned", total.0);
+/// ```
+///
+/// # Errors
+///
+/// Returns the same errors as [`run`].
+pub fn run_with_aggregators(
+    config: Config,
+    custom: &mut [&mut dyn Aggregator],
+) -> Result<(), FerrisError> {
+    run_with_stats(config, custom, Arc::new(ScanStats::default()))
+}
+
+/// Runs a scan like [`run_with_aggregators`], counting into the caller's `stats`: files,
+/// directories, bytes, errors by class and skipped entries.
+///
+/// The counts are updated as the scan goes, so another thread can show or export them while it
+/// runs; once it returns they are final. Imports count their files, processed and bytes only.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ferris_files::config::Config;
+/// use ferris_files::stats::ScanStats;
+/// use std::sync::Arc;
+///
+/// let stats = Arc::new(ScanStats::default());
+/// ferris_files::run_with_stats(Config::default(), &mut [], Arc::clone(&stats)).unwrap();
+/// let snapshot = stats.snapshot();
+/// println!("{} files in {} directories, {} errors", snapshot.files, snapshot.dirs, stats.total_errors());
+/// ```
+///
+/// # Errors
+///
+/// Returns the same errors as [`run`].
+pub fn run_with_stats(
+    config: Config,
+    custom: &mut [&mut dyn Aggregator],
+    stats: Arc<ScanStats>,
+) -> Result<(), FerrisError> {
+    run_with_progress(config, custom, stats, None)
+}
+
+/// Runs a scan like [`run_with_stats`], writing its live progress feed (see
+/// [`ProgressFeed`]) to `progress` instead of the file given with `--progress-json`.
+///
+/// This is how [`serve`] pushes the progress of its scans to WebSocket clients; without
+/// `progress` the scan behaves exactly like [`run_with_stats`].
+///
+/// # Errors
+///
+/// Returns the same errors as [`run`].
+pub fn run_with_progress(
+    config: Config,
+    custom: &mut [&mut dyn Aggregator],
+    stats: Arc<ScanStats>,
+    progress: Option<Box<dyn io::Write>>,
+) -> Result<(), FerrisError> {
+    let is_verbose = config.verbose;
+    let error_log: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let error_log_clone = error_log.clone();
+    let config_arc: Arc<Config> = Arc::new(config.clone());
+
+    if let Some((format, file)) = &config.import {
+        return run_import(&config, *format, file, custom, &stats);
+    }
+
+    if config.preview
+        && !preview::preview(&config.scan_roots(), &config.skip_dirs).map_err(FerrisError::scan)?
+    {
+        println!("{}", tr!("full-scan-skipped"));
+        return Ok(());
+    }
+
+    // Held until the results are saved; read-only runs save nothing and need no lock. Several
+    // roots are locked one by one rather than through their common ancestor, which would block
+    // unrelated scans beneath it
+    let _locks = if config.read_only {
+        Vec::new()
+    } else {
+        let roots: Vec<PathBuf> = config.scan_roots().iter().map(|root| canonical_root(root)).collect();
+        ScanLock::acquire_all(&roots, config.force).map_err(FerrisError::scan)?
+    };
+
+    let started_at = unix_now();
+    if config.porcelain {
+        porcelain::print_version();
+    } else if !config.machine_readable() {
+        let count = config.num_entries;
+        let root = if config.roots.is_empty() {
+            config.root_path.display().to_string()
+        } else {
+            let roots: Vec<String> =
+                config.roots.iter().map(|root| root.display().to_string()).collect();
+            roots.join(", ")
+        };
+        let searching = match (config.metric, config.newest) {
+            (Metric::Size, _) => tr!("searching", count = count, root = root),
+            (Metric::Mtime, false) => tr!("searching-oldest", count = count, root = root),
+            (Metric::Mtime, true) => tr!("searching-newest", count = count, root = root),
+            (Metric::Atime, false) => tr!("searching-unread", count = count, root = root),
+            (Metric::Atime, true) => tr!("searching-read", count = count, root = root),
+            (Metric::PathLength, _) => tr!("searching-longest", count = count, root = root),
+        };
+        println!("{}", searching);
+    }
+
+    let multi_progress = MultiProgress::new();
+    if config.machine_readable() {
+        multi_progress.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    let scan_progress = multi_progress.add(ProgressBar::new_spinner());
+    scan_progress.set_style(spinner_style());
+
+    let process_progress = multi_progress.add(ProgressBar::new_spinner());
+    process_progress.set_style(spinner_style());
+
+    let (tx, rx) = mpsc::channel();
+    // Candidates beyond the reported entries are kept for `more`
+    // With --then-by, files tied at the cutoff are all kept for it to choose from
+    let mut top_entries = ConcurrentTopEntries::new(config.num_entries.saturating_add(MORE_CANDIDATES));
+    if config.then_by.is_some() {
+        top_entries = top_entries.with_ties();
+    }
+    let top_entries = Arc::new(top_entries);
+    let root = canonical_root(&config.root_path);
+    let mut builtins = report::aggregators(&config, &root);
+    let mut aggregators = with_custom(&mut builtins, custom);
+    // The exports and the --dirs ranking only observe the files; they print no report section
+    let reported = aggregators.len();
+    let mut dir_totals = dir_ranking(&config, &root);
+    if let Some(totals) = dir_totals.as_mut() {
+        aggregators.push(totals);
+    }
+    let mut scan_tree =
+        (config.tree || config.export_ncdu.is_some() || config.export_treemap.is_some()).then(|| ScanTree::new(&config.root_path));
+    if let Some(tree) = scan_tree.as_mut() {
+        aggregators.push(tree);
+    }
+    let mut snapshot = config.save_snapshot.as_ref().map(|_| {
+        let root = console::canonicalize(&config.root_path)
+            .unwrap_or_else(|_| config.root_path.clone());
+        let mut snapshot = Snapshot::new(&root.to_string_lossy(), started_at);
+        snapshot.config_hash = Some(config.settings_hash());
+        snapshot
+    });
+    if let Some(snapshot) = snapshot.as_mut() {
+        aggregators.push(snapshot);
+    }
+    #[cfg(feature = "parquet")]
+    let mut parquet_export = match &config.export_parquet {
+        Some(path) => {
+            let root = console::canonicalize(&config.root_path)
+                .unwrap_or_else(|_| config.root_path.clone());
+            let export = ParquetExport::create(path, &root.to_string_lossy())
+                .map_err(|e| {
+                    FerrisError::report(io::Error::new(
+                        e.kind(),
+                        format!("could not create Parquet export {}: {}", path.display(), e),
+                    ))
+                })?
+                .with_mounts(quota::system_mounts());
+            if config.min_free > 0 {
+                let dir = path
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+                Some(export.with_reserve(dir, config.min_free))
+            } else {
+                Some(export)
+            }
+        }
+        None => None,
+    };
+    #[cfg(feature = "parquet")]
+    if let Some(export) = parquet_export.as_mut() {
+        aggregators.push(export);
+    }
+    let byte_totals = Arc::new(root_byte_totals(&config.root_path));
+    let scan_stamps = Mutex::new(ScanStamps::default());
+    let mut ranked = (config.metric != Metric::Size).then(RankedFiles::new);
+    let health = Arc::new(StorageHealth::default());
+    let shape = Arc::new(TreeShape::new(config.num_entries));
+    let hard_links = HardLinks::new(config.count_links);
+    let size_fallbacks = SizeFallbacks::load()
+        .with_apparent_sizes(config.apparent_size)
+        .with_sparse_skipped(config.skip_sparse);
+    let streams = config.ads.then(|| StreamTally::new(config.num_entries));
+    let mut feed = match progress {
+        Some(out) => Some(ProgressFeed::new(out)),
+        None => config
+            .progress_json
+            .as_deref()
+            .map(ProgressFeed::create)
+            .transpose()
+            .map_err(FerrisError::report)?,
+    };
+
+    // Directory scanner thread
+    let root_dirs = config.scan_roots();
+    let stats_clone = Arc::clone(&stats);
+    let byte_totals_clone = Arc::clone(&byte_totals);
+    let health_clone = Arc::clone(&health);
+    let shape_clone = Arc::clone(&shape);
+    let scan_handle = thread::spawn(move || {
+        parallel_search(
+            &root_dirs,
+            tx,
+            scan_progress,
+            config_arc.clone(),
+            error_log_clone.clone(),
+            stats_clone,
+            byte_totals_clone,
+            health_clone,
+            shape_clone,
+        )
+    });
+
+    // Process files as received
+    while let Ok(batch) = rx.recv() {
+        stats.files.fetch_add(batch.len(), Ordering::Relaxed);
+        process_batch(
+            batch,
+            &top_entries,
+            error_log.clone(),
+            &byte_totals,
+            &scan_stamps,
+            &health,
+            &size_fallbacks,
+            streams.as_ref(),
+            &hard_links,
+            &stats,
+            &mut aggregators,
+            (config.metric, config.newest, config.min_size),
+            ranked.as_mut(),
+            is_verbose,
+        );
+        let counts = stats.snapshot();
+
+        process_progress.set_message(tr!(
+            "processing",
+            files = counts.files,
+            processed = counts.processed,
+            failed = counts.failed,
+            bytes = counts.bytes.format_size()
+        ));
+        if let Some(feed) = feed.as_mut() {
+            feed.update(
+                counts.files,
+                counts.processed,
+                counts.failed,
+                counts.bytes,
+                || {
+                    let mut top = top_entries.get_entries();
+                    top.truncate(config.num_entries);
+                    if let Some(ranked) = &ranked {
+                        with_sizes(&mut top, ranked);
+                    }
+                    top
+                },
+            );
+        }
+    }
+
+    // Handle scanner thread result
+    match scan_handle.join() {
+        Ok(result) => result.map_err(FerrisError::scan)?,
+        Err(e) => {
+            if is_verbose {
+                error_log
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(format!("Scanner thread panicked: {:?}", e));
+            }
+        }
+    }
+
+    let counts = stats.snapshot();
+    process_progress.finish_with_message(tr!(
+        "processed",
+        files = counts.files,
+        processed = counts.processed,
+        failed = counts.failed,
+        bytes = counts.bytes.format_size()
+    ));
+    if let Some(feed) = feed.as_mut() {
+        feed.finish(counts.files, counts.processed, counts.failed, counts.bytes);
+    }
+    let mut json = None;
+    if config.format == OutputFormat::Json {
+        let root = console::canonicalize(&config.root_path)
+            .unwrap_or_else(|_| config.root_path.clone());
+        let mut report = JsonReport::new(&root.to_string_lossy());
+        report.set_stats(&stats);
+        report.summary.started_at = started_at;
+        report.summary.finished_at = unix_now();
+        report.add_storage_warnings(&health, &size_fallbacks);
+        report.errors = error_log.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        json = Some(report);
+    } else if config.format == OutputFormat::Text {
+        if config.porcelain {
+            counts.print_porcelain();
+        } else {
+            println!("{}", tr!("not-sized", summary = stats.skipped.summary()));
+            if !counts.errors.is_empty() {
+                println!("{}", tr!("scan-errors", summary = counts.error_summary()));
+            }
+        }
+        print_storage_warnings(&health, config.porcelain);
+        print_size_fallbacks(&size_fallbacks, config.porcelain);
+
+        if is_verbose {
+            if !config.porcelain {
+                println!();
+            }
+            error_log.lock().unwrap_or_else(PoisonError::into_inner).iter().for_each(|e| {
+                eprintln!("{}", e);
+            });
+        }
+
+        if !config.porcelain {
+            println!("\n");
+        }
+    }
+
+    let sections: Vec<ReportSection> = aggregators[..reported]
+        .iter()
+        .map(|aggregator| aggregator.finish())
+        .collect();
+    let mut stamps = scan_stamps.into_inner().unwrap_or_else(PoisonError::into_inner);
+    let mut entries = match &dir_totals {
+        Some(totals) => ranked_dirs(totals, config.min_size),
+        None => top_entries.get_entries(),
+    };
+    // Entries ranked by another metric carry their sizes from here on, in the ranked order
+    let measures: Option<HashMap<String, u64>> = match &ranked {
+        Some(ranked) => {
+            with_sizes(&mut entries, ranked);
+            Some(
+                entries
+                    .iter()
+                    .filter_map(|(path, _)| Some((path.clone(), ranked.get(path)?.1)))
+                    .collect(),
+            )
+        }
+        None => {
+            // Modification times as they were when the files were sized
+            report::order_entries(&mut entries, config.then_by, |path| {
+                stamps.get(path)?.modified
+            });
+            None
+        }
+    };
+    let mut more = entries.split_off(config.num_entries.min(entries.len()));
+    // Ties past the candidates were only kept to be ordered
+    more.truncate(MORE_CANDIDATES);
+    let kept: HashSet<&str> = entries.iter().chain(&more).map(|(path, _)| path.as_str()).collect();
+    stamps.retain(|path| kept.contains(path));
+    let partitions = partition_by_mount(&entries, &quota::system_mounts(), &root);
+    if entries.is_empty() {
+        if !config.machine_readable() {
+            println!("{}", tr!("no-files-found"));
+        }
+    } else {
+        let root = console::canonicalize(&config.root_path)
+            .unwrap_or_else(|_| config.root_path.clone());
+        let mut session = SavedScan::new(&root, &entries, &stamps).with_more(&more, &stamps);
+        session.dirs = config.dirs;
+        session.provenance.started_at = started_at;
+        session.provenance.config_hash = Some(config.settings_hash());
+        let previous = previous_sizes(&config, &session.provenance);
+        let facts = EntryFacts::gather(&config, &entries, &stamps, &hard_links, &size_fallbacks);
+
+        // Long scans often report files that were rotated away in the meantime
+        let (stale, unchecked) = stamps.recheck(
+            entries.iter().map(|(path, _)| path.as_str()),
+            RECHECK_BUDGET,
+        );
+        if config.wants_report(ReportKind::TopFiles) {
+            if let Some(json) = json.as_mut() {
+                json.add_entries(&entries, &stale, previous.as_ref());
+                if let Some(measures) = &measures {
+                    json.set_measures(config.metric, measures);
+                }
+                json.set_links(&facts.links);
+                json.set_other_sizes(config.apparent_size, &facts.other_sizes);
+                json.set_sparse(&facts.sparse);
+                json.set_partitions(&partitions);
+                json.set_roots(&config);
+                json.set_tags(&config.tags);
+                json.set_owners(&config.ownership);
+                json.set_costs(&config.costs);
+            } else if config.format == OutputFormat::Text {
+                let total_bytes = counts.bytes;
+                print_entries(
+                    &config,
+                    &entries,
+                    total_bytes,
+                    &stale,
+                    previous.as_ref(),
+                    measures.as_ref(),
+                    &facts,
+                );
+                if config.porcelain {
+                    print_partitions(&root, &partitions);
+                }
+                if unchecked > 0 && !config.porcelain {
+                    println!("{}", tr!("recheck-incomplete", count = unchecked));
+                }
+                if !more.is_empty() && !config.porcelain {
+                    println!("{}", tr!("more-available"));
+                }
+            }
+        }
+        for section in sections {
+            print_section(section, &config, json.as_mut());
+        }
+        if let Some(streams) = &streams {
+            print_section(streams.finish(), &config, json.as_mut());
+        }
+        if config.wants_report(ReportKind::FanOut) {
+            print_section(shape.section(), &config, json.as_mut());
+        }
+        if let (Some(tree), true) = (&scan_tree, config.tree) {
+            print_section(tree.tree_section(config.tree_depth), &config, json.as_mut());
+        }
+
+        if config.audit {
+            match json.as_mut() {
+                Some(json) => match audit::audit_entries(&entries) {
+                    Ok(findings) => json.set_audit(findings),
+                    Err(e) => log::warn!("{}", tr!("audit-skipped", error = e.to_string())),
+                },
+                None if config.format == OutputFormat::Text => {
+                    print_audit(&entries, config.porcelain)
+                }
+                None => {}
+            }
+        }
+
+        if config.attribute {
+            print_attribution(&entries);
+        }
+
+        save_session(&config, &session).map_err(FerrisError::report)?;
+        if !config.alerts.is_empty() {
+            let scan_end = UNIX_EPOCH + Duration::from_secs(session.provenance.finished_at);
+            let alerts = config
+                .alerts
+                .evaluate(&entries, previous.as_ref(), &stamps, scan_end);
+            match json.as_mut() {
+                Some(json) => json.add_alerts(&alerts),
+                None if config.format == OutputFormat::Text => {
+                    AlertRules::print(&alerts, config.porcelain)
+                }
+                None => {}
+            }
+            config.alerts.dispatch(&alerts);
+        }
+        if !config.read_only {
+            actions::run(&config, &entries, &stamps).map_err(FerrisError::action)?;
+        }
+    }
+
+    if let (Some(tree), Some(path)) = (&scan_tree, &config.export_ncdu) {
+        tree.save_ncdu(path, started_at).map_err(|e| {
+            FerrisError::report(io::Error::new(
+                e.kind(),
+                format!("could not write ncdu export {}: {}", path.display(), e),
+            ))
+        })?;
+        if !config.machine_readable() {
+            println!(
+                "{}",
+                tr!("ncdu-exported", file = path.display().to_string())
+            );
+        }
+    }
+    if let (Some(tree), Some(path)) = (&scan_tree, &config.export_treemap) {
+        tree.save_treemap(path).map_err(|e| {
+            FerrisError::report(io::Error::new(
+                e.kind(),
+                format!("could not write treemap export {}: {}", path.display(), e),
+            ))
+        })?;
+        if !config.machine_readable() {
+            println!(
+                "{}",
+                tr!("treemap-exported", file = path.display().to_string())
+            );
+        }
+    }
+    if let (Some(snapshot), Some(path)) = (&snapshot, &config.save_snapshot) {
+        snapshot.save(path).map_err(|e| {
+            FerrisError::report(io::Error::new(
+                e.kind(),
+                format!("could not save snapshot {}: {}", path.display(), e),
+            ))
+        })?;
+        if !config.machine_readable() {
+            println!(
+                "{}",
+                tr!(
+                    "snapshot-saved",
+                    files = snapshot.files.len(),
+                    file = path.display().to_string()
+                )
+            );
+        }
+    }
+    #[cfg(feature = "parquet")]
+    if let (Some(export), Some(path)) = (parquet_export, &config.export_parquet) {
+        let halted = export.is_halted();
+        let (_, rows) = export.close().map_err(|e| {
+            FerrisError::report(io::Error::new(
+                e.kind(),
+                format!("could not write Parquet export {}: {}", path.display(), e),
+            ))
+        })?;
+        if !config.machine_readable() {
+            println!(
+                "{}",
+                tr!(
+                    "parquet-exported",
+                    rows = rows,
+                    file = path.display().to_string()
+                )
+            );
+        }
+        if halted {
+            log::warn!(
+                "{}",
+                tr!(
+                    "parquet-export-halted",
+                    rows = rows,
+                    file = path.display().to_string(),
+                    reserve = config.min_free.format_size()
+                )
+            );
+        }
+    }
+
+    if config.quota {
+        print_quota(&config.root_path);
+    }
+
+    if config.deleted_open {
+        print_deleted_open(config.num_entries);
+    }
+
+    if let Ok(root) = console::canonicalize(&config.root_path) {
+        // The common ancestor of several roots was not scanned as a whole
+        if !config.machine_readable() && config.roots.is_empty() && reconcile::is_mount_point(&root) {
+            print_reconciliation(&root, &stats.skipped, &byte_totals);
+        }
+    }
+
+    if let Some(json) = json {
+        println!("{}", json.to_json());
+    }
+    if config.format == OutputFormat::Wire {
+        let root = console::canonicalize(&config.root_path)
+            .unwrap_or_else(|_| config.root_path.clone());
+        send_wire(
+            &root,
+            &entries,
+            &stamps,
+            &partitions,
+            started_at,
+            (counts.files, counts.processed, counts.failed),
+        )
+        .map_err(FerrisError::report)?;
+    }
+    Ok(())
+}
+
+/// Walks `config.root_path` like [`run`] and hands every sized file to `visit`, for commands
+/// that need one pass over the whole tree but no ranking, session or actions.
+///
+/// A spinner shows the progress unless `config.porcelain` is set, errors are printed with
+/// `config.verbose`, and storage warnings are printed once the walk is done.
+///
+/// # Errors
+///
+/// Returns an error if the directory scan fails.
+fn for_each_sized_file(
+    config: &Config,
+    mut visit: impl FnMut(FileInfo),
+) -> Result<(), Box<dyn Error>> {
+    let progress = ProgressBar::new_spinner();
+    if config.porcelain {
+        progress.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    progress.set_style(spinner_style());
+
+    let (tx, rx) = mpsc::channel();
+    let error_log: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let stats = Arc::new(ScanStats::default());
+    let byte_totals = Arc::new(ByteTotals::default());
+    let health = Arc::new(StorageHealth::default());
+    let size_fallbacks = SizeFallbacks::load()
+        .with_apparent_sizes(config.apparent_size)
+        .with_sparse_skipped(config.skip_sparse);
+    let hard_links = HardLinks::new(config.count_links);
+
+    let root_dirs = config.scan_roots();
+    let config_arc = Arc::new(config.clone());
+    let error_log_clone = error_log.clone();
+    let health_clone = Arc::clone(&health);
+    let scan_handle = thread::spawn(move || {
+        parallel_search(
+            &root_dirs,
+            tx,
+            progress,
+            config_arc,
+            error_log_clone,
+            stats,
+            byte_totals,
+            health_clone,
+            Arc::new(TreeShape::new(0)),
+        )
+    });
+
+    while let Ok(batch) = rx.recv() {
+        let sized: Vec<FileInfo> = batch
+            .into_par_iter()
+            .filter(|entry| entry.result.is_ok())
+            .filter_map(|entry| {
+                let metadata = fs::metadata(&entry.path).ok()?;
+                if !hard_links.claim(&metadata) || size_fallbacks.skips(&entry.path, &metadata) {
+                    return None;
+                }
+                let size = size_fallbacks.size(&entry.path, &metadata).ok()?;
+                Some(FileInfo {
+                    path: entry.path.to_string_lossy().into_owned(),
+                    size,
+                    modified: metadata.modified().ok(),
+                    uid: owner_of(&metadata),
+                    accessed: metadata.accessed().ok(),
+                })
+            })
+            .collect();
+        sized.into_iter().for_each(&mut visit);
+    }
+
+    match scan_handle.join() {
+        Ok(result) => result.map_err(Box::new)?,
+        Err(e) => {
+            if config.verbose {
+                error_log
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(format!("Scanner thread panicked: {:?}", e));
+            }
+        }
+    }
+    if config.verbose {
+        error_log.lock().unwrap_or_else(PoisonError::into_inner).iter().for_each(|e| {
+            eprintln!("{}", e);
+        });
+    }
+
+    print_storage_warnings(&health, config.porcelain);
+    print_size_fallbacks(&size_fallbacks, config.porcelain);
+    Ok(())
+}
+
+/// Scans `config.root_path` and prints how its space divides into broad categories.
+///
+/// Every file is sized like in [`run`] and classified by its path with
+/// [`categories::classify`]; no ranking, session or actions are involved. The result fits on
+/// one screen and answers where the space went before drilling down with a regular scan.
+///
+/// # Arguments
+///
+/// * `config` - An instance of a `Config` struct; only the scan settings are used
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Ok(()) if successful, or an Error if something fails
+///
+/// # Output
+///
+/// One line per category with its size, share of the total and number of files, or with
+/// `config.porcelain` one `category` record per category.
+pub fn summarize(config: Config) -> Result<(), Box<dyn Error>> {
+    if config.porcelain {
+        porcelain::print_version();
+    } else {
+        println!(
+            "{}",
+            tr!(
+                "summarizing",
+                root = config.root_path.display().to_string()
+            )
+        );
+    }
+
+    let mut totals = CategoryTotals::default();
+    for_each_sized_file(&config, |file| {
+        let category = categories::classify(Path::new(&file.path));
+        totals.add(category, file.size);
+        if let Some(cost) = config.costs.monthly(&file.path, file.size) {
+            totals.add_cost(category, cost);
+        }
+    })?;
+
+    let total = totals.total_bytes();
+    if !config.porcelain {
+        println!();
+    }
+    for category in Category::ALL {
+        let (bytes, files) = totals.get(category);
+        if config.porcelain {
+            println!(
+                "{}",
+                porcelain::record(
+                    "category",
+                    &[category.key(), &bytes.to_string(), &files.to_string()]
+                )
+            );
+            continue;
+        }
+        let share = if total == 0 {
+            0.0
+        } else {
+            bytes as f64 * 100.0 / total as f64
+        };
+        let line = format!(
+            "{:<20} {:>10} {:>6.1}%  {}",
+            category.label(),
+            bytes.format_size(),
+            share,
+            tr!("category-files", files = files)
+        );
+        if config.costs.is_enabled() {
+            println!("{}  {}", line, format_monthly(totals.cost(category)));
+        } else {
+            println!("{}", line);
+        }
+    }
+    if config.porcelain {
+        if config.costs.is_enabled() {
+            println!(
+                "{}",
+                porcelain::record(
+                    "cost-total",
+                    &[&format!("{:.4}", totals.total_cost()), &total.to_string()]
+                )
+            );
+        }
+    } else if config.costs.is_enabled() {
+        println!(
+            "{:<20} {:>10}  {}",
+            tr!("category-total"),
+            total.format_size(),
+            format_monthly(totals.total_cost())
+        );
+    } else {
+        println!("{:<20} {:>10}", tr!("category-total"), total.format_size());
+    }
+
+    Ok(())
+}
+
+/// Scans `config.root_path` and prints how much space `policy` would reclaim, per directory,
+/// without deleting anything.
+///
+/// Every file is sized like in [`run`] and checked against the policy as of the start of the
+/// scan; no ranking, session or actions are involved, so teams can weigh a retention policy
+/// before enforcing it.
+///
+/// # Arguments
+///
+/// * `config` - An instance of a `Config` struct; the scan settings, `num_entries` (the number
+///   of directories listed) and the storage rates are used
+/// * `policy` - The retention policy to simulate
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Ok(()) if successful, or an Error if something fails
+///
+/// # Output
+///
+/// The directories where the policy reclaims the most, then the reclaimable total against
+/// everything scanned, or with `config.porcelain` the `retention-dir` and `retention-total`
+/// records.
+pub fn simulate(config: Config, policy: RetentionPolicy) -> Result<(), Box<dyn Error>> {
+    if config.porcelain {
+        porcelain::print_version();
+    } else {
+        println!(
+            "{}",
+            tr!(
+                "simulating",
+                root = config.root_path.display().to_string()
+            )
+        );
+    }
+
+    let mut simulation = RetentionSimulation::new(policy, config.num_entries, SystemTime::now())
+        .with_costs(config.costs.clone());
+    for_each_sized_file(&config, |file| simulation.observe(&file))?;
+    simulation.finish().print(config.porcelain);
+    Ok(())
+}
+
+/// Scans `config.root_path`, then walks down from it interactively: prints the largest child
+/// of the current directory and asks whether to descend into it, until a file is reached.
+///
+/// A minimal alternative to a full TUI: one scan, then a line and a prompt per level (see
+/// [`drill::drill_down`]).
+///
+/// # Arguments
+///
+/// * `config` - An instance of a `Config` struct; only the scan settings are used
+/// * `levels` - Optional number of levels after which to stop descending
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Ok(()) if successful, or an Error if something fails
+///
+/// # Errors
+///
+/// Returns an error if `config` asks for porcelain or structured output, which an interactive
+/// walk cannot provide, or if the scan or the prompts fail.
+pub fn drill(config: Config, levels: Option<usize>) -> Result<(), Box<dyn Error>> {
+    if config.porcelain || config.format != OutputFormat::Text {
+        return Err(tr!("drill-interactive").into());
+    }
+    println!(
+        "{}",
+        tr!("drilling", root = config.root_path.display().to_string())
+    );
 
+    let mut tree = DrillTree::new(&canonical_root(&config.root_path));
+    for_each_sized_file(&config, |file| tree.observe(&file))?;
+    println!();
+    drill::drill_down(&tree, io::stdin().lock(), io::stdout().lock(), levels)?;
     Ok(())
 }
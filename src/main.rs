@@ -1,29 +1,179 @@
-use clap::Parser;
-use ferris_files::{args::Args, config::Config, run};
-use std::process;
+use ferris_files::actions::undo::undo_last;
+use ferris_files::alerts;
+use ferris_files::args::{Args, Command};
+use ferris_files::errors::FerrisError;
+use ferris_files::fleet::FleetOptions;
+use ferris_files::output::OutputFormat;
+use ferris_files::retention::RetentionPolicy;
+use ferris_files::serve::{serve, ServeOptions, TOKEN_VAR};
+use ferris_files::{
+    commands, config::Config, console, drill, i18n, integration, run, simulate, storage, summarize,
+    tr, units,
+};
 use std::time::Instant;
 
 fn main() {
-    std::env::set_var("RUST_LOG", "trace");
+    // The glob matchers log every rule set they compile, once per directory with ignore files
+    std::env::set_var("RUST_LOG", "trace,globset=warn,ignore=warn");
     env_logger::init();
 
     let start = Instant::now();
 
-    let args = Args::parse();
+    let args = Args::try_parse_with_sources(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    if args.assert_read_only {
+        storage::enforce_read_only();
+    }
+    i18n::init(args.lang.as_deref());
+    units::use_utc(args.utc);
+    console::enable_utf8();
+
+    if args.portable {
+        if let Err(e) = storage::enable_portable() {
+            log::error!("{}", tr!("portable-failed", error = e.to_string()));
+            console::exit(1);
+        }
+    }
+
+    if let Some(command) = &args.command {
+        if args.assert_read_only && command.writes() {
+            log::error!("{}", tr!("read-only-command"));
+            console::exit(1);
+        }
+        let result = match command {
+            Command::Undo => undo_last().map_err(Into::into),
+            Command::Clean { ids, actions } => commands::clean(ids, actions),
+            Command::Reveal { ids } => commands::reveal(ids),
+            Command::Copy { ids } => commands::copy(ids),
+            Command::Diff {
+                old,
+                new,
+                num_entries,
+                session,
+            } => commands::diff(old, new.as_deref(), session, *num_entries, args.porcelain),
+            Command::Merge {
+                sessions,
+                num_entries,
+                output,
+            } => commands::merge(sessions, *num_entries, output.as_deref(), args.porcelain),
+            Command::Fleet {
+                hosts,
+                directory,
+                num_entries,
+                jobs,
+                timeout,
+                remote_command,
+                output,
+            } => commands::fleet(
+                hosts,
+                &FleetOptions {
+                    directory: directory.clone(),
+                    num_entries: *num_entries,
+                    jobs: *jobs,
+                    timeout: *timeout,
+                    remote_command: remote_command.clone(),
+                },
+                output.as_deref(),
+                args.porcelain,
+            ),
+            Command::Query {
+                min_size,
+                glob,
+                modified_within,
+                session,
+            } => commands::query(
+                *min_size,
+                glob.as_deref(),
+                *modified_within,
+                session,
+                args.porcelain,
+            ),
+            Command::More {
+                num_entries,
+                session,
+            } => commands::more(*num_entries, session, args.porcelain),
+            Command::Summary { directory } => Config::build(&args).and_then(|mut config| {
+                if let Some(directory) = directory {
+                    config.root_path = directory.into();
+                    config.roots.clear();
+                }
+                summarize(config)
+            }),
+            Command::Simulate {
+                directory,
+                delete_older_than,
+                matches,
+            } => Config::build(&args).and_then(|mut config| {
+                if let Some(directory) = directory {
+                    config.root_path = directory.into();
+                    config.roots.clear();
+                }
+                simulate(config, RetentionPolicy::new(*delete_older_than, matches)?)
+            }),
+            Command::Drill { directory, levels } => Config::build(&args).and_then(|mut config| {
+                if let Some(directory) = directory {
+                    config.root_path = directory.into();
+                    config.roots.clear();
+                }
+                drill(config, *levels)
+            }),
+            Command::Serve {
+                listen,
+                token,
+                allow_actions,
+            } => Config::build(&args).and_then(|config| {
+                let options = ServeOptions {
+                    listen: *listen,
+                    token: token.clone().or_else(|| std::env::var(TOKEN_VAR).ok()),
+                    allow_actions: *allow_actions,
+                };
+                serve(config, &options)
+            }),
+            Command::InstallShellIntegration => integration::install_shell_integration(),
+            Command::UninstallShellIntegration => integration::uninstall_shell_integration(),
+            Command::InstallQuickAction => integration::install_quick_action(),
+            Command::UninstallQuickAction => integration::uninstall_quick_action(),
+        };
+        if let Err(e) = result {
+            log::error!("{e}");
+            console::exit(1);
+        }
+        console::restore();
+        return;
+    }
 
     let config = Config::build(&args).unwrap_or_else(|err| {
-        log::error!("Could not parse arguments: {}", err);
-        process::exit(1);
+        log::error!("{}", tr!("arguments-invalid", error = err.to_string()));
+        console::exit(FerrisError::config(err).exit_code());
     });
 
+    if args.print_config {
+        match config.format {
+            OutputFormat::Json => println!("{}", config.settings_json()),
+            _ => print!("{}", config.settings_toml()),
+        }
+        console::restore();
+        return;
+    }
+
+    let machine_readable = config.machine_readable();
     if let Err(e) = run(config) {
-        log::error!("Fatal Error: {e}");
-        process::exit(1);
+        log::error!("{}", tr!("fatal-error", error = e.to_string()));
+        console::exit(e.exit_code());
     }
 
-    let duration = start.elapsed();
-    println!(
-        "\nProgram completed in {:?} seconds",
-        duration.as_secs_f32()
-    );
+    if !machine_readable {
+        let duration = start.elapsed();
+        println!(
+            "\n{}",
+            tr!(
+                "completed",
+                seconds = format!("{:?}", duration.as_secs_f32())
+            )
+        );
+    }
+
+    if alerts::exit_requested() {
+        console::exit(alerts::ALERT_EXIT_CODE);
+    }
+    console::restore();
 }
@@ -0,0 +1,222 @@
+use crate::quota::{mount_for_path, MountInfo};
+use filesize::PathExt;
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Filesystem types (as listed in `/proc/self/mountinfo`) served over the network.
+const NETWORK_FS_TYPES: [&str; 11] = [
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afs",
+    "9p",
+    "ceph",
+    "glusterfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+];
+
+/// Returns true if `fs_type` is a network filesystem, whose block counts come from the server.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::netfs::is_network_fs;
+///
+/// assert!(is_network_fs("nfs4"));
+/// assert!(is_network_fs("cifs"));
+/// assert!(!is_network_fs("ext4"));
+/// ```
+pub fn is_network_fs(fs_type: &str) -> bool {
+    NETWORK_FS_TYPES.contains(&fs_type)
+}
+
+/// Returns true if an allocated size of `allocated` bytes cannot be right for a file of
+/// `len` bytes.
+///
+/// Some NFS and SMB servers report no blocks at all for files with data (e.g. files that are
+/// still being written, or backends that do not track allocation), which would size them as
+/// empty.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::netfs::blocks_unreliable;
+///
+/// assert!(blocks_unreliable(4096, 0));
+/// assert!(!blocks_unreliable(4096, 4096));
+/// assert!(!blocks_unreliable(0, 0));
+/// ```
+pub fn blocks_unreliable(len: u64, allocated: u64) -> bool {
+    len > 0 && allocated == 0
+}
+
+/// Files shorter than this are never taken for sparse, as block rounding and data stored inline
+/// in the inode make small files look half empty.
+const SPARSE_MIN_LEN: u64 = 1 << 20;
+
+/// Returns true if a file of `len` bytes with `allocated` bytes of blocks is sparse: at least
+/// 1 MiB long, less than half of it is allocated.
+///
+/// Files on compressing filesystems look the same from their metadata and are counted as sparse
+/// too; either way, they take much less space than their length says.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::netfs::is_sparse;
+///
+/// // A 20 GB VM image with 1 GB written
+/// assert!(is_sparse(20 << 30, 1 << 30));
+/// assert!(!is_sparse(20 << 30, 20 << 30));
+/// assert!(!is_sparse(4000, 0));
+/// ```
+pub fn is_sparse(len: u64, allocated: u64) -> bool {
+    len >= SPARSE_MIN_LEN && allocated < len / 2
+}
+
+/// A network mount whose block counts were found unreliable during a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackNote {
+    pub mount: MountInfo,
+    pub files: usize,
+}
+
+/// Sizes files by their allocated blocks, falling back to the apparent size for files on
+/// network mounts whose block counts are unreliable, or by their apparent size throughout (see
+/// [`SizeFallbacks::with_apparent_sizes`]).
+///
+/// Every fallback is counted per mount so the report can say which numbers are apparent sizes.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::netfs::SizeFallbacks;
+/// use ferris_files::quota::parse_mountinfo;
+/// use std::path::Path;
+///
+/// let fallbacks = SizeFallbacks::new(parse_mountinfo(
+///     "40 22 0:51 / /mnt/share rw - nfs4 nas:/export rw\n",
+/// ));
+/// assert_eq!(fallbacks.allocated_or_apparent(Path::new("/mnt/share/a.iso"), 700, 0), 700);
+/// assert_eq!(fallbacks.allocated_or_apparent(Path::new("/home/a.iso"), 700, 0), 0);
+///
+/// let notes = fallbacks.notes();
+/// assert_eq!(notes[0].mount.mount_point, Path::new("/mnt/share"));
+/// assert_eq!(notes[0].files, 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct SizeFallbacks {
+    mounts: Vec<MountInfo>,
+    notes: Mutex<HashMap<PathBuf, FallbackNote>>,
+    apparent: bool,
+    skip_sparse: bool,
+}
+
+impl SizeFallbacks {
+    /// Creates fallbacks for the given mount table.
+    pub fn new(mounts: Vec<MountInfo>) -> Self {
+        SizeFallbacks {
+            mounts,
+            ..Default::default()
+        }
+    }
+
+    /// Creates fallbacks for the mounts of the running system.
+    ///
+    /// Only Linux exposes a mount table with filesystem types; elsewhere sizes are never
+    /// replaced.
+    pub fn load() -> Self {
+        SizeFallbacks::new(crate::quota::system_mounts())
+    }
+
+    /// Sizes every file by its apparent size (`--apparent-size`) if `apparent` is set, which
+    /// leaves nothing to fall back from. Sparse files then count with their holes and files on
+    /// compressing filesystems with their uncompressed length.
+    pub fn with_apparent_sizes(mut self, apparent: bool) -> Self {
+        self.apparent = apparent;
+        self
+    }
+
+    /// Leaves sparse files out of the scan (`--skip-sparse`) if `skip` is set (see
+    /// [`SizeFallbacks::skips`]).
+    pub fn with_sparse_skipped(mut self, skip: bool) -> Self {
+        self.skip_sparse = skip;
+        self
+    }
+
+    /// Returns true if the file at `path` is sparse (see [`is_sparse`]).
+    ///
+    /// Files on network mounts reporting no blocks for them are not: their allocation is
+    /// unknown rather than empty.
+    pub fn sparse(&self, path: &Path, metadata: &Metadata) -> bool {
+        let Ok(allocated) = path.size_on_disk_fast(metadata) else {
+            return false;
+        };
+        let len = metadata.len();
+        if !is_sparse(len, allocated) {
+            return false;
+        }
+        !blocks_unreliable(len, allocated)
+            || mount_for_path(&self.mounts, path).is_none_or(|mount| !is_network_fs(&mount.fs_type))
+    }
+
+    /// Returns true if sparse files are skipped and the file at `path` is one.
+    pub fn skips(&self, path: &Path, metadata: &Metadata) -> bool {
+        self.skip_sparse && self.sparse(path, metadata)
+    }
+
+    /// Returns the size on disk of the file at `path`, or its apparent size if it lives on a
+    /// network mount that reported unreliable blocks for it or apparent sizes were asked for.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from determining the size on disk.
+    pub fn size(&self, path: &Path, metadata: &Metadata) -> io::Result<u64> {
+        if self.apparent {
+            return Ok(metadata.len());
+        }
+        let allocated = path.size_on_disk_fast(metadata)?;
+        Ok(self.allocated_or_apparent(path, metadata.len(), allocated))
+    }
+
+    /// Chooses between the `allocated` and apparent (`len`) size of the file at `path`.
+    pub fn allocated_or_apparent(&self, path: &Path, len: u64, allocated: u64) -> u64 {
+        if !blocks_unreliable(len, allocated) {
+            return allocated;
+        }
+        match mount_for_path(&self.mounts, path) {
+            Some(mount) if is_network_fs(&mount.fs_type) => {
+                self.notes
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .entry(mount.mount_point.clone())
+                    .or_insert_with(|| FallbackNote {
+                        mount: mount.clone(),
+                        files: 0,
+                    })
+                    .files += 1;
+                len
+            }
+            _ => allocated,
+        }
+    }
+
+    /// Returns the mounts where apparent sizes were used, ordered by mount point.
+    pub fn notes(&self) -> Vec<FallbackNote> {
+        let mut notes: Vec<FallbackNote> = self
+            .notes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect();
+        notes.sort_by(|a, b| a.mount.mount_point.cmp(&b.mount.mount_point));
+        notes
+    }
+}
@@ -0,0 +1,450 @@
+use crate::actions::guard::Staleness;
+use crate::alerts::Alert;
+use crate::audit::AuditFinding;
+use crate::config::Config;
+use crate::cost::CostModel;
+use crate::health::StorageHealth;
+use crate::netfs::SizeFallbacks;
+use crate::ownership::{Owner, OwnershipMap};
+use crate::report::{Metric, ReportSection};
+use crate::results::{entry_id, MountPartition};
+use crate::stats::ScanStats;
+use crate::tags::TagMap;
+use crate::traits::ByteSize;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Version of the JSON document, bumped only when a field is renamed, removed or changes meaning.
+pub const JSON_VERSION: u64 = 1;
+
+/// Format of the final results of a scan or import.
+///
+/// * `text` - Human readable lines, or tab separated records with `--porcelain`
+/// * `json` - One JSON document with the results, report sections, warnings and summary
+///   (see [`JsonReport`]), printed once the scan is done
+/// * `wire` - The ranked files and counts as compressed binary frames for another ferris-files
+///   to read with `--import wire` (see [`crate::wire`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Wire,
+}
+
+/// The results of a scan as printed by `--format json`.
+///
+/// Optional sections are left out when they were not requested or are empty, so their absence
+/// means "nothing to report".
+///
+/// * `version` - [`JSON_VERSION`]
+/// * `root` - Scanned directory, or imported inventory
+/// * `entries` - The ranked files, largest first (empty if `--report` leaves them out)
+/// * `partitions` - The IDs of the entries by root and, below it, by mount (see
+///   [`JsonPartition`])
+/// * `reports` - Sections of the other reports requested with `--report`
+/// * `audit` - Findings of `--audit`
+/// * `alerts` - Alert rules that fired
+/// * `disk_errors` / `apparent_sizes` - Storage warnings, see the README
+/// * `summary` - Counts of the files seen, sized, failed and skipped
+/// * `errors` - Individual errors, collected with `--verbose`
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::output::JsonReport;
+/// use std::collections::HashMap;
+///
+/// let mut report = JsonReport::new("/data");
+/// report.add_entries(&[("/data/big.iso".to_string(), 2048)], &HashMap::new(), None);
+/// report.summary.files = 1;
+///
+/// let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+/// assert_eq!(json["entries"][0]["rank"], 1);
+/// assert_eq!(json["entries"][0]["size"], 2048);
+/// assert_eq!(json["entries"][0]["human_size"], "2.00 KB");
+/// assert_eq!(json["summary"]["files"], 1);
+/// assert!(json.get("audit").is_none());
+/// ```
+#[derive(Debug, Default, Serialize)]
+pub struct JsonReport {
+    pub version: u64,
+    pub root: String,
+    pub entries: Vec<JsonEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub partitions: Vec<JsonPartition>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reports: Vec<JsonSection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit: Option<Vec<JsonAuditFinding>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<JsonAlert>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub disk_errors: Vec<JsonDiskErrors>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub apparent_sizes: Vec<JsonApparentSizes>,
+    pub summary: JsonSummary,
+    pub errors: Vec<String>,
+}
+
+/// A ranked file.
+///
+/// * `root` - The scanned directory the file lies beneath; only when several were scanned
+/// * `previous_size` - Size in the previous session of the same root (`null` for new entrants);
+///   absent without a previous session
+/// * `stale` / `current_size` - How the file changed since it was sized (`gone`, `replaced` or
+///   `resized`, with its new size); absent if it did not
+/// * `tag` - Label of the file in the tag map; absent if no rule matches it
+/// * `ownership` - `owner`, `team` and `cost_center` of the file in the ownership mapping (each
+///   absent if unknown); absent if no prefix covers it
+/// * `monthly_cost` - Estimated monthly storage cost of the file; absent without a rate for it
+/// * `modified` / `accessed` - Last modification or access in seconds since the Unix epoch; only
+///   with `--metric mtime` or `atime`
+/// * `links` - Number of hard links of the file, which is sized once; absent for a single link
+/// * `apparent_size` / `disk_size` - The size not ranked by (the apparent size, or the size on
+///   disk with `--apparent-size`); absent unless it differs by more than a tenth
+/// * `sparse` - `true` if the file is sparse; absent otherwise
+#[derive(Debug, Serialize)]
+pub struct JsonEntry {
+    pub rank: usize,
+    pub id: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    pub size: u64,
+    pub human_size: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_size: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ownership: Option<Owner>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apparent_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparse: Option<bool>,
+}
+
+/// A section of another report, with the rows in their porcelain form (record type and fields).
+#[derive(Debug, Serialize)]
+pub struct JsonSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    pub rows: Vec<JsonRow>,
+}
+
+/// A row of a [`JsonSection`].
+#[derive(Debug, Serialize)]
+pub struct JsonRow {
+    pub kind: String,
+    pub fields: Vec<String>,
+}
+
+/// A reported file with risky permissions.
+#[derive(Debug, Serialize)]
+pub struct JsonAuditFinding {
+    pub path: String,
+    pub size: u64,
+    pub flags: Vec<&'static str>,
+}
+
+/// An alert that fired; `rule` counts from 1 in the order of the alerts files.
+#[derive(Debug, Serialize)]
+pub struct JsonAlert {
+    pub rule: usize,
+    pub path: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub growth: Option<u64>,
+}
+
+/// A directory where reads failed in ways that suggest a failing disk.
+#[derive(Debug, Serialize)]
+pub struct JsonDiskErrors {
+    pub path: String,
+    pub io_errors: usize,
+    pub timeouts: usize,
+}
+
+/// The entries of one root, split by the mount they live on, the largest mount first.
+///
+/// A document holds the results of a single root, so there is one partition; the array leaves
+/// room for documents covering several.
+#[derive(Debug, Serialize)]
+pub struct JsonPartition {
+    pub root: String,
+    pub mounts: Vec<JsonMount>,
+}
+
+/// The entries living on one mount; `fs_type` is empty and `mount_point` the root where the
+/// mount is unknown.
+#[derive(Debug, Serialize)]
+pub struct JsonMount {
+    pub mount_point: String,
+    pub fs_type: String,
+    pub size: u64,
+    pub entries: Vec<String>,
+}
+
+/// A network mount whose files were sized by their apparent size.
+#[derive(Debug, Serialize)]
+pub struct JsonApparentSizes {
+    pub mount_point: String,
+    pub fs_type: String,
+    pub files: usize,
+}
+
+/// Counts of a scan or import.
+///
+/// * `files` - Files received from the scanner or read from the inventory
+/// * `dirs` - Directories listed
+/// * `processed` / `failed` - Files that could and could not be sized
+/// * `bytes` - Total size of the files that were sized, which the entries are a share of
+/// * `skipped` - Entries left out by kind, e.g. `symlinks` (only non-zero counts)
+/// * `errors` - Errors by class, e.g. `permission-denied` (only non-zero counts)
+/// * `started_at` / `finished_at` - Seconds since the Unix epoch
+#[derive(Debug, Default, Serialize)]
+pub struct JsonSummary {
+    pub files: usize,
+    pub dirs: usize,
+    pub processed: usize,
+    pub failed: usize,
+    pub bytes: u64,
+    pub skipped: BTreeMap<String, usize>,
+    pub errors: BTreeMap<String, usize>,
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+impl JsonReport {
+    /// Starts an empty document for the results of `root`.
+    pub fn new(root: &str) -> Self {
+        JsonReport {
+            version: JSON_VERSION,
+            root: root.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds the ranked `entries` with the same annotations as the text output.
+    pub fn add_entries(
+        &mut self,
+        entries: &[(String, u64)],
+        stale: &HashMap<String, Staleness>,
+        previous: Option<&HashMap<String, u64>>,
+    ) {
+        self.entries
+            .extend(entries.iter().enumerate().map(|(index, (path, size))| {
+                let state = stale.get(path);
+                JsonEntry {
+                    rank: index + 1,
+                    id: entry_id(path),
+                    path: path.clone(),
+                    root: None,
+                    size: *size,
+                    human_size: size.format_size(),
+                    previous_size: previous.map(|sizes| sizes.get(path).copied()),
+                    stale: state.map(Staleness::key),
+                    current_size: match state {
+                        Some(Staleness::Resized(len)) => Some(*len),
+                        _ => None,
+                    },
+                    tag: None,
+                    ownership: None,
+                    monthly_cost: None,
+                    modified: None,
+                    accessed: None,
+                    links: None,
+                    apparent_size: None,
+                    disk_size: None,
+                    sparse: None,
+                }
+            }));
+    }
+
+    /// Lists the IDs of the entries of the root by the mount they live on.
+    pub fn set_partitions(&mut self, partitions: &[MountPartition]) {
+        let mounts = partitions
+            .iter()
+            .map(|partition| JsonMount {
+                mount_point: partition.mount_point.to_string_lossy().into_owned(),
+                fs_type: partition.fs_type.clone(),
+                size: partition.size,
+                entries: partition.entries.iter().map(|path| entry_id(path)).collect(),
+            })
+            .collect();
+        self.partitions = vec![JsonPartition {
+            root: self.root.clone(),
+            mounts,
+        }];
+    }
+
+    /// Gives the ranked entries the times they were ranked by with `metric`; other measures
+    /// are already part of the entries.
+    pub fn set_measures(&mut self, metric: Metric, measures: &HashMap<String, u64>) {
+        for entry in &mut self.entries {
+            let measure = measures.get(&entry.path).copied();
+            match metric {
+                Metric::Mtime => entry.modified = measure,
+                Metric::Atime => entry.accessed = measure,
+                Metric::Size | Metric::PathLength => {}
+            }
+        }
+    }
+
+    /// Gives the ranked entries with several hard links their number of links.
+    pub fn set_links(&mut self, links: &HashMap<String, u64>) {
+        for entry in &mut self.entries {
+            entry.links = links.get(&entry.path).copied();
+        }
+    }
+
+    /// Gives the ranked entries the size they were not ranked by, the size on disk if
+    /// `apparent` sizes were ranked and the apparent size otherwise, where it differs.
+    pub fn set_other_sizes(&mut self, apparent: bool, sizes: &HashMap<String, u64>) {
+        for entry in &mut self.entries {
+            let size = sizes.get(&entry.path).copied();
+            if apparent {
+                entry.disk_size = size;
+            } else {
+                entry.apparent_size = size;
+            }
+        }
+    }
+
+    /// Flags the ranked entries that are `sparse`.
+    pub fn set_sparse(&mut self, sparse: &HashSet<String>) {
+        for entry in &mut self.entries {
+            entry.sparse = sparse.contains(&entry.path).then_some(true);
+        }
+    }
+
+    /// Labels the ranked entries with the root they were found beneath, if several were scanned.
+    pub fn set_roots(&mut self, config: &Config) {
+        for entry in &mut self.entries {
+            entry.root = config
+                .root_of(&entry.path)
+                .map(|root| root.to_string_lossy().into_owned());
+        }
+    }
+
+    /// Labels the ranked entries with their tags.
+    pub fn set_tags(&mut self, tags: &TagMap) {
+        for entry in &mut self.entries {
+            entry.tag = tags.tag(&entry.path).map(str::to_string);
+        }
+    }
+
+    /// Joins the ownership mapping into the ranked entries.
+    pub fn set_owners(&mut self, owners: &OwnershipMap) {
+        for entry in &mut self.entries {
+            entry.ownership = owners.owner(&entry.path).cloned();
+        }
+    }
+
+    /// Estimates the monthly storage cost of the ranked entries.
+    pub fn set_costs(&mut self, costs: &CostModel) {
+        for entry in &mut self.entries {
+            entry.monthly_cost = costs.monthly(&entry.path, entry.size);
+        }
+    }
+
+    /// Adds a report section.
+    pub fn add_section(&mut self, section: ReportSection) {
+        self.reports.push(JsonSection {
+            heading: section.heading,
+            rows: section
+                .rows
+                .into_iter()
+                .map(|row| JsonRow {
+                    kind: row.kind,
+                    fields: row.fields,
+                })
+                .collect(),
+        });
+    }
+
+    /// Sets the findings of the permission audit.
+    pub fn set_audit(&mut self, findings: Vec<AuditFinding>) {
+        self.audit = Some(
+            findings
+                .into_iter()
+                .map(|finding| JsonAuditFinding {
+                    flags: finding.flags.labels(),
+                    path: finding.path,
+                    size: finding.size,
+                })
+                .collect(),
+        );
+    }
+
+    /// Adds the alerts that fired.
+    pub fn add_alerts(&mut self, alerts: &[Alert]) {
+        self.alerts.extend(alerts.iter().map(|alert| JsonAlert {
+            rule: alert.rule + 1,
+            path: alert.path.clone(),
+            size: alert.size,
+            growth: alert.growth,
+        }));
+    }
+
+    /// Adds the storage warnings gathered during a scan.
+    pub fn add_storage_warnings(&mut self, health: &StorageHealth, size_fallbacks: &SizeFallbacks) {
+        self.disk_errors
+            .extend(health.suspects().into_iter().map(|suspect| JsonDiskErrors {
+                path: suspect.path.to_string_lossy().into_owned(),
+                io_errors: suspect.counts.io_errors,
+                timeouts: suspect.counts.timeouts,
+            }));
+        self.apparent_sizes
+            .extend(
+                size_fallbacks
+                    .notes()
+                    .into_iter()
+                    .map(|note| JsonApparentSizes {
+                        mount_point: note.mount.mount_point.to_string_lossy().into_owned(),
+                        fs_type: note.mount.fs_type.clone(),
+                        files: note.files,
+                    }),
+            );
+    }
+
+    /// Records the counts of a scan, with the non-zero skip counters keyed without their
+    /// `skipped-` prefix.
+    pub fn set_stats(&mut self, stats: &ScanStats) {
+        let counts = stats.snapshot();
+        self.summary.files = counts.files;
+        self.summary.dirs = counts.dirs;
+        self.summary.processed = counts.processed;
+        self.summary.failed = counts.failed;
+        self.summary.bytes = counts.bytes;
+        self.summary.errors = counts.errors;
+        self.summary.skipped = stats
+            .skipped
+            .counts()
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(id, count)| (id.trim_start_matches("skipped-").to_string(), count))
+            .collect();
+    }
+
+    /// Serializes the document, pretty printed.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("the JSON report only holds serializable data")
+    }
+}
@@ -0,0 +1,318 @@
+use crate::cost::{CostModel, GroupCosts};
+use crate::report::{share_line, Aggregator, FileInfo, ReportRow, ReportSection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Who a path belongs to, for chargeback: any of an owner, a team and a cost center.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Owner {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(
+        default,
+        alias = "cost-center",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cost_center: Option<String>,
+}
+
+impl Owner {
+    /// Returns the known fields joined by ` / `, e.g. `alice / analytics / CC-1001`.
+    pub fn label(&self) -> String {
+        [&self.owner, &self.team, &self.cost_center]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+/// A row of an ownership mapping.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct OwnershipRule {
+    prefix: String,
+    #[serde(flatten)]
+    owner: Owner,
+}
+
+/// An ownership mapping exported from an inventory or CMDB: path prefixes and who they belong to.
+///
+/// A path belongs to the rule with the longest prefix containing it, compared by whole path
+/// components, so `/srv/analytics` covers `/srv/analytics/model.bin` but not `/srv/analytics2`.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::ownership::OwnershipMap;
+///
+/// let owners = OwnershipMap::parse_csv("\
+/// prefix,owner,team,cost_center
+/// /srv,ops,platform,CC-1000
+/// /srv/analytics,alice,analytics,\"CC-1001, shared\"
+/// ").unwrap();
+/// let owner = owners.owner("/srv/analytics/model.bin").unwrap();
+/// assert_eq!(owner.owner.as_deref(), Some("alice"));
+/// assert_eq!(owner.cost_center.as_deref(), Some("CC-1001, shared"));
+/// assert_eq!(owners.owner("/srv/analytics2/x").unwrap().label(), "ops / platform / CC-1000");
+/// assert!(owners.owner("/home/alice/x").is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OwnershipMap {
+    // Longest prefixes first, so the first match is the most specific
+    rules: Vec<OwnershipRule>,
+}
+
+impl OwnershipMap {
+    fn new(mut rules: Vec<OwnershipRule>) -> Result<OwnershipMap, String> {
+        if let Some(rule) = rules.iter().find(|rule| rule.prefix.is_empty()) {
+            return Err(format!(
+                "ownership rule for '{}' has no prefix",
+                rule.owner.label()
+            ));
+        }
+        if let Some(rule) = rules.iter().find(|rule| rule.owner == Owner::default()) {
+            return Err(format!(
+                "ownership rule for '{}' names no owner, team or cost center",
+                rule.prefix
+            ));
+        }
+        rules.sort_by_key(|rule| std::cmp::Reverse(Path::new(&rule.prefix).components().count()));
+        Ok(OwnershipMap { rules })
+    }
+
+    /// Parses a CSV mapping with a header row naming its columns: `prefix` and any of `owner`,
+    /// `team` and `cost_center`, in any order. Other columns are ignored, fields may be quoted
+    /// with `"` and empty fields are unknown.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the header has no `prefix` column, a row has
+    /// an unterminated quote or a rule has no prefix or names nobody.
+    pub fn parse_csv(contents: &str) -> Result<OwnershipMap, String> {
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let header = parse_csv_row(lines.next().unwrap_or_default())?;
+        let column = |name: &str| {
+            header.iter().position(|field| {
+                field.trim().eq_ignore_ascii_case(name)
+                    || field.trim().eq_ignore_ascii_case(&name.replace('_', "-"))
+            })
+        };
+        let prefix = column("prefix").ok_or("the ownership CSV has no prefix column")?;
+        let (owner, team, cost_center) = (column("owner"), column("team"), column("cost_center"));
+
+        let mut rules = Vec::new();
+        for line in lines {
+            let fields = parse_csv_row(line)?;
+            let field = |index: Option<usize>| {
+                index
+                    .and_then(|index| fields.get(index))
+                    .map(|field| field.trim().to_string())
+                    .filter(|field| !field.is_empty())
+            };
+            rules.push(OwnershipRule {
+                prefix: field(Some(prefix)).unwrap_or_default(),
+                owner: Owner {
+                    owner: field(owner),
+                    team: field(team),
+                    cost_center: field(cost_center),
+                },
+            });
+        }
+        Self::new(rules)
+    }
+
+    /// Parses a JSON mapping: an array of objects with a `prefix` and any of `owner`, `team`
+    /// and `cost_center`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the document is not such an array or a rule
+    /// has no prefix or names nobody.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::ownership::OwnershipMap;
+    ///
+    /// let owners = OwnershipMap::parse_json(r#"[
+    ///     {"prefix": "/srv/analytics", "team": "analytics", "cost_center": "CC-1001"}
+    /// ]"#).unwrap();
+    /// assert_eq!(owners.owner("/srv/analytics/a").unwrap().label(), "analytics / CC-1001");
+    /// ```
+    pub fn parse_json(contents: &str) -> Result<OwnershipMap, String> {
+        let rules: Vec<OwnershipRule> =
+            serde_json::from_str(contents).map_err(|e| format!("invalid ownership JSON: {}", e))?;
+        Self::new(rules)
+    }
+
+    /// Reads a mapping from `path`, as JSON if it ends in `.json` and as CSV otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid mapping.
+    pub fn load(path: &Path) -> io::Result<OwnershipMap> {
+        let contents = fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let parsed = if is_json {
+            Self::parse_json(&contents)
+        } else {
+            Self::parse_csv(&contents)
+        };
+        parsed.map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Returns who `path` belongs to, if a prefix covers it.
+    pub fn owner(&self, path: &str) -> Option<&Owner> {
+        self.rules
+            .iter()
+            .find(|rule| Path::new(path).starts_with(&rule.prefix))
+            .map(|rule| &rule.owner)
+    }
+
+    /// Whether the mapping holds no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Splits a CSV row into its fields, unquoting quoted ones (`""` is a literal quote).
+fn parse_csv_row(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("unterminated quote in CSV row '{}'", line));
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Bytes and number of files per owner of the ownership mapping, for the `by-owner` report.
+///
+/// Prefixes naming the same owner, team and cost center are totalled together, and files no
+/// prefix covers are counted under the unknown owner, so the subtotals add up to the whole
+/// scan.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::ownership::{OwnerTotals, OwnershipMap};
+/// use ferris_files::report::{Aggregator, FileInfo};
+///
+/// let owners = OwnershipMap::parse_csv("prefix,team\n/srv/a,analytics\n/srv/b,analytics\n").unwrap();
+/// let mut totals = OwnerTotals::new(owners, 10);
+/// for (path, size) in [("/srv/a/x", 700), ("/srv/b/y", 200), ("/srv/c/z", 5)] {
+///     totals.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// let rows = totals.finish().rows;
+/// assert_eq!(rows[0].fields, ["900", "2", "", "analytics", ""]);
+/// assert_eq!(rows[1].fields, ["5", "1", "", "", ""]);
+/// ```
+#[derive(Debug, Default)]
+pub struct OwnerTotals {
+    owners: OwnershipMap,
+    limit: usize,
+    totals: HashMap<Owner, (u64, usize)>,
+    costs: GroupCosts<Owner>,
+}
+
+impl OwnerTotals {
+    /// Creates empty totals for the owners of `owners`, reporting the `limit` owners taking
+    /// the most bytes.
+    pub fn new(owners: OwnershipMap, limit: usize) -> Self {
+        OwnerTotals {
+            owners,
+            limit,
+            totals: HashMap::new(),
+            costs: GroupCosts::default(),
+        }
+    }
+
+    /// Also estimates the monthly cost of every owner at the rates of `costs`, for chargeback.
+    pub fn with_costs(mut self, costs: CostModel) -> Self {
+        self.costs = GroupCosts::new(costs);
+        self
+    }
+
+    /// Returns the owners taking the most bytes as `(owner, bytes, files)`, largest first;
+    /// the unknown owner has no fields set.
+    pub fn top(&self) -> Vec<(Owner, u64, usize)> {
+        let mut top: Vec<(Owner, u64, usize)> = self
+            .totals
+            .iter()
+            .map(|(owner, (bytes, files))| (owner.clone(), *bytes, *files))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.label().cmp(&b.0.label())));
+        top.truncate(self.limit);
+        top
+    }
+}
+
+impl Aggregator for OwnerTotals {
+    fn observe(&mut self, file: &FileInfo) {
+        let owner = self.owners.owner(&file.path).cloned().unwrap_or_default();
+        if let Some(cost) = self.costs.monthly(file) {
+            self.costs.add(owner.clone(), cost);
+        }
+        let (bytes, files) = self.totals.entry(owner).or_default();
+        *bytes += file.size;
+        *files += 1;
+    }
+
+    fn finish(&self) -> ReportSection {
+        let total = self.totals.values().map(|(bytes, _)| bytes).sum();
+        let rows = self
+            .top()
+            .into_iter()
+            .map(|(owner, bytes, files)| {
+                let label = if owner == Owner::default() {
+                    tr!("owner-none")
+                } else {
+                    owner.label()
+                };
+                let cost = self.costs.get(&owner);
+                let field = |value: Option<String>| value.unwrap_or_default();
+                ReportRow {
+                    text: share_line(&label, bytes, total, files, cost),
+                    kind: "owner-total".to_string(),
+                    fields: vec![
+                        bytes.to_string(),
+                        files.to_string(),
+                        field(owner.cost_center),
+                        field(owner.team),
+                        field(owner.owner),
+                    ],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-by-owner")),
+            rows,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::results::SavedEntry;
+
+/// Version of the `--porcelain` output format, bumped only for incompatible changes.
+///
+/// Within a version the format is fixed regardless of changes to the human output: one record
+/// per line, tab separated fields, the record type first and the path (the only free-form
+/// field) last, sizes in bytes. New record types may be added; existing ones never change.
+/// The records are documented in the README.
+pub const VERSION: u32 = 1;
+
+/// Escapes a field so it cannot break the record structure.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::porcelain::escape;
+///
+/// assert_eq!(escape("/data/a\tb\\c\n"), "/data/a\\tb\\\\c\\n");
+/// ```
+pub fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats a record of the given type from its fields.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::porcelain::record;
+///
+/// assert_eq!(record("entry", &["3b1f0c", "42", "", "/data/a"]), "entry\t3b1f0c\t42\t\t/data/a");
+/// ```
+pub fn record(kind: &str, fields: &[&str]) -> String {
+    let mut line = kind.to_string();
+    for field in fields {
+        line.push('\t');
+        line.push_str(&escape(field));
+    }
+    line
+}
+
+/// Prints the `version` record that starts every porcelain output.
+pub fn print_version() {
+    println!("{}", record("version", &[&VERSION.to_string()]));
+}
+
+/// Prints an `entry` record (or `added` / `removed`, given as `kind`) for a reported entry.
+pub fn print_entry(kind: &str, id: &str, size: u64, host: Option<&str>, path: &str) {
+    println!(
+        "{}",
+        record(
+            kind,
+            &[id, &size.to_string(), host.unwrap_or_default(), path]
+        )
+    );
+}
+
+/// Prints an `entry`-style record for an entry of a session.
+pub fn print_saved(kind: &str, entry: &SavedEntry) {
+    print_entry(
+        kind,
+        &entry.id,
+        entry.size,
+        entry.host.as_deref(),
+        &entry.path,
+    );
+}
@@ -0,0 +1,226 @@
+use crate::console;
+use crate::i18n;
+use crate::traits::ByteSize;
+use filesize::PathExt;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of directories read when trying to size a subtree exactly.
+const EXACT_DIR_LIMIT: usize = 256;
+
+/// Number of random descents spread over the unread directories of a large subtree.
+const PROBES: usize = 1024;
+
+/// Deepest level a random descent goes to, guarding against pathological trees.
+const MAX_PROBE_DEPTH: usize = 64;
+
+/// Approximate size of a top-level entry of the scan root.
+///
+/// * `path` - The top-level file or directory
+/// * `estimated_bytes` - Exact size, or the average of the sampled descents
+/// * `dirs_read` - Number of directories actually read for the estimate
+/// * `exhaustive` - True if the whole subtree was read, making the estimate exact
+#[derive(Debug, Clone)]
+pub struct SubtreeEstimate {
+    pub path: PathBuf,
+    pub estimated_bytes: u64,
+    pub dirs_read: usize,
+    pub exhaustive: bool,
+}
+
+/// Bytes of the files directly inside a directory and its subdirectories (sorted).
+type Listing = (u64, Vec<PathBuf>);
+
+/// Reads a directory without following symbolic links, leaving out skipped directories.
+fn list_dir(dir: &Path, skip_dirs: &HashSet<PathBuf>) -> Listing {
+    let mut bytes = 0;
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if !skip_dirs.iter().any(|skip| path.starts_with(skip)) {
+                subdirs.push(path);
+            }
+        } else if metadata.is_file() {
+            bytes += path.size_on_disk_fast(&metadata).unwrap_or(0);
+        }
+    }
+    subdirs.sort();
+    (bytes, subdirs)
+}
+
+/// Small deterministic xorshift generator, so repeated previews of a tree agree.
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Estimates the size of the subtree at `root`.
+///
+/// Up to [`EXACT_DIR_LIMIT`] directories are read breadth first, which sizes small subtrees
+/// exactly. Every directory left unread is then estimated from random descents (about
+/// [`PROBES`] in total): each descent walks to a leaf picking one subdirectory at random per
+/// level, and counts the files of every directory on its way multiplied by the number of
+/// alternatives skipped above it (Knuth's tree size estimator). Averaging the descents gives
+/// an unbiased, if noisy, estimate; starting them from many frontier directories keeps the
+/// noise down.
+/// Symbolic links are not followed and directories under `skip_dirs` are ignored.
+pub fn estimate_subtree(root: &Path, skip_dirs: &HashSet<PathBuf>) -> SubtreeEstimate {
+    let mut listings: HashMap<PathBuf, Listing> = HashMap::new();
+    let mut list = |dir: &Path| -> Listing {
+        listings
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| list_dir(dir, skip_dirs))
+            .clone()
+    };
+
+    // Try to read the whole subtree first
+    let mut queue = VecDeque::from([root.to_path_buf()]);
+    let mut exact = 0;
+    let mut dirs_read = 0;
+    while dirs_read < EXACT_DIR_LIMIT {
+        let Some(dir) = queue.pop_front() else {
+            return SubtreeEstimate {
+                path: root.to_path_buf(),
+                estimated_bytes: exact,
+                dirs_read,
+                exhaustive: true,
+            };
+        };
+        let (bytes, subdirs) = list(&dir);
+        dirs_read += 1;
+        exact += bytes;
+        queue.extend(subdirs);
+    }
+
+    // Estimate each unread directory of the frontier from its own random descents
+    let mut state = root
+        .as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(0x9e37_79b9_7f4a_7c15_u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100_0000_01b3)
+        })
+        | 1;
+    let probes = (PROBES / queue.len()).max(2);
+    let mut sampled = 0.0_f64;
+    for start in &queue {
+        let mut sum = 0.0_f64;
+        for _ in 0..probes {
+            let mut dir = start.clone();
+            let mut weight = 1.0_f64;
+            for _ in 0..MAX_PROBE_DEPTH {
+                let (bytes, subdirs) = list(&dir);
+                sum += bytes as f64 * weight;
+                if subdirs.is_empty() {
+                    break;
+                }
+                weight *= subdirs.len() as f64;
+                dir = subdirs[(next_random(&mut state) % subdirs.len() as u64) as usize].clone();
+            }
+        }
+        sampled += sum / probes as f64;
+    }
+
+    SubtreeEstimate {
+        path: root.to_path_buf(),
+        estimated_bytes: exact + sampled as u64,
+        dirs_read: listings.len(),
+        exhaustive: false,
+    }
+}
+
+/// Estimates every top-level entry of `root`, largest first.
+///
+/// # Errors
+///
+/// Returns an error if `root` cannot be read.
+pub fn estimate_top_level(
+    root: &Path,
+    skip_dirs: &HashSet<PathBuf>,
+) -> io::Result<Vec<SubtreeEstimate>> {
+    let mut estimates = Vec::new();
+    for entry in fs::read_dir(root)?.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if !skip_dirs.iter().any(|skip| path.starts_with(skip)) {
+                estimates.push(estimate_subtree(&path, skip_dirs));
+            }
+        } else if metadata.is_file() {
+            estimates.push(SubtreeEstimate {
+                estimated_bytes: path.size_on_disk_fast(&metadata).unwrap_or(0),
+                path,
+                dirs_read: 0,
+                exhaustive: true,
+            });
+        }
+    }
+    estimates.sort_by_key(|estimate| std::cmp::Reverse(estimate.estimated_bytes));
+    Ok(estimates)
+}
+
+/// Prints a quick sampled estimate of the top-level entries of each of `roots`, in turn, and
+/// asks once whether to continue with the full scan.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - True if the user chose to proceed with the full scan
+pub fn preview(roots: &[PathBuf], skip_dirs: &HashSet<String>) -> io::Result<bool> {
+    let skip_dirs: HashSet<PathBuf> = skip_dirs
+        .iter()
+        .filter_map(|dir| console::canonicalize(Path::new(dir)).ok())
+        .collect();
+    for (i, root) in roots.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_estimates(&console::canonicalize(root)?, &skip_dirs)?;
+    }
+
+    print!("\n{} ", tr!("preview-proceed"));
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(i18n::is_yes(&answer))
+}
+
+/// Prints the sampled estimates of the top-level entries of `root` and their total.
+fn print_estimates(root: &Path, skip_dirs: &HashSet<PathBuf>) -> io::Result<()> {
+    println!(
+        "{}",
+        tr!("preview-heading", root = root.display().to_string())
+    );
+    let estimates = estimate_top_level(root, skip_dirs)?;
+    let total: u64 = estimates.iter().map(|e| e.estimated_bytes).sum();
+    for estimate in &estimates {
+        let marker = if estimate.exhaustive { " " } else { "~" };
+        println!(
+            "{}{:>10}  {}",
+            marker,
+            estimate.estimated_bytes.format_size(),
+            estimate.path.display()
+        );
+    }
+    let marker = if estimates.iter().all(|e| e.exhaustive) {
+        " "
+    } else {
+        "~"
+    };
+    println!(
+        "{}{:>10}  {}",
+        marker,
+        total.format_size(),
+        tr!("preview-total")
+    );
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use crate::console;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Weights of the roots of a scan over several directories, read with `--priorities`.
+///
+/// Roots of higher weight are listed first and their files reach the progress feed and the
+/// ranking earlier, so a scan cut short by a deadline has covered what matters most. A root takes
+/// the weight of the deepest rule at or above it, and 0 without any; roots of equal weight are
+/// scanned side by side.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::priorities::RootPriorities;
+/// use std::path::{Path, PathBuf};
+///
+/// let priorities = RootPriorities::parse("
+///     /srv           10
+///     /srv/scratch   1
+/// ").unwrap();
+/// assert_eq!(priorities.weight(Path::new("/srv/db")), 10);
+/// assert_eq!(priorities.weight(Path::new("/srv/scratch/tmp")), 1);
+/// assert_eq!(priorities.weight(Path::new("/home")), 0);
+///
+/// let mut roots = vec![PathBuf::from("/home"), PathBuf::from("/srv/scratch"), PathBuf::from("/srv/db")];
+/// priorities.order(&mut roots);
+/// assert_eq!(roots, [Path::new("/srv/db"), Path::new("/srv/scratch"), Path::new("/home")]);
+/// assert_eq!(priorities.tiers(&roots), [0, 1, 2]);
+///
+/// assert!(RootPriorities::parse("/srv high").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RootPriorities {
+    rules: Vec<(PathBuf, u32)>,
+}
+
+impl RootPriorities {
+    /// Parses a priorities file: one `PATH WEIGHT` rule per line with a whole number weight,
+    /// blank lines and `#` comments ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first rule without a path and a whole number weight.
+    pub fn parse(contents: &str) -> Result<RootPriorities, String> {
+        let mut rules = Vec::new();
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let invalid = || format!("invalid priority rule '{}': expected a path and a whole number weight", line);
+            let mut words = line.split_whitespace();
+            let (Some(path), Some(weight), None) = (words.next(), words.next(), words.next()) else {
+                return Err(invalid());
+            };
+            rules.push((PathBuf::from(path), weight.parse().map_err(|_| invalid())?));
+        }
+        Ok(RootPriorities { rules })
+    }
+
+    /// Reads and parses the priorities file at `path`, resolving the paths of its rules as the
+    /// roots are (see [`console::canonicalize`]) where they exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or contains an invalid rule.
+    pub fn load(path: &Path) -> Result<RootPriorities, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let mut priorities = RootPriorities::parse(&contents)?;
+        for (rule, _) in &mut priorities.rules {
+            if let Ok(canonical) = console::canonicalize(rule) {
+                *rule = canonical;
+            }
+        }
+        Ok(priorities)
+    }
+
+    /// Whether the file held no rule.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns the weight of `root`: that of the deepest rule at or above it, or 0.
+    pub fn weight(&self, root: &Path) -> u32 {
+        self.rules
+            .iter()
+            .filter(|(rule, _)| root.starts_with(rule))
+            .max_by_key(|(rule, _)| rule.components().count())
+            .map_or(0, |(_, weight)| *weight)
+    }
+
+    /// Sorts `roots` by decreasing weight, keeping the order of roots of equal weight.
+    pub fn order(&self, roots: &mut [PathBuf]) {
+        roots.sort_by_key(|root| std::cmp::Reverse(self.weight(root)));
+    }
+
+    /// Returns the tier of each of `roots`: 0 for the heaviest, counting up per distinct weight
+    /// below it, so roots of equal weight share a tier.
+    pub fn tiers(&self, roots: &[PathBuf]) -> Vec<usize> {
+        let mut weights: Vec<u32> = roots.iter().map(|root| self.weight(root)).collect();
+        let tiers = weights.clone();
+        weights.sort_unstable_by(|a, b| b.cmp(a));
+        weights.dedup();
+        tiers
+            .iter()
+            .map(|weight| weights.iter().position(|w| w == weight).unwrap_or_default())
+            .collect()
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A file that has been unlinked from the directory tree but is still held open by a process.
+///
+/// Such files keep consuming disk space until every descriptor referring to them is closed,
+/// which makes them invisible to a directory scan while still counting towards `df`.
+#[derive(Debug, Clone)]
+pub struct DeletedOpenFile {
+    pub pid: u32,
+    pub process_name: String,
+    pub fd: u32,
+    pub path: String,
+    pub size: u64,
+    pub device: u64,
+    pub inode: u64,
+}
+
+/// Suffix the kernel appends to `/proc/<pid>/fd/<n>` link targets of unlinked files.
+const DELETED_SUFFIX: &str = " (deleted)";
+
+/// A process observed through `/proc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessRef {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// A process together with its owner and current working directory.
+#[derive(Debug, Clone)]
+pub struct ProcessCwd {
+    pub process: ProcessRef,
+    pub uid: Option<u32>,
+    pub cwd: PathBuf,
+}
+
+/// Parses a purely numeric directory entry name (a pid or a file descriptor number).
+fn numeric_name(entry: &fs::DirEntry) -> Option<u32> {
+    entry.file_name().to_str().and_then(|name| name.parse().ok())
+}
+
+/// Calls `visit` for every readable descriptor of every process that refers to a regular file.
+///
+/// The visitor receives the process directory (`/proc/<pid>`), the pid, the descriptor number,
+/// the link target as reported by the kernel and the metadata of the open file itself.
+/// Processes and descriptors that cannot be read, or that vanish mid-walk, are skipped.
+fn for_each_open_file(
+    mut visit: impl FnMut(&Path, u32, u32, &str, &fs::Metadata),
+) -> io::Result<()> {
+    for proc_entry in fs::read_dir("/proc")?.flatten() {
+        let Some(pid) = numeric_name(&proc_entry) else {
+            continue;
+        };
+
+        let proc_dir = proc_entry.path();
+        let Ok(fds) = fs::read_dir(proc_dir.join("fd")) else {
+            continue;
+        };
+
+        for fd_entry in fds.flatten() {
+            let Some(fd) = numeric_name(&fd_entry) else {
+                continue;
+            };
+            let Ok(target) = fs::read_link(fd_entry.path()) else {
+                continue;
+            };
+
+            // Following the descriptor link stats the open inode itself, even if unlinked
+            let Ok(metadata) = fs::metadata(fd_entry.path()) else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+
+            visit(&proc_dir, pid, fd, &target.to_string_lossy(), &metadata);
+        }
+    }
+
+    Ok(())
+}
+
+/// Walks `/proc/*/fd` and returns every regular file that is open but deleted.
+///
+/// Processes whose descriptors cannot be read (typically those of other users when not
+/// running as root) are skipped silently, as are descriptors that close mid-walk.
+/// The same deleted file opened by several descriptors is reported once per descriptor;
+/// use the `(device, inode)` pair to count its size only once.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<DeletedOpenFile>>` - Deleted files sorted by size in descending order,
+///   or an error if `/proc` itself cannot be read (e.g. on non-Linux platforms)
+pub fn deleted_open_files() -> io::Result<Vec<DeletedOpenFile>> {
+    let mut found = Vec::new();
+
+    for_each_open_file(|proc_dir, pid, fd, target, metadata| {
+        if let Some(path) = target.strip_suffix(DELETED_SUFFIX) {
+            found.push(DeletedOpenFile {
+                pid,
+                process_name: read_process_name(proc_dir),
+                fd,
+                path: path.to_string(),
+                size: allocated_size(metadata),
+                device: device_id(metadata),
+                inode: inode_number(metadata),
+            });
+        }
+    })?;
+
+    found.sort_by_key(|file| std::cmp::Reverse(file.size));
+    Ok(found)
+}
+
+/// Maps every open regular file, keyed by `(device, inode)`, to the processes holding it open.
+///
+/// Each process is listed at most once per file, however many descriptors it holds.
+pub fn open_file_holders() -> io::Result<HashMap<(u64, u64), Vec<ProcessRef>>> {
+    let mut names: HashMap<u32, String> = HashMap::new();
+    let mut holders: HashMap<(u64, u64), Vec<ProcessRef>> = HashMap::new();
+
+    for_each_open_file(|proc_dir, pid, _, _, metadata| {
+        let name = names
+            .entry(pid)
+            .or_insert_with(|| read_process_name(proc_dir))
+            .clone();
+        let processes = holders
+            .entry((device_id(metadata), inode_number(metadata)))
+            .or_default();
+        if !processes.iter().any(|process| process.pid == pid) {
+            processes.push(ProcessRef { pid, name });
+        }
+    })?;
+
+    Ok(holders)
+}
+
+/// Lists every readable process with its owner and current working directory.
+pub fn process_cwds() -> io::Result<Vec<ProcessCwd>> {
+    let mut processes = Vec::new();
+
+    for proc_entry in fs::read_dir("/proc")?.flatten() {
+        let Some(pid) = numeric_name(&proc_entry) else {
+            continue;
+        };
+
+        let proc_dir = proc_entry.path();
+        let Ok(cwd) = fs::read_link(proc_dir.join("cwd")) else {
+            continue;
+        };
+
+        #[cfg(unix)]
+        let uid = {
+            use std::os::unix::fs::MetadataExt;
+            fs::metadata(&proc_dir).ok().map(|metadata| metadata.uid())
+        };
+        #[cfg(not(unix))]
+        let uid = None;
+
+        processes.push(ProcessCwd {
+            process: ProcessRef {
+                pid,
+                name: read_process_name(&proc_dir),
+            },
+            uid,
+            cwd,
+        });
+    }
+
+    Ok(processes)
+}
+
+/// Reads the short command name of a process from `/proc/<pid>/comm`.
+pub fn read_process_name(proc_dir: &Path) -> String {
+    fs::read_to_string(proc_dir.join("comm"))
+        .map(|name| name.trim_end().to_string())
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+/// Returns the number of bytes allocated on disk for the file described by `metadata`.
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.blocks() * 512
+    }
+
+    #[cfg(not(unix))]
+    {
+        metadata.len()
+    }
+}
+
+/// Returns the id of the device containing the file described by `metadata`.
+fn device_id(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.dev()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// Returns the inode number of the file described by `metadata`.
+fn inode_number(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.ino()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two `progress` events, so fast scans do not flood the reader.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// One line of the live progress feed.
+///
+/// * `Progress` - Files received from the scanner so far, how many were sized and how many failed,
+///   and the bytes sized so far
+/// * `Top` - The current largest files, sent whenever they changed since the last `top` event
+/// * `Done` - Final counts, always the last event of a completed scan
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum ProgressEvent<'a> {
+    Progress {
+        elapsed_ms: u128,
+        files: usize,
+        processed: usize,
+        failed: usize,
+        bytes: u64,
+    },
+    Top {
+        elapsed_ms: u128,
+        entries: Vec<TopEntry<'a>>,
+    },
+    Done {
+        elapsed_ms: u128,
+        files: usize,
+        processed: usize,
+        failed: usize,
+        bytes: u64,
+    },
+}
+
+/// A ranked file in a `top` event.
+#[derive(Debug, Serialize)]
+pub struct TopEntry<'a> {
+    pub path: &'a str,
+    pub size: u64,
+}
+
+/// Writes a scan's progress as JSON lines for other programs to follow in real time.
+///
+/// The feed is meant to be piped into whatever carries it further (a log shipper, a UI on the
+/// same box); `serve` pushes the same events to WebSocket clients (see [`crate::serve`]). Events are throttled to [`PROGRESS_INTERVAL`] and every line
+/// is flushed as soon as it is written. If the reader goes away, the feed stops and the scan
+/// carries on.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::progress::ProgressFeed;
+///
+/// let mut out = Vec::new();
+/// {
+///     let mut feed = ProgressFeed::new(&mut out);
+///     feed.update(10, 9, 1, 4096, || vec![("/data/big.iso".to_string(), 200)]);
+///     feed.finish(10, 9, 1, 4096);
+/// }
+/// let lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+/// assert!(lines[0].starts_with(r#"{"event":"progress""#));
+/// assert!(lines[0].ends_with(r#""bytes":4096}"#));
+/// assert!(lines[1].contains(r#""entries":[{"path":"/data/big.iso","size":200}]"#));
+/// assert!(lines[2].starts_with(r#"{"event":"done""#));
+/// ```
+pub struct ProgressFeed<W: Write> {
+    out: Option<W>,
+    started: Instant,
+    last_update: Option<Instant>,
+    last_top: Vec<(String, u64)>,
+}
+
+impl ProgressFeed<Box<dyn Write>> {
+    /// Opens the feed on `path`, which may also be a named pipe or a `/dev/fd` path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` cannot be opened for writing.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        crate::storage::check_write(path)?;
+        Ok(ProgressFeed::new(Box::new(LineWriter::new(File::create(path)?))))
+    }
+}
+
+impl<W: Write> ProgressFeed<W> {
+    /// Starts a feed writing to `out`; elapsed times are measured from now.
+    pub fn new(out: W) -> Self {
+        ProgressFeed {
+            out: Some(out),
+            started: Instant::now(),
+            last_update: None,
+            last_top: Vec::new(),
+        }
+    }
+
+    /// Reports the running counts and, if they changed, the current largest files.
+    ///
+    /// Does nothing if the previous update was less than [`PROGRESS_INTERVAL`] ago; `top` is
+    /// only called when an update is due.
+    pub fn update(
+        &mut self,
+        files: usize,
+        processed: usize,
+        failed: usize,
+        bytes: u64,
+        top: impl FnOnce() -> Vec<(String, u64)>,
+    ) {
+        if self.out.is_none()
+            || self
+                .last_update
+                .is_some_and(|last| last.elapsed() < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+        self.last_update = Some(Instant::now());
+
+        self.send(&ProgressEvent::Progress {
+            elapsed_ms: self.started.elapsed().as_millis(),
+            files,
+            processed,
+            failed,
+            bytes,
+        });
+        let top = top();
+        if top != self.last_top {
+            self.send(&ProgressEvent::Top {
+                elapsed_ms: self.started.elapsed().as_millis(),
+                entries: top
+                    .iter()
+                    .map(|(path, size)| TopEntry { path, size: *size })
+                    .collect(),
+            });
+            self.last_top = top;
+        }
+    }
+
+    /// Reports the final counts and closes the feed.
+    pub fn finish(&mut self, files: usize, processed: usize, failed: usize, bytes: u64) {
+        self.send(&ProgressEvent::Done {
+            elapsed_ms: self.started.elapsed().as_millis(),
+            files,
+            processed,
+            failed,
+            bytes,
+        });
+        self.out = None;
+    }
+
+    /// Writes one event as a line, stopping the feed if the reader is gone.
+    fn send(&mut self, event: &ProgressEvent) {
+        let Some(out) = self.out.as_mut() else {
+            return;
+        };
+        let written = serde_json::to_string(event)
+            .map_err(io::Error::other)
+            .and_then(|line| writeln!(out, "{}", line));
+        if let Err(e) = written {
+            log::warn!("{}", tr!("progress-feed-closed", error = e.to_string()));
+            self.out = None;
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::storage;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the protect list file inside the system and user configuration directories.
+const PROTECT_FILE: &str = "protect";
+
+/// Expands a path rule into the globs matching the path itself and everything beneath it.
+///
+/// Rules without a `/` match a name anywhere, and a leading `~/` is expanded to the home
+/// directory.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::protect::rule_globs;
+///
+/// assert_eq!(rule_globs("*.kdbx"), ["**/*.kdbx", "**/*.kdbx/**"]);
+/// assert_eq!(rule_globs("/srv/db/"), ["/srv/db", "/srv/db/**"]);
+/// ```
+pub fn rule_globs(rule: &str) -> [String; 2] {
+    let home = std::env::var("HOME").ok();
+    let pattern = match (rule.strip_prefix("~/"), &home) {
+        (Some(rest), Some(home)) => format!("{}/{}", home.trim_end_matches('/'), rest),
+        _ if !rule.contains('/') => format!("**/{}", rule),
+        _ => rule.trim_end_matches('/').to_string(),
+    };
+    [pattern.clone(), format!("{}/**", pattern)]
+}
+
+/// Paths and globs that actions must never delete, move, archive, compress or suggest.
+///
+/// Each rule is a glob in the syntax of the `globset` crate and also protects everything
+/// beneath a matching directory. Rules without a `/` match a file or directory name anywhere
+/// (e.g. `*.kdbx`), and a leading `~/` is expanded to the home directory.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::protect::ProtectList;
+///
+/// let protect = ProtectList::from_rules(["/srv/db", "*.kdbx"]).unwrap();
+/// assert_eq!(protect.matching_rule("/srv/db/data/base.dat"), Some("/srv/db"));
+/// assert_eq!(protect.matching_rule("/home/me/vault.kdbx"), Some("*.kdbx"));
+/// assert_eq!(protect.matching_rule("/srv/dbx/dump.sql"), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProtectList {
+    rules: Vec<String>,
+    // Every rule is compiled to two globs: the rule itself and everything beneath it
+    set: GlobSet,
+}
+
+impl ProtectList {
+    /// Builds a protect list from individual rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first rule that is not a valid glob.
+    pub fn from_rules<I, S>(rules: I) -> Result<ProtectList, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = GlobSetBuilder::new();
+        let mut kept = Vec::new();
+
+        for rule in rules {
+            let rule = rule.as_ref().trim();
+            for glob in rule_globs(rule) {
+                let glob = Glob::new(&glob)
+                    .map_err(|e| format!("invalid protect rule '{}': {}", rule, e))?;
+                builder.add(glob);
+            }
+            kept.push(rule.to_string());
+        }
+
+        let set = builder.build().map_err(|e| e.to_string())?;
+        Ok(ProtectList { rules: kept, set })
+    }
+
+    /// Parses a protect list file: one rule per line, blank lines and `#` comments ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first rule that is not a valid glob.
+    pub fn parse(contents: &str) -> Result<ProtectList, String> {
+        Self::from_rules(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        )
+    }
+
+    /// Loads and combines the system wide and per-user protect lists.
+    ///
+    /// The lists are read from `protect` files in [`storage::system_config_dir`] and
+    /// [`storage::config_dir`]; missing files are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a protect file exists but cannot be read or contains an invalid rule,
+    /// since silently ignoring it would leave the listed paths unprotected.
+    pub fn load() -> io::Result<ProtectList> {
+        let mut contents = String::new();
+        for file in Self::files() {
+            match fs::read_to_string(&file) {
+                Ok(text) => {
+                    contents.push_str(&text);
+                    contents.push('\n');
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("could not read protect list {}: {}", file.display(), e),
+                    ))
+                }
+            }
+        }
+        Self::parse(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Locations of the system wide and per-user protect list files.
+    pub fn files() -> Vec<PathBuf> {
+        [storage::system_config_dir(), storage::config_dir()]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(PROTECT_FILE))
+            .collect()
+    }
+
+    /// Returns the first rule protecting `path`, if any.
+    pub fn matching_rule(&self, path: impl AsRef<Path>) -> Option<&str> {
+        self.set
+            .matches(path.as_ref())
+            .first()
+            .map(|index| self.rules[index / 2].as_str())
+    }
+
+    /// Whether the list holds no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The rules, in the order they were read.
+    pub fn rules(&self) -> &[String] {
+        &self.rules
+    }
+}
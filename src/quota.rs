@@ -0,0 +1,280 @@
+use crate::traits::ByteSize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A single entry of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+}
+
+/// Parses the contents of a `/proc/<pid>/mountinfo` file.
+///
+/// Lines that do not follow the documented format are ignored. Octal escapes used by the
+/// kernel for whitespace in paths (e.g. `\040`) are decoded.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::quota::parse_mountinfo;
+///
+/// let mounts = parse_mountinfo(
+///     "36 35 98:0 / /home rw,noatime master:1 - ext4 /dev/sda2 rw,usrquota\n",
+/// );
+/// assert_eq!(mounts[0].mount_point.to_str(), Some("/home"));
+/// assert_eq!(mounts[0].fs_type, "ext4");
+/// assert_eq!(mounts[0].source, "/dev/sda2");
+/// ```
+pub fn parse_mountinfo(contents: &str) -> Vec<MountInfo> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (mount_fields, fs_fields) = line.split_once(" - ")?;
+            let mount_point = mount_fields.split(' ').nth(4)?;
+            let mut fs_fields = fs_fields.split(' ');
+            let fs_type = fs_fields.next()?;
+            let source = fs_fields.next()?;
+
+            Some(MountInfo {
+                mount_point: PathBuf::from(unescape_mount_field(mount_point)),
+                fs_type: fs_type.to_string(),
+                source: unescape_mount_field(source),
+            })
+        })
+        .collect()
+}
+
+/// Decodes the `\ooo` octal escapes the kernel uses in mount table fields.
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let digits = &bytes[i + 1..i + 4];
+            if digits.iter().all(|b| (b'0'..=b'7').contains(b)) {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |acc, b| acc * 8 + u32::from(b - b'0'));
+                decoded.push(value as u8);
+                i += 4;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Returns the mounts of the running system.
+///
+/// Only Linux exposes a mount table with filesystem types; elsewhere, or if the table cannot be
+/// read, no mounts are known.
+pub fn system_mounts() -> Vec<MountInfo> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/proc/self/mountinfo")
+            .map(|contents| parse_mountinfo(&contents))
+            .unwrap_or_default()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Returns the mount containing `path`, i.e. the one with the longest matching mount point.
+pub fn mount_for_path<'a>(mounts: &'a [MountInfo], path: &Path) -> Option<&'a MountInfo> {
+    mounts
+        .iter()
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        .max_by_key(|mount| mount.mount_point.as_os_str().len())
+}
+
+/// Block usage and limits of a user on a filesystem with quotas enabled.
+///
+/// A limit of `None` means the corresponding limit is not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub used_bytes: u64,
+    pub soft_limit: Option<u64>,
+    pub hard_limit: Option<u64>,
+}
+
+impl QuotaUsage {
+    /// Returns the tightest configured limit (soft limit if set, otherwise the hard limit).
+    pub fn effective_limit(&self) -> Option<u64> {
+        self.soft_limit.or(self.hard_limit)
+    }
+
+    /// Renders the usage together with the remaining headroom (or the amount that must be
+    /// deleted to get back under quota).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::quota::QuotaUsage;
+    ///
+    /// let usage = QuotaUsage {
+    ///     used_bytes: 3 * 1024,
+    ///     soft_limit: Some(2 * 1024),
+    ///     hard_limit: Some(4 * 1024),
+    /// };
+    /// assert_eq!(
+    ///     usage.describe(),
+    ///     "3.00 KB used of 2.00 KB soft limit (hard limit 4.00 KB): delete at least 1.00 KB to get under quota"
+    /// );
+    /// ```
+    pub fn describe(&self) -> String {
+        let Some(limit) = self.effective_limit() else {
+            return tr!("quota-no-limit", used = self.used_bytes.format_size());
+        };
+
+        let kind = if self.soft_limit.is_some() {
+            "soft"
+        } else {
+            "hard"
+        };
+        let hard = match (self.soft_limit, self.hard_limit) {
+            (Some(_), Some(hard)) => {
+                format!(" {}", tr!("quota-hard-limit", limit = hard.format_size()))
+            }
+            _ => String::new(),
+        };
+
+        if self.used_bytes > limit {
+            tr!(
+                "quota-over",
+                used = self.used_bytes.format_size(),
+                limit = limit.format_size(),
+                kind = kind,
+                hard = hard,
+                excess = (self.used_bytes - limit).format_size()
+            )
+        } else {
+            tr!(
+                "quota-headroom",
+                used = self.used_bytes.format_size(),
+                limit = limit.format_size(),
+                kind = kind,
+                hard = hard,
+                headroom = (limit - self.used_bytes).format_size()
+            )
+        }
+    }
+}
+
+/// Queries the block quota of `uid` on the filesystem containing `path`.
+///
+/// On Linux the mount is looked up in `/proc/self/mountinfo` and its block device is queried
+/// with `quotactl(Q_GETQUOTA)`. Network filesystems are not queried directly since their
+/// quotas live on the server (use the `quota` tool, which speaks the rquota protocol).
+///
+/// # Returns
+///
+/// * `io::Result<(MountInfo, QuotaUsage)>` - The mount that was queried and the user's usage
+///
+/// # Errors
+///
+/// Returns an error if the mount cannot be determined, quotas are not enabled on it,
+/// the filesystem is a network filesystem, or the platform is not Linux.
+pub fn user_quota(path: &Path, uid: u32) -> io::Result<(MountInfo, QuotaUsage)> {
+    #[cfg(target_os = "linux")]
+    {
+        let mounts = parse_mountinfo(&std::fs::read_to_string("/proc/self/mountinfo")?);
+        let mount = mount_for_path(&mounts, path).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no mount found for scan root")
+        })?;
+
+        if crate::netfs::is_network_fs(&mount.fs_type) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{} quotas are enforced by the server; run `quota -u` to query them",
+                    mount.fs_type
+                ),
+            ));
+        }
+
+        let usage = linux::get_quota(&mount.source, uid)?;
+        Ok((mount, usage))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (path, uid);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "quota queries are only supported on Linux",
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::QuotaUsage;
+    use std::ffi::CString;
+    use std::io;
+
+    /// Size of the units used for block limits by the quota subsystem.
+    const QUOTABLOCK_SIZE: u64 = 1024;
+    const Q_GETQUOTA: u32 = 0x800007;
+    const USRQUOTA: u32 = 0;
+    const SUBCMDSHIFT: u32 = 8;
+
+    /// Mirror of `struct dqblk` from `<sys/quota.h>`; only the block fields are read.
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct Dqblk {
+        dqb_bhardlimit: u64,
+        dqb_bsoftlimit: u64,
+        dqb_curspace: u64,
+        dqb_ihardlimit: u64,
+        dqb_isoftlimit: u64,
+        dqb_curinodes: u64,
+        dqb_btime: u64,
+        dqb_itime: u64,
+        dqb_valid: u32,
+    }
+
+    /// Calls `quotactl(Q_GETQUOTA)` for a user on the given block device.
+    pub(super) fn get_quota(device: &str, uid: u32) -> io::Result<QuotaUsage> {
+        let device = CString::new(device)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let cmd = ((Q_GETQUOTA << SUBCMDSHIFT) | USRQUOTA) as libc::c_int;
+        let mut dqblk = Dqblk::default();
+
+        let status = unsafe {
+            libc::quotactl(
+                cmd,
+                device.as_ptr(),
+                uid as libc::c_int,
+                &mut dqblk as *mut Dqblk as *mut libc::c_char,
+            )
+        };
+        if status != 0 {
+            let err = io::Error::last_os_error();
+            return Err(match err.raw_os_error() {
+                Some(libc::ESRCH) => io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "quotas are not enabled on this filesystem",
+                ),
+                _ => err,
+            });
+        }
+
+        let limit = |blocks: u64| (blocks > 0).then_some(blocks * QUOTABLOCK_SIZE);
+        Ok(QuotaUsage {
+            used_bytes: dqblk.dqb_curspace,
+            soft_limit: limit(dqblk.dqb_bsoftlimit),
+            hard_limit: limit(dqblk.dqb_bhardlimit),
+        })
+    }
+}
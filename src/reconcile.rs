@@ -0,0 +1,186 @@
+use crate::traits::ByteSize;
+use std::io;
+use std::path::Path;
+
+/// Space usage of a mounted filesystem as reported by `statvfs` (the numbers `df` shows).
+#[derive(Debug, Clone, Copy)]
+pub struct FsUsage {
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    /// Free space unprivileged users may still write to
+    pub available_bytes: u64,
+    pub supports_snapshots: bool,
+}
+
+/// Queries `statvfs` (and on Linux `statfs`, for the filesystem type) for the filesystem
+/// containing `path`.
+///
+/// # Errors
+///
+/// Returns an error if the path contains interior NUL bytes, if the system calls fail,
+/// or with `Unsupported` on non-Unix platforms.
+pub fn fs_usage(path: &Path) -> io::Result<FsUsage> {
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let mut vfs: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut vfs) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let fragment = vfs.f_frsize as u64;
+        Ok(FsUsage {
+            total_bytes: vfs.f_blocks as u64 * fragment,
+            used_bytes: (vfs.f_blocks as u64).saturating_sub(vfs.f_bfree as u64) * fragment,
+            available_bytes: vfs.f_bavail as u64 * fragment,
+            supports_snapshots: supports_snapshots(&c_path),
+        })
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "filesystem usage queries are only supported on Unix platforms",
+        ))
+    }
+}
+
+/// Returns true if the filesystem type is known to keep snapshots that a scan cannot see.
+#[cfg(unix)]
+fn supports_snapshots(c_path: &std::ffi::CStr) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        const BTRFS_SUPER_MAGIC: i64 = 0x9123683e;
+        const ZFS_SUPER_MAGIC: i64 = 0x2fc12fc1;
+
+        let mut fs: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut fs) } != 0 {
+            return false;
+        }
+        matches!(fs.f_type as i64, BTRFS_SUPER_MAGIC | ZFS_SUPER_MAGIC)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        // APFS and ZFS on macOS/BSD keep local snapshots as well
+        let _ = c_path;
+        true
+    }
+}
+
+/// Returns true if `path` is the root of a mounted filesystem.
+///
+/// A path is considered a mount point when it is `/` or when it lives on a different
+/// device than its parent directory.
+pub fn is_mount_point(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let Some(parent) = path.parent() else {
+            return true;
+        };
+        match (std::fs::metadata(path), std::fs::metadata(parent)) {
+            (Ok(own), Ok(parent)) => own.dev() != parent.dev(),
+            _ => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.parent().is_none()
+    }
+}
+
+/// Breakdown of the difference between `df` used space and the bytes found by a scan.
+///
+/// Every figure refers only to the filesystem the scan root lives on, so files on other
+/// filesystems mounted beneath the root do not distort the comparison.
+#[derive(Debug)]
+pub struct Reconciliation {
+    pub used_bytes: u64,
+    pub file_bytes: u64,
+    pub dir_bytes: u64,
+    pub deleted_open_bytes: Option<u64>,
+    pub unscanned_dirs: usize,
+    pub supports_snapshots: bool,
+}
+
+impl Reconciliation {
+    /// Bytes accounted for by the known causes.
+    pub fn explained_bytes(&self) -> u64 {
+        self.file_bytes + self.dir_bytes + self.deleted_open_bytes.unwrap_or(0)
+    }
+
+    /// Used bytes not attributed to any known cause (negative if the scan found more
+    /// than `df` reports, e.g. because `--count-links` counted hardlinked files more than once).
+    pub fn unexplained_bytes(&self) -> i128 {
+        self.used_bytes as i128 - self.explained_bytes() as i128
+    }
+
+    /// Renders the reconciliation as human readable lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::reconcile::Reconciliation;
+    ///
+    /// let rec = Reconciliation {
+    ///     used_bytes: 10 * 1024,
+    ///     file_bytes: 6 * 1024,
+    ///     dir_bytes: 1024,
+    ///     deleted_open_bytes: Some(2 * 1024),
+    ///     unscanned_dirs: 0,
+    ///     supports_snapshots: false,
+    /// };
+    /// assert_eq!(rec.unexplained_bytes(), 1024);
+    /// assert!(rec.lines().iter().any(|l| l.contains("unexplained")));
+    /// ```
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            tr!("reconcile-used", size = self.used_bytes.format_size()),
+            tr!("reconcile-files", size = self.file_bytes.format_size()),
+            tr!("reconcile-dirs", size = self.dir_bytes.format_size()),
+        ];
+
+        match self.deleted_open_bytes {
+            Some(bytes) => lines.push(tr!("reconcile-deleted-open", size = bytes.format_size())),
+            None => lines.push(tr!("reconcile-deleted-open-unknown")),
+        }
+
+        let unexplained = self.unexplained_bytes();
+        if unexplained >= 0 {
+            lines.push(tr!(
+                "reconcile-unexplained",
+                size = (unexplained as u64).format_size()
+            ));
+        } else {
+            lines.push(tr!(
+                "reconcile-exceeds",
+                size = (unexplained.unsigned_abs() as u64).format_size()
+            ));
+        }
+
+        if unexplained > 0 {
+            if self.unscanned_dirs > 0 {
+                lines.push(format!(
+                    "  - {}",
+                    tr!("reconcile-unscanned", count = self.unscanned_dirs)
+                ));
+            }
+            if self.supports_snapshots {
+                lines.push(format!("  - {}", tr!("reconcile-snapshots")));
+            }
+            lines.push(format!("  - {}", tr!("reconcile-metadata")));
+        }
+
+        lines
+    }
+}
@@ -0,0 +1,1318 @@
+use crate::config::Config;
+use crate::artifacts::BuildArtifacts;
+use crate::cost::{format_monthly, CostModel, CostTotal, GroupCosts};
+use crate::ownership::OwnerTotals;
+use crate::porcelain;
+use crate::results::entry_id;
+use crate::tags::TagTotals;
+use crate::top_entries::{TopEntries, TopK};
+use crate::users::UserTotals;
+use crate::traits::ByteSize;
+use clap::ValueEnum;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Reports a scan can produce: the largest files (the default), the directories holding the
+/// most bytes, the bytes per file extension, a histogram of file sizes, the bytes per tag of the
+/// tag map (see [`crate::tags`]), the bytes per owner of the ownership mapping (see
+/// [`crate::ownership`]), the largest directories split by file age, the bytes per owning user
+/// (see [`crate::users`]) and the directories with the most entries along with the deepest one
+/// (see [`crate::tally::TreeShape`]), the large file names repeated across directories (see
+/// [`RepeatedNames`]) and the build output of the projects found (see
+/// [`crate::artifacts::BuildArtifacts`]). Any combination comes out of one traversal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportKind {
+    TopFiles,
+    TopDirs,
+    ByExt,
+    Histogram,
+    ByTag,
+    ByOwner,
+    AgeByDir,
+    ByUser,
+    FanOut,
+    DupNames,
+    Artifacts,
+}
+
+/// Secondary keys ordering results of equal size in the final report: the full path, the
+/// last modification (most recent first) or the file name, each in ascending order otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ThenBy {
+    Path,
+    Mtime,
+    Name,
+}
+
+/// Built-in criteria files are ranked by: their size (the default), their last modification or
+/// last access, the oldest first unless the newest are asked for, or the length of their path,
+/// the longest first. Library users can rank by anything else with [`TopFilesBy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Metric {
+    #[default]
+    Size,
+    Mtime,
+    Atime,
+    PathLength,
+}
+
+impl Metric {
+    /// Name of the metric as given to `--metric`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Size => "size",
+            Metric::Mtime => "mtime",
+            Metric::Atime => "atime",
+            Metric::PathLength => "path-length",
+        }
+    }
+
+    /// Whether the metric is a point in time, which `newest` ranks the other way round.
+    pub fn is_time(self) -> bool {
+        matches!(self, Metric::Mtime | Metric::Atime)
+    }
+
+    /// Measures `file`: its size in bytes, its last modification or access in seconds since the
+    /// Unix epoch, or the number of characters of its path. `None` if the time is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::report::{FileInfo, Metric};
+    ///
+    /// let file = FileInfo {
+    ///     path: "/srv/Música/mix.wav".to_string(),
+    ///     size: 4096,
+    ///     modified: None,
+    ///     uid: None,
+    ///     accessed: None,
+    /// };
+    /// assert_eq!(Metric::Size.measure(&file), Some(4096));
+    /// assert_eq!(Metric::PathLength.measure(&file), Some(19));
+    /// assert_eq!(Metric::Mtime.measure(&file), None);
+    /// ```
+    pub fn measure(self, file: &FileInfo) -> Option<u64> {
+        match self {
+            Metric::Size => Some(file.size),
+            Metric::Mtime => file.modified.map(unix_seconds),
+            Metric::Atime => file.accessed.map(unix_seconds),
+            Metric::PathLength => Some(file.path.chars().count() as u64),
+        }
+    }
+
+    /// Value `file` is ranked by in a [`TopEntries`], the highest first: its
+    /// [measure](Metric::measure), except that for times it grows the older the file is (the
+    /// more recent with `newest`). Files whose time is unknown are not ranked by it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::report::{FileInfo, Metric};
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let file = |secs| FileInfo {
+    ///     path: "/srv/log".to_string(),
+    ///     size: 10,
+    ///     modified: Some(UNIX_EPOCH + Duration::from_secs(secs)),
+    ///     uid: None,
+    ///     accessed: None,
+    /// };
+    /// assert_eq!(Metric::Size.value(&file(100), false), Some(10));
+    /// assert!(Metric::Mtime.value(&file(100), false) > Metric::Mtime.value(&file(200), false));
+    /// assert!(Metric::Mtime.value(&file(100), true) < Metric::Mtime.value(&file(200), true));
+    /// ```
+    pub fn value(self, file: &FileInfo, newest: bool) -> Option<u64> {
+        let measure = self.measure(file)?;
+        Some(if self.is_time() && !newest {
+            u64::MAX - measure
+        } else {
+            measure
+        })
+    }
+}
+
+/// Seconds since the Unix epoch of `time`, or 0 for times before it.
+pub fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// A reported file with the details secondary sort keys are taken from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    pub path: String,
+    pub size: u64,
+    /// Last modification, if the file could still be read (imported entries may not exist here)
+    pub modified: Option<SystemTime>,
+    /// Numeric id of the owning user, known only for files scanned on Unix
+    pub uid: Option<u32>,
+    /// Last access, if the platform records it (often only roughly, e.g. with `relatime`)
+    pub accessed: Option<SystemTime>,
+}
+
+impl FileInfo {
+    /// Gathers the details of a ranked `(path, size)` entry, with the modification time it had
+    /// when it was sized.
+    pub fn from_entry(path: &str, size: u64, modified: Option<SystemTime>) -> Self {
+        FileInfo {
+            path: path.to_string(),
+            size,
+            modified,
+            uid: None,
+            accessed: None,
+        }
+    }
+
+    /// Name of the file, or the whole path if it has none.
+    pub fn name(&self) -> &str {
+        Path::new(&self.path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.path)
+    }
+
+    /// Compares two files of equal size by `then_by`.
+    ///
+    /// Files whose modification time is unknown come after all others; remaining ties are
+    /// broken by path, so the order never depends on how the scan went.
+    pub fn cmp_by(&self, other: &FileInfo, then_by: ThenBy) -> Ordering {
+        let primary = match then_by {
+            ThenBy::Path => Ordering::Equal,
+            ThenBy::Mtime => match (self.modified, other.modified) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+            ThenBy::Name => self.name().cmp(other.name()),
+        };
+        primary.then_with(|| self.path.cmp(&other.path))
+    }
+}
+
+/// Orders `entries` by descending size and, among equal sizes, by `then_by`.
+///
+/// Without a key, entries of equal size keep the order they were ranked in. `modified` gives
+/// the modification time a path had when it was sized, as recorded by the scan, so that ordering
+/// by it does not have to read every file again.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{order_entries, ThenBy};
+///
+/// let mut entries = vec![
+///     ("/b/z.iso".to_string(), 100),
+///     ("/a/y.iso".to_string(), 100),
+///     ("/c/x.iso".to_string(), 200),
+/// ];
+/// order_entries(&mut entries, Some(ThenBy::Path), |_| None);
+/// assert_eq!(entries[1].0, "/a/y.iso");
+///
+/// order_entries(&mut entries, Some(ThenBy::Name), |_| None);
+/// assert_eq!(entries[0].0, "/c/x.iso");
+/// assert_eq!(entries[1].0, "/a/y.iso");
+/// assert_eq!(entries[2].0, "/b/z.iso");
+/// ```
+pub fn order_entries(
+    entries: &mut Vec<(String, u64)>,
+    then_by: Option<ThenBy>,
+    modified: impl Fn(&str) -> Option<SystemTime>,
+) {
+    let Some(then_by) = then_by else {
+        return;
+    };
+    let mut infos: Vec<FileInfo> = entries
+        .iter()
+        .map(|(path, size)| FileInfo::from_entry(path, *size, modified(path)))
+        .collect();
+    infos.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.cmp_by(b, then_by)));
+    *entries = infos
+        .into_iter()
+        .map(|info| (info.path, info.size))
+        .collect();
+}
+
+/// One section of a report: an optional heading and its rows, printable for people or scripts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReportSection {
+    /// Heading printed above the rows in the human readable output
+    pub heading: Option<String>,
+    pub rows: Vec<ReportRow>,
+}
+
+/// A row of a [`ReportSection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportRow {
+    /// Line printed in the human readable output
+    pub text: String,
+    /// Porcelain record type, e.g. `dir`
+    pub kind: String,
+    /// Porcelain fields after the type, the free-form one (if any) last
+    pub fields: Vec<String>,
+}
+
+impl ReportSection {
+    /// Prints the section, as text lines or as porcelain records.
+    pub fn print(&self, porcelain: bool) {
+        if let (Some(heading), false) = (&self.heading, porcelain) {
+            println!("\n{}", heading);
+        }
+        for row in &self.rows {
+            if porcelain {
+                let fields: Vec<&str> = row.fields.iter().map(String::as_str).collect();
+                println!("{}", porcelain::record(&row.kind, &fields));
+            } else {
+                println!("{}", row.text);
+            }
+        }
+    }
+}
+
+/// A single-pass analysis of the files of a scan.
+///
+/// Every sized file is handed to each aggregator, so any number of reports come out of one
+/// traversal. The built-in reports ([`TopFiles`], [`DirTotals`], [`ExtensionTotals`] and
+/// [`SizeHistogram`]) are aggregators, and library users can add their own through
+/// [`crate::run_with_aggregators`].
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo, ReportRow, ReportSection};
+///
+/// /// Counts files larger than a gigabyte
+/// #[derive(Default)]
+/// struct HugeFiles(usize);
+///
+/// impl Aggregator for HugeFiles {
+///     fn observe(&mut self, file: &FileInfo) {
+///         if file.size > 1 << 30 {
+///             self.0 += 1;
+///         }
+///     }
+///
+///     fn finish(&self) -> ReportSection {
+///         ReportSection {
+///             heading: Some("Huge files:".to_string()),
+///             rows: vec![ReportRow {
+///                 text: self.0.to_string(),
+///                 kind: "huge-files".to_string(),
+///                 fields: vec![self.0.to_string()],
+///             }],
+///         }
+///     }
+/// }
+///
+/// let mut huge = HugeFiles::default();
+/// huge.observe(&FileInfo { path: "/vm.img".to_string(), size: 8 << 30, modified: None, uid: None, accessed: None });
+/// huge.observe(&FileInfo { path: "/a.txt".to_string(), size: 12, modified: None, uid: None, accessed: None });
+/// assert_eq!(huge.finish().rows[0].fields, ["1"]);
+/// ```
+pub trait Aggregator {
+    /// Accounts for one sized file.
+    fn observe(&mut self, file: &FileInfo);
+
+    /// Returns the report of everything observed so far.
+    fn finish(&self) -> ReportSection;
+}
+
+/// Creates the aggregators of the reports requested in `config` other than the largest files,
+/// in the given order, each listing at most `config.num_entries` rows.
+///
+/// The largest files of a scan are ranked in parallel by
+/// [`crate::top_entries::ConcurrentTopEntries`] instead, as they also feed sessions and
+/// actions, and the `fan-out` report is collected by the scanner itself, which lists the
+/// directories. Directory totals only count directories strictly below `root`, and tag and owner
+/// totals use the tag map and ownership mapping of `config`. With storage rates, every report
+/// estimates the monthly cost of its rows and a [`CostTotal`] of the whole scan comes last.
+/// `config.stats` adds the histogram, unless requested already, and the [`SizeStats`], and
+/// `config.repo` the [`RepoSizes`] of `root`.
+pub fn aggregators(config: &Config, root: &Path) -> Vec<Box<dyn Aggregator>> {
+    let limit = config.num_entries;
+    let costs = &config.costs;
+    let mut aggregators: Vec<Box<dyn Aggregator>> = config
+        .reports
+        .iter()
+        .filter_map(|report| match report {
+            ReportKind::TopFiles | ReportKind::FanOut => None,
+            ReportKind::TopDirs => Some(Box::new(
+                DirTotals::new(root, limit).with_costs(costs.clone()),
+            ) as Box<dyn Aggregator>),
+            ReportKind::ByExt => Some(Box::new(
+                ExtensionTotals::new(limit).with_costs(costs.clone()),
+            ) as Box<dyn Aggregator>),
+            ReportKind::Histogram => {
+                Some(Box::new(SizeHistogram::default().with_costs(costs.clone()))
+                    as Box<dyn Aggregator>)
+            }
+            ReportKind::ByTag => Some(Box::new(
+                TagTotals::new(config.tags.clone(), limit).with_costs(costs.clone()),
+            ) as Box<dyn Aggregator>),
+            ReportKind::ByOwner => Some(Box::new(
+                OwnerTotals::new(config.ownership.clone(), limit).with_costs(costs.clone()),
+            ) as Box<dyn Aggregator>),
+            ReportKind::AgeByDir => Some(
+                Box::new(DirAges::new(root, limit, SystemTime::now())) as Box<dyn Aggregator>
+            ),
+            ReportKind::ByUser => Some(
+                Box::new(UserTotals::new(limit).with_costs(costs.clone())) as Box<dyn Aggregator>
+            ),
+            ReportKind::DupNames => {
+                Some(Box::new(RepeatedNames::new(limit)) as Box<dyn Aggregator>)
+            }
+            ReportKind::Artifacts => {
+                Some(Box::new(BuildArtifacts::new(limit)) as Box<dyn Aggregator>)
+            }
+        })
+        .collect();
+    if config.stats {
+        if !config.reports.contains(&ReportKind::Histogram) {
+            aggregators.push(Box::new(SizeHistogram::default().with_costs(costs.clone())));
+        }
+        aggregators.push(Box::new(SizeStats::default()));
+    }
+    if config.repo {
+        aggregators.push(Box::new(RepoSizes::new(root)));
+    }
+    if costs.is_enabled() {
+        aggregators.push(Box::new(CostTotal::new(costs.clone())));
+    }
+    aggregators
+}
+
+/// The largest files, for pipelines of library users; rows match the `top-files` report.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo, TopFiles};
+///
+/// let mut top = TopFiles::new(1);
+/// for (path, size) in [("/a.iso", 700), ("/b.iso", 900)] {
+///     top.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// let section = top.finish();
+/// assert_eq!(section.rows.len(), 1);
+/// assert!(section.rows[0].text.contains("/b.iso"));
+/// assert_eq!(section.rows[0].fields[1], "900");
+/// ```
+#[derive(Debug)]
+pub struct TopFiles {
+    top: TopEntries,
+}
+
+impl TopFiles {
+    /// Creates an aggregator keeping the `limit` largest files.
+    pub fn new(limit: usize) -> Self {
+        TopFiles {
+            top: TopEntries::new(limit),
+        }
+    }
+}
+
+impl Aggregator for TopFiles {
+    fn observe(&mut self, file: &FileInfo) {
+        if self.top.threshold().is_none_or(|min| file.size > min) {
+            self.top.insert(file.path.clone(), file.size);
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        let rows = self
+            .top
+            .to_vec()
+            .into_iter()
+            .map(|(path, size)| {
+                let id = entry_id(&path);
+                ReportRow {
+                    text: format!("[{}] {}: {}", id, path, size.format_size()),
+                    kind: "entry".to_string(),
+                    fields: vec![id, size.to_string(), String::new(), path],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: None,
+            rows,
+        }
+    }
+}
+
+/// The files ranking highest by any metric, for library users ranking by something the built-in
+/// [`Metric`]s do not measure. `metric` returns the key of a file, or `None` to leave it out;
+/// wrap it in [`std::cmp::Reverse`] to keep the lowest keys instead. Rows are of kind `ranked`,
+/// with the key and the path as fields.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo, TopFilesBy};
+///
+/// // The deepest files
+/// let mut deepest = TopFilesBy::new(1, |file: &FileInfo| Some(file.path.matches('/').count()));
+/// for path in ["/srv/a", "/srv/db/wal/0001", "/srv/db/base"] {
+///     deepest.observe(&FileInfo { path: path.to_string(), size: 1, modified: None, uid: None, accessed: None });
+/// }
+/// let section = deepest.finish();
+/// assert_eq!(section.rows.len(), 1);
+/// assert_eq!(section.rows[0].fields, ["4", "/srv/db/wal/0001"]);
+/// ```
+pub struct TopFilesBy<M, F> {
+    top: TopK<M>,
+    metric: F,
+}
+
+impl<M, F> TopFilesBy<M, F>
+where
+    M: Ord + Clone + Display,
+    F: Fn(&FileInfo) -> Option<M>,
+{
+    /// Creates an aggregator keeping the `limit` files with the highest keys under `metric`.
+    pub fn new(limit: usize, metric: F) -> Self {
+        TopFilesBy {
+            top: TopK::new(limit),
+            metric,
+        }
+    }
+}
+
+impl<M, F> Aggregator for TopFilesBy<M, F>
+where
+    M: Ord + Clone + Display,
+    F: Fn(&FileInfo) -> Option<M>,
+{
+    fn observe(&mut self, file: &FileInfo) {
+        let Some(key) = (self.metric)(file) else {
+            return;
+        };
+        if self.top.threshold().is_none_or(|min| key > min) {
+            self.top.insert(file.path.clone(), key);
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        let rows = self
+            .top
+            .to_vec()
+            .into_iter()
+            .map(|(path, key)| ReportRow {
+                text: format!("{}: {}", path, key),
+                kind: "ranked".to_string(),
+                fields: vec![key.to_string(), path],
+            })
+            .collect();
+        ReportSection {
+            heading: None,
+            rows,
+        }
+    }
+}
+
+/// Bytes beneath every directory below a root, for the `top-dirs` report.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, DirTotals, FileInfo};
+/// use std::path::{Path, PathBuf};
+///
+/// let mut totals = DirTotals::new(Path::new("/srv"), 2);
+/// for (path, size) in [("/srv/db/wal/1", 300), ("/srv/db/base", 200), ("/srv/www/index.html", 5)] {
+///     totals.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(
+///     totals.top(),
+///     [(PathBuf::from("/srv/db"), 500), (PathBuf::from("/srv/db/wal"), 300)]
+/// );
+/// assert_eq!(totals.finish().rows[0].fields, ["500", "/srv/db"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct DirTotals {
+    root: PathBuf,
+    limit: usize,
+    totals: HashMap<PathBuf, u64>,
+    costs: GroupCosts<PathBuf>,
+}
+
+impl DirTotals {
+    /// Creates empty totals for the directories below `root`, reporting the `limit` largest.
+    pub fn new(root: &Path, limit: usize) -> Self {
+        DirTotals {
+            root: root.to_path_buf(),
+            limit,
+            totals: HashMap::new(),
+            costs: GroupCosts::default(),
+        }
+    }
+
+    /// Also estimates the monthly cost of every directory at the rates of `costs`.
+    pub fn with_costs(mut self, costs: CostModel) -> Self {
+        self.costs = GroupCosts::new(costs);
+        self
+    }
+
+    /// Returns the directories holding the most bytes, largest first.
+    pub fn top(&self) -> Vec<(PathBuf, u64)> {
+        let mut top: Vec<(PathBuf, u64)> = self
+            .totals
+            .iter()
+            .map(|(path, size)| (path.clone(), *size))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(self.limit);
+        top
+    }
+}
+
+/// Returns the directories containing `path` strictly below `root`, innermost first.
+fn dirs_below<'a>(path: &'a str, root: &'a Path) -> impl Iterator<Item = &'a Path> {
+    // The filesystem root and the scan root would only repeat the total of the scan
+    Path::new(path)
+        .ancestors()
+        .skip(1)
+        .take_while(move |dir| *dir != root && dir.starts_with(root))
+        .filter(|dir| dir.parent().is_some())
+}
+
+impl Aggregator for DirTotals {
+    fn observe(&mut self, file: &FileInfo) {
+        let cost = self.costs.monthly(file);
+        for dir in dirs_below(&file.path, &self.root) {
+            match self.totals.get_mut(dir) {
+                Some(total) => *total += file.size,
+                None => {
+                    self.totals.insert(dir.to_path_buf(), file.size);
+                }
+            }
+            if let Some(cost) = cost {
+                self.costs.add(dir.to_path_buf(), cost);
+            }
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        let rows = self
+            .top()
+            .into_iter()
+            .map(|(path, size)| {
+                let cost = self.costs.get(&path);
+                let path = path.to_string_lossy().to_string();
+                let text = match cost {
+                    Some(cost) => format!(
+                        "{:>10}  {:>14}  {}",
+                        size.format_size(),
+                        format_monthly(cost),
+                        path
+                    ),
+                    None => format!("{:>10}  {}", size.format_size(), path),
+                };
+                ReportRow {
+                    text,
+                    kind: "dir".to_string(),
+                    fields: vec![size.to_string(), path],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-top-dirs")),
+            rows,
+        }
+    }
+}
+
+/// Upper bounds (inclusive) of the file ages of [`DirAges`]: 30 days and a year; older files
+/// go in a last bucket.
+pub const AGE_BOUNDS: [Duration; 2] = [
+    Duration::from_secs(30 * 86_400),
+    Duration::from_secs(365 * 86_400),
+];
+
+/// Bytes beneath the largest directories below a root split by the age of the files, for the
+/// `age-by-dir` report.
+///
+/// Ages are measured from the last modification to when the report was created, into the
+/// buckets of [`AGE_BOUNDS`], so active data and dead weight are told apart at a glance. Files
+/// modified in the future count as recent, and files whose modification time is unknown (such
+/// as imported entries) only count towards the total of their directories.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, DirAges, FileInfo};
+/// use std::path::{Path, PathBuf};
+/// use std::time::{Duration, SystemTime};
+///
+/// let now = SystemTime::now();
+/// let days = |days: u64| Some(now - Duration::from_secs(days * 86_400));
+/// let mut ages = DirAges::new(Path::new("/srv"), 10, now);
+/// for (path, size, modified) in [
+///     ("/srv/db/wal/1", 300, days(2)),
+///     ("/srv/db/base", 200, days(900)),
+///     ("/srv/www/index.html", 5, None),
+/// ] {
+///     ages.observe(&FileInfo { path: path.to_string(), size, modified, uid: None, accessed: None });
+/// }
+/// assert_eq!(ages.top()[0], (PathBuf::from("/srv/db"), 500, [300, 0, 200]));
+/// assert_eq!(ages.finish().rows[2].fields, ["5", "0", "0", "0", "/srv/www"]);
+/// ```
+#[derive(Debug)]
+pub struct DirAges {
+    root: PathBuf,
+    limit: usize,
+    now: SystemTime,
+    totals: HashMap<PathBuf, (u64, [u64; AGE_BOUNDS.len() + 1])>,
+}
+
+impl DirAges {
+    /// Creates empty totals for the directories below `root`, reporting the `limit` largest
+    /// with the ages of their files as of `now`.
+    pub fn new(root: &Path, limit: usize, now: SystemTime) -> Self {
+        DirAges {
+            root: root.to_path_buf(),
+            limit,
+            now,
+            totals: HashMap::new(),
+        }
+    }
+
+    /// Returns the directories holding the most bytes as `(directory, bytes, bytes per age
+    /// bucket)`, largest first.
+    pub fn top(&self) -> Vec<(PathBuf, u64, [u64; AGE_BOUNDS.len() + 1])> {
+        let mut top: Vec<(PathBuf, u64, [u64; AGE_BOUNDS.len() + 1])> = self
+            .totals
+            .iter()
+            .map(|(path, (size, ages))| (path.clone(), *size, *ages))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(self.limit);
+        top
+    }
+}
+
+/// Shades a share of 0 to 100 percent for the cells of the `age-by-dir` report.
+fn shade(share: f64) -> char {
+    match share {
+        share if share <= 0.0 => ' ',
+        share if share < 25.0 => '░',
+        share if share < 50.0 => '▒',
+        share if share < 75.0 => '▓',
+        _ => '█',
+    }
+}
+
+impl Aggregator for DirAges {
+    fn observe(&mut self, file: &FileInfo) {
+        let bucket = file.modified.map(|modified| {
+            let age = self.now.duration_since(modified).unwrap_or_default();
+            AGE_BOUNDS.partition_point(|bound| *bound < age)
+        });
+        for dir in dirs_below(&file.path, &self.root) {
+            let (size, ages) = match self.totals.get_mut(dir) {
+                Some(totals) => totals,
+                None => self.totals.entry(dir.to_path_buf()).or_default(),
+            };
+            *size += file.size;
+            if let Some(bucket) = bucket {
+                ages[bucket] += file.size;
+            }
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        let rows = self
+            .top()
+            .into_iter()
+            .map(|(path, size, ages)| {
+                let cells: Vec<String> = ages
+                    .iter()
+                    .map(|bytes| {
+                        let share = if size == 0 {
+                            0.0
+                        } else {
+                            *bytes as f64 * 100.0 / size as f64
+                        };
+                        format!("{}{:>4.0}%", shade(share), share)
+                    })
+                    .collect();
+                let path = path.to_string_lossy().to_string();
+                let mut fields = vec![size.to_string()];
+                fields.extend(ages.iter().map(u64::to_string));
+                fields.push(path.clone());
+                ReportRow {
+                    text: format!("{:>10}  {}  {}", size.format_size(), cells.join(" "), path),
+                    kind: "dir-age".to_string(),
+                    fields,
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-age-by-dir")),
+            rows,
+        }
+    }
+}
+
+/// Bytes and number of files per lowercase extension, for the `by-ext` report, each with its
+/// largest file.
+///
+/// Files without an extension are counted under the empty extension. Of files of the same
+/// size, the first observed is the largest.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, ExtensionTotals, FileInfo};
+///
+/// let mut totals = ExtensionTotals::new(2);
+/// for (path, size) in [("/a/x.ISO", 700), ("/b/y.iso", 300), ("/c/z.log", 50), ("/d/Makefile", 1)] {
+///     totals.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(totals.top(), [("iso".to_string(), 1000, 2), ("log".to_string(), 50, 1)]);
+/// assert_eq!(totals.total_bytes(), 1051);
+/// assert_eq!(totals.largest("iso"), Some(("/a/x.ISO", 700)));
+/// assert_eq!(totals.finish().rows[0].fields, ["1000", "2", "iso", "700", "/a/x.ISO"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct ExtensionTotals {
+    limit: usize,
+    totals: HashMap<String, (u64, usize)>,
+    largest: HashMap<String, (u64, String)>,
+    costs: GroupCosts<String>,
+}
+
+impl ExtensionTotals {
+    /// Creates empty totals, reporting the `limit` extensions taking the most bytes.
+    pub fn new(limit: usize) -> Self {
+        ExtensionTotals {
+            limit,
+            totals: HashMap::new(),
+            largest: HashMap::new(),
+            costs: GroupCosts::default(),
+        }
+    }
+
+    /// Also estimates the monthly cost of every extension at the rates of `costs`.
+    pub fn with_costs(mut self, costs: CostModel) -> Self {
+        self.costs = GroupCosts::new(costs);
+        self
+    }
+
+    /// Returns the extensions taking the most bytes as `(extension, bytes, files)`, largest first.
+    pub fn top(&self) -> Vec<(String, u64, usize)> {
+        let mut top: Vec<(String, u64, usize)> = self
+            .totals
+            .iter()
+            .map(|(extension, (bytes, files))| (extension.clone(), *bytes, *files))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(self.limit);
+        top
+    }
+
+    /// Returns the bytes of all extensions together.
+    pub fn total_bytes(&self) -> u64 {
+        self.totals.values().map(|(bytes, _)| bytes).sum()
+    }
+
+    /// Returns the largest file with `extension` (lowercase, without the dot) and its size.
+    pub fn largest(&self, extension: &str) -> Option<(&str, u64)> {
+        self.largest
+            .get(extension)
+            .map(|(size, path)| (path.as_str(), *size))
+    }
+}
+
+impl Aggregator for ExtensionTotals {
+    fn observe(&mut self, file: &FileInfo) {
+        let extension = Path::new(&file.path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if let Some(cost) = self.costs.monthly(file) {
+            self.costs.add(extension.clone(), cost);
+        }
+        match self.largest.get_mut(&extension) {
+            Some(largest) if file.size > largest.0 => *largest = (file.size, file.path.clone()),
+            Some(_) => {}
+            None => {
+                self.largest
+                    .insert(extension.clone(), (file.size, file.path.clone()));
+            }
+        }
+        let (bytes, files) = self.totals.entry(extension).or_default();
+        *bytes += file.size;
+        *files += 1;
+    }
+
+    fn finish(&self) -> ReportSection {
+        let total = self.total_bytes();
+        let rows = self
+            .top()
+            .into_iter()
+            .map(|(extension, bytes, files)| {
+                let label = if extension.is_empty() {
+                    tr!("extension-none")
+                } else {
+                    format!(".{}", extension)
+                };
+                let line = share_line(&label, bytes, total, files, self.costs.get(&extension));
+                let (path, size) = self.largest(&extension).unwrap_or_default();
+                ReportRow {
+                    text: format!(
+                        "{}  {}",
+                        line,
+                        tr!("extension-largest", path = path, size = size.format_size())
+                    ),
+                    kind: "extension".to_string(),
+                    fields: vec![
+                        bytes.to_string(),
+                        files.to_string(),
+                        extension,
+                        size.to_string(),
+                        path.to_string(),
+                    ],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-by-ext")),
+            rows,
+        }
+    }
+}
+
+/// Files from this size on are compared by name in the `dup-names` report; smaller ones are
+/// too many to remember and too small to matter.
+pub const DUP_NAME_MIN_SIZE: u64 = 1 << 20;
+
+/// Large files whose name repeats across directories, for the `dup-names` report.
+///
+/// Only names are compared, exactly and without reading any content, so this is a cheap hint
+/// of copy sprawl (the same `backup.tar` in five places) to follow up with a real duplicate
+/// check. Files below [`DUP_NAME_MIN_SIZE`] are ignored. Names are ranked by the bytes of all
+/// their copies, and each names its largest copy.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo, RepeatedNames};
+///
+/// let mut names = RepeatedNames::new(5);
+/// for (path, size) in [
+///     ("/home/ann/backup.tar", 3 << 20),
+///     ("/srv/old/backup.tar", 5 << 20),
+///     ("/mnt/usb/backup.tar", 4 << 20),
+///     ("/srv/db/dump.sql", 9 << 20),
+///     ("/tmp/backup.tar", 10),
+/// ] {
+///     names.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(names.top(), [("backup.tar".to_string(), 12 << 20, 3)]);
+/// assert_eq!(names.largest("backup.tar"), Some(("/srv/old/backup.tar", 5 << 20)));
+/// assert_eq!(
+///     names.finish().rows[0].fields,
+///     ["12582912", "3", "backup.tar", "5242880", "/srv/old/backup.tar"]
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct RepeatedNames {
+    limit: usize,
+    totals: HashMap<String, (u64, usize)>,
+    largest: HashMap<String, (u64, String)>,
+}
+
+impl RepeatedNames {
+    /// Creates empty totals, reporting the `limit` repeated names taking the most bytes.
+    pub fn new(limit: usize) -> Self {
+        RepeatedNames {
+            limit,
+            totals: HashMap::new(),
+            largest: HashMap::new(),
+        }
+    }
+
+    /// Returns the names found more than once as `(name, bytes, copies)`, largest first.
+    pub fn top(&self) -> Vec<(String, u64, usize)> {
+        let mut top: Vec<(String, u64, usize)> = self
+            .totals
+            .iter()
+            .filter(|(_, (_, copies))| *copies > 1)
+            .map(|(name, (bytes, copies))| (name.clone(), *bytes, *copies))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(self.limit);
+        top
+    }
+
+    /// Returns the largest copy of `name` and its size.
+    pub fn largest(&self, name: &str) -> Option<(&str, u64)> {
+        self.largest
+            .get(name)
+            .map(|(size, path)| (path.as_str(), *size))
+    }
+}
+
+impl Aggregator for RepeatedNames {
+    fn observe(&mut self, file: &FileInfo) {
+        if file.size < DUP_NAME_MIN_SIZE {
+            return;
+        }
+        let name = file.name();
+        match self.largest.get_mut(name) {
+            Some(largest) if file.size > largest.0 => *largest = (file.size, file.path.clone()),
+            Some(_) => {}
+            None => {
+                self.largest
+                    .insert(name.to_string(), (file.size, file.path.clone()));
+            }
+        }
+        let (bytes, copies) = self.totals.entry(name.to_string()).or_default();
+        *bytes += file.size;
+        *copies += 1;
+    }
+
+    fn finish(&self) -> ReportSection {
+        let rows = self
+            .top()
+            .into_iter()
+            .map(|(name, bytes, copies)| {
+                let (path, size) = self.largest(&name).unwrap_or_default();
+                ReportRow {
+                    text: format!(
+                        "{:>10}  {}  {}",
+                        bytes.format_size(),
+                        tr!("dup-name-copies", name = name.as_str(), copies = copies),
+                        tr!("extension-largest", path = path, size = size.format_size())
+                    ),
+                    kind: "dup-name".to_string(),
+                    fields: vec![
+                        bytes.to_string(),
+                        copies.to_string(),
+                        name,
+                        size.to_string(),
+                        path.to_string(),
+                    ],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-dup-names")),
+            rows,
+        }
+    }
+}
+
+/// Bytes and files of a git repository's `.git` directory against those of its working tree,
+/// the section printed by `--repo`.
+///
+/// Files are counted under `.git` if they lie in the `.git` directory of `root`; a `.git` file
+/// (in worktrees and submodules) points to data outside the repository, which is not counted.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo, RepoSizes};
+/// use std::path::Path;
+///
+/// let mut sizes = RepoSizes::new(Path::new("/src/app"));
+/// for (path, size) in [
+///     ("/src/app/.git/objects/pack/pack-1.pack", 900),
+///     ("/src/app/.gitignore", 10),
+///     ("/src/app/src/main.rs", 90),
+/// ] {
+///     sizes.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(sizes.git(), (900, 1));
+/// assert_eq!(sizes.worktree(), (100, 2));
+/// assert_eq!(sizes.finish().rows[0].fields, ["900", "1", "git"]);
+/// ```
+#[derive(Debug)]
+pub struct RepoSizes {
+    root: PathBuf,
+    git_dir: PathBuf,
+    git: (u64, usize),
+    worktree: (u64, usize),
+}
+
+impl RepoSizes {
+    /// Creates empty totals for the repository rooted at `root`.
+    pub fn new(root: &Path) -> Self {
+        RepoSizes {
+            root: root.to_path_buf(),
+            git_dir: root.join(".git"),
+            git: (0, 0),
+            worktree: (0, 0),
+        }
+    }
+
+    /// Returns the bytes and files inside `.git`.
+    pub fn git(&self) -> (u64, usize) {
+        self.git
+    }
+
+    /// Returns the bytes and files of the working tree, everything but `.git`.
+    pub fn worktree(&self) -> (u64, usize) {
+        self.worktree
+    }
+}
+
+impl Aggregator for RepoSizes {
+    fn observe(&mut self, file: &FileInfo) {
+        let (bytes, files) = if Path::new(&file.path).starts_with(&self.git_dir) {
+            &mut self.git
+        } else {
+            &mut self.worktree
+        };
+        *bytes += file.size;
+        *files += 1;
+    }
+
+    fn finish(&self) -> ReportSection {
+        let total = self.git.0 + self.worktree.0;
+        let rows = [
+            ("git", ".git".to_string(), self.git),
+            ("worktree", tr!("repo-worktree"), self.worktree),
+        ]
+        .into_iter()
+        .map(|(part, label, (bytes, files))| ReportRow {
+            text: share_line(&label, bytes, total, files, None),
+            kind: "repo-size".to_string(),
+            fields: vec![bytes.to_string(), files.to_string(), part.to_string()],
+        })
+        .collect();
+        ReportSection {
+            heading: Some(tr!("report-repo", root = self.root.display().to_string())),
+            rows,
+        }
+    }
+}
+
+/// Upper bounds (exclusive) of the buckets of [`SizeHistogram`]; larger files go in a last one.
+pub const HISTOGRAM_BOUNDS: [u64; 6] = [4 << 10, 64 << 10, 1 << 20, 16 << 20, 256 << 20, 4 << 30];
+
+/// Bytes and number of files per size bucket, for the `histogram` report.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo, SizeHistogram};
+///
+/// let mut histogram = SizeHistogram::default();
+/// for size in [100, 3000, 5 << 30] {
+///     histogram.observe(&FileInfo { path: "/f".to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(histogram.bucket(0), (3100, 2));
+/// assert_eq!(histogram.bucket(6), (5 << 30, 1));
+/// ```
+#[derive(Debug, Default)]
+pub struct SizeHistogram {
+    buckets: [(u64, usize); HISTOGRAM_BOUNDS.len() + 1],
+    costs: GroupCosts<usize>,
+}
+
+impl SizeHistogram {
+    /// Also estimates the monthly cost of every bucket at the rates of `costs`.
+    pub fn with_costs(mut self, costs: CostModel) -> Self {
+        self.costs = GroupCosts::new(costs);
+        self
+    }
+
+    /// Returns the bytes and number of files of bucket `index` (see [`HISTOGRAM_BOUNDS`]).
+    pub fn bucket(&self, index: usize) -> (u64, usize) {
+        self.buckets[index]
+    }
+}
+
+impl Aggregator for SizeHistogram {
+    fn observe(&mut self, file: &FileInfo) {
+        let index = HISTOGRAM_BOUNDS.partition_point(|bound| *bound <= file.size);
+        if let Some(cost) = self.costs.monthly(file) {
+            self.costs.add(index, cost);
+        }
+        self.buckets[index].0 += file.size;
+        self.buckets[index].1 += 1;
+    }
+
+    fn finish(&self) -> ReportSection {
+        let total = self.buckets.iter().map(|(bytes, _)| bytes).sum();
+        let rows = self
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(index, (bytes, files))| {
+                let (label, bound) = match HISTOGRAM_BOUNDS.get(index) {
+                    Some(bound) => (format!("< {}", bound.format_size()), bound.to_string()),
+                    None => (
+                        format!(">= {}", HISTOGRAM_BOUNDS[index - 1].format_size()),
+                        String::new(),
+                    ),
+                };
+                ReportRow {
+                    text: share_line(&label, *bytes, total, *files, self.costs.get(&index)),
+                    kind: "histogram".to_string(),
+                    fields: vec![bound, bytes.to_string(), files.to_string()],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-histogram")),
+            rows,
+        }
+    }
+}
+
+/// Linear sub-buckets per power of two of [`SizeStats`]; the median is estimated to within
+/// 1/64 of its value.
+const STATS_SUB_BUCKETS: usize = 32;
+
+/// Bits of [`STATS_SUB_BUCKETS`], below which sizes are counted exactly.
+const STATS_SHIFT: u32 = STATS_SUB_BUCKETS.trailing_zeros();
+
+/// Number, total, mean and median size of all files, for `--stats`.
+///
+/// Sizes are counted in buckets growing with powers of two, each split into
+/// [`STATS_SUB_BUCKETS`] linear steps, so the median comes out of a fixed amount of memory
+/// however many files are observed. Sizes below that step count are kept exactly.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo, SizeStats};
+///
+/// let mut stats = SizeStats::default();
+/// for size in [3, 10, 5000, 1 << 30] {
+///     stats.observe(&FileInfo { path: "/f".to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(stats.files(), 4);
+/// assert_eq!(stats.mean(), ((1 << 30) + 5013) / 4);
+/// assert_eq!(stats.median(), Some(10));
+/// assert_eq!(stats.finish().rows[0].fields[..2], ["4", "1073746837"]);
+/// ```
+#[derive(Debug)]
+pub struct SizeStats {
+    files: u64,
+    total: u64,
+    buckets: Vec<u64>,
+}
+
+impl Default for SizeStats {
+    fn default() -> Self {
+        SizeStats {
+            files: 0,
+            total: 0,
+            buckets: vec![0; STATS_SUB_BUCKETS * (u64::BITS as usize - STATS_SHIFT as usize + 1)],
+        }
+    }
+}
+
+impl SizeStats {
+    /// Returns the number of files observed.
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    /// Returns the bytes of all files observed.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the mean size in bytes, 0 without any file.
+    pub fn mean(&self) -> u64 {
+        self.total.checked_div(self.files).unwrap_or_default()
+    }
+
+    /// Estimates the median size in bytes (the lower one for an even number of files) as the
+    /// middle of its bucket; `None` without any file.
+    pub fn median(&self) -> Option<u64> {
+        let middle = self.files.checked_sub(1)? / 2;
+        let mut seen = 0;
+        let index = self.buckets.iter().position(|count| {
+            seen += count;
+            seen > middle
+        })?;
+        let (low, width) = Self::bucket_range(index);
+        Some(low + width / 2)
+    }
+
+    /// Returns the bucket counting files of `size` bytes.
+    fn bucket_index(size: u64) -> usize {
+        if size < STATS_SUB_BUCKETS as u64 {
+            return size as usize;
+        }
+        let shift = (u64::BITS - 1 - size.leading_zeros()) - STATS_SHIFT;
+        let step = (size >> shift) as usize - STATS_SUB_BUCKETS;
+        STATS_SUB_BUCKETS * (shift as usize + 1) + step
+    }
+
+    /// Returns the smallest size of bucket `index` and the number of sizes it spans.
+    fn bucket_range(index: usize) -> (u64, u64) {
+        if index < STATS_SUB_BUCKETS {
+            return (index as u64, 1);
+        }
+        let shift = (index / STATS_SUB_BUCKETS - 1) as u32;
+        let step = (index % STATS_SUB_BUCKETS + STATS_SUB_BUCKETS) as u64;
+        (step << shift, 1 << shift)
+    }
+}
+
+impl Aggregator for SizeStats {
+    fn observe(&mut self, file: &FileInfo) {
+        self.files += 1;
+        self.total += file.size;
+        self.buckets[Self::bucket_index(file.size)] += 1;
+    }
+
+    fn finish(&self) -> ReportSection {
+        let median = self.median().unwrap_or_default();
+        ReportSection {
+            heading: Some(tr!("report-stats")),
+            rows: vec![ReportRow {
+                text: tr!(
+                    "stats-line",
+                    files = self.files,
+                    total = self.total.format_size(),
+                    mean = self.mean().format_size(),
+                    median = median.format_size()
+                ),
+                kind: "stats".to_string(),
+                fields: vec![
+                    self.files.to_string(),
+                    self.total.to_string(),
+                    self.mean().to_string(),
+                    median.to_string(),
+                ],
+            }],
+        }
+    }
+}
+
+/// Formats a `label`, its bytes, their share of `total`, the number of files and the estimated
+/// monthly `cost`, if any, like the lines of `summary`.
+pub(crate) fn share_line(
+    label: &str,
+    bytes: u64,
+    total: u64,
+    files: usize,
+    cost: Option<f64>,
+) -> String {
+    let share = if total == 0 {
+        0.0
+    } else {
+        bytes as f64 * 100.0 / total as f64
+    };
+    let line = format!(
+        "{:<20} {:>10} {:>6.1}%  {}",
+        label,
+        bytes.format_size(),
+        share,
+        tr!("category-files", files = files)
+    );
+    match cost {
+        Some(cost) => format!("{}  {}", line, format_monthly(cost)),
+        None => line,
+    }
+}
@@ -0,0 +1,796 @@
+use crate::actions::guard::{Fingerprint, ScanStamps};
+use crate::actions::verify::to_hex;
+use crate::host::hostname;
+use crate::quota::{mount_for_path, MountInfo};
+use crate::storage;
+use crate::units::format_unix_time;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of hex digits in an entry ID.
+const ID_LENGTH: usize = 6;
+
+/// Version of the session document written by this build.
+///
+/// The version is bumped whenever a field is renamed, removed or changes meaning; adding
+/// optional fields does not change it. Older documents are migrated on load, documents of a
+/// newer version are rejected rather than misread. See `schemas/session.schema.json`.
+///
+/// * 1 - Unversioned sessions: `root`, optional `host` and `entries`
+/// * 2 - Adds `schema_version` and the provenance fields
+pub const SCHEMA_VERSION: u64 = 2;
+
+/// Number of further candidates a session keeps beyond the reported entries, so `more` can
+/// page through them without rescanning.
+pub const MORE_CANDIDATES: usize = 1000;
+
+/// File name of the saved results of the last scan inside the cache directory.
+const LAST_SCAN_FILE: &str = "last-scan.json";
+
+/// Returns the short, stable ID of a reported path.
+///
+/// The ID is the start of the SHA-256 digest of the path, so the same path always gets the
+/// same ID across scans and machines.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::results::entry_id;
+///
+/// let id = entry_id("/data/big.iso");
+/// assert_eq!(id.len(), 6);
+/// assert_eq!(id, entry_id("/data/big.iso"));
+/// assert_ne!(id, entry_id("/data/other.iso"));
+/// ```
+pub fn entry_id(path: &str) -> String {
+    let mut hex = to_hex(&Sha256::digest(path.as_bytes()));
+    hex.truncate(ID_LENGTH);
+    hex
+}
+
+/// A line of the listed results: one entry, or the entries sharing a directory collapsed into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Listed<'a> {
+    Entry(&'a str, u64),
+    Siblings { parent: &'a Path, count: usize, size: u64 },
+}
+
+/// Lists `entries` as they are, except that the entries of any directory holding more than
+/// `limit` of them become one [`Listed::Siblings`] line where the largest of them ranked, so ten
+/// chunks of the same backup do not crowd out everything else.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::results::{collapse_siblings, Listed};
+/// use std::path::Path;
+///
+/// let entries = [
+///     ("/srv/backup/chunk-1".to_string(), 900),
+///     ("/srv/db.sqlite".to_string(), 800),
+///     ("/srv/backup/chunk-2".to_string(), 700),
+///     ("/srv/backup/chunk-3".to_string(), 600),
+/// ];
+/// assert_eq!(
+///     collapse_siblings(&entries, 2),
+///     [
+///         Listed::Siblings { parent: Path::new("/srv/backup"), count: 3, size: 2200 },
+///         Listed::Entry("/srv/db.sqlite", 800),
+///     ]
+/// );
+/// assert_eq!(collapse_siblings(&entries, 3).len(), 4);
+/// ```
+pub fn collapse_siblings(entries: &[(String, u64)], limit: usize) -> Vec<Listed<'_>> {
+    fn parent(path: &str) -> Option<&Path> {
+        Path::new(path).parent()
+    }
+    let mut siblings: HashMap<&Path, (usize, u64)> = HashMap::new();
+    for (path, size) in entries {
+        if let Some(parent) = parent(path) {
+            let (count, total) = siblings.entry(parent).or_default();
+            *count += 1;
+            *total += size;
+        }
+    }
+    let mut listed = Vec::with_capacity(entries.len());
+    let mut collapsed = HashSet::new();
+    for (path, size) in entries {
+        match parent(path).map(|parent| (parent, siblings[parent])) {
+            Some((parent, (count, total))) if count > limit => {
+                // Later siblings are already part of the line
+                if collapsed.insert(parent) {
+                    listed.push(Listed::Siblings {
+                        parent,
+                        count,
+                        size: total,
+                    });
+                }
+            }
+            _ => listed.push(Listed::Entry(path, *size)),
+        }
+    }
+    listed
+}
+
+/// The reported entries living on one mount below a root, for the structured outputs.
+///
+/// * `mount_point` - Mount the entries live on; the root itself if the mount is unknown
+/// * `fs_type` - Filesystem type of the mount (e.g. `ext4`), empty if unknown
+/// * `entries` - Paths of the entries, in the order they were reported
+/// * `size` - Total size of the entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountPartition {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub entries: Vec<String>,
+    pub size: u64,
+}
+
+/// Splits the reported `entries` of a scan of `root` by the mount of `mounts` they live on,
+/// the largest partition first.
+///
+/// A scan crosses into every filesystem mounted below its root, so one report can mix a root
+/// filesystem with network shares and removable drives. Entries outside every known mount (e.g.
+/// without a mount table, as outside Linux) are put under `root`.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::quota::parse_mountinfo;
+/// use ferris_files::results::partition_by_mount;
+/// use std::path::Path;
+///
+/// let mounts = parse_mountinfo(
+///     "22 1 8:2 / / rw - ext4 /dev/sda2 rw\n\
+///      40 22 0:51 / /srv/nas rw - nfs4 nas:/export rw\n",
+/// );
+/// let entries = [
+///     ("/srv/nas/backup.tar".to_string(), 900),
+///     ("/srv/db.sqlite".to_string(), 800),
+///     ("/srv/nas/dump.sql".to_string(), 700),
+/// ];
+/// let partitions = partition_by_mount(&entries, &mounts, Path::new("/srv"));
+/// assert_eq!(partitions[0].mount_point, Path::new("/srv/nas"));
+/// assert_eq!(partitions[0].entries, ["/srv/nas/backup.tar", "/srv/nas/dump.sql"]);
+/// assert_eq!(partitions[0].size, 1600);
+/// assert_eq!(partitions[1].fs_type, "ext4");
+///
+/// let unknown = partition_by_mount(&entries, &[], Path::new("/srv"));
+/// assert_eq!(unknown[0].mount_point, Path::new("/srv"));
+/// assert_eq!(unknown[0].entries.len(), 3);
+/// ```
+pub fn partition_by_mount(
+    entries: &[(String, u64)],
+    mounts: &[MountInfo],
+    root: &Path,
+) -> Vec<MountPartition> {
+    let mut partitions: Vec<MountPartition> = Vec::new();
+    for (path, size) in entries {
+        let (mount_point, fs_type) = match mount_for_path(mounts, Path::new(path)) {
+            Some(mount) => (mount.mount_point.as_path(), mount.fs_type.as_str()),
+            None => (root, ""),
+        };
+        let index = match partitions.iter().position(|p| p.mount_point == mount_point) {
+            Some(index) => index,
+            None => {
+                partitions.push(MountPartition {
+                    mount_point: mount_point.to_path_buf(),
+                    fs_type: fs_type.to_string(),
+                    entries: Vec::new(),
+                    size: 0,
+                });
+                partitions.len() - 1
+            }
+        };
+        partitions[index].entries.push(path.clone());
+        partitions[index].size += size;
+    }
+    partitions.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.mount_point.cmp(&b.mount_point)));
+    partitions
+}
+
+/// A reported entry as saved for follow-up commands.
+///
+/// * `id` - Short stable ID, see [`entry_id`]
+/// * `fingerprint` - Scan-time identity of the file, used to detect later changes
+/// * `host` - Machine the entry was found on (set in merged sessions only)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedEntry {
+    pub id: String,
+    pub path: String,
+    pub size: u64,
+    pub fingerprint: Option<Fingerprint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+/// Where, when and how a session was produced, so archived reports stay interpretable.
+///
+/// * `host` - Machine the scan ran on; `None` for merged sessions spanning several hosts
+/// * `root` - Canonical scan root (empty for merged sessions)
+/// * `started_at` / `finished_at` - Seconds since the Unix epoch at which the scan started and ended
+/// * `tool_version` - Version of ferris-files that wrote the session
+/// * `config_hash` - Hash of the settings that affect results, see [`crate::config::Config::settings_hash`]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Provenance {
+    #[serde(default)]
+    pub host: Option<String>,
+    pub root: PathBuf,
+    #[serde(default)]
+    pub started_at: u64,
+    #[serde(default)]
+    pub finished_at: u64,
+    #[serde(default)]
+    pub tool_version: String,
+    #[serde(default)]
+    pub config_hash: Option<String>,
+}
+
+impl Provenance {
+    /// Describes the provenance in a single line.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![tr!(
+            "provenance-root",
+            root = self.root.display().to_string()
+        )];
+        if let Some(host) = &self.host {
+            parts.push(tr!("provenance-host", host = host.as_str()));
+        }
+        if self.finished_at > 0 {
+            parts.push(tr!(
+                "provenance-scanned",
+                started = format_unix_time(self.started_at),
+                seconds = self.finished_at.saturating_sub(self.started_at)
+            ));
+        }
+        if !self.tool_version.is_empty() {
+            parts.push(format!("ferris-files {}", self.tool_version));
+        }
+        if let Some(hash) = &self.config_hash {
+            parts.push(tr!("provenance-config", hash = hash.as_str()));
+        }
+        parts.join(", ")
+    }
+
+    /// Whether both sessions recorded their settings and the settings differ, so their entries
+    /// cannot be compared like for like. Sessions from before settings were recorded are
+    /// given the benefit of the doubt.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::results::Provenance;
+    ///
+    /// let old = Provenance { config_hash: Some("1f0c".into()), ..Default::default() };
+    /// let new = Provenance { config_hash: Some("9ab2".into()), ..Default::default() };
+    /// assert!(old.settings_differ(&new));
+    /// assert!(!old.settings_differ(&old));
+    /// assert!(!old.settings_differ(&Provenance::default()));
+    /// ```
+    pub fn settings_differ(&self, other: &Provenance) -> bool {
+        matches!((&self.config_hash, &other.config_hash), (Some(a), Some(b)) if a != b)
+    }
+
+    /// Whether the sessions were written by different versions of ferris-files, which may size
+    /// files differently (see [`versions_differ`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::results::Provenance;
+    ///
+    /// let old = Provenance { tool_version: "0.1.2".into(), ..Default::default() };
+    /// let new = Provenance { tool_version: "0.2.0".into(), ..Default::default() };
+    /// assert!(old.version_differs(&new));
+    /// assert!(!new.version_differs(&new));
+    /// ```
+    pub fn version_differs(&self, other: &Provenance) -> bool {
+        versions_differ(&self.tool_version, &other.tool_version)
+    }
+}
+
+/// Whether two recorded versions of ferris-files differ. An empty version was not recorded and
+/// differs from none; `unknown`, which sessions from before provenance are migrated to, differs
+/// from every actual version.
+pub fn versions_differ(a: &str, b: &str) -> bool {
+    !a.is_empty() && !b.is_empty() && a != b
+}
+
+/// Builds the saved form of `entries`, with their scan-time fingerprints.
+fn saved_entries(entries: &[(String, u64)], stamps: &ScanStamps) -> Vec<SavedEntry> {
+    entries
+        .iter()
+        .map(|(path, size)| SavedEntry {
+            id: entry_id(path),
+            path: path.clone(),
+            size: *size,
+            fingerprint: stamps.get(path).copied(),
+            host: None,
+        })
+        .collect()
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// The results of a scan (a session), saved so later invocations can operate on them.
+///
+/// * `schema_version` - Version of the document layout, see [`SCHEMA_VERSION`]
+/// * `provenance` - Stored inline at the top level of the JSON document
+/// * `sources` - Provenance of every session combined into a merged session
+/// * `more` - Candidates ranked below the entries shown so far, largest first, for `more`
+/// * `dirs` - Whether the entries are directories ranked by `--dirs` rather than files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedScan {
+    pub schema_version: u64,
+    #[serde(flatten)]
+    pub provenance: Provenance,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<Provenance>,
+    pub entries: Vec<SavedEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub more: Vec<SavedEntry>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dirs: bool,
+}
+
+impl SavedEntry {
+    /// The entry's path, prefixed with `host:` for entries of merged sessions.
+    pub fn location(&self) -> String {
+        match &self.host {
+            Some(host) => format!("{}:{}", host, self.path),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Whether the file was last modified at most `window` before `until`, as seen by the scan.
+    ///
+    /// Entries without a scan-time modification time (e.g. imported ones) never match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::actions::guard::Fingerprint;
+    /// use ferris_files::results::SavedEntry;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let scanned = SystemTime::now();
+    /// let mut entry = SavedEntry {
+    ///     id: "3b1f0c".to_string(),
+    ///     path: "/var/log/app.log".to_string(),
+    ///     size: 2 << 30,
+    ///     fingerprint: Some(Fingerprint {
+    ///         len: 2 << 30,
+    ///         modified: Some(scanned - Duration::from_secs(3600)),
+    ///         inode: None,
+    ///     }),
+    ///     host: None,
+    /// };
+    /// let day = Duration::from_secs(24 * 3600);
+    /// assert!(entry.modified_within(day, scanned));
+    /// assert!(!entry.modified_within(Duration::from_secs(60), scanned));
+    ///
+    /// entry.fingerprint = None;
+    /// assert!(!entry.modified_within(day, scanned));
+    /// ```
+    pub fn modified_within(&self, window: Duration, until: SystemTime) -> bool {
+        self.fingerprint
+            .and_then(|fingerprint| fingerprint.modified)
+            .is_some_and(|modified| {
+                until
+                    .duration_since(modified)
+                    .map_or(true, |age| age <= window)
+            })
+    }
+}
+
+impl SavedScan {
+    /// Builds the saved form of the reported entries and their scan-time fingerprints.
+    ///
+    /// The provenance records this host and version and stamps both start and end with the
+    /// current time; callers that know when the scan started should set `started_at`.
+    pub fn new(root: &Path, entries: &[(String, u64)], stamps: &ScanStamps) -> Self {
+        let now = unix_now();
+        SavedScan {
+            schema_version: SCHEMA_VERSION,
+            provenance: Provenance {
+                host: hostname(),
+                root: root.to_path_buf(),
+                started_at: now,
+                finished_at: now,
+                tool_version: env!("CARGO_PKG_VERSION").to_string(),
+                config_hash: None,
+            },
+            sources: Vec::new(),
+            entries: saved_entries(entries, stamps),
+            more: Vec::new(),
+            dirs: false,
+        }
+    }
+
+    /// Also keeps `more`, the candidates ranked below the reported entries, for `more`.
+    pub fn with_more(mut self, more: &[(String, u64)], stamps: &ScanStamps) -> Self {
+        self.more = saved_entries(more, stamps);
+        self
+    }
+
+    /// Moves up to `count` of the further candidates to the entries, so IDs of the next page
+    /// resolve like those reported by the scan, and returns them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::actions::guard::ScanStamps;
+    /// use ferris_files::results::SavedScan;
+    /// use std::path::Path;
+    ///
+    /// let entries = [("/data/a".to_string(), 30)];
+    /// let more = [("/data/b".to_string(), 20), ("/data/c".to_string(), 10)];
+    /// let stamps = ScanStamps::default();
+    /// let mut scan = SavedScan::new(Path::new("/data"), &entries, &stamps).with_more(&more, &stamps);
+    ///
+    /// let page: Vec<u64> = scan.next_page(1).iter().map(|entry| entry.size).collect();
+    /// assert_eq!(page, [20]);
+    /// assert_eq!(scan.entries.len(), 2);
+    /// assert_eq!(scan.next_page(5).len(), 1);
+    /// assert!(scan.next_page(5).is_empty());
+    /// ```
+    pub fn next_page(&mut self, count: usize) -> &[SavedEntry] {
+        let shown = self.entries.len();
+        let page = self.more.drain(..count.min(self.more.len()));
+        self.entries.extend(page);
+        &self.entries[shown..]
+    }
+
+    /// Location of the results of the last scan.
+    pub fn last_scan_path() -> io::Result<PathBuf> {
+        Ok(storage::cache_dir()?.join(LAST_SCAN_FILE))
+    }
+
+    /// Writes the results to `path`, replacing any earlier results atomically.
+    ///
+    /// The document is written and synced to a `.partial` file next to `path` before it is
+    /// renamed over it, so a crash leaves either the earlier or the new results in place, never a
+    /// truncated mix. A complete `.partial` file left behind by a crash before the rename is
+    /// picked up by [`SavedScan::load`].
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::check_write(path)?;
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        let partial = partial_path(path);
+        let mut file = fs::File::create(&partial)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&partial, path)?;
+        sync_parent(path)
+    }
+
+    /// Reads results written by [`SavedScan::save`].
+    ///
+    /// If a save was interrupted after its document was complete but before it replaced `path`,
+    /// the newer document is moved into place and returned; an incomplete one is discarded.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `NotFound` error with a hint to run a scan first if no results exist, and an
+    /// `InvalidData` error if the document is malformed or of a newer schema version.
+    pub fn load(path: &Path) -> io::Result<SavedScan> {
+        if let Some(recovered) = recover_partial(path) {
+            return Ok(recovered);
+        }
+        let json = fs::read_to_string(path).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(
+                    e.kind(),
+                    format!("no saved scan at {}; run a scan first", path.display()),
+                )
+            } else {
+                e
+            }
+        })?;
+        Self::from_json(&json).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Parses a session document of any supported schema version.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the document is not valid JSON, does not match
+    /// its schema version or was written by a newer version of the tool.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::results::{SavedScan, SCHEMA_VERSION};
+    ///
+    /// // An unversioned session written before provenance was recorded
+    /// let scan = SavedScan::from_json(r#"{"root": "/data", "entries": []}"#).unwrap();
+    /// assert_eq!(scan.schema_version, SCHEMA_VERSION);
+    /// assert_eq!(scan.provenance.tool_version, "unknown");
+    ///
+    /// assert!(SavedScan::from_json(r#"{"schema_version": 99, "entries": []}"#).is_err());
+    /// ```
+    pub fn from_json(json: &str) -> Result<SavedScan, String> {
+        let document: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        serde_json::from_value(migrate(document)?).map_err(|e| e.to_string())
+    }
+
+    /// Looks up entries by ID or unique ID prefix, preserving the order of `ids`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first ID that matches no entry or more than one entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::actions::guard::ScanStamps;
+    /// use ferris_files::results::{entry_id, SavedScan};
+    /// use std::path::Path;
+    ///
+    /// let entries = vec![("/data/big.iso".to_string(), 200)];
+    /// let scan = SavedScan::new(Path::new("/data"), &entries, &ScanStamps::default());
+    /// let id = entry_id("/data/big.iso");
+    ///
+    /// let found = scan.resolve(&[id[..3].to_string()]).unwrap();
+    /// assert_eq!(found[0].path, "/data/big.iso");
+    /// assert!(scan.resolve(&["zzz".to_string()]).is_err());
+    /// ```
+    pub fn resolve(&self, ids: &[String]) -> Result<Vec<&SavedEntry>, String> {
+        let mut found: Vec<&SavedEntry> = Vec::new();
+        for id in ids {
+            let id = id.trim().to_ascii_lowercase();
+            let matches: Vec<_> = self
+                .entries
+                .iter()
+                .filter(|entry| !id.is_empty() && entry.id.starts_with(&id))
+                .collect();
+            match matches.as_slice() {
+                [entry] => {
+                    if !found.iter().any(|seen| seen.id == entry.id) {
+                        found.push(entry);
+                    }
+                }
+                [] => return Err(format!("no entry of the session has ID '{}'", id)),
+                _ => return Err(format!("ID '{}' is ambiguous; use more characters", id)),
+            }
+        }
+        Ok(found)
+    }
+
+    /// Rebuilds the scan-time fingerprints of the saved entries.
+    pub fn stamps(&self) -> ScanStamps {
+        let mut stamps = ScanStamps::default();
+        for entry in &self.entries {
+            if let Some(fingerprint) = entry.fingerprint {
+                stamps.insert(entry.path.clone(), fingerprint);
+            }
+        }
+        stamps
+    }
+}
+
+/// Location of the file a session is written to before it replaces `path`.
+fn partial_path(path: &Path) -> PathBuf {
+    path.with_extension("json.partial")
+}
+
+/// Moves the document of a save to `path` that was interrupted before its rename into place.
+///
+/// Returns the recovered session, or `None` if there was nothing to recover. A document that
+/// does not parse was cut short by the crash and is removed, leaving the earlier results.
+fn recover_partial(path: &Path) -> Option<SavedScan> {
+    storage::check_write(path).ok()?;
+    let partial = partial_path(path);
+    let json = fs::read_to_string(&partial).ok()?;
+    let Ok(scan) = SavedScan::from_json(&json) else {
+        let _ = fs::remove_file(&partial);
+        return None;
+    };
+    fs::rename(&partial, path).ok()?;
+    let _ = sync_parent(path);
+    log::warn!(
+        "{}",
+        tr!("session-recovered", path = path.display().to_string())
+    );
+    Some(scan)
+}
+
+/// Syncs the directory holding `path` so a rename into it survives a crash.
+#[cfg(unix)]
+pub(crate) fn sync_parent(path: &Path) -> io::Result<()> {
+    match path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        Some(parent) => fs::File::open(parent)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+/// Syncs the directory holding `path` so a rename into it survives a crash.
+///
+/// Directories cannot be opened for syncing on this platform; the rename is left to the
+/// filesystem.
+#[cfg(not(unix))]
+pub(crate) fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Upgrades a session document to [`SCHEMA_VERSION`], one version at a time.
+fn migrate(mut document: serde_json::Value) -> Result<serde_json::Value, String> {
+    let object = document
+        .as_object_mut()
+        .ok_or("a session must be a JSON object")?;
+    let mut version = match object.get("schema_version") {
+        None => 1,
+        Some(value) => value.as_u64().ok_or("schema_version must be a number")?,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "session has schema version {}, but this version of ferris-files only reads up to {}; \
+             please upgrade",
+            version, SCHEMA_VERSION
+        ));
+    }
+
+    while version < SCHEMA_VERSION {
+        match version {
+            1 => {
+                // Provenance was not recorded yet
+                object
+                    .entry("tool_version")
+                    .or_insert_with(|| "unknown".into());
+            }
+            _ => return Err(format!("unknown session schema version {}", version)),
+        }
+        version += 1;
+    }
+    object.insert("schema_version".to_string(), version.into());
+    Ok(document)
+}
+
+/// Differences between the entries of two saved scans, matched by host and path.
+///
+/// * `added` - Entries only reported by the newer scan
+/// * `removed` - Entries no longer reported (deleted, shrunk or pushed out of the top entries)
+/// * `resized` - Entries reported by both scans with a different size, as `(old, new)`
+#[derive(Debug, Default)]
+pub struct ScanDiff<'a> {
+    pub added: Vec<&'a SavedEntry>,
+    pub removed: Vec<&'a SavedEntry>,
+    pub resized: Vec<(&'a SavedEntry, &'a SavedEntry)>,
+}
+
+/// Compares the entries of an older and a newer scan.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::actions::guard::ScanStamps;
+/// use ferris_files::results::{diff, SavedScan};
+/// use std::path::Path;
+///
+/// let stamps = ScanStamps::default();
+/// let old = SavedScan::new(Path::new("/"), &[("/a".into(), 10), ("/b".into(), 20)], &stamps);
+/// let new = SavedScan::new(Path::new("/"), &[("/b".into(), 30), ("/c".into(), 5)], &stamps);
+///
+/// let changes = diff(&old, &new);
+/// assert_eq!(changes.added[0].path, "/c");
+/// assert_eq!(changes.removed[0].path, "/a");
+/// assert_eq!(changes.resized[0].1.size, 30);
+/// ```
+pub fn diff<'a>(old: &'a SavedScan, new: &'a SavedScan) -> ScanDiff<'a> {
+    let key = |entry: &'a SavedEntry| (entry.host.as_deref(), entry.path.as_str());
+    let before: HashMap<_, &SavedEntry> = old.entries.iter().map(|e| (key(e), e)).collect();
+    let after: HashMap<_, &SavedEntry> = new.entries.iter().map(|e| (key(e), e)).collect();
+
+    let mut changes = ScanDiff::default();
+    for entry in &new.entries {
+        match before.get(&key(entry)) {
+            None => changes.added.push(entry),
+            Some(previous) if previous.size != entry.size => {
+                changes.resized.push((previous, entry))
+            }
+            Some(_) => {}
+        }
+    }
+    changes.removed = old
+        .entries
+        .iter()
+        .filter(|entry| !after.contains_key(&key(entry)))
+        .collect();
+    changes
+}
+
+/// Combines sessions from several hosts into one ranked session, largest entries first.
+///
+/// Each entry is tagged with the host of its session (falling back to `fallback_host`, e.g.
+/// the file name, for sessions without one) and given an ID derived from host and path, so
+/// the same path on different machines stays distinguishable. At most `limit` entries are kept.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::actions::guard::ScanStamps;
+/// use ferris_files::results::{merge, SavedScan};
+/// use std::path::Path;
+///
+/// let stamps = ScanStamps::default();
+/// let mut web = SavedScan::new(Path::new("/"), &[("/var/log/a".into(), 10)], &stamps);
+/// web.provenance.host = Some("web".into());
+/// let mut db = SavedScan::new(Path::new("/"), &[("/var/log/a".into(), 30)], &stamps);
+/// db.provenance.host = Some("db".into());
+///
+/// let merged = merge(&[(web, "web.json".into()), (db, "db.json".into())], 10);
+/// assert_eq!(merged.entries[0].host.as_deref(), Some("db"));
+/// assert_ne!(merged.entries[0].id, merged.entries[1].id);
+/// assert_eq!(merged.sources.len(), 2);
+/// ```
+pub fn merge(sessions: &[(SavedScan, String)], limit: usize) -> SavedScan {
+    let mut entries: Vec<SavedEntry> = sessions
+        .iter()
+        .flat_map(|(scan, fallback_host)| {
+            scan.entries.iter().map(move |entry| {
+                let host = entry
+                    .host
+                    .clone()
+                    .or_else(|| scan.provenance.host.clone())
+                    .unwrap_or_else(|| fallback_host.clone());
+                SavedEntry {
+                    id: entry_id(&format!("{}:{}", host, entry.path)),
+                    host: Some(host),
+                    ..entry.clone()
+                }
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.host.cmp(&b.host)));
+    entries.truncate(limit);
+
+    let sources: Vec<Provenance> = sessions
+        .iter()
+        .flat_map(|(scan, _)| {
+            if scan.sources.is_empty() {
+                vec![scan.provenance.clone()]
+            } else {
+                scan.sources.clone()
+            }
+        })
+        .collect();
+    SavedScan {
+        schema_version: SCHEMA_VERSION,
+        provenance: Provenance {
+            started_at: sources
+                .iter()
+                .map(|s| s.started_at)
+                .min()
+                .unwrap_or_default(),
+            finished_at: sources
+                .iter()
+                .map(|s| s.finished_at)
+                .max()
+                .unwrap_or_default(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Default::default()
+        },
+        sources,
+        entries,
+        more: Vec::new(),
+        dirs: false,
+    }
+}
@@ -0,0 +1,242 @@
+use crate::cost::{format_monthly, CostModel, GroupCosts};
+use crate::protect::rule_globs;
+use crate::report::{Aggregator, FileInfo, ReportRow, ReportSection};
+use crate::traits::ByteSize;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// A hypothetical retention policy: which files it would delete.
+///
+/// A file falls under the policy if it was not modified for at least the given age and matches
+/// any of the given globs; either criterion may be left out. Globs use the protect list syntax
+/// (see [`rule_globs`]), so `*.bak` matches a name anywhere. Files whose modification time is
+/// unknown are never old enough.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::retention::RetentionPolicy;
+/// use std::time::{Duration, SystemTime};
+///
+/// let day = Duration::from_secs(86_400);
+/// let now = SystemTime::now();
+/// let policy = RetentionPolicy::new(Some(180 * day), &["*.bak".to_string()]).unwrap();
+/// assert!(policy.applies("/srv/db/dump.bak", Some(now - 200 * day), now));
+/// assert!(!policy.applies("/srv/db/dump.bak", Some(now - 10 * day), now));
+/// assert!(!policy.applies("/srv/db/dump.bak", None, now));
+/// assert!(!policy.applies("/srv/db/base.dat", Some(now - 200 * day), now));
+///
+/// assert!(RetentionPolicy::new(None, &[]).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    older_than: Option<Duration>,
+    // Every glob is compiled to two: the glob itself and everything beneath it
+    set: Option<GlobSet>,
+}
+
+impl RetentionPolicy {
+    /// Creates the policy deleting files not modified for `older_than` that match any of
+    /// `patterns`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if neither criterion is given or a glob is invalid.
+    pub fn new(older_than: Option<Duration>, patterns: &[String]) -> Result<Self, String> {
+        if older_than.is_none() && patterns.is_empty() {
+            return Err("a retention policy needs an age or a glob to match".to_string());
+        }
+        let set = if patterns.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                for glob in rule_globs(pattern) {
+                    builder.add(
+                        Glob::new(&glob)
+                            .map_err(|e| format!("invalid glob '{}': {}", pattern, e))?,
+                    );
+                }
+            }
+            Some(builder.build().map_err(|e| e.to_string())?)
+        };
+        Ok(RetentionPolicy { older_than, set })
+    }
+
+    /// Whether the policy would delete the file at `path`, last modified at `modified`, at
+    /// the time `now`.
+    pub fn applies(&self, path: &str, modified: Option<SystemTime>, now: SystemTime) -> bool {
+        let old_enough = self.older_than.is_none_or(|age| {
+            modified
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|elapsed| elapsed >= age)
+        });
+        old_enough && self.set.as_ref().is_none_or(|set| set.is_match(path))
+    }
+}
+
+/// Space a [`RetentionPolicy`] would reclaim, per directory, for the `simulate` command.
+///
+/// Files count towards the directory directly containing them, so the directories add up to
+/// the reclaimable total. Nothing is deleted; the rows end with the reclaimable total and
+/// everything observed, to weigh the policy against the whole tree.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo};
+/// use ferris_files::retention::{RetentionPolicy, RetentionSimulation};
+/// use std::time::SystemTime;
+///
+/// let now = SystemTime::now();
+/// let policy = RetentionPolicy::new(None, &["*.bak".to_string()]).unwrap();
+/// let mut simulation = RetentionSimulation::new(policy, 10, now);
+/// for (path, size) in [("/srv/db/a.bak", 700), ("/srv/db/b.bak", 100), ("/srv/db/base", 5)] {
+///     simulation.observe(&FileInfo { path: path.to_string(), size, modified: Some(now), uid: None, accessed: None });
+/// }
+/// let rows = simulation.finish().rows;
+/// assert_eq!(rows[0].fields, ["800", "2", "/srv/db"]);
+/// assert_eq!(rows[1].fields, ["800", "2", "805", "3"]);
+/// ```
+#[derive(Debug)]
+pub struct RetentionSimulation {
+    policy: RetentionPolicy,
+    limit: usize,
+    now: SystemTime,
+    directories: HashMap<PathBuf, (u64, usize)>,
+    reclaimed: (u64, usize),
+    scanned: (u64, usize),
+    costs: GroupCosts<PathBuf>,
+    reclaimed_cost: Option<f64>,
+}
+
+impl RetentionSimulation {
+    /// Creates an empty simulation of `policy` as applied at `now`, reporting the `limit`
+    /// directories where it reclaims the most bytes.
+    pub fn new(policy: RetentionPolicy, limit: usize, now: SystemTime) -> Self {
+        RetentionSimulation {
+            policy,
+            limit,
+            now,
+            directories: HashMap::new(),
+            reclaimed: (0, 0),
+            scanned: (0, 0),
+            costs: GroupCosts::default(),
+            reclaimed_cost: None,
+        }
+    }
+
+    /// Also estimates the monthly cost saved at the rates of `costs`.
+    pub fn with_costs(mut self, costs: CostModel) -> Self {
+        self.reclaimed_cost = costs.is_enabled().then_some(0.0);
+        self.costs = GroupCosts::new(costs);
+        self
+    }
+
+    /// Returns the directories where the policy reclaims the most bytes as
+    /// `(directory, bytes, files)`, largest first.
+    pub fn top(&self) -> Vec<(PathBuf, u64, usize)> {
+        let mut top: Vec<(PathBuf, u64, usize)> = self
+            .directories
+            .iter()
+            .map(|(path, (bytes, files))| (path.clone(), *bytes, *files))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(self.limit);
+        top
+    }
+
+    /// Returns the bytes and number of files the policy would delete.
+    pub fn reclaimed(&self) -> (u64, usize) {
+        self.reclaimed
+    }
+}
+
+/// Returns `bytes` as a percentage of `total`.
+fn share(bytes: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        bytes as f64 * 100.0 / total as f64
+    }
+}
+
+impl Aggregator for RetentionSimulation {
+    fn observe(&mut self, file: &FileInfo) {
+        self.scanned.0 += file.size;
+        self.scanned.1 += 1;
+        if !self.policy.applies(&file.path, file.modified, self.now) {
+            return;
+        }
+        self.reclaimed.0 += file.size;
+        self.reclaimed.1 += 1;
+        let directory = Path::new(&file.path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        if let Some(cost) = self.costs.monthly(file) {
+            self.costs.add(directory.clone(), cost);
+            if let Some(total) = self.reclaimed_cost.as_mut() {
+                *total += cost;
+            }
+        }
+        let (bytes, files) = self.directories.entry(directory).or_default();
+        *bytes += file.size;
+        *files += 1;
+    }
+
+    fn finish(&self) -> ReportSection {
+        let (bytes, files) = self.reclaimed;
+        let mut rows: Vec<ReportRow> = self
+            .top()
+            .into_iter()
+            .map(|(path, bytes, files)| {
+                let share = share(bytes, self.reclaimed.0);
+                let mut text = format!(
+                    "{:>10} {:>6.1}%  {}",
+                    bytes.format_size(),
+                    share,
+                    tr!("category-files", files = files)
+                );
+                if let Some(cost) = self.costs.get(&path) {
+                    text = format!("{}  {}", text, format_monthly(cost));
+                }
+                let path = path.to_string_lossy().to_string();
+                ReportRow {
+                    text: format!("{}  {}", text, path),
+                    kind: "retention-dir".to_string(),
+                    fields: vec![bytes.to_string(), files.to_string(), path],
+                }
+            })
+            .collect();
+        let mut text = format!(
+            "\n{}",
+            tr!(
+                "retention-total",
+                size = bytes.format_size(),
+                files = files,
+                scanned = self.scanned.0.format_size(),
+                share = format!("{:.1}", share(bytes, self.scanned.0))
+            )
+        );
+        if let Some(cost) = self.reclaimed_cost {
+            text = format!("{}  {}", text, format_monthly(cost));
+        }
+        rows.push(ReportRow {
+            text,
+            kind: "retention-total".to_string(),
+            fields: vec![
+                bytes.to_string(),
+                files.to_string(),
+                self.scanned.0.to_string(),
+                self.scanned.1.to_string(),
+            ],
+        });
+        ReportSection {
+            heading: Some(tr!("report-retention")),
+            rows,
+        }
+    }
+}
@@ -0,0 +1,686 @@
+use crate::args::{Args, Command};
+use crate::config::Config;
+use crate::results::SavedScan;
+use crate::stats::ScanStats;
+use clap::Parser;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::{Role, WebSocket};
+use tungstenite::Message;
+
+/// Largest request, head and body, the server reads.
+const MAX_REQUEST: usize = 64 * 1024;
+
+/// How long a WebSocket client may go without an event before it is pinged, which also notices
+/// clients that went away.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Most connections served at once; more are answered with `503` until one closes.
+const MAX_CONNECTIONS: usize = 64;
+
+/// How long a connection may take to send its request, or to take a response or event, before
+/// it is dropped.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Environment variable a token is read from when `--token` is not given, keeping it out of the
+/// process list.
+pub const TOKEN_VAR: &str = "FERRIS_FILES_TOKEN";
+
+/// Settings of `serve` besides those of the scans it runs, which come from the usual options.
+///
+/// * `listen` - Address the server listens on
+/// * `token` - Token every request must carry (see [`ServerState::authorized`]); required unless
+///   the server only listens on a loopback address and is read-only
+/// * `allow_actions` - Whether `POST /api/clean` may act on results, and scans may run with
+///   actions; the server is read-only otherwise
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub listen: SocketAddr,
+    pub token: Option<String>,
+    pub allow_actions: bool,
+}
+
+/// Where the scan of a server is at.
+///
+/// * `Idle` - No scan was started yet
+/// * `Scanning` - A scan is running
+/// * `Done` - The last scan completed; its results are served
+/// * `Failed` - The last scan stopped with an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanState {
+    Idle,
+    Scanning,
+    Done,
+    Failed,
+}
+
+/// The scan of a server and its counts, which are live while it runs.
+#[derive(Debug)]
+struct ScanStatus {
+    state: ScanState,
+    stats: Option<Arc<ScanStats>>,
+    error: Option<String>,
+}
+
+/// The WebSocket clients following the scans, and the latest `progress` and `top` events, which
+/// clients connecting mid scan receive first.
+#[derive(Debug, Default)]
+struct Subscribers {
+    clients: Vec<Sender<String>>,
+    latest: Vec<(String, String)>,
+}
+
+/// An HTTP request as far as the server reads it.
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    // Header names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Reads the request line, the headers and a body of `Content-Length` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error for a malformed request, or one larger than 64 KiB.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::serve::Request;
+    ///
+    /// let raw = "POST /api/scan?quick HTTP/1.1\r\nHost: nas\r\nContent-Length: 2\r\n\r\n{}";
+    /// let request = Request::read(raw.as_bytes()).unwrap();
+    /// assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/api/scan"));
+    /// assert_eq!(request.query.as_deref(), Some("quick"));
+    /// assert_eq!(request.header("host"), Some("nas"));
+    /// assert_eq!(request.body, b"{}");
+    /// assert!(Request::read("GET\r\n\r\n".as_bytes()).is_err());
+    /// ```
+    pub fn read(stream: impl Read) -> io::Result<Request> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+        let mut reader = BufReader::new(stream.take(MAX_REQUEST as u64));
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target), Some(_)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("malformed request line"));
+        };
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, Some(query.to_string())),
+            None => (target, None),
+        };
+        let mut request = Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query,
+            ..Default::default()
+        };
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("request head ends early"));
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid("malformed header"))?;
+            request
+                .headers
+                .push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+        let length: u64 = match request.header("content-length") {
+            Some(length) => length.parse().map_err(|_| invalid("malformed length"))?,
+            None => 0,
+        };
+        reader.take(length).read_to_end(&mut request.body)?;
+        if (request.body.len() as u64) < length {
+            return Err(invalid("request body ends early or is too large"));
+        }
+        Ok(request)
+    }
+
+    /// Returns the value of the header `name` (lowercase), if sent.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the request asks to switch to the WebSocket protocol.
+    pub fn is_upgrade(&self) -> bool {
+        self.header("upgrade")
+            .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+    }
+}
+
+/// A JSON response with its status code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    /// A response carrying `{"error": message}`.
+    fn error(status: u16, message: String) -> Response {
+        Response {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+
+    /// Writes the response and lets the connection close.
+    fn write_to(&self, mut out: impl Write) -> io::Result<()> {
+        let body = self.body.to_string();
+        // One write, so the head and body leave in as few packets as possible
+        let reason = match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            426 => "Upgrade Required",
+            503 => "Service Unavailable",
+            _ => "Error",
+        };
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            body.len(),
+            body
+        );
+        out.write_all(response.as_bytes())?;
+        out.flush()
+    }
+}
+
+/// A server running scans on request and pushing their live progress to WebSocket clients.
+///
+/// The routes, all answering with JSON:
+///
+/// * `GET /api/status` - State of the scan (see [`ScanState`]), its root, its counts (see
+///   [`crate::stats::StatsSnapshot`]) while and after it runs, and its error if it failed
+/// * `POST /api/scan` - Starts a scan with the settings the server was started with; only one
+///   runs at a time
+/// * `GET /api/results` - The results of the last completed scan, as saved in its session file
+/// * `GET /api/events` - A WebSocket pushing the events of the live progress feed (see
+///   [`crate::progress::ProgressEvent`]), one text message each, for every scan from then on
+/// * `POST /api/clean` - Applies an action to entries of the last scan like the `clean`
+///   subcommand; refused with `403` unless the server allows actions
+///
+/// Requests addressed to another host or sent from another site answer `403` (see
+/// [`ServerState::same_origin`]), and with a token every route answers `401` to requests without
+/// it. Scans print their report to the server's console as usual and save their session like any
+/// scan; the results served are those of the session given with `--save-session`, or else the
+/// last scan of the user.
+pub struct Server {
+    listener: TcpListener,
+    state: Arc<ServerState>,
+    max_connections: usize,
+    timeout: Duration,
+}
+
+/// A connection being served, counted in the connections open until it is dropped.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// What the connections of a server share.
+pub struct ServerState {
+    config: Config,
+    options: ServeOptions,
+    session: Option<PathBuf>,
+    scan: Mutex<ScanStatus>,
+    subscribers: Mutex<Subscribers>,
+}
+
+impl Server {
+    /// Listens on the address of `options`, running scans with `config` once asked to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be bound.
+    pub fn bind(config: Config, options: &ServeOptions) -> io::Result<Server> {
+        let listener = TcpListener::bind(options.listen)?;
+        // Requests name the port actually bound, which differs from port 0
+        let options = ServeOptions {
+            listen: listener.local_addr()?,
+            ..options.clone()
+        };
+        Ok(Server {
+            listener,
+            state: Arc::new(ServerState::new(config, &options)),
+            max_connections: MAX_CONNECTIONS,
+            timeout: IO_TIMEOUT,
+        })
+    }
+
+    /// Serves at most `max_connections` connections at once (at least one), dropping those that
+    /// do not send their request or take their answer within `timeout`.
+    pub fn with_limits(mut self, max_connections: usize, timeout: Duration) -> Server {
+        self.max_connections = max_connections.max(1);
+        self.timeout = timeout;
+        self
+    }
+
+    /// The address the server listens on, with the port chosen if it was bound to port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves connections until the process ends, each on a thread of its own.
+    ///
+    /// Idle or slow clients are dropped after 10 seconds without progress, and at most 64
+    /// connections, WebSocket clients included, are served at once, so a few sockets left open
+    /// cannot exhaust the process; connections beyond that are answered with `503`.
+    pub fn run(self) {
+        let open = Arc::new(AtomicUsize::new(0));
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = stream
+                        .set_read_timeout(Some(self.timeout))
+                        .and_then(|()| stream.set_write_timeout(Some(self.timeout)))
+                    {
+                        log::warn!("{}", tr!("serve-connection-failed", error = e.to_string()));
+                        continue;
+                    }
+                    if open.load(Ordering::SeqCst) >= self.max_connections {
+                        // The write timeout bounds the time this takes from accepting others
+                        let _ = Response::error(503, tr!("serve-busy")).write_to(&stream);
+                        continue;
+                    }
+                    open.fetch_add(1, Ordering::SeqCst);
+                    let slot = ConnectionSlot(Arc::clone(&open));
+                    let state = Arc::clone(&self.state);
+                    thread::spawn(move || {
+                        let _slot = slot;
+                        state.handle(stream);
+                    });
+                }
+                Err(e) => log::warn!("{}", tr!("serve-connection-failed", error = e.to_string())),
+            }
+        }
+    }
+}
+
+impl ServerState {
+    /// State of a server that has not scanned yet.
+    pub fn new(config: Config, options: &ServeOptions) -> Self {
+        ServerState {
+            session: config.save_session.clone(),
+            config,
+            options: options.clone(),
+            scan: Mutex::new(ScanStatus {
+                state: ScanState::Idle,
+                stats: None,
+                error: None,
+            }),
+            subscribers: Mutex::new(Subscribers::default()),
+        }
+    }
+
+    /// Reads a request from `stream` and answers it, or turns the connection into a WebSocket.
+    fn handle(self: Arc<Self>, mut stream: TcpStream) {
+        let request = match Request::read(&stream) {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = Response::error(400, e.to_string()).write_to(&stream);
+                return;
+            }
+        };
+        if request.path == "/api/events"
+            && request.method == "GET"
+            && request.is_upgrade()
+            && self.same_origin(&request)
+            && self.authorized(&request)
+        {
+            if let Some(key) = request.header("sec-websocket-key") {
+                self.stream_events(stream, key);
+                return;
+            }
+        }
+        if let Err(e) = self.respond(&request).write_to(&mut stream) {
+            log::debug!("{}", tr!("serve-connection-failed", error = e.to_string()));
+        }
+    }
+
+    /// Whether `request` was addressed to the server itself and not sent from another site.
+    ///
+    /// Its `Host` must name the address and port the server listens on, or `localhost` for a
+    /// loopback address, so a site whose name was rebound to this machine is refused; a server
+    /// listening on every interface takes any host name, as it requires a token anyway. An
+    /// `Origin`, which browsers send with WebSocket upgrades and cross-site requests, must be the
+    /// host of the request itself.
+    pub fn same_origin(&self, request: &Request) -> bool {
+        let Some(host) = request.header("host") else {
+            return false;
+        };
+        self.serves_host(host)
+            && request.header("origin").is_none_or(|origin| {
+                origin
+                    .strip_prefix("http://")
+                    .or_else(|| origin.strip_prefix("https://"))
+                    .is_some_and(|origin| origin.eq_ignore_ascii_case(host))
+            })
+    }
+
+    /// Whether the `Host` header `host` names the address the server listens on.
+    fn serves_host(&self, host: &str) -> bool {
+        let listen = self.options.listen;
+        let (name, port) = match host.rsplit_once(':') {
+            Some((name, port)) if !port.ends_with(']') => (name, port.parse().ok()),
+            _ => (host, Some(80)),
+        };
+        if port != Some(listen.port()) {
+            return false;
+        }
+        let name = name.trim_start_matches('[').trim_end_matches(']');
+        listen.ip().is_unspecified()
+            || name.parse::<IpAddr>().is_ok_and(|ip| ip == listen.ip())
+            || (listen.ip().is_loopback() && name.eq_ignore_ascii_case("localhost"))
+    }
+
+    /// Whether `request` carries the server's token, if it has one: as `Authorization: Bearer
+    /// TOKEN`, or as `?token=TOKEN` for browsers, which cannot send headers to a WebSocket.
+    pub fn authorized(&self, request: &Request) -> bool {
+        let Some(token) = &self.options.token else {
+            return true;
+        };
+        let sent = request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| {
+                request
+                    .query
+                    .as_deref()?
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("token="))
+            });
+        sent.is_some_and(|sent| same_token(sent.as_bytes(), token.as_bytes()))
+    }
+
+    /// Answers a request to any route but the WebSocket itself.
+    pub fn respond(self: &Arc<Self>, request: &Request) -> Response {
+        if !self.same_origin(request) {
+            return Response::error(403, tr!("serve-foreign-origin"));
+        }
+        if !self.authorized(request) {
+            return Response::error(401, tr!("serve-unauthorized"));
+        }
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/api/status") => self.status(),
+            ("POST", "/api/scan") => self.start_scan(),
+            ("GET", "/api/results") => self.results(),
+            ("POST", "/api/clean") => self.clean(&request.body),
+            ("GET", "/api/events") => Response::error(426, tr!("serve-upgrade-required")),
+            (_, "/api/status" | "/api/scan" | "/api/results" | "/api/clean" | "/api/events") => Response::error(
+                405,
+                tr!("serve-method-not-allowed", method = request.method.as_str()),
+            ),
+            _ => Response::error(404, tr!("serve-not-found", path = request.path.as_str())),
+        }
+    }
+
+    fn status(&self) -> Response {
+        let scan = self.scan.lock().unwrap_or_else(PoisonError::into_inner);
+        Response {
+            status: 200,
+            body: json!({
+                "state": scan.state,
+                "root": self.config.root_path,
+                "read_only": !self.options.allow_actions,
+                "stats": scan.stats.as_ref().map(|stats| stats.snapshot()),
+                "error": scan.error,
+            }),
+        }
+    }
+
+    /// Starts a scan on a thread of its own, unless one is running.
+    fn start_scan(self: &Arc<Self>) -> Response {
+        let stats = Arc::new(ScanStats::default());
+        {
+            let mut scan = self.scan.lock().unwrap_or_else(PoisonError::into_inner);
+            if scan.state == ScanState::Scanning {
+                return Response::error(409, tr!("serve-scan-running"));
+            }
+            *scan = ScanStatus {
+                state: ScanState::Scanning,
+                stats: Some(Arc::clone(&stats)),
+                error: None,
+            };
+        }
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .latest
+            .clear();
+
+        let state = Arc::clone(self);
+        thread::spawn(move || {
+            let feed = Box::new(Broadcast {
+                state: Arc::clone(&state),
+                line: Vec::new(),
+            });
+            let result = crate::run_with_progress(state.config.clone(), &mut [], stats, Some(feed));
+            let mut scan = state.scan.lock().unwrap_or_else(PoisonError::into_inner);
+            match result {
+                Ok(()) => scan.state = ScanState::Done,
+                Err(e) => {
+                    log::error!("{}", tr!("fatal-error", error = e.to_string()));
+                    scan.state = ScanState::Failed;
+                    scan.error = Some(e.to_string());
+                }
+            }
+        });
+        Response {
+            status: 202,
+            body: json!({ "state": ScanState::Scanning }),
+        }
+    }
+
+    fn results(&self) -> Response {
+        let path = match &self.session {
+            Some(path) => Ok(path.clone()),
+            None => SavedScan::last_scan_path(),
+        };
+        match path.and_then(|path| SavedScan::load(&path)) {
+            Ok(scan) => Response {
+                status: 200,
+                body: serde_json::to_value(scan).unwrap_or_default(),
+            },
+            Err(e) => Response::error(404, tr!("serve-no-results", error = e.to_string())),
+        }
+    }
+
+    /// Applies the action of a `POST /api/clean` body to entries of the last scan.
+    ///
+    /// The body is a JSON object of `clean` options named as on the command line, e.g.
+    /// `{"ids": "3b1f0c,9a42de", "move-to": "/mnt/archive"}`, with `true` for flags; they are
+    /// parsed like the subcommand's own. Prompting and other session files are refused.
+    fn clean(&self, body: &[u8]) -> Response {
+        if !self.options.allow_actions {
+            return Response::error(403, tr!("serve-read-only"));
+        }
+        if self.scan.lock().unwrap_or_else(PoisonError::into_inner).state == ScanState::Scanning {
+            return Response::error(409, tr!("serve-scan-running"));
+        }
+        let options: serde_json::Map<String, Value> = match serde_json::from_slice(body) {
+            Ok(options) => options,
+            Err(e) => return Response::error(400, e.to_string()),
+        };
+        let mut argv = vec!["ferris-files".to_string(), "clean".to_string()];
+        for (name, value) in options {
+            if name == "interactive" || name == "session" {
+                return Response::error(400, tr!("serve-option-refused", option = name.as_str()));
+            }
+            match value {
+                Value::Bool(true) => argv.push(format!("--{}", name)),
+                Value::Bool(false) => {}
+                Value::String(value) => argv.extend([format!("--{}", name), value]),
+                value => argv.extend([format!("--{}", name), value.to_string()]),
+            }
+        }
+        let (mut ids, actions) = match Args::try_parse_from(argv).map(|args| args.command) {
+            Ok(Some(Command::Clean { ids, actions })) => (ids, actions),
+            Ok(_) => return Response::error(400, tr!("clean-needs-action")),
+            Err(e) => return Response::error(400, e.to_string()),
+        };
+        ids.session.session = self
+            .session
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string());
+        match crate::commands::clean(&ids, &actions) {
+            Ok(()) => Response {
+                status: 200,
+                body: json!({ "ids": ids.ids }),
+            },
+            Err(e) => Response::error(400, e.to_string()),
+        }
+    }
+
+    /// Sends one line of the progress feed to every WebSocket client, remembering it for those
+    /// connecting later if it is the latest `progress` or `top` event.
+    pub fn publish(&self, line: String) {
+        let event = serde_json::from_str::<Value>(&line)
+            .ok()
+            .and_then(|event| Some(event.get("event")?.as_str()?.to_string()));
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(event) = event.filter(|event| event == "progress" || event == "top") {
+            subscribers.latest.retain(|(kind, _)| *kind != event);
+            subscribers.latest.push((event, line.clone()));
+        }
+        subscribers
+            .clients
+            .retain(|client| client.send(line.clone()).is_ok());
+    }
+
+    /// Subscribes to the progress feed, receiving the latest events first.
+    pub fn subscribe(&self) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::channel();
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (_, line) in &subscribers.latest {
+            let _ = tx.send(line.clone());
+        }
+        subscribers.clients.push(tx);
+        rx
+    }
+
+    /// Completes the WebSocket handshake and pushes events until the client goes away.
+    fn stream_events(&self, mut stream: TcpStream, key: &str) {
+        let handshake = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        );
+        if stream.write_all(handshake.as_bytes()).is_err() {
+            return;
+        }
+        let events = self.subscribe();
+        let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+        loop {
+            let message = match events.recv_timeout(PING_INTERVAL) {
+                Ok(line) => Message::text(line),
+                Err(RecvTimeoutError::Timeout) => Message::Ping(Default::default()),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if socket.send(message).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// The progress feed of a server's scan, handing every complete line to
+/// [`ServerState::publish`].
+struct Broadcast {
+    state: Arc<ServerState>,
+    line: Vec<u8>,
+}
+
+impl Write for Broadcast {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+            self.state
+                .publish(String::from_utf8_lossy(&line[..end]).to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Compares two tokens in a time independent of where they first differ.
+fn same_token(sent: &[u8], token: &[u8]) -> bool {
+    sent.len() == token.len()
+        && sent
+            .iter()
+            .zip(token)
+            .fold(0, |differs, (a, b)| differs | (a ^ b))
+            == 0
+}
+
+/// Runs the server until the process ends.
+///
+/// # Errors
+///
+/// Returns an error if the server would listen beyond the loopback interface or allow actions
+/// without a token, if the scans have actions but the server does not allow them, or if the
+/// address of `options` cannot be bound.
+pub fn serve(config: Config, options: &ServeOptions) -> Result<(), Box<dyn Error>> {
+    if options.token.is_none() && !options.listen.ip().is_loopback() {
+        return Err(tr!("serve-token-required", address = options.listen.to_string()).into());
+    }
+    // Any local process could otherwise move the user's files
+    if options.token.is_none() && options.allow_actions {
+        return Err(tr!("serve-actions-token-required").into());
+    }
+    if config.has_actions() && !options.allow_actions {
+        return Err(tr!("serve-actions-refused").into());
+    }
+    let server = Server::bind(config, options)?;
+    println!(
+        "{}",
+        tr!("serve-listening", address = server.local_addr()?.to_string())
+    );
+    server.run();
+    Ok(())
+}
@@ -0,0 +1,285 @@
+use crate::report::{Aggregator, FileInfo, ReportSection};
+use crate::results::versions_differ;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Version of the snapshot layout that is written; newer snapshots are rejected when loaded.
+pub const SNAPSHOT_VERSION: u64 = 1;
+
+/// Compression level of snapshots, favouring speed since they are written at the end of a scan.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Magic number every zstd frame, and so every snapshot, starts with.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The size of every file of a scan, saved with `--save-snapshot` so two scans of the same
+/// tree can be compared by [`diff`] long after the reports were printed.
+///
+/// Unlike a session, which keeps only the reported entries, a snapshot keeps every sized file;
+/// directory totals are derived from them when needed. Snapshots are zstd compressed JSON
+/// documents (`.ffs` by convention).
+///
+/// * `version` - Version of the document layout, see [`SNAPSHOT_VERSION`]
+/// * `root` - Directory that was scanned
+/// * `taken_at` - Seconds since the Unix epoch at which the scan started
+/// * `tool_version` - Version of ferris-files that took the snapshot (empty for snapshots from
+///   before it was recorded)
+/// * `config_hash` - Hash of the settings that affect results, see
+///   [`crate::config::Config::settings_hash`]
+/// * `files` - Bytes of every sized file by path
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo};
+/// use ferris_files::snapshot::Snapshot;
+///
+/// let mut snapshot = Snapshot::new("/srv", 1700000000);
+/// for (path, size) in [("/srv/db/wal/1", 300), ("/srv/db/base", 200), ("/srv/index", 5)] {
+///     snapshot.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// let directories = snapshot.directories();
+/// assert_eq!(directories["/srv"], 505);
+/// assert_eq!(directories["/srv/db"], 500);
+/// assert_eq!(directories["/srv/db/wal"], 300);
+///
+/// let mut saved = Vec::new();
+/// snapshot.write(&mut saved).unwrap();
+/// assert_eq!(Snapshot::read(&saved[..]).unwrap(), snapshot);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u64,
+    pub root: String,
+    pub taken_at: u64,
+    #[serde(default)]
+    pub tool_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    pub files: BTreeMap<String, u64>,
+}
+
+impl Snapshot {
+    /// Creates an empty snapshot of the scan of `root` started at `taken_at`, stamped with the
+    /// running version of ferris-files.
+    pub fn new(root: &str, taken_at: u64) -> Self {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            root: root.to_string(),
+            taken_at,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash: None,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Whether both snapshots recorded their settings and the settings differ, like
+    /// [`crate::results::Provenance::settings_differ`].
+    pub fn settings_differ(&self, other: &Snapshot) -> bool {
+        matches!((&self.config_hash, &other.config_hash), (Some(a), Some(b)) if a != b)
+    }
+
+    /// Whether the snapshots were taken by different versions of ferris-files (see
+    /// [`versions_differ`]).
+    pub fn version_differs(&self, other: &Snapshot) -> bool {
+        versions_differ(&self.tool_version, &other.tool_version)
+    }
+
+    /// Returns the bytes beneath every directory from the root down that holds a sized file.
+    pub fn directories(&self) -> BTreeMap<String, u64> {
+        let root = Path::new(&self.root);
+        let mut directories: BTreeMap<String, u64> = BTreeMap::new();
+        for (path, size) in &self.files {
+            for dir in Path::new(path)
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(root))
+            {
+                *directories
+                    .entry(dir.to_string_lossy().into_owned())
+                    .or_default() += size;
+            }
+        }
+        directories
+    }
+
+    /// Writes the snapshot, compressed, to `out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `out` fails.
+    pub fn write(&self, out: impl Write) -> io::Result<()> {
+        let mut encoder = zstd::Encoder::new(out, COMPRESSION_LEVEL)?;
+        serde_json::to_writer(&mut encoder, self).map_err(io::Error::other)?;
+        encoder.finish()?.flush()
+    }
+
+    /// Reads a snapshot written by [`Snapshot::write`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error if the input is not a snapshot or is of a newer version.
+    pub fn read(input: impl Read) -> io::Result<Snapshot> {
+        let snapshot: Snapshot = serde_json::from_reader(zstd::Decoder::new(input)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "snapshot version {} is newer than the supported version {}",
+                    snapshot.version, SNAPSHOT_VERSION
+                ),
+            ));
+        }
+        Ok(snapshot)
+    }
+
+    /// Saves the snapshot to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        crate::storage::check_write(path)?;
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()
+    }
+
+    /// Loads the snapshot saved at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or does not hold a snapshot.
+    pub fn load(path: &Path) -> io::Result<Snapshot> {
+        let file = File::open(path)?;
+        Self::read(BufReader::new(file))
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    }
+
+    /// Whether the file at `path` looks like a snapshot rather than a JSON session; `false` if
+    /// it cannot be read, leaving the error to whatever loads it.
+    pub fn is_snapshot(path: &Path) -> bool {
+        let mut magic = [0; 4];
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok_and(|()| magic == ZSTD_MAGIC)
+    }
+}
+
+impl Aggregator for Snapshot {
+    fn observe(&mut self, file: &FileInfo) {
+        self.files.insert(file.path.clone(), file.size);
+    }
+
+    fn finish(&self) -> ReportSection {
+        ReportSection {
+            heading: None,
+            rows: Vec::new(),
+        }
+    }
+}
+
+/// A file or directory whose size differs between two snapshots.
+///
+/// * `path` - Path of the file or directory
+/// * `is_dir` - Whether the path is a directory, sized by everything beneath it
+/// * `before` - Bytes in the older snapshot, `None` if it had not appeared yet
+/// * `after` - Bytes in the newer snapshot, `None` if it vanished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotChange {
+    pub path: String,
+    pub is_dir: bool,
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+}
+
+impl SnapshotChange {
+    /// Names the change: `grew`, `shrank`, `appeared` or `vanished`.
+    pub fn kind(&self) -> &'static str {
+        match (self.before, self.after) {
+            (None, _) => "appeared",
+            (_, None) => "vanished",
+            (Some(before), Some(after)) if after > before => "grew",
+            _ => "shrank",
+        }
+    }
+
+    /// Returns the bytes gained, negative if space was freed.
+    pub fn delta(&self) -> i128 {
+        self.after.unwrap_or_default() as i128 - self.before.unwrap_or_default() as i128
+    }
+}
+
+/// Compares two snapshots, returning the directories and the files whose size changed, each
+/// ordered by the bytes gained or freed, largest first.
+///
+/// Paths are compared as recorded, so both snapshots should be of the same directory.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::snapshot::{diff, Snapshot};
+///
+/// let mut old = Snapshot::new("/srv", 1700000000);
+/// old.files.insert("/srv/db/base".to_string(), 200);
+/// old.files.insert("/srv/tmp/x".to_string(), 50);
+/// let mut new = Snapshot::new("/srv", 1700600000);
+/// new.files.insert("/srv/db/base".to_string(), 900);
+/// new.files.insert("/srv/db/wal".to_string(), 100);
+///
+/// let (directories, files) = diff(&old, &new);
+/// let kinds: Vec<_> = directories.iter().map(|c| (c.kind(), c.path.as_str())).collect();
+/// assert_eq!(kinds, [("grew", "/srv/db"), ("grew", "/srv"), ("vanished", "/srv/tmp")]);
+/// let kinds: Vec<_> = files.iter().map(|c| (c.kind(), c.path.as_str())).collect();
+/// assert_eq!(
+///     kinds,
+///     [("grew", "/srv/db/base"), ("appeared", "/srv/db/wal"), ("vanished", "/srv/tmp/x")]
+/// );
+/// ```
+pub fn diff(old: &Snapshot, new: &Snapshot) -> (Vec<SnapshotChange>, Vec<SnapshotChange>) {
+    let directories = changes(&old.directories(), &new.directories(), true);
+    let files = changes(&old.files, &new.files, false);
+    (directories, files)
+}
+
+/// Returns the paths whose size differs between `before` and `after`, largest change first.
+fn changes(
+    before: &BTreeMap<String, u64>,
+    after: &BTreeMap<String, u64>,
+    is_dir: bool,
+) -> Vec<SnapshotChange> {
+    let mut changes: Vec<SnapshotChange> = after
+        .iter()
+        .filter_map(|(path, &size)| {
+            let previous = before.get(path).copied();
+            (previous != Some(size)).then(|| SnapshotChange {
+                path: path.clone(),
+                is_dir,
+                before: previous,
+                after: Some(size),
+            })
+        })
+        .collect();
+    changes.extend(
+        before
+            .iter()
+            .filter(|(path, _)| !after.contains_key(*path))
+            .map(|(path, &size)| SnapshotChange {
+                path: path.clone(),
+                is_dir,
+                before: Some(size),
+                after: None,
+            }),
+    );
+    changes.sort_by(|a, b| {
+        b.delta()
+            .unsigned_abs()
+            .cmp(&a.delta().unsigned_abs())
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    changes
+}
@@ -0,0 +1,206 @@
+use crate::health::classify;
+use crate::i18n::{self, FluentArgs};
+use crate::porcelain;
+use crate::tally::SkipTally;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Classes of errors a scan counts separately.
+///
+/// * `PermissionDenied` - Directories and files the scan may not read
+/// * `NotFound` - Entries that vanished between being listed and being sized
+/// * `Hardware` - I/O errors and timeouts hinting at failing storage (see [`classify`])
+/// * `Other` - Everything else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    PermissionDenied,
+    NotFound,
+    Hardware,
+    Other,
+}
+
+impl ErrorClass {
+    /// Every class, in report order.
+    pub const ALL: [ErrorClass; 4] = [
+        ErrorClass::PermissionDenied,
+        ErrorClass::NotFound,
+        ErrorClass::Hardware,
+        ErrorClass::Other,
+    ];
+
+    /// Classifies an error of a scan read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::stats::ErrorClass;
+    /// use std::io;
+    ///
+    /// let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+    /// assert_eq!(ErrorClass::of(&denied), ErrorClass::PermissionDenied);
+    /// let timeout = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+    /// assert_eq!(ErrorClass::of(&timeout), ErrorClass::Hardware);
+    /// ```
+    pub fn of(err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => ErrorClass::PermissionDenied,
+            io::ErrorKind::NotFound => ErrorClass::NotFound,
+            _ if classify(err).is_some() => ErrorClass::Hardware,
+            _ => ErrorClass::Other,
+        }
+    }
+
+    /// Name of the class in reports and porcelain records.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorClass::PermissionDenied => "permission-denied",
+            ErrorClass::NotFound => "not-found",
+            ErrorClass::Hardware => "hardware",
+            ErrorClass::Other => "other",
+        }
+    }
+}
+
+/// Running counts of a scan, updated without locks by the scanner threads and the processing
+/// loop.
+///
+/// * `files` - Files handed from the scanner to processing, including those that failed
+/// * `dirs` - Directories listed
+/// * `processed` - Files sized, or deliberately left out while processing (further hard links,
+///   sparse files)
+/// * `failed` - Files that could not be sized
+/// * `bytes` - Total size of the files sized
+/// * `skipped` - Entries intentionally not sized, by reason
+///
+/// Errors are counted by [`ErrorClass`], once each, where they occur. Library users can share
+/// the stats of a scan with [`crate::run_with_stats`] and read them while it runs.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::stats::{ErrorClass, ScanStats};
+/// use std::io;
+/// use std::sync::atomic::Ordering;
+///
+/// let stats = ScanStats::default();
+/// stats.dirs.fetch_add(2, Ordering::Relaxed);
+/// stats.skipped.symlinks.fetch_add(1, Ordering::Relaxed);
+/// stats.record_error(&io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+/// assert_eq!(stats.errors(ErrorClass::PermissionDenied), 1);
+///
+/// let snapshot = stats.snapshot();
+/// assert_eq!((snapshot.dirs, snapshot.symlinks, snapshot.skipped), (2, 1, 1));
+/// assert_eq!(snapshot.errors["permission-denied"], 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    pub files: AtomicUsize,
+    pub dirs: AtomicUsize,
+    pub processed: AtomicUsize,
+    pub failed: AtomicUsize,
+    pub bytes: AtomicU64,
+    pub skipped: SkipTally,
+    errors: [AtomicUsize; ErrorClass::ALL.len()],
+}
+
+impl ScanStats {
+    /// Counts `err` under its [`ErrorClass`].
+    pub fn record_error(&self, err: &io::Error) {
+        self.errors[ErrorClass::of(err) as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the number of errors of `class` so far.
+    pub fn errors(&self, class: ErrorClass) -> usize {
+        self.errors[class as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of errors of every class so far.
+    pub fn total_errors(&self) -> usize {
+        ErrorClass::ALL.iter().map(|class| self.errors(*class)).sum()
+    }
+
+    /// Copies the counts as they are now.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            files: self.files.load(Ordering::Relaxed),
+            dirs: self.dirs.load(Ordering::Relaxed),
+            processed: self.processed.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            symlinks: self.skipped.symlinks.load(Ordering::Relaxed),
+            skipped: self.skipped.counts().iter().map(|(_, count)| count).sum(),
+            errors: ErrorClass::ALL
+                .iter()
+                .map(|class| (class.name().to_string(), self.errors(*class)))
+                .filter(|(_, count)| *count > 0)
+                .collect(),
+        }
+    }
+}
+
+/// The counts of a [`ScanStats`] at one point, as reported in every output format.
+///
+/// `errors` only holds the classes that occurred, by [`ErrorClass::name`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StatsSnapshot {
+    pub files: usize,
+    pub dirs: usize,
+    pub processed: usize,
+    pub failed: usize,
+    pub bytes: u64,
+    pub symlinks: usize,
+    pub skipped: usize,
+    pub errors: BTreeMap<String, usize>,
+}
+
+impl StatsSnapshot {
+    /// Summarizes the errors by class as a comma separated list, or `"none"` without any.
+    pub fn error_summary(&self) -> String {
+        if self.errors.is_empty() {
+            return tr!("skipped-none");
+        }
+        self.classes()
+            .map(|(class, count)| {
+                let mut args = FluentArgs::new();
+                args.set("count", count);
+                i18n::message(&format!("scan-errors-{}", class), Some(&args))
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Prints the `scan-stats` record followed by a `scan-error` record per class of error.
+    pub fn print_porcelain(&self) {
+        println!(
+            "{}",
+            porcelain::record(
+                "scan-stats",
+                &[
+                    &self.files.to_string(),
+                    &self.dirs.to_string(),
+                    &self.processed.to_string(),
+                    &self.failed.to_string(),
+                    &self.bytes.to_string(),
+                    &self.symlinks.to_string(),
+                    &self.skipped.to_string(),
+                ]
+            )
+        );
+        for (class, count) in self.classes() {
+            println!(
+                "{}",
+                porcelain::record("scan-error", &[class, &count.to_string()])
+            );
+        }
+    }
+
+    /// Returns the classes of errors that occurred with their counts, in [`ErrorClass::ALL`] order.
+    fn classes(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        ErrorClass::ALL.iter().filter_map(|class| {
+            let count = *self.errors.get(class.name())?;
+            Some((class.name(), count))
+        })
+    }
+}
@@ -0,0 +1,362 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::actions::verify::to_hex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Name of the per-application directory created inside the platform cache directory.
+const APP_DIR: &str = "ferris-files";
+
+/// Name of the directory next to the executable that holds all state in portable mode.
+const PORTABLE_DIR: &str = "ferris-files-data";
+
+/// Data directory next to the executable, set once portable mode is enabled.
+static PORTABLE_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Whether every write is refused, set once by [`enforce_read_only`].
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Refuses every file system write of the process from now on, for `--assert-read-only`.
+///
+/// Everything that creates, changes, moves or removes a file (sessions, exports, journals,
+/// actions, undo, scripts) calls [`check_write`] first, so a write that slipped past the
+/// validation of the options fails with an error instead of touching the disk. There is no way
+/// back: auditors can rely on it for the rest of the run.
+pub fn enforce_read_only() {
+    READ_ONLY.store(true, Ordering::SeqCst);
+}
+
+/// Whether [`enforce_read_only`] was called.
+pub fn read_only() -> bool {
+    READ_ONLY.load(Ordering::SeqCst)
+}
+
+/// Fails if writes are refused (see [`enforce_read_only`]); called before writing to `path`.
+///
+/// # Errors
+///
+/// Returns a `PermissionDenied` error naming `path` in read-only mode.
+pub fn check_write(path: &Path) -> io::Result<()> {
+    if read_only() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            tr!("read-only-write", path = path.display().to_string()),
+        ));
+    }
+    Ok(())
+}
+
+/// Switches to portable mode, in which config, cache, session and journal files are kept in a
+/// `ferris-files-data` directory next to the executable instead of the user's directories.
+///
+/// This suits running the binary from removable media on machines being triaged, leaving
+/// nothing behind in their home directories. The system wide configuration directory is still
+/// consulted, so protect lists set up by the machine's administrators keep applying.
+///
+/// # Returns
+///
+/// * `io::Result<PathBuf>` - The portable data directory
+///
+/// # Errors
+///
+/// Returns an error if the path of the running executable cannot be determined.
+pub fn enable_portable() -> io::Result<PathBuf> {
+    let dir = portable_root(&env::current_exe()?)?;
+    Ok(PORTABLE_ROOT.get_or_init(|| dir).clone())
+}
+
+/// Returns the portable data directory of the executable at `exe`.
+pub(crate) fn portable_root(exe: &Path) -> io::Result<PathBuf> {
+    exe.parent()
+        .map(|parent| parent.join(PORTABLE_DIR))
+        .ok_or_else(|| io::Error::other("the executable has no parent directory"))
+}
+
+/// Returns the directory used for caches, sessions and undo journals, creating it if needed.
+///
+/// The location follows platform conventions:
+/// - Linux and other Unix: `$XDG_CACHE_HOME/ferris-files` or `~/.cache/ferris-files`
+/// - macOS: `~/Library/Caches/ferris-files`
+/// - Windows: `%LOCALAPPDATA%\ferris-files`
+/// - Portable mode (see [`enable_portable`]): `ferris-files-data/cache` next to the executable
+///
+/// # Errors
+///
+/// Returns an error if no home/cache directory can be determined from the environment
+/// or if the directory cannot be created, which read-only mode refuses.
+pub fn cache_dir() -> io::Result<PathBuf> {
+    let dir = cache_dir_for(PORTABLE_ROOT.get().map(PathBuf::as_path)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "could not determine a cache directory from the environment",
+        )
+    })?;
+    if !dir.is_dir() {
+        check_write(&dir)?;
+        std::fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Returns the per-user configuration directory (which may not exist).
+///
+/// The location follows platform conventions:
+/// - Linux and other Unix: `$XDG_CONFIG_HOME/ferris-files` or `~/.config/ferris-files`
+/// - macOS: `~/Library/Application Support/ferris-files`
+/// - Windows: `%APPDATA%\ferris-files`
+/// - Portable mode (see [`enable_portable`]): `ferris-files-data/config` next to the executable
+pub fn config_dir() -> Option<PathBuf> {
+    config_dir_for(PORTABLE_ROOT.get().map(PathBuf::as_path))
+}
+
+/// Cache directory in the portable data directory `portable`, or the platform's without one.
+pub(crate) fn cache_dir_for(portable: Option<&Path>) -> Option<PathBuf> {
+    match portable {
+        Some(root) => Some(root.join("cache")),
+        None => platform_cache_base().map(|base| base.join(APP_DIR)),
+    }
+}
+
+/// Configuration directory in the portable data directory `portable`, or the platform's
+/// without one.
+pub(crate) fn config_dir_for(portable: Option<&Path>) -> Option<PathBuf> {
+    match portable {
+        Some(root) => Some(root.join("config")),
+        None => platform_config_base().map(|base| base.join(APP_DIR)),
+    }
+}
+
+/// Returns the system wide configuration directory shared by all users.
+///
+/// This is `/etc/ferris-files` on Unix and `%PROGRAMDATA%\ferris-files` on Windows.
+pub fn system_config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        from_env("PROGRAMDATA").map(|base| base.join(APP_DIR))
+    } else {
+        Some(PathBuf::from("/etc").join(APP_DIR))
+    }
+}
+
+/// Checks that the file at `path` can be written, without changing it, so an unwritable
+/// destination is reported before a long scan rather than after.
+///
+/// An existing file is opened for appending; a missing one is created and removed again. With
+/// `must_be_new` an existing file is refused, for destinations that are never overwritten.
+///
+/// # Errors
+///
+/// Returns an error if `path` is a directory, exists while `must_be_new` is set, or cannot be
+/// opened or created, e.g. because its directory is missing or read only.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::storage::check_writable;
+///
+/// let dir = std::env::temp_dir();
+/// assert!(check_writable(&dir.join("ferris-files-doc-probe.json"), false).is_ok());
+/// assert!(!dir.join("ferris-files-doc-probe.json").exists());
+/// assert!(check_writable(&dir, false).is_err());
+/// assert!(check_writable(&dir.join("missing-dir").join("out.json"), false).is_err());
+/// ```
+pub fn check_writable(path: &Path, must_be_new: bool) -> io::Result<()> {
+    check_write(path)?;
+    if path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "is a directory",
+        ));
+    }
+    if path.exists() {
+        if must_be_new {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "already exists",
+            ));
+        }
+        return File::options().append(true).open(path).map(|_| ());
+    }
+    File::options().write(true).create_new(true).open(path)?;
+    fs::remove_file(path)
+}
+
+/// Advisory lock on a scanned root, held while a scan runs so that two invocations scanning the
+/// same root do not interleave their writes to the last scan, sessions and journals.
+///
+/// The lock is a file in the `locks` directory of the cache holding the PID of its owner, and is
+/// removed when the lock is dropped. A lock left behind by a process that no longer runs is taken
+/// over; on platforms where that cannot be checked, only `force` does.
+#[derive(Debug)]
+pub struct ScanLock {
+    path: PathBuf,
+}
+
+impl ScanLock {
+    /// Locks `root` in the cache directory (see [`ScanLock::acquire_in`]).
+    pub fn acquire(root: &Path, force: bool) -> io::Result<ScanLock> {
+        Self::acquire_in(&cache_dir()?.join("locks"), root, force)
+    }
+
+    /// Locks each of `roots` in the cache directory (see [`ScanLock::acquire_all_in`]).
+    pub fn acquire_all(roots: &[PathBuf], force: bool) -> io::Result<Vec<ScanLock>> {
+        Self::acquire_all_in(&cache_dir()?.join("locks"), roots, force)
+    }
+
+    /// Locks each of `roots`, a scan over several directories, as a scan of it alone would, in
+    /// sorted order so that two such scans cannot each hold part of the other's roots. The locks
+    /// taken are released again if one of them cannot be.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first root that cannot be locked (see [`ScanLock::acquire_in`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::storage::ScanLock;
+    /// use std::path::{Path, PathBuf};
+    ///
+    /// let dir = std::env::temp_dir().join(format!("ff-locks-doc-{}", std::process::id()));
+    /// let roots = [PathBuf::from("/srv/media"), PathBuf::from("/mnt/archive")];
+    /// let locks = ScanLock::acquire_all_in(&dir, &roots, false).unwrap();
+    /// assert_eq!(locks.len(), 2);
+    /// // Scans of one of the roots wait, those of their common ancestor or elsewhere do not
+    /// assert!(ScanLock::acquire_in(&dir, Path::new("/srv/media"), false).is_err());
+    /// assert!(ScanLock::acquire_in(&dir, Path::new("/"), false).is_ok());
+    /// assert!(ScanLock::acquire_all_in(&dir, &[PathBuf::from("/home"), PathBuf::from("/mnt/archive")], false).is_err());
+    /// // The lock of /home was released again
+    /// assert!(ScanLock::acquire_in(&dir, Path::new("/home"), false).is_ok());
+    /// # drop(locks);
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn acquire_all_in(dir: &Path, roots: &[PathBuf], force: bool) -> io::Result<Vec<ScanLock>> {
+        let mut roots = roots.to_vec();
+        roots.sort();
+        roots.dedup();
+        roots
+            .iter()
+            .map(|root| Self::acquire_in(dir, root, force))
+            .collect()
+    }
+
+    /// Locks `root` with a lock file in `dir`, taking over the lock of another running process
+    /// if `force` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WouldBlock` error naming the PID of the other process if it holds the lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ferris_files::storage::ScanLock;
+    /// use std::path::Path;
+    ///
+    /// let dir = std::env::temp_dir().join(format!("ff-lock-doc-{}", std::process::id()));
+    /// let lock = ScanLock::acquire_in(&dir, Path::new("/srv"), false).unwrap();
+    /// // This process is still running, so only force takes the lock over
+    /// assert!(ScanLock::acquire_in(&dir, Path::new("/srv"), false).is_err());
+    /// assert!(ScanLock::acquire_in(&dir, Path::new("/home"), false).is_ok());
+    /// let forced = ScanLock::acquire_in(&dir, Path::new("/srv"), true).unwrap();
+    /// # drop((lock, forced));
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn acquire_in(dir: &Path, root: &Path, force: bool) -> io::Result<ScanLock> {
+        check_write(dir)?;
+        fs::create_dir_all(dir)?;
+        let name = to_hex(&Sha256::digest(root.to_string_lossy().as_bytes()));
+        let path = dir.join(format!("{}.lock", name));
+        loop {
+            match File::options().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}\n{}", std::process::id(), root.display())?;
+                    return Ok(ScanLock { path });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+            let owner = fs::read_to_string(&path)
+                .ok()
+                .and_then(|text| text.lines().next()?.parse::<u32>().ok());
+            match owner {
+                Some(pid) if process_running(pid) && !force => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WouldBlock,
+                        tr!("scan-locked", pid = pid, root = root.display().to_string()),
+                    ));
+                }
+                Some(pid) if process_running(pid) => {
+                    log::warn!("{}", tr!("scan-lock-forced", pid = pid));
+                }
+                _ => log::debug!("Taking over stale lock {}", path.display()),
+            }
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for ScanLock {
+    /// Removes the lock file, unless another process forced its way in since.
+    fn drop(&mut self) {
+        let owner = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| text.lines().next()?.parse::<u32>().ok());
+        if owner == Some(std::process::id()) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Whether a process with this PID is running; assumed on platforms where it cannot be checked.
+#[cfg(unix)]
+fn process_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks whether the process exists and may be signalled
+    let rc = unsafe { libc::kill(pid, 0) };
+    rc == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether a process with this PID is running; assumed on platforms where it cannot be checked.
+#[cfg(not(unix))]
+fn process_running(_pid: u32) -> bool {
+    true
+}
+
+/// Reads a non-empty environment variable as a path.
+fn from_env(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Returns the platform specific base configuration directory, without the application component.
+fn platform_config_base() -> Option<PathBuf> {
+    if cfg!(windows) {
+        from_env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        from_env("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        from_env("XDG_CONFIG_HOME").or_else(|| from_env("HOME").map(|home| home.join(".config")))
+    }
+}
+
+/// Returns the platform specific base cache directory, without the application component.
+fn platform_cache_base() -> Option<PathBuf> {
+    if cfg!(windows) {
+        from_env("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        from_env("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        from_env("XDG_CACHE_HOME").or_else(|| from_env("HOME").map(|home| home.join(".cache")))
+    }
+}
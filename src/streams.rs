@@ -0,0 +1,180 @@
+use crate::report::{ReportRow, ReportSection};
+use crate::top_entries::TopEntries;
+use crate::traits::ByteSize;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Returns the name of a named data stream as listed by `FindFirstStreamW`
+/// (`:Zone.Identifier:$DATA`), or `None` for the unnamed default stream (`::$DATA`) and
+/// anything that is not a data stream.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::streams::stream_name;
+///
+/// assert_eq!(stream_name(":Zone.Identifier:$DATA"), Some("Zone.Identifier"));
+/// assert_eq!(stream_name("::$DATA"), None);
+/// assert_eq!(stream_name("Zone.Identifier"), None);
+/// ```
+pub fn stream_name(raw: &str) -> Option<&str> {
+    let name = raw.strip_prefix(':')?.strip_suffix(":$DATA")?;
+    (!name.is_empty()).then_some(name)
+}
+
+/// Lists the named alternate data streams of the file at `path` with their sizes in bytes.
+///
+/// Only NTFS and ReFS volumes on Windows have named streams; elsewhere the list is empty.
+///
+/// # Errors
+///
+/// Returns an error if the streams of the file cannot be listed.
+#[cfg(windows)]
+pub fn named_streams(path: &Path) -> io::Result<Vec<(String, u64)>> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::{ERROR_HANDLE_EOF, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard,
+        WIN32_FIND_STREAM_DATA,
+    };
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut data: WIN32_FIND_STREAM_DATA = unsafe { std::mem::zeroed() };
+    let handle = unsafe {
+        FindFirstStreamW(
+            wide.as_ptr(),
+            FindStreamInfoStandard,
+            &mut data as *mut WIN32_FIND_STREAM_DATA as *mut _,
+            0,
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        let error = io::Error::last_os_error();
+        // Files without any stream, e.g. on volumes that do not support them
+        if error.raw_os_error() == Some(ERROR_HANDLE_EOF as i32) {
+            return Ok(Vec::new());
+        }
+        return Err(error);
+    }
+
+    let mut streams = Vec::new();
+    loop {
+        let len = data
+            .cStreamName
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(data.cStreamName.len());
+        let raw = String::from_utf16_lossy(&data.cStreamName[..len]);
+        if let Some(name) = stream_name(&raw) {
+            streams.push((name.to_string(), data.StreamSize.max(0) as u64));
+        }
+        let found =
+            unsafe { FindNextStreamW(handle, &mut data as *mut WIN32_FIND_STREAM_DATA as *mut _) };
+        if found == 0 {
+            break;
+        }
+    }
+    let error = io::Error::last_os_error();
+    unsafe { FindClose(handle) };
+    match error.raw_os_error() {
+        Some(code) if code == ERROR_HANDLE_EOF as i32 => Ok(streams),
+        _ => Err(error),
+    }
+}
+
+/// Lists the named alternate data streams of the file at `path`; always empty, as only
+/// Windows has them.
+///
+/// # Errors
+///
+/// Never fails on this platform.
+#[cfg(not(windows))]
+pub fn named_streams(_path: &Path) -> io::Result<Vec<(String, u64)>> {
+    Ok(Vec::new())
+}
+
+/// Named alternate data streams found by `--ads`, for the section listing the largest.
+///
+/// Streams are named `path:stream`, the notation Windows tools accept to open them. Files whose
+/// streams cannot be listed are sized without them.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::streams::StreamTally;
+///
+/// let streams = StreamTally::new(1);
+/// let extra = streams.add(
+///     r"C:\Users\me\setup.zip",
+///     &[("Zone.Identifier".to_string(), 26), ("cache".to_string(), 4096)],
+/// );
+/// assert_eq!(extra, 4122);
+/// let rows = streams.finish().rows;
+/// assert_eq!(rows.len(), 1);
+/// assert_eq!(rows[0].fields, ["4096", r"C:\Users\me\setup.zip:cache"]);
+/// ```
+#[derive(Debug)]
+pub struct StreamTally {
+    largest: Mutex<TopEntries>,
+    streams: AtomicUsize,
+    bytes: AtomicU64,
+}
+
+impl StreamTally {
+    /// Creates an empty tally listing the `limit` largest streams.
+    pub fn new(limit: usize) -> Self {
+        StreamTally {
+            largest: Mutex::new(TopEntries::new(limit)),
+            streams: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Lists and records the named streams of the file at `path`, returning their total size.
+    pub fn record(&self, path: &Path) -> u64 {
+        match named_streams(path) {
+            Ok(streams) if !streams.is_empty() => self.add(&path.to_string_lossy(), &streams),
+            _ => 0,
+        }
+    }
+
+    /// Records the named `streams` of the file at `path`, returning their total size.
+    pub fn add(&self, path: &str, streams: &[(String, u64)]) -> u64 {
+        let total: u64 = streams.iter().map(|(_, size)| size).sum();
+        self.streams.fetch_add(streams.len(), Ordering::Relaxed);
+        self.bytes.fetch_add(total, Ordering::Relaxed);
+        if let Ok(mut largest) = self.largest.lock() {
+            for (name, size) in streams {
+                largest.insert(format!("{}:{}", path, name), *size);
+            }
+        }
+        total
+    }
+
+    /// Returns the section listing the largest streams, headed by the number and total size
+    /// of all streams found.
+    pub fn finish(&self) -> ReportSection {
+        let rows = self
+            .largest
+            .lock()
+            .map(|mut largest| largest.get_entries().to_vec())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(stream, size)| ReportRow {
+                text: format!("{:>10}  {}", size.format_size(), stream),
+                kind: "stream".to_string(),
+                fields: vec![size.to_string(), stream],
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!(
+                "report-streams",
+                count = self.streams.load(Ordering::Relaxed),
+                size = self.bytes.load(Ordering::Relaxed).format_size()
+            )),
+            rows,
+        }
+    }
+}
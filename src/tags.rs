@@ -0,0 +1,230 @@
+use crate::cost::{CostModel, GroupCosts};
+use crate::protect::rule_globs;
+use crate::report::{share_line, Aggregator, FileInfo, ReportRow, ReportSection};
+use crate::storage;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Name of the tag map file inside the system and user configuration directories.
+const TAGS_FILE: &str = "tags";
+
+/// Labels for known paths, e.g. the team owning them or what they hold.
+///
+/// Each rule is a path glob with the protect list syntax (see [`rule_globs`]) followed by a
+/// label, and also tags everything beneath a matching directory. A path takes the label of the
+/// first rule matching it, so specific rules go before general ones and every file counts
+/// towards exactly one tag.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::tags::TagMap;
+///
+/// let tags = TagMap::parse("
+///     /srv/analytics/exports  backups
+///     /srv/analytics          team-analytics
+///     *.bak                   backups
+/// ").unwrap();
+/// assert_eq!(tags.tag("/srv/analytics/exports/2024.csv"), Some("backups"));
+/// assert_eq!(tags.tag("/srv/analytics/model.bin"), Some("team-analytics"));
+/// assert_eq!(tags.tag("/home/me/notes.bak"), Some("backups"));
+/// assert_eq!(tags.tag("/home/me/notes.txt"), None);
+///
+/// assert!(TagMap::parse("/srv/analytics").is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TagMap {
+    labels: Vec<String>,
+    // Every rule is compiled to two globs: the rule itself and everything beneath it
+    set: GlobSet,
+}
+
+impl TagMap {
+    /// Parses a tag map file: one `PATH LABEL` rule per line, blank lines and `#` comments
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first rule without a label or with an invalid glob.
+    pub fn parse(contents: &str) -> Result<TagMap, String> {
+        let mut builder = GlobSetBuilder::new();
+        let mut labels = Vec::new();
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let invalid = |detail: String| format!("invalid tag rule '{}': {}", line, detail);
+            let mut words = line.split_whitespace();
+            let (Some(path), Some(label), None) = (words.next(), words.next(), words.next()) else {
+                return Err(invalid("expected a path and a label".to_string()));
+            };
+            for glob in rule_globs(path) {
+                builder.add(Glob::new(&glob).map_err(|e| invalid(e.to_string()))?);
+            }
+            labels.push(label.to_string());
+        }
+        let set = builder.build().map_err(|e| e.to_string())?;
+        Ok(TagMap { labels, set })
+    }
+
+    /// Loads and combines the system wide and per-user tag maps, system rules first.
+    ///
+    /// The rules are read from `tags` files in [`storage::system_config_dir`] and
+    /// [`storage::config_dir`]; missing files are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a tags file exists but cannot be read or contains an invalid rule.
+    pub fn load() -> io::Result<TagMap> {
+        let mut contents = String::new();
+        for file in Self::files() {
+            match fs::read_to_string(&file) {
+                Ok(text) => {
+                    contents.push_str(&text);
+                    contents.push('\n');
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("could not read tag map {}: {}", file.display(), e),
+                    ))
+                }
+            }
+        }
+        Self::parse(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Locations of the system wide and per-user tag map files.
+    pub fn files() -> Vec<PathBuf> {
+        [storage::system_config_dir(), storage::config_dir()]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(TAGS_FILE))
+            .collect()
+    }
+
+    /// Returns the label of the first rule matching `path`, if any.
+    pub fn tag(&self, path: &str) -> Option<&str> {
+        self.set
+            .matches(path)
+            .first()
+            .map(|index| self.labels[index / 2].as_str())
+    }
+
+    /// Whether the map holds no rules.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+/// Bytes and number of files per tag, for the `by-tag` report.
+///
+/// Files no rule matches are counted under the empty tag, so the subtotals add up to the
+/// whole scan.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo};
+/// use ferris_files::tags::{TagMap, TagTotals};
+///
+/// let tags = TagMap::parse("/srv/analytics team-analytics\n*.bak backups").unwrap();
+/// let mut totals = TagTotals::new(tags, 10);
+/// for (path, size) in [("/srv/analytics/a.parquet", 700), ("/srv/db.bak", 200), ("/srv/x", 5)] {
+///     totals.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+/// }
+/// assert_eq!(
+///     totals.top(),
+///     [
+///         ("team-analytics".to_string(), 700, 1),
+///         ("backups".to_string(), 200, 1),
+///         (String::new(), 5, 1)
+///     ]
+/// );
+/// assert_eq!(totals.finish().rows[1].fields, ["200", "1", "backups"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct TagTotals {
+    tags: TagMap,
+    limit: usize,
+    totals: HashMap<String, (u64, usize)>,
+    costs: GroupCosts<String>,
+}
+
+impl TagTotals {
+    /// Creates empty totals for the labels of `tags`, reporting the `limit` tags taking the
+    /// most bytes.
+    pub fn new(tags: TagMap, limit: usize) -> Self {
+        TagTotals {
+            tags,
+            limit,
+            totals: HashMap::new(),
+            costs: GroupCosts::default(),
+        }
+    }
+
+    /// Also estimates the monthly cost of every tag at the rates of `costs`.
+    pub fn with_costs(mut self, costs: CostModel) -> Self {
+        self.costs = GroupCosts::new(costs);
+        self
+    }
+
+    /// Returns the tags taking the most bytes as `(label, bytes, files)`, largest first.
+    pub fn top(&self) -> Vec<(String, u64, usize)> {
+        let mut top: Vec<(String, u64, usize)> = self
+            .totals
+            .iter()
+            .map(|(label, (bytes, files))| (label.clone(), *bytes, *files))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(self.limit);
+        top
+    }
+}
+
+impl Aggregator for TagTotals {
+    fn observe(&mut self, file: &FileInfo) {
+        let label = self.tags.tag(&file.path).unwrap_or_default();
+        if let Some(cost) = self.costs.monthly(file) {
+            self.costs.add(label.to_string(), cost);
+        }
+        match self.totals.get_mut(label) {
+            Some((bytes, files)) => {
+                *bytes += file.size;
+                *files += 1;
+            }
+            None => {
+                self.totals.insert(label.to_string(), (file.size, 1));
+            }
+        }
+    }
+
+    fn finish(&self) -> ReportSection {
+        let total = self.totals.values().map(|(bytes, _)| bytes).sum();
+        let rows = self
+            .top()
+            .into_iter()
+            .map(|(label, bytes, files)| {
+                let text = if label.is_empty() {
+                    tr!("tag-none")
+                } else {
+                    label.clone()
+                };
+                ReportRow {
+                    text: share_line(&text, bytes, total, files, self.costs.get(&label)),
+                    kind: "tag-total".to_string(),
+                    fields: vec![bytes.to_string(), files.to_string(), label],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-by-tag")),
+            rows,
+        }
+    }
+}
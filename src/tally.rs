@@ -0,0 +1,324 @@
+use crate::i18n::{self, FluentArgs};
+use crate::report::{ReportRow, ReportSection};
+use crate::top_entries::TopEntries;
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Thread-safe counters for entries the scan intentionally does not size.
+///
+/// The directory scanner increments these while traversing so the final summary can
+/// explain what was ignored (and therefore why the scanned total may differ from `df`).
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::tally::SkipTally;
+/// use std::sync::atomic::Ordering;
+///
+/// let tally = SkipTally::default();
+/// tally.symlinks.fetch_add(2, Ordering::Relaxed);
+/// tally.fifos.fetch_add(1, Ordering::Relaxed);
+/// assert_eq!(tally.summary(), "2 symlinks, 1 FIFO");
+/// ```
+#[derive(Debug, Default)]
+pub struct SkipTally {
+    pub symlinks: AtomicUsize,
+    pub sockets: AtomicUsize,
+    pub fifos: AtomicUsize,
+    pub block_devices: AtomicUsize,
+    pub char_devices: AtomicUsize,
+    pub excluded_dirs: AtomicUsize,
+    pub unreadable_dirs: AtomicUsize,
+    pub foreign_owned: AtomicUsize,
+    pub too_deep_dirs: AtomicUsize,
+    pub truncated_dirs: AtomicUsize,
+    pub hard_links: AtomicUsize,
+    pub sparse: AtomicUsize,
+    pub not_included: AtomicUsize,
+    pub ignored: AtomicUsize,
+    pub depth_limited_dirs: AtomicUsize,
+    pub shallow_files: AtomicUsize,
+}
+
+impl SkipTally {
+    /// Returns `(message id, count)` pairs for every counter in display order.
+    pub fn counts(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("skipped-symlinks", self.symlinks.load(Ordering::Relaxed)),
+            ("skipped-sockets", self.sockets.load(Ordering::Relaxed)),
+            ("skipped-fifos", self.fifos.load(Ordering::Relaxed)),
+            (
+                "skipped-block-devices",
+                self.block_devices.load(Ordering::Relaxed),
+            ),
+            (
+                "skipped-char-devices",
+                self.char_devices.load(Ordering::Relaxed),
+            ),
+            (
+                "skipped-excluded-dirs",
+                self.excluded_dirs.load(Ordering::Relaxed),
+            ),
+            (
+                "skipped-unreadable-dirs",
+                self.unreadable_dirs.load(Ordering::Relaxed),
+            ),
+            (
+                "skipped-foreign-owned",
+                self.foreign_owned.load(Ordering::Relaxed),
+            ),
+            (
+                "skipped-too-deep-dirs",
+                self.too_deep_dirs.load(Ordering::Relaxed),
+            ),
+            (
+                "skipped-truncated-dirs",
+                self.truncated_dirs.load(Ordering::Relaxed),
+            ),
+            (
+                "skipped-hard-links",
+                self.hard_links.load(Ordering::Relaxed),
+            ),
+            ("skipped-sparse", self.sparse.load(Ordering::Relaxed)),
+            (
+                "skipped-not-included",
+                self.not_included.load(Ordering::Relaxed),
+            ),
+            ("skipped-ignored", self.ignored.load(Ordering::Relaxed)),
+            (
+                "skipped-depth-limited-dirs",
+                self.depth_limited_dirs.load(Ordering::Relaxed),
+            ),
+            (
+                "skipped-shallow-files",
+                self.shallow_files.load(Ordering::Relaxed),
+            ),
+        ]
+    }
+
+    /// Formats all non-zero counters as a comma separated list, or `"none"` if nothing was skipped.
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = self
+            .counts()
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(id, count)| {
+                let mut args = FluentArgs::new();
+                args.set("count", count);
+                i18n::message(id, Some(&args))
+            })
+            .collect();
+
+        if parts.is_empty() {
+            tr!("skipped-none")
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Thread-safe record of the files with several hard links, so each is sized once however many
+/// paths lead to it (Unix only).
+///
+/// A file hardlinked ten times takes its space once, but a scan reaches it through every link.
+/// The first link seen is sized and ranked, with its number of links; the others are left out
+/// (and counted in [`SkipTally::hard_links`]) unless every link is to be counted, as `du -l`
+/// does. Files with a single link, the vast majority, never touch the lock.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::tally::HardLinks;
+///
+/// let links = HardLinks::new(false);
+/// let metadata = std::fs::metadata("Cargo.toml").unwrap();
+/// assert!(links.claim(&metadata));
+/// ```
+#[derive(Debug, Default)]
+pub struct HardLinks {
+    count_all: bool,
+    seen: Mutex<HashMap<(u64, u64), u64>>,
+}
+
+impl HardLinks {
+    /// Creates an empty record; with `count_all`, every link is sized as a file of its own.
+    pub fn new(count_all: bool) -> Self {
+        Self {
+            count_all,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the file of `metadata` is to be sized: it has a single link, it is the first of
+    /// its links seen, or every link is counted.
+    pub fn claim(&self, metadata: &Metadata) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            if metadata.nlink() > 1 {
+                let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+                let first = seen
+                    .insert((metadata.dev(), metadata.ino()), metadata.nlink())
+                    .is_none();
+                return first || self.count_all;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = metadata;
+        true
+    }
+
+    /// Returns the number of links of the file with device and inode number `inode`, if it has
+    /// several.
+    pub fn links(&self, inode: (u64, u64)) -> Option<u64> {
+        self.seen
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&inode)
+            .copied()
+    }
+}
+
+/// Thread-safe running totals of the bytes accounted for by the scan on the device the scan root
+/// lives on.
+///
+/// A scan of a whole filesystem can thus be compared against its `statvfs` usage even when other
+/// filesystems are mounted beneath the root; the overall total is in
+/// [`ScanStats::bytes`](crate::stats::ScanStats::bytes).
+#[derive(Debug, Default)]
+pub struct ByteTotals {
+    pub root_device: Option<u64>,
+    pub root_device_file_bytes: AtomicU64,
+    pub root_device_dir_bytes: AtomicU64,
+}
+
+impl ByteTotals {
+    /// Creates empty totals for a scan rooted on `root_device` (if known).
+    pub fn new(root_device: Option<u64>) -> Self {
+        Self {
+            root_device,
+            ..Default::default()
+        }
+    }
+
+    /// Records the on-disk size of a regular file residing on `device`.
+    pub fn add_file(&self, device: Option<u64>, size: u64) {
+        if device.is_some() && device == self.root_device {
+            self.root_device_file_bytes.fetch_add(size, Ordering::Relaxed);
+        }
+    }
+
+    /// Records the on-disk size of a directory residing on `device`.
+    pub fn add_dir(&self, device: Option<u64>, size: u64) {
+        if device.is_some() && device == self.root_device {
+            self.root_device_dir_bytes.fetch_add(size, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Thread-safe record of the shape of the scanned tree, for the `fan-out` report: the
+/// directories listing the most entries and the deepest directory below the scan root.
+///
+/// Directories with millions of entries or nested hundreds of levels deep slow down backups and
+/// syncs far more than their size suggests. The scanner records every directory once it has
+/// listed it; a directory with fewer entries than the widest kept so far, nested no deeper than
+/// the deepest, costs two atomic loads.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::tally::TreeShape;
+/// use std::path::{Path, PathBuf};
+///
+/// let shape = TreeShape::new(2);
+/// shape.record(Path::new("/srv"), 3, 0);
+/// shape.record(Path::new("/srv/spool"), 90_000, 1);
+/// shape.record(Path::new("/srv/a/b/c"), 12, 3);
+/// assert_eq!(shape.widest(), [("/srv/spool".to_string(), 90_000), ("/srv/a/b/c".to_string(), 12)]);
+/// assert_eq!(shape.deepest(), Some((3, PathBuf::from("/srv/a/b/c"))));
+/// ```
+#[derive(Debug)]
+pub struct TreeShape {
+    widest: Mutex<TopEntries>,
+    floor: AtomicU64,
+    deepest: Mutex<Option<(usize, PathBuf)>>,
+    /// Depth of `deepest` plus one, or 0 before any directory was recorded
+    levels: AtomicUsize,
+}
+
+impl TreeShape {
+    /// Creates an empty record keeping the `limit` directories with the most entries.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            widest: Mutex::new(TopEntries::new(limit)),
+            floor: AtomicU64::new(0),
+            deepest: Mutex::new(None),
+            levels: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records that `dir`, `depth` levels below the scan root, listed `entries` entries.
+    pub fn record(&self, dir: &Path, entries: usize, depth: usize) {
+        if depth >= self.levels.load(Ordering::Relaxed) {
+            let mut deepest = self.deepest.lock().unwrap_or_else(PoisonError::into_inner);
+            if deepest.as_ref().is_none_or(|(max, _)| depth > *max) {
+                *deepest = Some((depth, dir.to_path_buf()));
+                self.levels.store(depth + 1, Ordering::Relaxed);
+            }
+        }
+        let entries = entries as u64;
+        if entries > self.floor.load(Ordering::Relaxed) {
+            let mut widest = self.widest.lock().unwrap_or_else(PoisonError::into_inner);
+            widest.insert(dir.display().to_string(), entries);
+            if let Some(floor) = widest.threshold() {
+                self.floor.store(floor, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns the directories with the most entries as `(directory, entries)`, widest first.
+    pub fn widest(&self) -> Vec<(String, u64)> {
+        self.widest
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .to_vec()
+    }
+
+    /// Returns the deepest directory and its depth below the scan root, if any was listed.
+    pub fn deepest(&self) -> Option<(usize, PathBuf)> {
+        self.deepest
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the `fan-out` report: a row per directory of [`TreeShape::widest`], then the
+    /// deepest directory.
+    pub fn section(&self) -> ReportSection {
+        let mut rows: Vec<ReportRow> = self
+            .widest()
+            .into_iter()
+            .map(|(dir, entries)| ReportRow {
+                text: format!("{:>10}  {}", entries, dir),
+                kind: "fan-out".to_string(),
+                fields: vec![entries.to_string(), dir],
+            })
+            .collect();
+        if let Some((depth, dir)) = self.deepest() {
+            let dir = dir.display().to_string();
+            rows.push(ReportRow {
+                text: tr!("report-fan-out-deepest", depth = depth, path = dir.clone()),
+                kind: "depth".to_string(),
+                fields: vec![depth.to_string(), dir],
+            });
+        }
+        ReportSection {
+            heading: Some(tr!("report-fan-out")),
+            rows,
+        }
+    }
+}
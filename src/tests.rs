@@ -1,6 +1,40 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::top_entries::TopEntries;
+    use crate::audit::PermissionFlags;
+    use crate::config::Config;
+    use crate::categories::{self, Category, CategoryTotals};
+    use crate::i18n::{is_yes, supported_language};
+    use crate::health::{classify, HardwareError, StorageHealth};
+    use crate::import::{import_entries, ImportFormat};
+    use crate::export::ScanTree;
+    use crate::snapshot::{self, Snapshot};
+    use crate::netfs::SizeFallbacks;
+    use crate::porcelain::record;
+    use crate::progress::ProgressFeed;
+    use crate::output::JsonReport;
+    use crate::fleet::{parse_hosts, read_scan};
+    use crate::wire::{FrameReader, FrameWriter, Message, WireEntry, ENTRIES_PER_FRAME, WIRE_VERSION};
+    use crate::preview::estimate_subtree;
+    use crate::protect::ProtectList;
+    use crate::tags::{TagMap, TagTotals};
+    use crate::ownership::{OwnerTotals, OwnershipMap};
+    use crate::cost::{CostModel, CostTotal};
+    use crate::retention::{RetentionPolicy, RetentionSimulation};
+    use crate::drill::{drill_down, Child, DrillTree};
+    use crate::alerts::{AlertAction, AlertRules};
+    use crate::actions::guard::{Fingerprint, ScanStamps, Staleness};
+    use crate::actions::verify::ReadLimiter;
+    use crate::actions::journal::{latest_pending, mark_undone, read_entries, ActionKind, Journal};
+    use crate::actions::script::{render, ScriptKind};
+    use crate::actions::Selection;
+    use crate::results::{entry_id, Provenance, SavedScan, SCHEMA_VERSION};
+    use crate::quota::{mount_for_path, parse_mountinfo, QuotaUsage};
+    use crate::report::{aggregators, order_entries, DirTotals, ExtensionTotals, FileInfo, ReportKind, SizeHistogram, ThenBy, TopFiles, HISTOGRAM_BOUNDS};
+    use crate::report::{Aggregator, DirAges, AGE_BOUNDS};
+    use crate::units::{parse_duration, parse_size};
+    use std::path::Path;
+    use crate::top_entries::{ConcurrentTopEntries, TopEntries, HEAP_THRESHOLD, MAX_TIES};
     use crate::ByteSize;
 
     // Test Invariant 1: Largest entries always appear first
@@ -129,6 +163,36 @@ mod tests {
     }
 
     #[test]
+    fn test_repeated_paths_are_deduplicated() {
+        let mut top = TopEntries::new(3);
+
+        // The same file reached twice, e.g. through a bind mount, is listed once
+        top.insert("a".to_string(), 100);
+        top.insert("b".to_string(), 80);
+        top.insert("a".to_string(), 100);
+        assert_eq!(top.get_entries(), [("a".to_string(), 100), ("b".to_string(), 80)]);
+
+        // A re-scan replaces the earlier size, wherever it ranks now
+        top.insert("c".to_string(), 60);
+        assert!(top.insert("b".to_string(), 120));
+        assert_eq!(
+            top.get_entries(),
+            [("b".to_string(), 120), ("a".to_string(), 100), ("c".to_string(), 60)]
+        );
+        assert!(top.insert("b".to_string(), 10));
+        assert_eq!(top.get_entries().len(), 3);
+        assert_eq!(top.get_entries()[2], ("b".to_string(), 10));
+
+        // A path that fell out of the collection can come back
+        top.insert("d".to_string(), 90);
+        assert!(!top.get_entries().iter().any(|(path, _)| path == "b"));
+        assert!(top.insert("b".to_string(), 95));
+        let paths: Vec<&str> = top.get_entries().iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["a", "b", "d"]);
+    }
+
+    #[test]
+    #[allow(clippy::needless_range_loop)]
     fn test_large_volume_maintains_invariants() {
         let mut top = TopEntries::new(5);
         
@@ -279,4 +343,3422 @@ mod tests {
         assert_eq!(1_u64.format_size(), "1 bytes");
         assert_eq!(10_u64.format_size(), "10 bytes");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_permission_flags_from_mode() {
+        let private = PermissionFlags::from_mode(0o100600);
+        assert!(!private.any());
+        assert!(private.labels().is_empty());
+
+        let shared = PermissionFlags::from_mode(0o100644);
+        assert_eq!(shared.labels(), vec!["world-readable"]);
+
+        let risky = PermissionFlags::from_mode(0o106777);
+        assert!(risky.setuid && risky.setgid && risky.world_writable && risky.world_readable);
+        assert_eq!(
+            risky.labels(),
+            vec!["setuid", "setgid", "world-writable", "world-readable"]
+        );
+    }
+
+    #[test]
+    fn test_mountinfo_parsing_and_lookup() {
+        let mounts = parse_mountinfo(
+            "22 1 8:1 / / rw,relatime - ext4 /dev/sda1 rw\n\
+             36 22 8:2 / /home rw,relatime - ext4 /dev/sda2 rw,usrquota\n\
+             37 22 0:40 / /mnt/my\\040share rw - nfs4 server:/export rw\n\
+             malformed line\n",
+        );
+        assert_eq!(mounts.len(), 3);
+        assert_eq!(mounts[2].mount_point, Path::new("/mnt/my share"));
+
+        let home = mount_for_path(&mounts, Path::new("/home/alice/videos")).unwrap();
+        assert_eq!(home.source, "/dev/sda2");
+
+        let root = mount_for_path(&mounts, Path::new("/homework")).unwrap();
+        assert_eq!(root.source, "/dev/sda1");
+    }
+
+    #[test]
+    fn test_quota_headroom() {
+        let usage = QuotaUsage {
+            used_bytes: 1024,
+            soft_limit: None,
+            hard_limit: Some(3 * 1024),
+        };
+        assert_eq!(usage.effective_limit(), Some(3 * 1024));
+        assert_eq!(
+            usage.describe(),
+            "1.00 KB used of 3.00 KB hard limit: 2.00 KB headroom"
+        );
+
+        let unlimited = QuotaUsage {
+            used_bytes: 1024,
+            soft_limit: None,
+            hard_limit: None,
+        };
+        assert_eq!(unlimited.describe(), "1.00 KB used, no limit set");
+    }
+
+    #[test]
+    fn test_parse_size_units() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("10B"), Ok(10));
+        assert_eq!(parse_size("1KB"), Ok(1000));
+        assert_eq!(parse_size("1 kib"), Ok(1024));
+        assert_eq!(parse_size("1K"), Ok(1024));
+        assert_eq!(parse_size("3GB"), Ok(3_000_000_000));
+        assert_eq!(parse_size("3G"), Ok(3 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("2TiB"), Ok(2 * 1024_u64.pow(4)));
+        assert_eq!(parse_size(" 0.5MB "), Ok(500_000));
+
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("12XB").is_err());
+        assert!(parse_size("1.2.3MB").is_err());
+        assert!(parse_size("99999999999TB").is_err());
+    }
+
+    #[test]
+    fn test_delete_script_rendering() {
+        let entries = vec![
+            ("/data/it's big.iso".to_string(), 2048_u64),
+            ("/data/small.log".to_string(), 10_u64),
+        ];
+        let selection = Selection {
+            min_size: Some(1024),
+            ..Default::default()
+        };
+        let selected = selection.select(&entries);
+
+        let shell = render(ScriptKind::Shell, Path::new("/data"), &selection, &selected);
+        assert!(shell.starts_with("#!/bin/sh\n"));
+        assert!(shell.contains("set -eu"));
+        assert!(shell.contains("# 2.00 KB\nrm -f -- '/data/it'\\''s big.iso'\n"));
+        assert!(!shell.contains("small.log"));
+
+        let powershell = render(
+            ScriptKind::PowerShell,
+            Path::new("/data"),
+            &selection,
+            &selected,
+        );
+        assert!(!powershell.starts_with("#!"));
+        assert!(powershell.contains("Remove-Item -LiteralPath '/data/it''s big.iso' -Force"));
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("30s").map(|d| d.as_secs()), Ok(30));
+        assert_eq!(parse_duration("5m").map(|d| d.as_secs()), Ok(300));
+        assert_eq!(parse_duration("1d").map(|d| d.as_secs()), Ok(86_400));
+        assert_eq!(parse_duration("1y").map(|d| d.as_secs()), Ok(365 * 86_400));
+        assert_eq!(parse_duration(" 7 D ").map(|d| d.as_secs()), Ok(7 * 86_400));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("1.5d").is_err());
+        assert!(parse_duration("3 fortnights").is_err());
+        assert!(parse_duration("99999999999999999y").is_err());
+    }
+
+    #[test]
+    fn test_journal_roundtrip_and_undone_batches() {
+        let dir = std::env::temp_dir().join(format!("ferris-journal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let empty = Journal::create_in(&dir).unwrap();
+        assert_eq!(empty.finish().unwrap(), None);
+        assert_eq!(latest_pending(&dir).unwrap(), None);
+
+        let mut journal = Journal::create_in(&dir).unwrap();
+        journal
+            .record(ActionKind::Move, "/data/a.iso", Path::new("/mnt/a.iso"), None, &[1; 32])
+            .unwrap();
+        journal
+            .record(
+                ActionKind::Archive,
+                "/data/logs/b.log",
+                Path::new("/mnt/old.tar"),
+                Some(Path::new("logs/b.log")),
+                &[2; 32],
+            )
+            .unwrap();
+        let path = journal.finish().unwrap().unwrap();
+
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, ActionKind::Move);
+        assert_eq!(entries[0].member, None);
+        assert_eq!(entries[1].member.as_deref(), Some("logs/b.log"));
+        assert_eq!(entries[1].sha256, "02".repeat(32));
+
+        assert_eq!(latest_pending(&dir).unwrap(), Some(path.clone()));
+        mark_undone(&path).unwrap();
+        assert_eq!(latest_pending(&dir).unwrap(), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_protect_list_blocks_selection() {
+        let protect = ProtectList::parse(
+            "# keep the database and key files\n/srv/db/\n\n  *.kdbx  \n/home/*/.ssh\n",
+        )
+        .unwrap();
+        assert_eq!(protect.matching_rule("/srv/db"), Some("/srv/db/"));
+        assert_eq!(protect.matching_rule("/srv/db/a/b.dat"), Some("/srv/db/"));
+        assert_eq!(protect.matching_rule("/home/me/.ssh/id_ed25519"), Some("/home/*/.ssh"));
+        assert_eq!(protect.matching_rule("/home/me/keys.kdbx"), Some("*.kdbx"));
+        assert_eq!(protect.matching_rule("/home/me/.sshd/x"), None);
+        assert!(ProtectList::parse("/srv/[db").is_err());
+
+        let selection = Selection {
+            protect,
+            ..Default::default()
+        };
+        let entries = vec![
+            ("/srv/db/base.dat".to_string(), 300),
+            ("/srv/logs/old.log".to_string(), 200),
+        ];
+        let selected = selection.select(&entries);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].0, "/srv/logs/old.log");
+    }
+
+    #[test]
+    fn test_scan_stamps_detect_changes() {
+        let path = std::env::temp_dir().join(format!("ferris-stamp-{}", std::process::id()));
+        std::fs::write(&path, b"scanned").unwrap();
+        let key = path.to_str().unwrap().to_string();
+
+        let mut stamps = ScanStamps::default();
+        assert!(stamps.verify_unchanged(&key).is_err());
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        stamps.insert(key.clone(), Fingerprint::from_metadata(&metadata));
+        assert!(stamps.verify_unchanged(&key).is_ok());
+
+        std::fs::write(&path, b"grew since the scan").unwrap();
+        let err = stamps.verify_unchanged(&key).unwrap_err();
+        assert!(err.to_string().contains("changed since the scan"));
+
+        stamps.retain(|kept| kept != key);
+        assert!(stamps.get(&key).is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_saved_scan_roundtrip_and_id_lookup() {
+        let entries = vec![
+            ("/data/a.iso".to_string(), 300),
+            ("/data/b.iso".to_string(), 200),
+        ];
+        let mut stamps = ScanStamps::default();
+        stamps.insert(
+            "/data/a.iso".to_string(),
+            Fingerprint { len: 300, modified: None, inode: Some((1, 2)) },
+        );
+        let scan = SavedScan::new(Path::new("/data"), &entries, &stamps);
+        assert_eq!(scan.entries[0].id, entry_id("/data/a.iso"));
+        assert!(scan.entries[0].fingerprint.is_some());
+        assert!(scan.entries[1].fingerprint.is_none());
+
+        let path = std::env::temp_dir().join(format!("ferris-scan-{}.json", std::process::id()));
+        scan.save(&path).unwrap();
+        let loaded = SavedScan::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, scan);
+
+        let b = entry_id("/data/b.iso");
+        let found = loaded.resolve(&[b.to_uppercase(), b.clone()]).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "/data/b.iso");
+        assert!(loaded.resolve(&["".to_string()]).is_err());
+        assert!(loaded.stamps().get("/data/a.iso").is_some());
+    }
+
+    #[test]
+    fn test_read_limiter_caps_concurrent_reads() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let limiter = ReadLimiter::new(0);
+        limiter.set_max(2);
+        let active = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = limiter.acquire();
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert_eq!(active.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_preview_sizes_small_subtrees_exactly() {
+        use filesize::PathExt;
+
+        let root = std::env::temp_dir().join(format!("ferris-preview-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        std::fs::create_dir_all(root.join("skipped")).unwrap();
+        std::fs::write(root.join("top.bin"), vec![1u8; 10_000]).unwrap();
+        std::fs::write(root.join("a/b/deep.bin"), vec![2u8; 50_000]).unwrap();
+        std::fs::write(root.join("skipped/big.bin"), vec![3u8; 90_000]).unwrap();
+
+        let expected: u64 = ["top.bin", "a/b/deep.bin"]
+            .iter()
+            .map(|file| root.join(file).size_on_disk().unwrap())
+            .sum();
+        let skip = [root.join("skipped")].into_iter().collect();
+        let estimate = estimate_subtree(&root, &skip);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(estimate.exhaustive);
+        assert_eq!(estimate.dirs_read, 3);
+        assert_eq!(estimate.estimated_bytes, expected);
+    }
+
+    #[test]
+    fn test_session_schema_matches_written_sessions() {
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../schemas/session.schema.json")).unwrap();
+        assert_eq!(schema["properties"]["schema_version"]["const"], SCHEMA_VERSION);
+
+        let scan = SavedScan::new(
+            Path::new("/data"),
+            &[("/data/a".to_string(), 1)],
+            &ScanStamps::default(),
+        );
+        let written = serde_json::to_value(&scan).unwrap();
+        let known = schema["properties"].as_object().unwrap();
+        for field in written.as_object().unwrap().keys() {
+            assert!(known.contains_key(field), "{} is missing from the schema", field);
+        }
+
+        // Unversioned sessions written before provenance are migrated on load
+        let old = r#"{"root": "/data", "host": "web", "entries": [
+            {"id": "abc123", "path": "/data/a", "size": 1, "fingerprint": null}
+        ]}"#;
+        let migrated = SavedScan::from_json(old).unwrap();
+        assert_eq!(migrated.schema_version, SCHEMA_VERSION);
+        assert_eq!(migrated.provenance.host.as_deref(), Some("web"));
+        assert_eq!(migrated.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_import_inventories() {
+        let collect = |format, input: &str| {
+            let mut files = Vec::new();
+            let skipped =
+                import_entries(format, input.as_bytes(), |path, size| files.push((path, size)))
+                    .unwrap();
+            (files, skipped)
+        };
+
+        // find lists files only; a line without a size is skipped
+        let (files, skipped) = collect(ImportFormat::Find, "5\t/a/x y\nbogus\n7\t/a/b/z\n");
+        assert_eq!(files, vec![("/a/x y".to_string(), 5), ("/a/b/z".to_string(), 7)]);
+        assert_eq!(skipped, 1);
+
+        // Excluded and special files are left out, dsize is preferred over asize
+        let ncdu = r#"[1, 2, {"progname": "ncdu"},
+            [{"name": "/srv", "asize": 4096},
+                {"name": "big", "asize": 100, "dsize": 4096},
+                [{"name": "logs"}, {"name": "a.log", "asize": 10}, {"name": "sock", "notreg": true}],
+                {"name": "skip", "excluded": "pattern"}]]"#;
+        let (files, skipped) = collect(ImportFormat::Ncdu, ncdu);
+        assert_eq!(
+            files,
+            vec![("/srv/big".to_string(), 4096), ("/srv/logs/a.log".to_string(), 10)]
+        );
+        assert_eq!(skipped, 0);
+
+        assert!(import_entries(ImportFormat::Ncdu, "[2, 0, {}]".as_bytes(), |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn test_message_catalogs_match() {
+        let ids = |catalog: &str| -> std::collections::BTreeSet<String> {
+            assert!(fluent_bundle::FluentResource::try_new(catalog.to_string()).is_ok());
+            catalog
+                .lines()
+                .filter_map(|line| line.split_once(" ="))
+                .map(|(id, _)| id.to_string())
+                .filter(|id| !id.starts_with([' ', '#']))
+                .collect()
+        };
+        let english = ids(include_str!("../locales/en/ferris-files.ftl"));
+        let spanish = ids(include_str!("../locales/es/ferris-files.ftl"));
+        assert_eq!(english, spanish);
+
+        assert_eq!(supported_language("es_MX.UTF-8"), Some("es"));
+        assert_eq!(supported_language("C"), None);
+        assert!(is_yes("Y\n"));
+    }
+
+    #[test]
+    fn test_porcelain_records_keep_their_shape() {
+        let hostile = "/data/tab\there/new\nline\r\\end";
+        let line = record("entry", &["3b1f0c", "42", "", hostile]);
+
+        assert!(!line.contains('\n') && !line.contains('\r'));
+        let fields: Vec<&str> = line.split('\t').collect();
+        assert_eq!(fields.len(), 5);
+        assert_eq!(fields[0], "entry");
+        assert_eq!(fields[3], "");
+        assert_eq!(fields[4], "/data/tab\\there/new\\nline\\r\\\\end");
+    }
+
+    #[test]
+    fn test_storage_health_clusters_hardware_errors() {
+        let health = StorageHealth::default();
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let timeout = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        assert_eq!(classify(&denied), None);
+
+        health.record(Path::new("/srv/private/key"), false, &denied);
+        health.record(Path::new("/srv/slow/a"), false, &timeout);
+        health.record(Path::new("/srv/slow/b"), false, &timeout);
+        assert!(health.suspects().is_empty());
+
+        #[cfg(unix)]
+        {
+            let eio = std::io::Error::from_raw_os_error(libc::EIO);
+            assert_eq!(classify(&eio), Some(HardwareError::Io));
+            health.record(Path::new("/srv/disk/bad"), true, &eio);
+            health.record(Path::new("/srv/disk/bad/x.bin"), false, &eio);
+            let suspects = health.suspects();
+            assert_eq!(suspects.len(), 1);
+            assert_eq!(suspects[0].path, Path::new("/srv/disk/bad"));
+            assert_eq!(suspects[0].counts.io_errors, 2);
+        }
+        #[cfg(not(unix))]
+        let _ = HardwareError::Io;
+    }
+
+    #[test]
+    fn test_network_mounts_fall_back_to_apparent_size() {
+        let fallbacks = SizeFallbacks::new(parse_mountinfo(
+            "22 1 8:2 / / rw - ext4 /dev/sda2 rw\n\
+             40 22 0:51 / /mnt/nas rw - nfs4 nas:/export rw\n\
+             41 40 0:52 / /mnt/nas/scratch rw - tmpfs tmpfs rw\n",
+        ));
+
+        // Local filesystems and files with blocks keep their size on disk
+        assert_eq!(fallbacks.allocated_or_apparent(Path::new("/srv/sparse"), 1 << 30, 0), 0);
+        assert_eq!(fallbacks.allocated_or_apparent(Path::new("/mnt/nas/a"), 100, 4096), 4096);
+        assert_eq!(fallbacks.allocated_or_apparent(Path::new("/mnt/nas/scratch/b"), 100, 0), 0);
+        assert!(fallbacks.notes().is_empty());
+
+        assert_eq!(fallbacks.allocated_or_apparent(Path::new("/mnt/nas/c"), 100, 0), 100);
+        assert_eq!(fallbacks.allocated_or_apparent(Path::new("/mnt/nas/d/e"), 200, 0), 200);
+        let notes = fallbacks.notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].mount.fs_type, "nfs4");
+        assert_eq!(notes[0].files, 2);
+    }
+
+    #[test]
+    fn test_apparent_sizes_rank_sparse_files_by_length() {
+        let path = std::env::temp_dir().join(format!("ferris-sparse-{}", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_len(64 << 20).unwrap();
+        let metadata = file.metadata().unwrap();
+
+        let fallbacks = SizeFallbacks::new(Vec::new()).with_apparent_sizes(true);
+        assert_eq!(fallbacks.size(&path, &metadata).unwrap(), 64 << 20);
+        #[cfg(unix)]
+        assert!(SizeFallbacks::new(Vec::new()).size(&path, &metadata).unwrap() < 64 << 20);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_categories_prefer_what_can_be_freed() {
+        assert_eq!(categories::classify(Path::new("/home/ann/.cache/thumbs/a.png")), Category::Caches);
+        assert_eq!(categories::classify(Path::new("/home/ann/VirtualBox VMs/kali/kali.vdi")), Category::VirtualMachines);
+        assert_eq!(categories::classify(Path::new("/srv/app/logs/2024-01.gz")), Category::Logs);
+        assert_eq!(categories::classify(Path::new("/home/ann/Downloads/setup.dmg")), Category::Packages);
+        assert_eq!(categories::classify(Path::new("/home/ann/Music/album/01.FLAC")), Category::UserMedia);
+
+        let mut totals = CategoryTotals::default();
+        totals.add(Category::Logs, 10);
+        totals.add(Category::Logs, 5);
+        totals.add(Category::Other, 1);
+        assert_eq!(totals.get(Category::Logs), (15, 2));
+        assert_eq!(totals.get(Category::Caches), (0, 0));
+        assert_eq!(totals.total_bytes(), 16);
+    }
+
+    #[test]
+    fn test_only_skipping_guards_change_the_settings_hash() {
+        let config = Config {
+            root_path: "/srv".into(),
+            num_entries: 10,
+            depth_guard: 256,
+            max_dir_entries: 1_000_000,
+            ..Default::default()
+        };
+        let tighter = Config {
+            depth_guard: 8,
+            ..config.clone()
+        };
+        assert_eq!(config.settings_hash(), tighter.settings_hash());
+
+        let skipping = Config {
+            skip_pathological: true,
+            ..tighter.clone()
+        };
+        assert_ne!(tighter.settings_hash(), skipping.settings_hash());
+        assert_ne!(
+            skipping.settings_hash(),
+            Config {
+                depth_guard: 16,
+                ..skipping.clone()
+            }
+            .settings_hash()
+        );
+    }
+
+    #[test]
+    fn test_recheck_finds_stale_entries() {
+        let dir = std::env::temp_dir().join(format!("ferris-recheck-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut stamps = ScanStamps::default();
+        let mut keys = Vec::new();
+        for name in ["kept", "rotated", "growing"] {
+            let path = dir.join(name);
+            std::fs::write(&path, b"scanned").unwrap();
+            let key = path.to_str().unwrap().to_string();
+            stamps.insert(key.clone(), Fingerprint::from_metadata(&std::fs::metadata(&path).unwrap()));
+            keys.push(key);
+        }
+        std::fs::remove_file(dir.join("rotated")).unwrap();
+        std::fs::write(dir.join("growing"), b"grew since the scan").unwrap();
+
+        let paths = keys.iter().map(String::as_str);
+        let (stale, unchecked) = stamps.recheck(paths, std::time::Duration::from_secs(60));
+        assert_eq!(unchecked, 0);
+        assert_eq!(stale.len(), 2);
+        assert_eq!(stale[&keys[1]], Staleness::Gone);
+        assert_eq!(stale[&keys[2]], Staleness::Resized(19));
+
+        // An exhausted budget leaves every entry unchecked
+        let (stale, unchecked) = stamps.recheck(keys.iter().map(String::as_str), std::time::Duration::ZERO);
+        assert!(stale.is_empty());
+        assert_eq!(unchecked, 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_size_delta_against_previous_session() {
+        assert_eq!(crate::size_delta(3072, Some(1024)), "+2.00 KB");
+        assert_eq!(crate::size_delta(1024, Some(3072)), "-2.00 KB");
+        assert_eq!(crate::size_delta(1024, Some(1024)), "unchanged");
+        assert_eq!(crate::size_delta(1024, None), "new");
+    }
+
+    #[test]
+    fn test_heap_backed_entries_match_the_sorted_vector() {
+        let max = HEAP_THRESHOLD + 8;
+        let mut top = TopEntries::new(max);
+
+        // 5000 distinct sizes in scrambled order, 7919 being coprime with 5000
+        for i in 0..5000u64 {
+            let size = (i * 7919) % 5000;
+            top.insert(format!("file_{}", size), size);
+        }
+        let entries = top.get_entries();
+        assert_eq!(entries.len(), max);
+        for (i, (path, size)) in entries.iter().enumerate() {
+            assert_eq!(*size, 4999 - i as u64);
+            assert_eq!(*path, format!("file_{}", size));
+        }
+
+        // Sorting for output does not stop further inserts, and repeated paths still replace
+        let smallest = 5000 - max as u64;
+        assert!(!top.insert("late".to_string(), smallest));
+        assert!(top.insert("file_4999".to_string(), 6000));
+        assert!(top.insert("file_4998".to_string(), 0));
+        let entries = top.get_entries();
+        assert_eq!(entries.len(), max);
+        assert_eq!(entries[0], ("file_4999".to_string(), 6000));
+        assert_eq!(entries[max - 1], ("file_4998".to_string(), 0));
+
+        // Among equal sizes the newest ranks first and the oldest is dropped first, as in a vector
+        assert!(top.insert("tie_old".to_string(), 4997));
+        assert!(top.insert("tie_new".to_string(), 4997));
+        let entries = top.get_entries();
+        let ties: Vec<&str> = entries[1..4].iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(ties, ["tie_new", "tie_old", "file_4997"]);
+        assert!(!entries.iter().any(|(path, _)| path == "file_4998"));
+    }
+
+    #[test]
+    fn test_concurrent_entries_match_a_single_collection() {
+        let concurrent = ConcurrentTopEntries::with_shards(50, 8);
+        let mut single = TopEntries::new(50);
+        let sizes: Vec<u64> = (0..2000u64).map(|i| (i * 7919) % 2000).collect();
+
+        rayon::scope(|scope| {
+            for chunk in sizes.chunks(250) {
+                let concurrent = &concurrent;
+                scope.spawn(move |_| {
+                    for size in chunk {
+                        concurrent.insert(format!("file_{}", size), *size);
+                    }
+                });
+            }
+        });
+        for size in &sizes {
+            single.insert(format!("file_{}", size), *size);
+        }
+        assert_eq!(concurrent.get_entries(), single.get_entries());
+
+        // A path always goes to the same shard, so repeated paths are still replaced
+        concurrent.insert("file_1999".to_string(), 1);
+        let entries = concurrent.get_entries();
+        assert_eq!(entries.len(), 50);
+        assert_eq!(entries[0], ("file_1998".to_string(), 1998));
+        assert!(!entries.iter().any(|(path, _)| path == "file_1999"));
+    }
+
+    #[test]
+    fn test_batch_inserts_match_single_inserts() {
+        for max in [5, 100, HEAP_THRESHOLD + 8] {
+            let mut batched = TopEntries::new(max);
+            let mut single = TopEntries::new(max);
+            let items: Vec<(String, u64)> = (0..5000u64)
+                .map(|i| (i * 7919) % 5000)
+                .map(|size| (format!("file_{}", size), size))
+                .collect();
+            for chunk in items.chunks(700) {
+                batched.insert_batch(chunk);
+                for (path, size) in chunk {
+                    single.insert(path.clone(), *size);
+                }
+                assert_eq!(batched.get_entries(), single.get_entries());
+            }
+            assert_eq!(batched.get_entries().len(), max);
+            assert!(batched.contains("file_4999"));
+            assert!(!batched.contains("file_0"));
+        }
+
+        // Repeated paths keep their latest size, even one that drops below the others
+        let mut top = TopEntries::new(3);
+        top.insert_batch(&[("a".to_string(), 100), ("b".to_string(), 80), ("c".to_string(), 60)]);
+        top.insert_batch(&[
+            ("d".to_string(), 90),
+            ("a".to_string(), 10),
+            ("d".to_string(), 70),
+            ("e".to_string(), 60),
+        ]);
+        assert_eq!(
+            top.get_entries(),
+            [("b".to_string(), 80), ("d".to_string(), 70), ("e".to_string(), 60)]
+        );
+        assert!(!top.contains("a"));
+    }
+
+    #[test]
+    fn test_threshold_tracks_the_smallest_kept_size() {
+        let mut top = TopEntries::new(3);
+        assert_eq!(top.threshold(), None);
+        top.insert_batch(&[("a".to_string(), 30), ("b".to_string(), 10)]);
+        assert_eq!(top.threshold(), None);
+        top.insert("c".to_string(), 20);
+        assert_eq!(top.threshold(), Some(10));
+        top.insert("d".to_string(), 40);
+        assert_eq!(top.threshold(), Some(20));
+
+        let mut heap = TopEntries::new(HEAP_THRESHOLD + 1);
+        for size in 0..=HEAP_THRESHOLD as u64 {
+            heap.insert(format!("file_{}", size), size + 100);
+        }
+        assert_eq!(heap.threshold(), Some(100));
+        heap.insert("big".to_string(), 5000);
+        assert_eq!(heap.threshold(), Some(101));
+
+        // The concurrent threshold is the lowest among shards, so it never hides a keeper
+        let concurrent = ConcurrentTopEntries::with_shards(2, 4);
+        let items: Vec<(String, u64)> = (0..400u64).map(|i| (format!("file_{}", i), i)).collect();
+        concurrent.insert_batch(&items);
+        let threshold = concurrent.threshold().unwrap();
+        let entries = concurrent.get_entries();
+        assert!(entries.iter().all(|(_, size)| *size > threshold));
+        assert_eq!(entries, [("file_399".to_string(), 399), ("file_398".to_string(), 398)]);
+    }
+
+    #[test]
+    fn test_equal_sizes_are_ordered_by_the_secondary_key() {
+        let older = "/y/b-older.img".to_string();
+        let newer = "/y/a-newer.img".to_string();
+        let missing = "/y/c-missing.img".to_string();
+        let now = std::time::SystemTime::now();
+        let modified = std::collections::HashMap::from([
+            (older.clone(), now - std::time::Duration::from_secs(3600)),
+            (newer.clone(), now),
+        ]);
+        let ranked = vec![
+            (missing.clone(), 4096),
+            (older.clone(), 4096),
+            ("/z/large.iso".to_string(), 8192),
+            (newer.clone(), 4096),
+        ];
+
+        let mut entries = ranked.clone();
+        order_entries(&mut entries, None, |_| None);
+        assert_eq!(entries, ranked);
+
+        // Files whose modification time is unknown sort after the others
+        order_entries(&mut entries, Some(ThenBy::Mtime), |path| modified.get(path).copied());
+        let paths: Vec<&str> = entries.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["/z/large.iso", newer.as_str(), older.as_str(), missing.as_str()]);
+
+        order_entries(&mut entries, Some(ThenBy::Name), |_| None);
+        let paths: Vec<&str> = entries.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["/z/large.iso", newer.as_str(), older.as_str(), missing.as_str()]);
+    }
+
+    #[test]
+    fn test_ties_at_the_cutoff_are_left_to_the_secondary_key() {
+        // Five files of one size straddle `-n 3` below a larger one, whatever order they come in
+        let tied = ["/t/e", "/t/c", "/t/a", "/t/d", "/t/b"];
+        let expected = [("/t/big".to_string(), 200), ("/t/a".to_string(), 100), ("/t/b".to_string(), 100)];
+        for rotation in 0..tied.len() {
+            let top = ConcurrentTopEntries::with_shards(3, 2).with_ties();
+            top.insert("/t/big".to_string(), 200);
+            for i in 0..tied.len() {
+                // As process_batch admits them
+                if top.threshold().is_none_or(|min| 100 >= min) {
+                    top.insert_batch(&[(tied[(i + rotation) % tied.len()].to_string(), 100)]);
+                }
+            }
+            let mut entries = top.get_entries();
+            assert_eq!(entries.len(), 6);
+            order_entries(&mut entries, Some(ThenBy::Path), |_| None);
+            entries.truncate(3);
+            assert_eq!(entries, expected);
+        }
+
+        // Without a secondary key the first ones sized are kept, as before
+        let top = ConcurrentTopEntries::with_shards(3, 1);
+        top.insert_batch(&tied.map(|path| (path.to_string(), 100)));
+        assert!(!top.keeps_ties());
+        assert_eq!(top.get_entries().len(), 3);
+
+        // Heap-backed collections keep them as well, until larger entries fill them up
+        let max = HEAP_THRESHOLD + 1;
+        let mut heap = TopEntries::new(max).with_ties();
+        for i in 0..max + 10 {
+            assert!(heap.insert(format!("/t/{:05}", i), 100));
+        }
+        heap.insert("/t/big".to_string(), 200);
+        assert_eq!(heap.get_entries().len(), max + 11);
+        assert!(heap.contains("/t/00000"));
+        for i in 0..max - 1 {
+            heap.insert(format!("/t/large-{:05}", i), 300);
+        }
+        assert_eq!(heap.get_entries().len(), max);
+        assert!(!heap.contains("/t/00000"));
+    }
+
+    #[test]
+    fn test_reports_share_one_pass_over_the_files() {
+        let files: Vec<FileInfo> = [
+            ("/srv/db/wal/0001", 300),
+            ("/srv/db/base.dat", 200),
+            ("/srv/www/index.html", 5),
+            ("/srv/README", 1),
+        ]
+        .into_iter()
+        .map(|(path, size)| FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None })
+        .collect();
+
+        // Only the reports besides the largest files need an aggregator, in the requested order
+        let config = Config {
+            reports: vec![ReportKind::ByExt, ReportKind::TopFiles, ReportKind::TopDirs],
+            num_entries: 10,
+            ..Default::default()
+        };
+        assert_eq!(aggregators(&config, Path::new("/srv")).len(), 2);
+
+        let mut dirs = DirTotals::new(Path::new("/srv"), 10);
+        let mut extensions = ExtensionTotals::new(10);
+        let mut histogram = SizeHistogram::default();
+        let mut top = TopFiles::new(2);
+        for file in &files {
+            let fan_out: [&mut dyn Aggregator; 4] =
+                [&mut dirs, &mut extensions, &mut histogram, &mut top];
+            for aggregator in fan_out {
+                aggregator.observe(file);
+            }
+        }
+        assert_eq!(
+            dirs.top(),
+            [
+                (Path::new("/srv/db").to_path_buf(), 500),
+                (Path::new("/srv/db/wal").to_path_buf(), 300),
+                (Path::new("/srv/www").to_path_buf(), 5),
+            ]
+        );
+        assert_eq!(
+            extensions.top(),
+            [
+                ("".to_string(), 301, 2),
+                ("dat".to_string(), 200, 1),
+                ("html".to_string(), 5, 1),
+            ]
+        );
+        assert_eq!(extensions.largest(""), Some(("/srv/db/wal/0001", 300)));
+        assert_eq!(extensions.largest("iso"), None);
+        assert!(extensions.finish().rows[0].text.contains("/srv/db/wal/0001"));
+
+        // Imported inventories have no scan root: every directory but the filesystem's counts
+        let mut imported = DirTotals::new(Path::new(""), 1);
+        for file in &files {
+            imported.observe(file);
+        }
+        assert_eq!(imported.top(), [(Path::new("/srv").to_path_buf(), 506)]);
+
+        // Every aggregator renders a section, printable for people or as porcelain records
+        assert_eq!(histogram.bucket(0), (506, 4));
+        let section = histogram.finish();
+        assert_eq!(section.rows.len(), HISTOGRAM_BOUNDS.len() + 1);
+        assert_eq!(section.rows[0].kind, "histogram");
+        assert_eq!(section.rows[0].fields, ["4096", "506", "4"]);
+        assert_eq!(section.rows[HISTOGRAM_BOUNDS.len()].fields[0], "");
+        let section = top.finish();
+        assert!(section.heading.is_none());
+        let paths: Vec<&str> = section.rows.iter().map(|row| row.fields[3].as_str()).collect();
+        assert_eq!(paths, ["/srv/db/wal/0001", "/srv/db/base.dat"]);
+        assert_eq!(dirs.finish().rows[0].fields, ["500", "/srv/db"]);
+        assert_eq!(extensions.finish().rows[1].fields, ["200", "1", "dat", "200", "/srv/db/base.dat"]);
+
+        let mut config = Config::default();
+        assert!(config.wants_report(ReportKind::TopFiles));
+        assert!(!config.wants_report(ReportKind::ByExt));
+        config.reports = vec![ReportKind::ByExt];
+        assert!(!config.wants_report(ReportKind::TopFiles));
+    }
+
+    #[test]
+    fn test_recent_writes_are_measured_from_the_scan() {
+        use std::time::Duration;
+
+        let scan_end = std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let entry = |modified: Option<std::time::SystemTime>| crate::results::SavedEntry {
+            id: "3b1f0c".to_string(),
+            path: "/var/log/app.log".to_string(),
+            size: 4 << 30,
+            fingerprint: Some(Fingerprint { len: 4 << 30, modified, inode: None }),
+            host: None,
+        };
+        let day = parse_duration("24h").unwrap();
+
+        assert!(entry(Some(scan_end - Duration::from_secs(3_600))).modified_within(day, scan_end));
+        assert!(!entry(Some(scan_end - Duration::from_secs(2 * 86_400))).modified_within(day, scan_end));
+        // Clock skew can put a write after the scan's end; it is still recent
+        assert!(entry(Some(scan_end + Duration::from_secs(60))).modified_within(day, scan_end));
+        assert!(!entry(None).modified_within(day, scan_end));
+    }
+
+    #[test]
+    fn test_alert_rules_check_size_growth_and_recency() {
+        use std::collections::HashMap;
+        use std::time::Duration;
+
+        let rules = AlertRules::parse(
+            "# comment\n\
+             /var/log size>=1GB within=1h syslog\n\
+             *.dump growth>=2GB webhook=https://hooks.example.com/x exit\n",
+        )
+        .unwrap();
+        assert_eq!(rules.rules.len(), 2);
+        assert_eq!(rules.rules[1].actions[0], AlertAction::Webhook("https://hooks.example.com/x".to_string()));
+        assert!(AlertRules::parse("/var/log sizes>=1GB").is_err());
+
+        let scan_end = std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let stamp = |age: u64| Fingerprint { len: 0, modified: Some(scan_end - Duration::from_secs(age)), inode: None };
+        let mut stamps = ScanStamps::default();
+        stamps.insert("/var/log/fresh.log".to_string(), stamp(60));
+        stamps.insert("/var/log/old.log".to_string(), stamp(7_200));
+        let entries = vec![
+            ("/var/log/fresh.log".to_string(), 3_000_000_000),
+            ("/var/log/old.log".to_string(), 3_000_000_000),
+            ("/var/log/small.log".to_string(), 1_000),
+            ("/srv/db/grown.dump".to_string(), 9_000_000_000),
+            ("/srv/db/steady.dump".to_string(), 9_000_000_000),
+            ("/srv/db/new.dump".to_string(), 2_500_000_000),
+        ];
+        let previous = HashMap::from([
+            ("/srv/db/grown.dump".to_string(), 6_000_000_000),
+            ("/srv/db/steady.dump".to_string(), 8_500_000_000),
+        ]);
+
+        let fired = |previous: Option<&HashMap<String, u64>>| {
+            rules
+                .evaluate(&entries, previous, &stamps, scan_end)
+                .into_iter()
+                .map(|alert| (alert.rule, alert.path))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            fired(Some(&previous)),
+            [
+                (0, "/var/log/fresh.log".to_string()),
+                (1, "/srv/db/grown.dump".to_string()),
+                (1, "/srv/db/new.dump".to_string()),
+            ]
+        );
+        // Growth cannot be told without a previous session
+        assert_eq!(fired(None), [(0, "/var/log/fresh.log".to_string())]);
+    }
+
+    #[test]
+    fn test_interrupted_session_save_is_recovered() {
+        let dir = std::env::temp_dir().join(format!("ff-recover-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("last-scan.json");
+        let old = SavedScan::new(Path::new("/data"), &[("/data/old.iso".to_string(), 1)], &ScanStamps::default());
+        let new = SavedScan::new(Path::new("/data"), &[("/data/new.iso".to_string(), 2)], &ScanStamps::default());
+        old.save(&path).unwrap();
+        assert!(!dir.join("last-scan.json.partial").exists());
+
+        // A crash after the new document was written but before it replaced the old one
+        new.save(&dir.join("staged.json")).unwrap();
+        std::fs::rename(dir.join("staged.json"), dir.join("last-scan.json.partial")).unwrap();
+        assert_eq!(SavedScan::load(&path).unwrap().entries[0].path, "/data/new.iso");
+        assert!(!dir.join("last-scan.json.partial").exists());
+        assert_eq!(SavedScan::load(&path).unwrap().entries[0].path, "/data/new.iso");
+
+        // A crash halfway through writing leaves the earlier results
+        std::fs::write(dir.join("last-scan.json.partial"), "{\"schema_version\": 2, \"entr").unwrap();
+        assert_eq!(SavedScan::load(&path).unwrap().entries[0].path, "/data/new.iso");
+        assert!(!dir.join("last-scan.json.partial").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_progress_feed_is_throttled_and_sends_changed_top_entries() {
+        let top = vec![("/data/big.iso".to_string(), 200)];
+        let mut out = Vec::new();
+        {
+            let mut feed = ProgressFeed::new(&mut out);
+            feed.update(10, 10, 0, 1000, || top.clone());
+            // Within the interval nothing is sent and the top entries are not even collected
+            feed.update(20, 20, 0, 2000, || unreachable!());
+            std::thread::sleep(crate::progress::PROGRESS_INTERVAL);
+            // Unchanged top entries are not repeated
+            feed.update(30, 29, 1, 2900, || top.clone());
+            feed.finish(30, 29, 1, 2900);
+            feed.update(40, 40, 0, 4000, || unreachable!());
+        }
+        let events: Vec<serde_json::Value> = std::str::from_utf8(&out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let kinds: Vec<&str> = events.iter().map(|event| event["event"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["progress", "top", "progress", "done"]);
+        assert_eq!(events[2]["files"], 30);
+        assert_eq!(events[2]["bytes"], 2900);
+        assert_eq!(events[3]["failed"], 1);
+        assert_eq!(events[3]["bytes"], 2900);
+    }
+
+    #[test]
+    fn test_json_report_annotates_entries_like_the_text_output() {
+        use std::collections::HashMap;
+
+        let entries = vec![
+            ("/data/big.iso".to_string(), 300),
+            ("/data/new.iso".to_string(), 200),
+            ("/data/gone.log".to_string(), 100),
+        ];
+        let stale = HashMap::from([
+            ("/data/big.iso".to_string(), Staleness::Resized(350)),
+            ("/data/gone.log".to_string(), Staleness::Gone),
+        ]);
+        let previous = HashMap::from([("/data/big.iso".to_string(), 250)]);
+        let mut report = JsonReport::new("/data");
+        report.add_entries(&entries, &stale, Some(&previous));
+        report.add_section(ExtensionTotals::new(5).finish());
+        report.summary.bytes = 4096;
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["summary"]["bytes"], 4096);
+        let ranked = json["entries"].as_array().unwrap();
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0]["id"], entry_id("/data/big.iso"));
+        assert_eq!(ranked[0]["previous_size"], 250);
+        assert_eq!(ranked[0]["stale"], "resized");
+        assert_eq!(ranked[0]["current_size"], 350);
+        // A new entrant has a null previous size, unlike a scan without a previous session
+        assert!(ranked[1]["previous_size"].is_null() && ranked[1].get("previous_size").is_some());
+        assert!(ranked[1].get("stale").is_none());
+        assert_eq!(ranked[2]["rank"], 3);
+        assert_eq!(ranked[2]["stale"], "gone");
+        assert!(ranked[2].get("current_size").is_none());
+        assert_eq!(json["reports"].as_array().unwrap().len(), 1);
+
+        let mut without_history = JsonReport::new("/data");
+        without_history.add_entries(&entries, &HashMap::new(), None);
+        let json: serde_json::Value = serde_json::from_str(&without_history.to_json()).unwrap();
+        assert!(json["entries"][1].get("previous_size").is_none());
+        assert!(json.get("reports").is_none());
+    }
+
+    #[test]
+    fn test_wire_streams_round_trip_compactly() {
+        let entries: Vec<WireEntry> = (0..ENTRIES_PER_FRAME as u64 + 10)
+            .map(|i| WireEntry {
+                path: format!("/srv/data/projects/alpha/shard-{:06}/part.parquet", i),
+                size: 1_000_000 - i,
+                modified: (i % 2 == 0).then_some(1_700_000_000 + i),
+            })
+            .collect();
+        let hello = Message::Hello {
+            host: Some("db-01".to_string()),
+            root: "/srv".to_string(),
+            tool_version: "0.1.2".to_string(),
+            started_at: 1_700_000_000,
+        };
+        let done = Message::Done { files: 9000, processed: 8990, failed: 10, finished_at: 1_700_000_060 };
+
+        let mut stream = Vec::new();
+        let mut writer = FrameWriter::new(&mut stream).unwrap();
+        writer.send(&hello).unwrap();
+        writer.send_entries(entries.clone()).unwrap();
+        writer.send(&done).unwrap();
+        let raw_len: usize = entries.iter().map(|entry| entry.path.len() + 16).sum();
+        assert!(stream.len() * 10 < raw_len, "{} bytes for {} raw", stream.len(), raw_len);
+
+        // A frame of a kind added by a newer agent is skipped
+        stream.extend_from_slice(&[200, 0, 0, 0, 0]);
+
+        let mut reader = FrameReader::new(stream.as_slice()).unwrap();
+        let mut messages = Vec::new();
+        while let Some(message) = reader.read_message().unwrap() {
+            messages.push(message);
+        }
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0], hello);
+        let received: Vec<WireEntry> = messages[1..3]
+            .iter()
+            .flat_map(|message| match message {
+                Message::Entries(entries) => entries.clone(),
+                _ => panic!("expected entries"),
+            })
+            .collect();
+        assert_eq!(received, entries);
+        assert_eq!(messages[3], done);
+
+        let mut newer = stream.clone();
+        newer[3] = WIRE_VERSION + 1;
+        assert!(FrameReader::new(newer.as_slice()).is_err());
+        assert!(FrameReader::new(&b"9\t/srv/a\n"[..]).is_err());
+
+        let skipped = import_entries(ImportFormat::Wire, stream.as_slice(), |_, _| {}).unwrap();
+        assert_eq!(skipped, 0);
+        let cut = &stream[..stream.len() / 2];
+        assert!(import_entries(ImportFormat::Wire, cut, |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn test_fleet_reads_a_remote_scan_into_a_session() {
+        let hosts = parse_hosts("web-01\n  # staging\n\nops@db-01:/srv/pg\n").unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[1].destination, "ops@db-01");
+        assert_eq!(hosts[1].directory.as_deref(), Some("/srv/pg"));
+
+        let mut stream = Vec::new();
+        let mut writer = FrameWriter::new(&mut stream).unwrap();
+        writer
+            .send(&Message::Hello {
+                host: Some("db-01".to_string()),
+                root: "/srv/pg".to_string(),
+                tool_version: "0.1.2".to_string(),
+                started_at: 1_700_000_000,
+            })
+            .unwrap();
+        writer
+            .send_entries(vec![
+                WireEntry { path: "/srv/pg/base/16384".to_string(), size: 900, modified: Some(1_700_000_000) },
+                WireEntry { path: "/srv/pg/wal/0001".to_string(), size: 300, modified: None },
+            ])
+            .unwrap();
+        let truncated = stream.clone();
+        let mut done = Vec::new();
+        FrameWriter::new(&mut done)
+            .unwrap()
+            .send(&Message::Done { files: 40, processed: 39, failed: 1, finished_at: 1_700_000_030 })
+            .unwrap();
+        // Frames follow the 4 byte preamble
+        stream.extend_from_slice(&done[4..]);
+
+        let scan = read_scan(stream.as_slice()).unwrap();
+        assert_eq!((scan.files, scan.failed), (40, 1));
+        assert_eq!(scan.session.provenance.host.as_deref(), Some("db-01"));
+        assert_eq!(scan.session.provenance.finished_at, 1_700_000_030);
+        assert_eq!(scan.session.entries.len(), 2);
+        assert_eq!(scan.session.entries[0].id, entry_id("/srv/pg/base/16384"));
+        assert!(scan.session.entries[0].fingerprint.is_some());
+        assert!(scan.session.entries[1].fingerprint.is_none());
+
+        // An agent that dies before its done frame is a failed host, not an empty one
+        let error = read_scan(truncated.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_tags_label_results_and_total_every_file() {
+        use std::collections::HashMap;
+
+        let tags = TagMap::parse(
+            "/srv/analytics/exports backups\n/srv/analytics team-analytics\n*.bak backups\n",
+        )
+        .unwrap();
+        let mut totals = TagTotals::new(tags.clone(), 10);
+        let files = [
+            ("/srv/analytics/model.bin", 600),
+            ("/srv/analytics/exports/2024.csv", 300),
+            ("/srv/db/nightly.bak", 200),
+            ("/srv/media/film.m4v", 100),
+        ];
+        for (path, size) in files {
+            totals.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        let rows = totals.finish().rows;
+        assert_eq!(rows[0].kind, "tag-total");
+        assert_eq!(rows[0].fields, ["600", "1", "team-analytics"]);
+        assert_eq!(rows[1].fields, ["500", "2", "backups"]);
+        assert_eq!(rows[2].fields, ["100", "1", ""]);
+
+        let entries: Vec<(String, u64)> =
+            files.iter().map(|(path, size)| (path.to_string(), *size)).collect();
+        let mut report = JsonReport::new("/srv");
+        report.add_entries(&entries, &HashMap::new(), None);
+        report.set_tags(&tags);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["entries"][1]["tag"], "backups");
+        assert!(json["entries"][3].get("tag").is_none());
+    }
+
+    #[test]
+    fn test_ownership_mapping_joins_owners_into_results() {
+        use std::collections::HashMap;
+
+        let csv = "Team,Prefix,Cost_Center,Notes\r\n\
+                   platform,/srv,CC-1000,\"shared, \"\"legacy\"\"\"\r\n\
+                   analytics,/srv/analytics,CC-1001,\r\n";
+        let owners = OwnershipMap::parse_csv(csv).unwrap();
+        let analytics = owners.owner("/srv/analytics/model.bin").unwrap();
+        assert_eq!(analytics.team.as_deref(), Some("analytics"));
+        assert_eq!(analytics.owner, None);
+        assert_eq!(owners.owner("/srv/analyticsx/a").unwrap().team.as_deref(), Some("platform"));
+        assert!(owners.owner("/home/a").is_none());
+
+        assert!(OwnershipMap::parse_csv("prefix,team\n/srv,\"ops\n").is_err());
+        assert!(OwnershipMap::parse_csv("prefix,team\n/srv,\n").is_err());
+        assert!(OwnershipMap::parse_json(r#"[{"team": "ops"}]"#).is_err());
+        let json = OwnershipMap::parse_json(r#"[{"prefix": "/srv", "cost-center": "CC-9"}]"#).unwrap();
+        assert_eq!(json.owner("/srv/a").unwrap().label(), "CC-9");
+
+        let mut totals = OwnerTotals::new(owners.clone(), 10);
+        for (path, size) in [("/srv/analytics/a", 500), ("/srv/b", 300), ("/srv/analytics/c", 100), ("/tmp/d", 1)] {
+            totals.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        let rows = totals.finish().rows;
+        assert_eq!(rows[0].kind, "owner-total");
+        assert_eq!(rows[0].fields, ["600", "2", "CC-1001", "analytics", ""]);
+        assert_eq!(rows[1].fields, ["300", "1", "CC-1000", "platform", ""]);
+        assert_eq!(rows[2].fields, ["1", "1", "", "", ""]);
+
+        let mut report = JsonReport::new("/srv");
+        report.add_entries(&[("/srv/b".to_string(), 300), ("/tmp/d".to_string(), 1)], &HashMap::new(), None);
+        report.set_owners(&owners);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["entries"][0]["ownership"]["cost_center"], "CC-1000");
+        assert!(json["entries"][0]["ownership"].get("owner").is_none());
+        assert!(json["entries"][1].get("ownership").is_none());
+    }
+
+    #[test]
+    fn test_costs_are_summed_per_file_at_each_tier() {
+        let costs = CostModel::parse("/mnt/archive 0.004\n", Some(0.1)).unwrap();
+        let gb = 1u64 << 30;
+        let files = [("/mnt/archive/a.tar", 100 * gb), ("/srv/b.tar", 10 * gb), ("/srv/c.log", 5 * gb)];
+
+        let mut extensions = ExtensionTotals::new(10).with_costs(costs.clone());
+        let mut total = CostTotal::new(costs.clone());
+        for (path, size) in files {
+            let file = FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None };
+            extensions.observe(&file);
+            total.observe(&file);
+        }
+        // 100 GB archived at 0.004 plus 10 GB at the default rate
+        assert!(extensions.finish().rows[0].text.contains("1.40/month  "));
+        assert_eq!(total.finish().rows[0].fields, ["1.9000", (115 * gb).to_string().as_str()]);
+
+        // With a rate, every report also gets the total of the scan
+        let config = Config {
+            reports: vec![ReportKind::ByExt],
+            costs,
+            ..Default::default()
+        };
+        assert_eq!(aggregators(&config, Path::new("/")).len(), 2);
+        assert!(ExtensionTotals::new(10).finish().rows.is_empty());
+        assert!(!CostModel::default().is_enabled());
+    }
+
+    #[test]
+    fn test_ncdu_export_keeps_the_whole_tree() {
+        let mut tree = ScanTree::new(Path::new("/srv"));
+        for (path, size) in [
+            ("/srv/db/wal/0001", 300),
+            ("/srv/db/base.dat", 200),
+            ("/srv/www/\"quoted\" name.html", 5),
+            ("/srv/README", 1),
+            ("/elsewhere/ignored", 7),
+        ] {
+            tree.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        assert!(tree.finish().rows.is_empty());
+
+        let mut export = Vec::new();
+        tree.write_ncdu(&mut export, 1700000000).unwrap();
+        let document: serde_json::Value = serde_json::from_slice(&export).unwrap();
+        assert_eq!(document[0], 1);
+        assert_eq!(document[1], 2);
+        assert_eq!(document[2]["progname"], "ferris-files");
+        assert_eq!(document[2]["timestamp"], 1700000000);
+        // The root is named by its path, directories are arrays and files carry their disk usage
+        let root = &document[3];
+        assert_eq!(root[0]["name"], "/srv");
+        assert_eq!(root[1][0]["name"], "db");
+        assert_eq!(root[1][1][0]["name"], "wal");
+        assert_eq!(root[1][1][1], serde_json::json!({"name": "0001", "dsize": 300}));
+        assert_eq!(root[1][2], serde_json::json!({"name": "base.dat", "dsize": 200}));
+        assert_eq!(root[2][1]["name"], "\"quoted\" name.html");
+        assert_eq!(root[3]["name"], "README");
+        assert_eq!(root.as_array().unwrap().len(), 4);
+
+        let mut files = Vec::new();
+        let skipped = import_entries(ImportFormat::Ncdu, &export[..], |path, size| {
+            files.push((path, size))
+        })
+        .unwrap();
+        assert_eq!(skipped, 0);
+        assert_eq!(files.len(), 4);
+        assert!(files.contains(&("/srv/www/\"quoted\" name.html".to_string(), 5)));
+        assert!(files.contains(&("/srv/db/wal/0001".to_string(), 300)));
+    }
+
+    #[test]
+    fn test_retention_simulation_counts_old_matching_files_per_directory() {
+        let day = std::time::Duration::from_secs(86_400);
+        let now = std::time::SystemTime::now();
+        let policy = RetentionPolicy::new(
+            Some(180 * day),
+            &["*.bak".to_string(), "/srv/dumps".to_string()],
+        )
+        .unwrap();
+        let costs = CostModel::parse("", Some(1.0)).unwrap();
+        let mut simulation = RetentionSimulation::new(policy, 10, now).with_costs(costs);
+        for (path, size, age) in [
+            ("/srv/db/old.bak", 3 << 30, Some(365)),
+            ("/srv/db/recent.bak", 5 << 30, Some(10)),
+            ("/srv/db/base.dat", 7 << 30, Some(400)),
+            ("/srv/dumps/2023/01.sql", 1 << 30, Some(500)),
+            ("/srv/dumps/2023/02.sql", 1 << 30, Some(470)),
+            ("/srv/www/site.bak", 1 << 30, None),
+        ] {
+            simulation.observe(&FileInfo {
+                path: path.to_string(),
+                size,
+                modified: age.map(|days| now - days * day),
+                uid: None,
+                accessed: None,
+            });
+        }
+
+        assert_eq!(simulation.reclaimed(), (5 << 30, 3));
+        assert_eq!(
+            simulation.top(),
+            [
+                (Path::new("/srv/db").to_path_buf(), 3 << 30, 1),
+                (Path::new("/srv/dumps/2023").to_path_buf(), 2 << 30, 2),
+            ]
+        );
+        let rows = simulation.finish().rows;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1].kind, "retention-dir");
+        assert!(rows[0].text.contains("3.00/month"));
+        assert_eq!(rows[2].kind, "retention-total");
+        assert_eq!(
+            rows[2].fields,
+            [(5u64 << 30).to_string(), "3".to_string(), (18u64 << 30).to_string(), "6".to_string()]
+        );
+        assert!(rows[2].text.contains("5.00/month"));
+    }
+
+    #[test]
+    fn test_dir_ages_split_directories_into_age_buckets() {
+        let now = std::time::SystemTime::now();
+        let day = std::time::Duration::from_secs(86_400);
+        let mut ages = DirAges::new(Path::new("/srv"), 2, now);
+        for (path, size, modified) in [
+            // A bound itself still counts as the younger bucket
+            ("/srv/db/recent", 10, Some(now - AGE_BOUNDS[0])),
+            ("/srv/db/future", 1, Some(now + day)),
+            ("/srv/db/year", 100, Some(now - AGE_BOUNDS[1])),
+            ("/srv/db/old/dump", 1000, Some(now - 2 * AGE_BOUNDS[1])),
+            ("/srv/db/unknown", 5, None),
+            ("/srv/www/index.html", 3, Some(now)),
+            ("/srv/top-level-file", 50, Some(now)),
+        ] {
+            ages.observe(&FileInfo { path: path.to_string(), size, modified, uid: None, accessed: None });
+        }
+
+        assert_eq!(
+            ages.top(),
+            [
+                (Path::new("/srv/db").to_path_buf(), 1116, [11, 100, 1000]),
+                (Path::new("/srv/db/old").to_path_buf(), 1000, [0, 0, 1000]),
+            ]
+        );
+        let rows = ages.finish().rows;
+        assert_eq!(rows[0].kind, "dir-age");
+        assert_eq!(rows[0].fields, ["1116", "11", "100", "1000", "/srv/db"]);
+        assert!(rows[1].text.contains("    0%     0% █ 100%  /srv/db/old"));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_export_streams_row_groups() {
+        use crate::export::{ParquetExport, PARQUET_ROW_GROUP};
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::RowAccessor;
+
+        let path = std::env::temp_dir().join(format!("ferris-export-{}.parquet", std::process::id()));
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1_700_000_000_123);
+        let mut export = ParquetExport::new(
+            std::fs::File::create(&path).unwrap(),
+            Some("db-01"),
+            "/srv",
+        )
+        .unwrap();
+        for index in 0..PARQUET_ROW_GROUP + 2 {
+            export.observe(&FileInfo {
+                path: format!("/srv/data/{}", index),
+                size: index as u64,
+                modified: (index % 2 == 0).then_some(modified),
+                uid: None,
+                accessed: None,
+            });
+        }
+        let (_, rows) = export.close().unwrap();
+        assert_eq!(rows, PARQUET_ROW_GROUP as u64 + 2);
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.num_row_groups(), 2);
+        assert_eq!(metadata.row_group(1).num_rows(), 2);
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .skip(PARQUET_ROW_GROUP)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(rows[0].get_string(0).unwrap(), "db-01");
+        assert_eq!(rows[0].get_string(1).unwrap(), "/srv");
+        assert_eq!(rows[0].get_string(2).unwrap(), &format!("/srv/data/{}", PARQUET_ROW_GROUP));
+        assert_eq!(rows[0].get_ulong(3).unwrap(), PARQUET_ROW_GROUP as u64);
+        assert_eq!(rows[0].get_timestamp_millis(4).unwrap(), 1_700_000_000_123);
+        assert!(rows[1].get_timestamp_millis(4).is_err());
+        assert!(rows[1].get_string(5).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_drill_follows_the_largest_child_down_to_a_file() {
+        let mut tree = DrillTree::new(Path::new("/srv"));
+        for (path, size) in [
+            ("/srv/db/wal/0001", 300),
+            ("/srv/db/wal/0002", 250),
+            ("/srv/db/base.dat", 600),
+            ("/srv/www/index.html", 5),
+            ("/elsewhere/ignored", 9000),
+        ] {
+            tree.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        assert_eq!(tree.size(Path::new("/srv")), 1155);
+        // A file larger than every sibling directory is the largest child
+        assert_eq!(
+            tree.largest_child(Path::new("/srv/db")),
+            Some(Child { path: "/srv/db/base.dat".into(), size: 600, is_dir: false })
+        );
+        assert_eq!(tree.largest_child(Path::new("/srv/db/base.dat")), None);
+
+        // Enter descends, the walk stops at the file
+        let mut out = Vec::new();
+        let end = drill_down(&tree, "\n\n".as_bytes(), &mut out, None).unwrap();
+        assert_eq!(end, Path::new("/srv/db"));
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("/srv/db/\n"));
+        assert!(out.contains("/srv/db/base.dat\n"));
+        assert_eq!(out.matches("[Y/n]").count(), 1);
+
+        // Declining, running out of input and the level limit all stop at the current directory
+        assert_eq!(drill_down(&tree, "n\n".as_bytes(), Vec::new(), None).unwrap(), Path::new("/srv"));
+        assert_eq!(drill_down(&tree, "".as_bytes(), Vec::new(), None).unwrap(), Path::new("/srv"));
+        assert_eq!(drill_down(&tree, "y\n".as_bytes(), Vec::new(), Some(0)).unwrap(), Path::new("/srv"));
+    }
+
+    #[test]
+    fn test_snapshot_diff_reports_directories_and_files() {
+        let mut old = Snapshot::new("/srv", 1700000000);
+        let mut new = Snapshot::new("/srv", 1700600000);
+        for (path, size) in [("/srv/db/base", 200), ("/srv/db/wal/1", 300), ("/srv/tmp/x", 50), ("/srv/same", 9)] {
+            old.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        for (path, size) in [("/srv/db/base", 100), ("/srv/db/wal/1", 300), ("/srv/db/wal/2", 400), ("/srv/same", 9)] {
+            new.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+        }
+
+        let path = std::env::temp_dir().join(format!("ferris-snapshot-{}.ffs", std::process::id()));
+        old.save(&path).unwrap();
+        assert!(Snapshot::is_snapshot(&path));
+        let old = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (directories, files) = snapshot::diff(&old, &new);
+        let summary = |changes: &[snapshot::SnapshotChange]| {
+            changes
+                .iter()
+                .map(|change| (change.kind(), change.path.clone(), change.delta()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary(&directories),
+            [
+                ("grew", "/srv/db/wal".to_string(), 400),
+                ("grew", "/srv/db".to_string(), 300),
+                ("grew", "/srv".to_string(), 250),
+                ("vanished", "/srv/tmp".to_string(), -50),
+            ]
+        );
+        assert_eq!(
+            summary(&files),
+            [
+                ("appeared", "/srv/db/wal/2".to_string(), 400),
+                ("shrank", "/srv/db/base".to_string(), -100),
+                ("vanished", "/srv/tmp/x".to_string(), -50),
+            ]
+        );
+        assert!(directories.iter().all(|change| change.is_dir));
+        assert_eq!(files[0].before, None);
+        assert_eq!(files[2].after, None);
+    }
+
+    #[test]
+    fn test_session_pages_through_more_candidates() {
+        let stamps = ScanStamps::default();
+        let entries = [("/data/a".to_string(), 40)];
+        let more = [("/data/b".to_string(), 30), ("/data/c".to_string(), 20), ("/data/d".to_string(), 10)];
+        let scan = SavedScan::new(Path::new("/data"), &entries, &stamps).with_more(&more, &stamps);
+
+        let path = std::env::temp_dir().join(format!("ferris-more-{}.json", std::process::id()));
+        scan.save(&path).unwrap();
+        let mut loaded = SavedScan::load(&path).unwrap();
+        assert_eq!(loaded.more.len(), 3);
+        let page: Vec<String> = loaded.next_page(2).iter().map(|entry| entry.path.clone()).collect();
+        assert_eq!(page, ["/data/b", "/data/c"]);
+        loaded.save(&path).unwrap();
+
+        // The shown page resolves by ID and the next call continues after it
+        let mut loaded = SavedScan::load(&path).unwrap();
+        assert!(loaded.resolve(&[entry_id("/data/c")]).is_ok());
+        assert_eq!(loaded.next_page(2).len(), 1);
+        assert!(loaded.more.is_empty());
+        loaded.save(&path).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!json.contains("\"more\""));
+    }
+
+    #[test]
+    fn test_treemap_export_rolls_up_directories() {
+        let mut tree = ScanTree::new(Path::new("/srv"));
+        for (path, size) in [("/srv/db/wal/0001", 300), ("/srv/db/wal/0002", 100), ("/srv/db/base.dat", 200), ("/srv/README", 1), ("/elsewhere/x", 7)] {
+            tree.observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        let mut export = Vec::new();
+        tree.write_treemap(&mut export).unwrap();
+        let root: serde_json::Value = serde_json::from_slice(&export).unwrap();
+
+        assert_eq!(root["name"], "/srv");
+        assert_eq!(root["value"], 601);
+        let db = &root["children"][0];
+        assert_eq!((&db["name"], &db["value"]), (&serde_json::json!("db"), &serde_json::json!(600)));
+        assert_eq!(db["children"][0]["name"], "wal");
+        assert_eq!(db["children"][0]["value"], 400);
+        assert_eq!(db["children"][0]["children"].as_array().unwrap().len(), 2);
+        assert_eq!(db["children"][1], serde_json::json!({"name": "base.dat", "value": 200}));
+        assert_eq!(root["children"][1], serde_json::json!({"name": "README", "value": 1}));
+        assert_eq!(root["children"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_dirs_sessions_are_kept_apart_from_file_rankings() {
+        let files = Config { root_path: "/srv".into(), num_entries: 10, ..Default::default() };
+        let dirs = Config { dirs: true, ..files.clone() };
+        assert_ne!(files.settings_hash(), dirs.settings_hash());
+
+        let stamps = ScanStamps::default();
+        let mut scan = SavedScan::new(Path::new("/srv"), &[("/srv/db".to_string(), 500)], &stamps);
+        let json = serde_json::to_string(&scan).unwrap();
+        assert!(!json.contains("\"dirs\""));
+        scan.dirs = true;
+        let json = serde_json::to_string(&scan).unwrap();
+        assert!(SavedScan::from_json(&json).unwrap().dirs);
+    }
+
+    #[test]
+    fn test_stream_tally_keeps_the_largest_streams() {
+        use crate::streams::{stream_name, StreamTally};
+
+        assert_eq!(stream_name(":big:$DATA"), Some("big"));
+        assert_eq!(stream_name("::$DATA"), None);
+        assert_eq!(stream_name(":odd:$INDEX_ALLOCATION"), None);
+
+        let streams = StreamTally::new(2);
+        assert_eq!(streams.add("C:\\a.zip", &[("Zone.Identifier".to_string(), 26)]), 26);
+        assert_eq!(streams.add("C:\\b.bin", &[("x".to_string(), 900), ("y".to_string(), 100)]), 1000);
+        let section = streams.finish();
+        assert!(section.heading.unwrap().contains('3'));
+        let fields: Vec<_> = section.rows.iter().map(|row| row.fields.clone()).collect();
+        assert_eq!(fields, [["900", "C:\\b.bin:x"], ["100", "C:\\b.bin:y"]]);
+        assert!(section.rows.iter().all(|row| row.kind == "stream"));
+    }
+
+    #[test]
+    fn test_check_outputs_fails_before_the_scan() {
+        let dir = std::env::temp_dir().join(format!("ferris-outputs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("existing.tar");
+        std::fs::write(&existing, b"kept").unwrap();
+
+        let config = Config {
+            save_snapshot: Some(dir.join("scan.ffs")),
+            delete_script: Some(existing.clone()),
+            ..Default::default()
+        };
+        assert!(config.check_outputs().is_ok());
+        assert!(!dir.join("scan.ffs").exists());
+        assert_eq!(std::fs::read(&existing).unwrap(), b"kept");
+
+        let missing = Config {
+            export_ncdu: Some(dir.join("missing").join("tree.json")),
+            ..Default::default()
+        };
+        assert!(missing.check_outputs().unwrap_err().to_string().contains("--export-ncdu"));
+
+        let archive = Config { archive_to: Some(existing.clone()), ..Default::default() };
+        assert!(archive.check_outputs().unwrap_err().to_string().contains("--archive-to"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tree_section_orders_children_by_size() {
+        let mut tree = ScanTree::new(Path::new("/srv"));
+        tree.add("/srv/logs/a.log", 10);
+        tree.add("/srv/db/base/1", 500);
+        tree.add("/srv/db/base/2", 100);
+        tree.add("/srv/db/wal", 50);
+
+        let section = tree.tree_section(None);
+        let fields: Vec<_> = section.rows.iter().map(|row| row.fields.join(" ")).collect();
+        assert_eq!(
+            fields,
+            [
+                "660 0 /srv",
+                "650 1 /srv/db",
+                "600 2 /srv/db/base",
+                "500 3 /srv/db/base/1",
+                "100 3 /srv/db/base/2",
+                "50 2 /srv/db/wal",
+                "10 1 /srv/logs",
+                "10 2 /srv/logs/a.log",
+            ]
+        );
+        assert!(section.rows[5].text.ends_with("│   └── wal"));
+        assert!(section.rows[7].text.ends_with("    └── a.log"));
+
+        let shallow = tree.tree_section(Some(2));
+        assert_eq!(shallow.rows.len(), 6);
+        assert!(shallow.rows.iter().all(|row| row.kind == "tree"));
+    }
+
+    #[test]
+    fn test_sessions_with_other_settings_are_not_compared() {
+        let base = Config { root_path: std::path::PathBuf::from("/srv"), num_entries: 10, ..Default::default() };
+        let excluded = Config { skip_dirs: std::collections::HashSet::from(["cache".to_string()]), ..base.clone() };
+        let provenance = |config: &Config| Provenance {
+            config_hash: Some(config.settings_hash()),
+            ..Default::default()
+        };
+        assert!(!provenance(&base).settings_differ(&provenance(&base)));
+        assert!(provenance(&base).settings_differ(&provenance(&excluded)));
+        assert!(!Provenance::default().settings_differ(&provenance(&base)));
+    }
+
+    #[test]
+    fn test_user_totals_count_every_file_once() {
+        use crate::users::UserTotals;
+
+        let config = Config { reports: vec![ReportKind::ByUser], num_entries: 5, ..Default::default() };
+        let mut reports = aggregators(&config, Path::new("/home"));
+        assert_eq!(reports.len(), 1);
+        for (path, size, uid) in [("/home/a/1", 10, Some(4_000_000_002)), ("/home/b/1", 30, Some(4_000_000_003)), ("/home/a/2", 25, Some(4_000_000_002)), ("/home/c", 1, None)] {
+            reports[0].observe(&FileInfo { path: path.to_string(), size, modified: None, uid, accessed: None });
+        }
+        let section = reports[0].finish();
+        let fields: Vec<_> = section.rows.iter().map(|row| row.fields[..3].join(" ")).collect();
+        assert_eq!(fields, ["35 2 4000000002", "30 1 4000000003", "1 1 "]);
+        assert!(section.rows.iter().all(|row| row.kind == "user"));
+
+        let mut limited = UserTotals::new(1);
+        limited.observe(&FileInfo { path: "/x".to_string(), size: 1, modified: None, uid: Some(0), accessed: None });
+        limited.observe(&FileInfo { path: "/y".to_string(), size: 2, modified: None, uid: None, accessed: None });
+        assert_eq!(limited.top(), [(None, 2, 1)]);
+    }
+
+    #[test]
+    fn test_ferris_error_maps_stages_to_exit_codes() {
+        use crate::errors::{FerrisError, SearchError};
+        use std::error::Error;
+
+        let scan = FerrisError::scan(SearchError::PathError("bad".to_string()));
+        assert_eq!(scan.exit_code(), 4);
+        assert_eq!(scan.to_string(), "Path error: bad");
+        assert!(scan.source().is_some());
+        let codes: Vec<i32> = [
+            FerrisError::config("invalid"),
+            FerrisError::report("disk full"),
+            FerrisError::action(std::io::Error::other("exists")),
+        ]
+        .iter()
+        .map(FerrisError::exit_code)
+        .collect();
+        assert_eq!(codes, [2, 5, 6]);
+        assert_ne!(codes[0], crate::alerts::ALERT_EXIT_CODE);
+    }
+
+    #[test]
+    fn test_size_stats_estimate_the_median_closely() {
+        use crate::report::SizeStats;
+
+        let mut stats = SizeStats::default();
+        assert_eq!(stats.median(), None);
+        assert_eq!(stats.mean(), 0);
+        for size in (1..=1001u64).map(|i| i * 1_000_003) {
+            stats.observe(&FileInfo { path: "/f".to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        let exact = 501 * 1_000_003u64;
+        let median = stats.median().unwrap();
+        assert!(median.abs_diff(exact) * 64 <= exact, "{} vs {}", median, exact);
+        assert_eq!(stats.mean(), exact);
+        assert_eq!(stats.total(), exact * 1001);
+
+        stats.observe(&FileInfo { path: "/max".to_string(), size: u64::MAX / 2, modified: None, uid: None, accessed: None });
+        assert_eq!(stats.files(), 1002);
+
+        let config = Config { stats: true, reports: vec![ReportKind::Histogram], ..Default::default() };
+        let kinds: Vec<_> = aggregators(&config, Path::new("/"))
+            .iter()
+            .map(|aggregator| aggregator.finish().rows[0].kind.clone())
+            .collect();
+        assert_eq!(kinds, ["histogram", "stats"]);
+    }
+
+    #[test]
+    fn test_parsers_reject_malformed_input_without_panicking() {
+        let inputs = [
+            "", " ", ".", "..", "1..5KB", "-1", "1e400", "NaN", "inf GB", "99999999999999999999999",
+            "18446744073709551615", "18446744073709551615y", "5 🦀", "é", "\u{0}", "[!", "{a,b",
+            "size>=", "within=soon", "/srv size>=1TB growth>=", "prefix,team\n\"unterminated",
+            "{\"prefixes\": [", "host:port:extra\t", "36 35 98:0 /mnt1 /mnt2 rw - ext3",
+        ];
+        for input in inputs {
+            let _ = parse_size(input);
+            let _ = parse_duration(input);
+            let _ = crate::cost::parse_rate(input);
+            let _ = ProtectList::parse(input);
+            let _ = TagMap::parse(input);
+            let _ = AlertRules::parse(input);
+            let _ = CostModel::parse(input, Some(0.02));
+            let _ = OwnershipMap::parse_csv(input);
+            let _ = OwnershipMap::parse_json(input);
+            let _ = parse_hosts(input);
+            let _ = parse_mountinfo(input);
+            let _ = SavedScan::from_json(input);
+            let _ = RetentionPolicy::new(None, &[input.to_string()]);
+            for format in [ImportFormat::Du, ImportFormat::Find, ImportFormat::Ncdu, ImportFormat::Wire] {
+                let _ = import_entries(format, input.as_bytes(), |_, _| {});
+            }
+        }
+        assert!(parse_size("1e400").is_err());
+        assert!(parse_duration("18446744073709551615y").is_err());
+    }
+
+    #[cfg(all(feature = "parquet", unix))]
+    #[test]
+    fn test_parquet_export_halts_when_free_space_runs_low() {
+        use crate::export::{ParquetExport, PARQUET_ROW_GROUP};
+
+        let dir = std::env::temp_dir();
+        for (min_free, halted) in [(1, false), (u64::MAX, true)] {
+            let mut export = ParquetExport::new(Vec::new(), None, "/srv")
+                .unwrap()
+                .with_reserve(&dir, min_free);
+            for index in 0..PARQUET_ROW_GROUP + 2 {
+                export.observe(&FileInfo {
+                    path: format!("/srv/data/{}", index),
+                    size: 1,
+                    modified: None,
+                    uid: None,
+                    accessed: None,
+                });
+            }
+            assert_eq!(export.is_halted(), halted);
+            let (parquet, rows) = export.close().unwrap();
+            assert_eq!(rows, if halted { 0 } else { PARQUET_ROW_GROUP as u64 + 2 });
+            assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+        }
+    }
+
+    #[test]
+    fn test_timestamps_shift_across_midnight_and_years() {
+        use crate::units::format_timestamp;
+
+        assert_eq!(format_timestamp(0, Some(-3_600)), "1969-12-31 23:00:00 -01:00");
+        assert_eq!(format_timestamp(0, None), "1970-01-01T00:00:00Z");
+        // Chatham Islands daylight time, 13:45 ahead of UTC
+        assert_eq!(
+            format_timestamp(1_735_646_400, Some(49_500)),
+            "2025-01-01 01:45:00 +13:45"
+        );
+        assert_eq!(format_timestamp(1_709_164_800, None), "2024-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_mtime_metric_ranks_the_oldest_files_first() {
+        use crate::report::Metric;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let files: Vec<FileInfo> = [("/srv/new", Some(300)), ("/srv/old", Some(100)), ("/srv/mid", Some(200)), ("/srv/unknown", None)]
+            .into_iter()
+            .map(|(path, secs)| FileInfo {
+                path: path.to_string(),
+                size: 1,
+                modified: secs.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+                uid: None,
+                accessed: None,
+            })
+            .collect();
+        for (newest, expected) in [(false, ["/srv/old", "/srv/mid"]), (true, ["/srv/new", "/srv/mid"])] {
+            let mut top = TopEntries::new(2);
+            for file in &files {
+                if let Some(value) = Metric::Mtime.value(file, newest) {
+                    top.insert(file.path.clone(), value);
+                }
+            }
+            let ranked: Vec<&str> = top.get_entries().iter().map(|(path, _)| path.as_str()).collect();
+            assert_eq!(ranked, expected);
+        }
+    }
+
+    #[test]
+    fn test_powershell_delete_scripts_start_with_a_byte_order_mark() {
+        use crate::actions::script::write_delete_script;
+
+        let dir = std::env::temp_dir();
+        let entries = vec![("C:\\Users\\Zoë\\Música\\mix.wav".to_string(), 4096)];
+        for (name, bom) in [("ferris-bom-test.ps1", true), ("ferris-bom-test.sh", false)] {
+            let path = dir.join(name);
+            write_delete_script(&path, Path::new("C:\\Users"), &Selection::default(), &entries).unwrap();
+            let script = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(script.starts_with('\u{feff}'), bom);
+            assert!(script.contains("Zoë\\Música"));
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_top_k_ranks_by_any_ordered_key() {
+        use crate::report::{Aggregator, Metric, TopFilesBy};
+        use crate::top_entries::TopK;
+        use std::cmp::Reverse;
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        // Reversed keys keep the lowest, here the least recently accessed files
+        let mut stale: TopK<Reverse<SystemTime>> = TopK::new(2);
+        for (path, secs) in [("/srv/a", 300), ("/srv/b", 100), ("/srv/c", 200)] {
+            stale.insert(path.to_string(), Reverse(UNIX_EPOCH + Duration::from_secs(secs)));
+        }
+        let ranked: Vec<&str> = stale.get_entries().iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(ranked, ["/srv/b", "/srv/c"]);
+
+        let files: Vec<FileInfo> = ["/srv/x", "/srv/Música/a-long-name.wav", "/srv/mid/y"]
+            .into_iter()
+            .map(|path| FileInfo { path: path.to_string(), size: 1, modified: None, uid: None, accessed: None })
+            .collect();
+        let mut longest = TopFilesBy::new(2, |file: &FileInfo| Metric::PathLength.measure(file));
+        for file in &files {
+            longest.observe(file);
+        }
+        let rows = longest.finish().rows;
+        assert_eq!(rows[0].fields, ["27", "/srv/Música/a-long-name.wav"]);
+        assert_eq!(rows[1].fields, ["10", "/srv/mid/y"]);
+
+        // Unknown access times are left out rather than ranked as the epoch
+        let mut unread = TopFilesBy::new(3, |file: &FileInfo| Metric::Atime.value(file, false));
+        for file in &files {
+            unread.observe(file);
+        }
+        assert!(unread.finish().rows.is_empty());
+    }
+
+    #[test]
+    fn test_collapse_siblings_lists_crowded_directories_once() {
+        use crate::results::{collapse_siblings, Listed};
+
+        let entries: Vec<(String, u64)> = [("/srv/wal/3", 90), ("/srv/a.iso", 80), ("/srv/wal/1", 70), ("/srv/wal/2", 60), ("/srv/b.iso", 50), ("/", 1)]
+            .into_iter()
+            .map(|(path, size)| (path.to_string(), size))
+            .collect();
+        // /srv holds only two of them directly, so its files stay listed
+        assert_eq!(
+            collapse_siblings(&entries, 2),
+            [
+                Listed::Siblings { parent: Path::new("/srv/wal"), count: 3, size: 220 },
+                Listed::Entry("/srv/a.iso", 80),
+                Listed::Entry("/srv/b.iso", 50),
+                Listed::Entry("/", 1),
+            ]
+        );
+        assert_eq!(collapse_siblings(&entries, 1).len(), 3);
+    }
+
+    #[test]
+    fn test_tree_shape_keeps_the_widest_and_deepest_directories_across_threads() {
+        use crate::tally::TreeShape;
+
+        let shape = std::sync::Arc::new(TreeShape::new(3));
+        let handles: Vec<_> = (0..4)
+            .map(|thread| {
+                let shape = std::sync::Arc::clone(&shape);
+                std::thread::spawn(move || {
+                    for i in 0..250 {
+                        let n = thread * 250 + i;
+                        shape.record(Path::new(&format!("/srv/{}", n)), n, n % 17);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let widest: Vec<u64> = shape.widest().iter().map(|(_, entries)| *entries).collect();
+        assert_eq!(widest, [999, 998, 997]);
+        assert_eq!(shape.deepest().map(|(depth, _)| depth), Some(16));
+
+        let section = shape.section();
+        assert_eq!(section.rows.len(), 4);
+        assert_eq!(section.rows[0].fields, ["999", "/srv/999"]);
+        assert_eq!(section.rows[3].kind, "depth");
+
+        // The root alone is the deepest directory of a flat tree
+        let flat = TreeShape::new(1);
+        flat.record(Path::new("/srv"), 0, 0);
+        assert_eq!(flat.deepest(), Some((0, std::path::PathBuf::from("/srv"))));
+        assert!(flat.widest().is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_hard_links_are_sized_once_unless_all_are_counted() {
+        use crate::tally::HardLinks;
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = std::env::temp_dir().join(format!("ferris-hard-links-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("data"), vec![0u8; 4096]).unwrap();
+        std::fs::write(dir.join("single"), b"x").unwrap();
+        for i in 0..3 {
+            std::fs::hard_link(dir.join("data"), dir.join(format!("link{}", i))).unwrap();
+        }
+        let metadata = |name: &str| std::fs::metadata(dir.join(name)).unwrap();
+
+        let links = HardLinks::new(false);
+        let claimed: Vec<bool> = ["link1", "data", "single", "link0", "single"].iter().map(|name| links.claim(&metadata(name))).collect();
+        assert_eq!(claimed, [true, false, true, false, true]);
+        let data = metadata("data");
+        assert_eq!(links.links((data.dev(), data.ino())), Some(4));
+        let single = metadata("single");
+        assert_eq!(links.links((single.dev(), single.ino())), None);
+
+        let all = HardLinks::new(true);
+        assert!(["data", "link0", "link1"].iter().all(|name| all.claim(&metadata(name))));
+        assert_eq!(all.links((data.dev(), data.ino())), Some(4));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_assert_read_only_refuses_writing_options_and_commands() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["ferris-files", "--porcelain", "--assert-read-only"];
+            argv.extend_from_slice(extra);
+            Args::try_parse_from(argv).unwrap()
+        };
+        for option in ["--save-session", "--export-treemap", "--emit-delete-script"] {
+            let err = Config::build(&parse(&["-d", "/tmp", option, "/tmp/out"])).err().unwrap();
+            assert!(err.to_string().contains(option), "{}", err);
+        }
+        assert!(Config::build(&parse(&["-d", "/tmp"])).unwrap().read_only);
+        assert!(!Config::build(&Args::try_parse_from(["ferris-files", "--porcelain", "-d", "/tmp"]).unwrap()).unwrap().read_only);
+
+        let writes = |extra: &[&str]| parse(extra).command.unwrap().writes();
+        assert!(writes(&["undo"]));
+        assert!(writes(&["merge", "a.json", "b.json", "--output", "merged.json"]));
+        assert!(!writes(&["merge", "a.json", "b.json"]));
+        assert!(!writes(&["query", "--min-size", "1GB"]));
+    }
+
+    #[test]
+    fn test_scan_locks_block_running_scans_and_take_over_stale_ones() {
+        let dir = std::env::temp_dir().join(format!("ferris-locks-{}", std::process::id()));
+        let root = Path::new("/srv/data");
+        let lock = crate::storage::ScanLock::acquire_in(&dir, root, false).unwrap();
+        let err = crate::storage::ScanLock::acquire_in(&dir, root, false).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        assert!(err.to_string().contains(&std::process::id().to_string()));
+        drop(lock);
+
+        // A lock whose owner is gone is taken over without --force, and released again
+        std::mem::forget(crate::storage::ScanLock::acquire_in(&dir, root, false).unwrap());
+        let path = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        std::fs::write(&path, format!("{}\n/srv/data\n", u32::MAX)).unwrap();
+        let taken = crate::storage::ScanLock::acquire_in(&dir, root, false).unwrap();
+        assert!(path.exists());
+        drop(taken);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sparse_files_are_flagged_and_can_be_skipped() {
+        let path = std::env::temp_dir().join(format!("ferris-holes-{}", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_len(32 << 20).unwrap();
+        let metadata = file.metadata().unwrap();
+
+        let fallbacks = SizeFallbacks::new(Vec::new());
+        assert!(!fallbacks.skips(&path, &metadata));
+        #[cfg(unix)]
+        {
+            assert!(fallbacks.sparse(&path, &metadata));
+            assert!(SizeFallbacks::new(Vec::new()).with_sparse_skipped(true).skips(&path, &metadata));
+        }
+        // Blocks reported as missing by a network mount say nothing about holes
+        let nfs = SizeFallbacks::new(parse_mountinfo(&format!(
+            "40 22 0:51 / {} rw - nfs4 nas:/export rw\n",
+            std::env::temp_dir().display()
+        )));
+        assert!(!nfs.sparse(&path, &metadata));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_min_size_parses_si_and_binary_units() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let config = |extra: &[&str]| {
+            let mut argv = vec!["ferris-files", "--porcelain", "-d", "/tmp"];
+            argv.extend_from_slice(extra);
+            Config::build(&Args::try_parse_from(argv).unwrap()).unwrap()
+        };
+        assert_eq!(config(&[]).min_size, 0);
+        assert_eq!(config(&["--min-size", "100MB"]).min_size, 100_000_000);
+        assert_eq!(config(&["--min-size", "100MiB"]).min_size, 100 << 20);
+        assert!(Args::try_parse_from(["ferris-files", "--min-size", "lots"]).is_err());
+        // Sessions ranked from another size on do not compare like for like
+        assert_ne!(config(&[]).settings_hash(), config(&["--min-size", "1G"]).settings_hash());
+    }
+
+    #[test]
+    fn test_include_limits_the_scan_to_matching_files() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["ferris-files", "--porcelain", "-d", "/tmp"];
+            argv.extend_from_slice(extra);
+            Config::build(&Args::try_parse_from(argv).unwrap())
+        };
+        let config = parse(&["--include", "*.log", "--include", "*.BAK"]).unwrap();
+        assert_eq!(config.include.patterns(), ["*.log", "*.BAK"]);
+        assert!(config.include.is_match("/var/log/app/error.log"));
+        assert!(config.include.is_match("/srv/db.BAK"));
+        assert!(!config.include.is_match("/srv/db.bak.gz"));
+        assert!(parse(&[]).unwrap().include.is_empty());
+        assert!(parse(&["--include", "*.{log"]).is_err());
+        assert_ne!(parse(&[]).unwrap().settings_hash(), config.settings_hash());
+    }
+
+    #[test]
+    fn test_entries_are_partitioned_by_mount_in_json_and_wire() {
+        use crate::output::JsonReport;
+        use crate::quota::parse_mountinfo;
+        use crate::results::partition_by_mount;
+        use crate::wire::WireMount;
+
+        let mounts = parse_mountinfo(
+            "22 1 8:2 / / rw - ext4 /dev/sda2 rw\n\
+             40 22 0:51 / /mnt/nas rw - nfs4 nas:/export rw\n\
+             41 40 0:52 / /mnt/nas/scratch rw - tmpfs tmpfs rw\n",
+        );
+        let entries = [
+            ("/mnt/nas/scratch/tmp.bin".to_string(), 50),
+            ("/mnt/nas/video.mkv".to_string(), 300),
+            ("/home/ann/disk.img".to_string(), 200),
+        ];
+        let partitions = partition_by_mount(&entries, &mounts, Path::new("/"));
+        let sizes: Vec<(&str, u64)> = partitions
+            .iter()
+            .map(|partition| (partition.mount_point.to_str().unwrap(), partition.size))
+            .collect();
+        assert_eq!(sizes, [("/mnt/nas", 300), ("/", 200), ("/mnt/nas/scratch", 50)]);
+
+        let mut report = JsonReport::new("/");
+        report.add_entries(&entries, &std::collections::HashMap::new(), None);
+        report.set_partitions(&partitions);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["partitions"][0]["root"], "/");
+        assert_eq!(json["partitions"][0]["mounts"][2]["fs_type"], "tmpfs");
+        assert_eq!(json["partitions"][0]["mounts"][0]["entries"][0], json["entries"][1]["id"]);
+
+        let mut stream = Vec::new();
+        let mount = WireMount { mount_point: "/mnt/nas".to_string(), fs_type: "nfs4".to_string() };
+        let mut writer = FrameWriter::new(&mut stream).unwrap();
+        writer.send(&Message::Mounts(vec![mount.clone()])).unwrap();
+        let mut reader = FrameReader::new(stream.as_slice()).unwrap();
+        assert_eq!(reader.read_message().unwrap(), Some(Message::Mounts(vec![mount])));
+    }
+
+    #[test]
+    fn test_exclude_globs_match_directory_names_and_paths() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["ferris-files", "--porcelain", "-d", "/tmp"];
+            argv.extend_from_slice(extra);
+            Config::build(&Args::try_parse_from(argv).unwrap())
+        };
+        let config = parse(&["-x", "node_modules", "-x", "**/target", "--exclude", "/var/cache/**"]).unwrap();
+        assert_eq!(config.exclude.patterns(), ["node_modules", "**/target", "/var/cache/**"]);
+        assert!(config.exclude.is_match("/home/ann/app/node_modules"));
+        assert!(config.exclude.is_match("/home/ann/app/node_modules/left-pad"));
+        assert!(config.exclude.is_match("/home/ann/crate/target"));
+        assert!(config.exclude.is_match("/var/cache/apt"));
+        assert!(!config.exclude.is_match("/var/cache"));
+        assert!(!config.exclude.is_match("/home/ann/targets"));
+        assert!(parse(&[]).unwrap().exclude.is_empty());
+        assert!(parse(&["-x", "[oops"]).is_err());
+        assert_ne!(parse(&[]).unwrap().settings_hash(), config.settings_hash());
+    }
+
+    #[test]
+    fn test_dup_names_report_ranks_large_names_repeated_across_directories() {
+        use crate::report::DUP_NAME_MIN_SIZE;
+
+        let config = Config { reports: vec![ReportKind::DupNames], num_entries: 5, ..Default::default() };
+        let mut reports = aggregators(&config, Path::new("/"));
+        assert_eq!(reports.len(), 1);
+        for (path, size) in [
+            ("/srv/a/disk.img", 2 * DUP_NAME_MIN_SIZE),
+            ("/srv/b/disk.img", 3 * DUP_NAME_MIN_SIZE),
+            ("/srv/a/notes.txt", 10),
+            ("/srv/b/notes.txt", 10),
+            ("/srv/a/unique.iso", 9 * DUP_NAME_MIN_SIZE),
+        ] {
+            reports[0].observe(&FileInfo { path: path.to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        let section = reports[0].finish();
+        // Small files and names seen once are left out
+        assert_eq!(section.rows.len(), 1);
+        assert_eq!(section.rows[0].kind, "dup-name");
+        assert_eq!(section.rows[0].fields[..3], [(5 * DUP_NAME_MIN_SIZE).to_string(), "2".to_string(), "disk.img".to_string()]);
+        assert_eq!(section.rows[0].fields[4], "/srv/b/disk.img");
+    }
+
+    #[test]
+    fn test_gitignore_rules_nest_and_stop_at_the_root() {
+        use crate::filter::{IgnoreRules, GITIGNORE_FILES};
+
+        let dir = std::env::temp_dir().join(format!("ferris-files-gitignore-{}", std::process::id()));
+        let root = dir.join("repo");
+        std::fs::create_dir_all(root.join(".git/info")).unwrap();
+        std::fs::create_dir_all(root.join("web/node_modules")).unwrap();
+        // Rules above the root are not read
+        std::fs::write(dir.join(".gitignore"), "*\n").unwrap();
+        std::fs::write(root.join(".git/info/exclude"), "*.tmp\n").unwrap();
+        std::fs::write(root.join(".gitignore"), "node_modules/\n!keep.tmp\n").unwrap();
+        std::fs::write(root.join("web/.gitignore"), "!node_modules/\n").unwrap();
+
+        let rules = IgnoreRules::new(std::slice::from_ref(&root), &GITIGNORE_FILES);
+        let top = rules.for_dir(&root);
+        assert!(top.is_ignored(&root.join("scratch.tmp"), false));
+        // The .gitignore next to .git overrides its exclude file
+        assert!(!top.is_ignored(&root.join("keep.tmp"), false));
+        assert!(!top.is_ignored(&root.join("Cargo.toml"), false));
+        assert!(top.is_ignored(&root.join("node_modules"), true));
+        // Deeper rules override those above
+        let web = rules.for_dir(&root.join("web"));
+        assert!(!web.is_ignored(&root.join("web/node_modules"), true));
+        assert!(web.is_ignored(&root.join("web/cache.tmp"), false));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        use crate::args::Args;
+        use clap::Parser;
+        let config = |extra: &[&str]| {
+            let mut argv = vec!["ferris-files", "--porcelain", "-d", "/tmp"];
+            argv.extend_from_slice(extra);
+            Config::build(&Args::try_parse_from(argv).unwrap()).unwrap()
+        };
+        assert!(config(&["--respect-gitignore"]).respect_gitignore);
+        assert_ne!(config(&[]).settings_hash(), config(&["--respect-gitignore"]).settings_hash());
+    }
+
+    #[test]
+    fn test_repo_scans_the_enclosing_repository_and_splits_off_git() {
+        use crate::args::Args;
+        use crate::report::aggregators;
+        use clap::Parser;
+
+        let repo = std::env::temp_dir().join(format!("ferris-files-repo-{}", std::process::id()));
+        std::fs::create_dir_all(repo.join(".git/objects")).unwrap();
+        std::fs::create_dir_all(repo.join("src/bin")).unwrap();
+        let repo = crate::console::canonicalize(&repo).unwrap();
+        let sub = repo.join("src/bin").to_string_lossy().to_string();
+        let config = |extra: &[&str]| {
+            let mut argv = vec!["ferris-files", "--porcelain", "-d", &sub];
+            argv.extend_from_slice(extra);
+            Config::build(&Args::try_parse_from(argv).unwrap())
+        };
+
+        let with_repo = config(&["--repo"]).unwrap();
+        assert_eq!(with_repo.root_path, repo);
+        assert!(with_repo.respect_gitignore);
+        assert!(!config(&["--repo", "--no-respect-gitignore"]).unwrap().respect_gitignore);
+        assert_eq!(config(&[]).unwrap().root_path, Path::new(&sub));
+        assert!(config(&["--repo", "--import", "du", "-"]).is_err());
+
+        let mut reports = aggregators(&with_repo, &repo);
+        let sizes = reports.last_mut().unwrap();
+        for (path, size) in [(".git/objects/pack", 700), ("src/bin/main.rs", 300)] {
+            sizes.observe(&FileInfo { path: repo.join(path).to_string_lossy().to_string(), size, modified: None, uid: None, accessed: None });
+        }
+        let section = sizes.finish();
+        assert_eq!(section.rows[0].fields, ["700", "1", "git"]);
+        assert_eq!(section.rows[1].fields, ["300", "1", "worktree"]);
+
+        std::fs::remove_dir_all(&repo).unwrap();
+        let outside = std::env::temp_dir().join(format!("ferris-files-no-repo-{}", std::process::id()));
+        std::fs::create_dir_all(&outside).unwrap();
+        let argv = ["ferris-files", "--porcelain", "--repo", "-d", outside.to_str().unwrap()];
+        assert!(Config::build(&Args::try_parse_from(argv).unwrap()).is_err());
+        std::fs::remove_dir_all(&outside).unwrap();
+    }
+
+    #[test]
+    fn test_ferrisignore_prunes_subtrees_over_gitignore() {
+        use crate::filter::{IgnoreRules, FERRISIGNORE_FILE, GITIGNORE_FILES};
+
+        let root = std::env::temp_dir().join(format!("ferris-files-ferrisignore-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("shared/render-cache")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.partial\n").unwrap();
+        std::fs::write(root.join(".ferrisignore"), "!keep.partial\n").unwrap();
+        std::fs::write(root.join("shared/.ferrisignore"), "render-cache/\n").unwrap();
+
+        let files: Vec<&str> = GITIGNORE_FILES.into_iter().chain([FERRISIGNORE_FILE]).collect();
+        let rules = IgnoreRules::new(std::slice::from_ref(&root), &files);
+        let top = rules.for_dir(&root);
+        assert!(top.is_ignored(&root.join("upload.partial"), false));
+        // .ferrisignore is read after the .gitignore of the same directory
+        assert!(!top.is_ignored(&root.join("keep.partial"), false));
+        let shared = rules.for_dir(&root.join("shared"));
+        assert!(shared.is_ignored(&root.join("shared/render-cache"), true));
+        assert!(!shared.is_ignored(&root.join("shared/render-cache"), false));
+        // Without git's files only .ferrisignore counts
+        let only_ferris = IgnoreRules::new(std::slice::from_ref(&root), &[FERRISIGNORE_FILE]);
+        assert!(!only_ferris.for_dir(&root).is_ignored(&root.join("upload.partial"), false));
+        assert!(only_ferris.for_dir(&root.join("shared")).is_ignored(&root.join("shared/render-cache"), true));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_artifacts_report_finds_build_output_per_project() {
+        use crate::artifacts::ProjectKind;
+
+        let root = std::env::temp_dir().join(format!("ferris-files-artifacts-{}", std::process::id()));
+        for dir in ["engine/target/debug", "site/node_modules/a/node_modules", "app/build", "notes/build"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join("engine/Cargo.toml"), "").unwrap();
+        std::fs::write(root.join("site/package.json"), "{}").unwrap();
+        std::fs::write(root.join("site/node_modules/a/package.json"), "{}").unwrap();
+        // Gradle wins over CMake for a shared build directory
+        std::fs::write(root.join("app/build.gradle.kts"), "").unwrap();
+        std::fs::write(root.join("app/CMakeLists.txt"), "").unwrap();
+
+        let config = Config { reports: vec![ReportKind::Artifacts], num_entries: 10, ..Default::default() };
+        let mut reports = aggregators(&config, &root);
+        for (path, size) in [
+            ("engine/target/debug/engine", 700),
+            ("site/node_modules/a/node_modules/b.js", 100),
+            ("site/node_modules/a/index.js", 50),
+            ("app/build/libs/app.jar", 300),
+            ("notes/build/notes.pdf", 900),
+            ("engine/src/main.rs", 10),
+        ] {
+            let path = root.join(path).to_string_lossy().to_string();
+            reports[0].observe(&FileInfo { path, size, modified: None, uid: None, accessed: None });
+        }
+        let rows = reports[0].finish().rows;
+        let found: Vec<(&str, &str, &str)> = rows
+            .iter()
+            .map(|row| (row.fields[0].as_str(), row.fields[2].as_str(), row.fields[4].as_str()))
+            .collect();
+        let dir = |path: &str| root.join(path).to_string_lossy().to_string();
+        assert_eq!(
+            found,
+            [
+                ("700", "cargo", dir("engine/target").as_str()),
+                ("300", "gradle", dir("app/build").as_str()),
+                ("150", "node", dir("site/node_modules").as_str()),
+            ]
+        );
+        assert_eq!(rows[2].fields[1], "2");
+        if cfg!(unix) {
+            assert_eq!(
+                ProjectKind::Cargo.clean_command(Path::new("/src/it's/target")),
+                r#"cargo clean --manifest-path '/src/it'\''s/Cargo.toml'"#
+            );
+            assert_eq!(ProjectKind::Node.clean_command(Path::new("/web/node_modules")), "rm -rf '/web/node_modules'");
+            assert_eq!(ProjectKind::Gradle.clean_command(Path::new("/app/build")), "gradle -p '/app' clean");
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_depth_limits_are_parsed_and_change_the_settings() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["ferris-files", "--porcelain", "-d", "/tmp"];
+            argv.extend_from_slice(extra);
+            Config::build(&Args::try_parse_from(argv).unwrap())
+        };
+        let config = parse(&["--max-depth", "2", "--min-depth", "1"]).unwrap();
+        assert_eq!((config.max_depth, config.min_depth), (2, 1));
+        let unlimited = parse(&[]).unwrap();
+        assert_eq!((unlimited.max_depth, unlimited.min_depth), (0, 0));
+        assert_ne!(unlimited.settings_hash(), config.settings_hash());
+        assert_ne!(
+            parse(&["--max-depth", "2"]).unwrap().settings_hash(),
+            parse(&["--min-depth", "2"]).unwrap().settings_hash()
+        );
+        assert!(parse(&["--min-depth", "3", "--import", "du", "-"]).is_err());
+
+        let tally = crate::tally::SkipTally::default();
+        tally.depth_limited_dirs.fetch_add(2, std::sync::atomic::Ordering::Relaxed);
+        tally.shallow_files.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let summary = tally.summary();
+        assert!(summary.contains("2 directories at --max-depth"), "{}", summary);
+        assert!(summary.contains("1 file above --min-depth"), "{}", summary);
+
+        // The guard against runaway trees is a separate option that does not limit the scan
+        let guarded = parse(&["--depth-guard", "8"]).unwrap();
+        assert_eq!((guarded.depth_guard, guarded.max_depth), (8, 0));
+        assert_eq!(parse(&[]).unwrap().depth_guard, 256);
+
+        // --max-depth stops the traversal itself
+        let root = std::env::temp_dir().join(format!("ferris-max-depth-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+        for (name, size) in [("top.bin", 1), ("a/one.bin", 10), ("a/b/two.bin", 100), ("a/b/c/three.bin", 1000)] {
+            std::fs::write(root.join(name), vec![0u8; size]).unwrap();
+        }
+        let argv = ["ferris-files", "-d", root.to_str().unwrap(), "--apparent-size", "--assert-read-only", "--max-depth", "2"];
+        let stats = std::sync::Arc::new(crate::stats::ScanStats::default());
+        let config = Config::build(&Args::try_parse_from(argv).unwrap()).unwrap();
+        crate::run_with_stats(config, &mut [], std::sync::Arc::clone(&stats)).unwrap();
+        assert_eq!(stats.snapshot().bytes, 11);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_scan_stats_count_errors_by_class_and_fill_the_json_summary() {
+        use crate::output::JsonReport;
+        use crate::stats::{ErrorClass, ScanStats};
+        use std::io;
+        use std::sync::atomic::Ordering;
+
+        let stats = ScanStats::default();
+        stats.files.fetch_add(5, Ordering::Relaxed);
+        stats.processed.fetch_add(4, Ordering::Relaxed);
+        stats.failed.fetch_add(1, Ordering::Relaxed);
+        stats.bytes.fetch_add(4096, Ordering::Relaxed);
+        stats.dirs.fetch_add(3, Ordering::Relaxed);
+        stats.skipped.fifos.fetch_add(2, Ordering::Relaxed);
+        stats.record_error(&io::Error::new(io::ErrorKind::NotFound, "gone"));
+        stats.record_error(&io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+        stats.record_error(&io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+        stats.record_error(&io::Error::other("odd"));
+        assert_eq!(stats.errors(ErrorClass::PermissionDenied), 2);
+        assert_eq!(stats.errors(ErrorClass::Hardware), 0);
+        assert_eq!(stats.total_errors(), 4);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.skipped, 2);
+        assert_eq!(snapshot.symlinks, 0);
+        assert!(!snapshot.errors.contains_key("hardware"));
+        assert_eq!(
+            snapshot.error_summary(),
+            "2 permission denied, 1 vanished during the scan, 1 other"
+        );
+        assert_eq!(ScanStats::default().snapshot().error_summary(), "none");
+
+        let mut report = JsonReport::new("/srv");
+        report.set_stats(&stats);
+        assert_eq!(
+            (report.summary.files, report.summary.dirs, report.summary.processed),
+            (5, 3, 4)
+        );
+        assert_eq!((report.summary.failed, report.summary.bytes), (1, 4096));
+        assert_eq!(report.summary.skipped["fifos"], 2);
+        assert_eq!(report.summary.errors["permission-denied"], 2);
+    }
+
+    #[test]
+    fn test_effective_settings_reflect_the_options_in_toml_and_json() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let args = Args::try_parse_with_sources([
+            "ferris-files", "--print-config", "-d", "/tmp", "-x", "*.log", "--max-depth", "3",
+            "--then-by", "path", "--batch_Size", "1000",
+        ])
+        .unwrap();
+        assert!(args.print_config);
+        let config = Config::build(&args).unwrap();
+
+        let toml = config.settings_toml();
+        assert!(toml.contains("\nexclude = [\"*.log\"]  # command line\n"), "{}", toml);
+        assert!(toml.contains("\nmax_depth = 3  # command line\n"), "{}", toml);
+        assert!(toml.contains("\nthen_by = \"path\"  # command line\n"), "{}", toml);
+        // Given explicitly, even though it is the default value
+        assert!(toml.contains("\nbatch_size = 1000  # command line\n"), "{}", toml);
+        assert!(toml.contains("\nnum_entries = 10  # default\n"), "{}", toml);
+        assert!(toml.contains("\n# save_session =  (default)\n"), "{}", toml);
+        assert!(toml.starts_with("num_threads = "), "{}", toml);
+        assert!(toml.lines().next().unwrap().ends_with("  # system"), "{}", toml);
+        assert_eq!(toml.lines().count(), config.effective_settings().len());
+
+        let text = config.settings_json();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["root_path"]["value"], "/tmp");
+        assert_eq!(json["root_path"]["source"], "command line");
+        assert_eq!(json["format"]["value"], "text");
+        assert_eq!(json["save_session"]["value"], serde_json::Value::Null);
+        assert_eq!(json["settings_hash"]["value"], config.settings_hash());
+        assert_eq!(json["settings_hash"]["source"], "system");
+        // The settings keep the order of the fields
+        assert!(text.find("\"num_threads\"").unwrap() < text.find("\"batch_size\"").unwrap());
+        assert_eq!(json.as_object().unwrap().len(), config.effective_settings().len());
+
+        // Printing the settings does not probe the outputs, which would create files
+        let unwritable = ["ferris-files", "-d", "/tmp", "--save-session", "/nonexistent/dir/scan.json"];
+        assert!(Config::build(&Args::try_parse_from(unwritable).unwrap()).is_err());
+        let printed = Args::try_parse_from(unwritable.iter().chain(&["--print-config"])).unwrap();
+        assert!(Config::build(&printed).is_ok());
+    }
+
+    #[test]
+    fn test_sessions_and_snapshots_of_other_versions_are_flagged() {
+        use crate::snapshot::Snapshot;
+        use std::io::Write;
+
+        let current = Provenance { tool_version: env!("CARGO_PKG_VERSION").to_string(), ..Default::default() };
+        let older = Provenance { tool_version: "0.0.1".to_string(), ..Default::default() };
+        let migrated = Provenance { tool_version: "unknown".to_string(), ..Default::default() };
+        assert!(older.version_differs(&current));
+        assert!(migrated.version_differs(&current));
+        assert!(!current.version_differs(&current));
+        assert!(!Provenance::default().version_differs(&current));
+
+        let mut snapshot = Snapshot::new("/srv", 1700000000);
+        snapshot.config_hash = Some("1f0c".to_string());
+        assert_eq!(snapshot.tool_version, env!("CARGO_PKG_VERSION"));
+        let mut saved = Vec::new();
+        snapshot.write(&mut saved).unwrap();
+        assert_eq!(Snapshot::read(&saved[..]).unwrap(), snapshot);
+
+        // Snapshots from before the version and settings were recorded still load
+        let mut legacy = Vec::new();
+        let mut encoder = zstd::Encoder::new(&mut legacy, 3).unwrap();
+        encoder
+            .write_all(br#"{"version":1,"root":"/srv","taken_at":1690000000,"files":{"/srv/a":5}}"#)
+            .unwrap();
+        encoder.finish().unwrap();
+        let legacy = Snapshot::read(&legacy[..]).unwrap();
+        assert_eq!((legacy.tool_version.as_str(), legacy.config_hash.as_deref()), ("", None));
+        assert!(!legacy.version_differs(&snapshot));
+        assert!(!legacy.settings_differ(&snapshot));
+
+        let mut upgraded = snapshot.clone();
+        upgraded.tool_version = "99.0.0".to_string();
+        upgraded.config_hash = Some("9ab2".to_string());
+        assert!(snapshot.version_differs(&upgraded));
+        assert!(snapshot.settings_differ(&upgraded));
+    }
+
+    #[test]
+    fn test_several_roots_are_deduplicated_under_their_common_ancestor() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let base = std::env::temp_dir().join(format!("ferris-files-test-roots-{}", std::process::id()));
+        std::fs::create_dir_all(base.join("media/films")).unwrap();
+        std::fs::create_dir_all(base.join("archive")).unwrap();
+        let base = base.canonicalize().unwrap();
+        let parse = |dirs: &[&str], extra: &[&str]| {
+            let mut argv = vec!["ferris-files".to_string(), "--porcelain".to_string()];
+            for dir in dirs {
+                argv.push("-d".to_string());
+                argv.push(base.join(dir).to_string_lossy().to_string());
+            }
+            argv.extend(extra.iter().map(|arg| arg.to_string()));
+            Config::build(&Args::try_parse_from(argv).unwrap())
+        };
+
+        let config = parse(&["media/films", "archive", "media"], &[]).unwrap();
+        assert_eq!(config.roots, [base.join("archive"), base.join("media")]);
+        assert_eq!(config.root_path, base);
+        assert_eq!(config.scan_roots(), config.roots);
+        let film = base.join("media/films/trip.mkv");
+        assert_eq!(config.root_of(&film.to_string_lossy()), Some(base.join("media").as_path()));
+        assert_eq!(config.root_of("/elsewhere/file"), None);
+
+        // A root nested in the only other one leaves a single root
+        let single = parse(&["media", "media/films"], &[]).unwrap();
+        assert!(single.roots.is_empty());
+        assert_eq!(single.root_path, base.join("media"));
+        assert_eq!(single.scan_roots(), [base.join("media")]);
+        assert_ne!(single.settings_hash(), config.settings_hash());
+
+        assert!(parse(&["media", "archive"], &["--repo"]).is_err());
+        // Each root is previewed in turn
+        let preview = ["ferris-files", "--preview", "-d", "/tmp", "-d", "/var"];
+        assert!(Config::build(&Args::try_parse_from(preview).unwrap()).unwrap().preview);
+        assert!(parse(&["media", "missing"], &[]).is_err());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_server_routes_requests_and_replays_the_latest_events() {
+        use crate::args::Args;
+        use crate::serve::{Request, ServeOptions, ServerState};
+        use clap::Parser;
+        use std::sync::Arc;
+
+        let config = Config::build(&Args::try_parse_from(["ferris-files", "-d", "/tmp"]).unwrap()).unwrap();
+        let options = ServeOptions {
+            listen: "127.0.0.1:7878".parse().unwrap(),
+            token: None,
+            allow_actions: false,
+        };
+        let state = Arc::new(ServerState::new(config, &options));
+        let request = |method: &str, path: &str| Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: vec![("host".to_string(), "127.0.0.1:7878".to_string())],
+            ..Default::default()
+        };
+
+        let status = state.respond(&request("GET", "/api/status"));
+        assert_eq!(status.status, 200);
+        assert_eq!(status.body["state"], "idle");
+        assert_eq!(status.body["root"], "/tmp");
+        assert_eq!(state.respond(&request("DELETE", "/api/scan")).status, 405);
+        assert_eq!(state.respond(&request("GET", "/api/events")).status, 426);
+        assert_eq!(state.respond(&request("GET", "/index.html")).status, 404);
+
+        // Clients connecting mid scan first get the latest progress and top events
+        state.publish(r#"{"event":"progress","files":1}"#.to_string());
+        state.publish(r#"{"event":"top","entries":[]}"#.to_string());
+        state.publish(r#"{"event":"progress","files":2}"#.to_string());
+        let events = state.subscribe();
+        assert_eq!(events.try_recv().unwrap(), r#"{"event":"top","entries":[]}"#);
+        assert_eq!(events.try_recv().unwrap(), r#"{"event":"progress","files":2}"#);
+        assert!(events.try_recv().is_err());
+        state.publish(r#"{"event":"done","files":2}"#.to_string());
+        assert_eq!(events.try_recv().unwrap(), r#"{"event":"done","files":2}"#);
+    }
+
+    #[test]
+    fn test_server_refuses_requests_without_the_token_and_actions_when_read_only() {
+        use crate::args::Args;
+        use crate::serve::{serve, Request, ServeOptions, ServerState};
+        use clap::Parser;
+        use std::sync::Arc;
+
+        let config = Config::build(&Args::try_parse_from(["ferris-files", "-d", "/tmp"]).unwrap()).unwrap();
+        let options = |token: Option<&str>, allow_actions| ServeOptions {
+            listen: "127.0.0.1:7878".parse().unwrap(),
+            token: token.map(str::to_string),
+            allow_actions,
+        };
+        let request = |method: &str, target: &str, headers: &[(&str, &str)], body: &str| {
+            let mut raw = format!("{} {} HTTP/1.1\r\nHost: localhost:7878\r\nContent-Length: {}\r\n", method, target, body.len());
+            for (name, value) in headers {
+                raw.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            raw.push_str("\r\n");
+            raw.push_str(body);
+            Request::read(raw.as_bytes()).unwrap()
+        };
+
+        let guarded = Arc::new(ServerState::new(config.clone(), &options(Some("s3cret"), false)));
+        assert_eq!(guarded.respond(&request("GET", "/api/status", &[], "")).status, 401);
+        let wrong = [("Authorization", "Bearer s3cre7")];
+        assert_eq!(guarded.respond(&request("GET", "/api/status", &wrong, "")).status, 401);
+        let bearer = [("Authorization", "Bearer s3cret")];
+        let status = guarded.respond(&request("GET", "/api/status", &bearer, ""));
+        assert_eq!((status.status, &status.body["read_only"]), (200, &serde_json::json!(true)));
+        assert_eq!(guarded.respond(&request("GET", "/api/status?token=s3cret", &[], "")).status, 200);
+        assert_eq!(guarded.respond(&request("POST", "/api/scan", &[], "")).status, 401);
+
+        // Action endpoints stay closed on a read-only server, whoever asks
+        let clean = r#"{"ids": "3b1f0c", "emit-delete-script": "/tmp/rm.sh"}"#;
+        assert_eq!(guarded.respond(&request("POST", "/api/clean", &bearer, clean)).status, 403);
+        let open = Arc::new(ServerState::new(config.clone(), &options(None, true)));
+        let prompting = r#"{"ids": "3b1f0c", "interactive": true}"#;
+        assert_eq!(open.respond(&request("POST", "/api/clean", &[], prompting)).status, 400);
+        assert_eq!(open.respond(&request("POST", "/api/clean", &[], "[1]")).status, 400);
+
+        // Listening beyond localhost needs a token, and scans with actions need --allow-actions
+        let mut exposed = options(None, false);
+        exposed.listen = "0.0.0.0:0".parse().unwrap();
+        assert!(serve(config.clone(), &exposed).is_err());
+        let mut moving = config.clone();
+        moving.move_to = Some("/mnt/archive".into());
+        assert!(serve(moving, &options(None, false)).is_err());
+        // Actions need a token even on localhost, where any local process or web page can connect
+        let err = serve(config.clone(), &options(None, true)).unwrap_err().to_string();
+        assert!(err.contains("--token"), "{}", err);
+        let args = Args::try_parse_from(["ferris-files", "serve", "--allow-actions"]).unwrap();
+        assert!(args.command.is_some_and(|command| command.writes()));
+    }
+
+    #[test]
+    fn test_priorities_order_several_roots_and_their_listing() {
+        use crate::args::Args;
+        use crate::WorkQueue;
+        use clap::Parser;
+        use std::path::PathBuf;
+
+        let base = std::env::temp_dir().join(format!("ferris-files-test-priorities-{}", std::process::id()));
+        for dir in ["archive", "media", "scratch"] {
+            std::fs::create_dir_all(base.join(dir)).unwrap();
+        }
+        let base = base.canonicalize().unwrap();
+        let file = base.join("priorities");
+        std::fs::write(&file, format!("# media first\n{} 10\n{} 5\n", base.join("media").display(), base.join("scratch").display())).unwrap();
+        let parse = |priorities: Option<&PathBuf>, dirs: &[&str]| {
+            let mut argv = vec!["ferris-files".to_string()];
+            for dir in dirs {
+                argv.extend(["-d".to_string(), base.join(dir).to_string_lossy().to_string()]);
+            }
+            if let Some(file) = priorities {
+                argv.extend(["--priorities".to_string(), file.to_string_lossy().to_string()]);
+            }
+            Config::build(&Args::try_parse_from(argv).unwrap())
+        };
+
+        let config = parse(Some(&file), &["archive", "media", "scratch"]).unwrap();
+        assert_eq!(config.roots, [base.join("media"), base.join("scratch"), base.join("archive")]);
+        assert_eq!(config.priorities.tiers(&config.roots), [0, 1, 2]);
+        // The order of scanning does not change the results
+        let unordered = parse(None, &["archive", "media", "scratch"]).unwrap();
+        assert_eq!(unordered.roots, [base.join("archive"), base.join("media"), base.join("scratch")]);
+        assert_eq!(config.settings_hash(), unordered.settings_hash());
+        assert!(parse(Some(&file), &["media"]).is_err());
+        std::fs::write(&file, "/srv high\n").unwrap();
+        assert!(parse(Some(&file), &["archive", "media"]).is_err());
+        std::fs::remove_dir_all(&base).unwrap();
+
+        // Lighter trees are only listed while the heavier ones have nothing queued
+        let mut queue = WorkQueue::default();
+        queue.push(1, PathBuf::from("/archive"), 0);
+        queue.push(0, PathBuf::from("/media"), 0);
+        queue.push(0, PathBuf::from("/media/films"), 1);
+        assert_eq!(queue.pop(), Some((PathBuf::from("/media"), 0, 0)));
+        assert_eq!(queue.pop(), Some((PathBuf::from("/media/films"), 1, 0)));
+        assert_eq!(queue.pop(), Some((PathBuf::from("/archive"), 0, 1)));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_reconciliation_counts_the_root_and_explains_the_gap() {
+        use crate::reconcile::{is_mount_point, Reconciliation};
+        use filesize::PathExt;
+        use std::sync::atomic::Ordering;
+
+        let dir = std::env::temp_dir().join(format!("ferris-reconcile-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+
+        // The root directory takes space of its own, which the scanner never sizes
+        let metadata = std::fs::metadata(&dir).unwrap();
+        let totals = crate::root_byte_totals(&dir);
+        assert_eq!(totals.root_device, crate::device_of(&metadata));
+        let own = dir.size_on_disk_fast(&metadata).unwrap();
+        assert_eq!(totals.root_device_dir_bytes.load(Ordering::Relaxed), own);
+        totals.add_file(totals.root_device, 4096);
+        totals.add_file(totals.root_device.map(|device| device + 1), 8192);
+        assert_eq!(totals.root_device_file_bytes.load(Ordering::Relaxed), 4096);
+        assert_eq!(crate::root_byte_totals(&dir.join("missing")).root_device, None);
+
+        assert!(is_mount_point(Path::new("/")));
+        assert!(!is_mount_point(&dir.join("sub")));
+
+        let mut rec = Reconciliation {
+            used_bytes: 10_000,
+            file_bytes: 6_000,
+            dir_bytes: 1_000,
+            deleted_open_bytes: None,
+            unscanned_dirs: 2,
+            supports_snapshots: true,
+        };
+        assert_eq!(rec.explained_bytes(), 7_000);
+        assert_eq!(rec.unexplained_bytes(), 3_000);
+        let lines = rec.lines().join("\n");
+        assert!(lines.contains("unknown"), "{}", lines);
+        assert!(lines.contains("2 directories were excluded or unreadable"), "{}", lines);
+        assert!(lines.contains("snapshots"), "{}", lines);
+
+        // Counting hard links more than once can find more than df
+        rec.deleted_open_bytes = Some(500);
+        rec.file_bytes = 12_000;
+        assert_eq!(rec.unexplained_bytes(), -3_500);
+        let lines = rec.lines().join("\n");
+        assert!(lines.contains("Scan exceeds df by"), "{}", lines);
+        assert!(!lines.contains("excluded or unreadable"), "{}", lines);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_move_and_archive_verify_copies_and_skip_changed_files() {
+        use crate::actions::relocate::{archive_entries, move_entries};
+
+        let dir = std::env::temp_dir().join(format!("ferris-relocate-{}", std::process::id()));
+        let root = dir.join("data");
+        std::fs::create_dir_all(root.join("logs")).unwrap();
+        let journal_dir = dir.join("journal");
+        std::fs::create_dir_all(&journal_dir).unwrap();
+
+        let mut stamps = ScanStamps::default();
+        let mut entries = Vec::new();
+        for (name, contents) in [("logs/a.log", "alpha"), ("b.iso", "bravo"), ("logs/c.log", "charlie")] {
+            let path = root.join(name);
+            std::fs::write(&path, contents).unwrap();
+            let key = path.to_str().unwrap().to_string();
+            stamps.insert(key.clone(), Fingerprint::from_metadata(&std::fs::metadata(&path).unwrap()));
+            entries.push((key, contents.len() as u64));
+        }
+        // Changed since the scan, so left alone
+        std::fs::write(root.join("b.iso"), "bravo, grown").unwrap();
+
+        let target = dir.join("moved");
+        let mut journal = Journal::create_in(&journal_dir).unwrap();
+        let selected: Vec<&(String, u64)> = entries[..2].iter().collect();
+        let report = move_entries(&root, &target, &selected, &stamps, &mut journal);
+        assert_eq!(report.succeeded, [entries[0].clone()]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, entries[1].0);
+        assert_eq!(std::fs::read_to_string(target.join("logs/a.log")).unwrap(), "alpha");
+        assert!(!root.join("logs/a.log").exists());
+        assert!(root.join("b.iso").exists());
+        let moved = read_entries(&journal.finish().unwrap().unwrap()).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].action, ActionKind::Move);
+
+        // A file already at the destination is never overwritten
+        std::fs::write(root.join("logs/a.log"), "alpha again").unwrap();
+        let key = entries[0].0.clone();
+        stamps.insert(key.clone(), Fingerprint::from_metadata(&std::fs::metadata(&key).unwrap()));
+        let mut journal = Journal::create_in(&journal_dir).unwrap();
+        let report = move_entries(&root, &target, &[&entries[0]], &stamps, &mut journal);
+        assert_eq!(report.failed[0].1.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(target.join("logs/a.log")).unwrap(), "alpha");
+
+        let archive = dir.join("old.tar");
+        let selected: Vec<&(String, u64)> = entries[1..].iter().collect();
+        let report = archive_entries(&root, &archive, &selected, &stamps, &mut journal).unwrap();
+        assert_eq!(report.succeeded, [entries[2].clone()]);
+        assert_eq!(report.failed.len(), 1);
+        assert!(!root.join("logs/c.log").exists());
+        let mut members = Vec::new();
+        for member in tar::Archive::new(std::fs::File::open(&archive).unwrap()).entries().unwrap() {
+            members.push(member.unwrap().path().unwrap().into_owned());
+        }
+        assert_eq!(members, [Path::new("logs/c.log")]);
+        let archived = read_entries(&journal.finish().unwrap().unwrap()).unwrap();
+        assert_eq!(archived[0].member.as_deref(), Some("logs/c.log"));
+
+        // An existing archive fails the whole action before anything is removed
+        let mut journal = Journal::create_in(&journal_dir).unwrap();
+        assert!(archive_entries(&root, &archive, &[&entries[0]], &stamps, &mut journal).is_err());
+        assert!(root.join("logs/a.log").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_special_files_are_tallied_by_type() {
+        use crate::tally::SkipTally;
+        use std::os::unix::net::UnixListener;
+
+        let dir = std::env::temp_dir().join(format!("ferris-special-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fifo = dir.join("pipe");
+        let c_fifo = std::ffi::CString::new(fifo.to_str().unwrap()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_fifo.as_ptr(), 0o600) }, 0);
+        let _listener = UnixListener::bind(dir.join("socket")).unwrap();
+        std::fs::write(dir.join("regular"), b"sized").unwrap();
+
+        let tally = SkipTally::default();
+        for name in ["pipe", "socket", "regular"] {
+            crate::count_special_file(&std::fs::metadata(dir.join(name)).unwrap(), &tally);
+        }
+        crate::count_special_file(&std::fs::metadata("/dev/null").unwrap(), &tally);
+        assert_eq!(tally.summary(), "1 socket, 1 FIFO, 1 character device");
+        assert_eq!(SkipTally::default().summary(), "none");
+
+        tally.symlinks.fetch_add(3, std::sync::atomic::Ordering::Relaxed);
+        assert!(tally.summary().starts_with("3 symlinks, "));
+        let counts = tally.counts();
+        assert_eq!(counts[0], ("skipped-symlinks", 3));
+        assert_eq!(counts.iter().filter(|(_, count)| *count > 0).count(), 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_deleted_files_held_open_are_found() {
+        use crate::procfs::{deleted_open_files, read_process_name};
+        use std::io::Write;
+        use std::os::unix::fs::MetadataExt;
+        use std::os::unix::io::AsRawFd;
+
+        let path = std::env::temp_dir().join(format!("ferris-deleted-open-{}", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(&[7; 64 * 1024]).unwrap();
+        file.sync_all().unwrap();
+        let metadata = file.metadata().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let found = deleted_open_files().unwrap();
+        let ours = found
+            .iter()
+            .find(|open| open.pid == std::process::id() && open.fd == file.as_raw_fd() as u32)
+            .expect("the deleted file held open by this process");
+        assert_eq!(ours.path, path.to_string_lossy());
+        assert_eq!((ours.device, ours.inode), (metadata.dev(), metadata.ino()));
+        assert_eq!(ours.size, metadata.blocks() * 512);
+        assert_eq!(ours.process_name, read_process_name(Path::new("/proc/self")));
+        assert!(found.windows(2).all(|pair| pair[0].size >= pair[1].size));
+
+        // Closing the descriptor releases the file
+        drop(file);
+        let found = deleted_open_files().unwrap();
+        assert!(!found.iter().any(|open| open.path == path.to_string_lossy()));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_results_are_attributed_to_the_processes_holding_them() {
+        use crate::attribution::{attribute_entries, AttributionSource};
+        use crate::users::UserNames;
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = std::env::temp_dir().join(format!("ferris-attribution-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let held = dir.join("held.log");
+        let idle = dir.join("idle.log");
+        std::fs::write(&held, b"written by this process").unwrap();
+        std::fs::write(&idle, b"left behind").unwrap();
+        let _open = std::fs::File::open(&held).unwrap();
+        let owner = UserNames::default().name(std::fs::metadata(&held).unwrap().uid());
+
+        let entries = vec![
+            (held.to_string_lossy().to_string(), 300),
+            (idle.to_string_lossy().to_string(), 500),
+            (dir.join("gone.log").to_string_lossy().to_string(), 900),
+        ];
+        let groups = attribute_entries(&entries).unwrap();
+        // Vanished files are left out, and the largest group comes first
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].source, AttributionSource::OwnerOnly);
+        assert_eq!(groups[0].files, [entries[1].clone()]);
+        assert_eq!(groups[0].label(), owner);
+
+        assert_eq!(groups[1].source, AttributionSource::HeldOpen);
+        assert_eq!(groups[1].total, 300);
+        assert!(groups[1].processes.iter().any(|process| process.pid == std::process::id()));
+        let label = groups[1].label();
+        assert!(label.starts_with(&format!("{} - ", owner)), "{}", label);
+        assert!(label.contains(&format!("[{}]", std::process::id())), "{}", label);
+        assert!(label.ends_with(" (holds open)"), "{}", label);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sessions_feed_diff_and_query() {
+        use crate::args::SessionArgs;
+        use crate::commands;
+        use crate::results::diff;
+
+        let dir = std::env::temp_dir().join(format!("ferris-sessions-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stamps = ScanStamps::default();
+        let old = SavedScan::new(
+            Path::new("/data"),
+            &[("/data/kept.iso".to_string(), 100), ("/data/shrunk.iso".to_string(), 400), ("/data/gone.iso".to_string(), 50)],
+            &stamps,
+        );
+        let new = SavedScan::new(
+            Path::new("/data"),
+            &[("/data/shrunk.iso".to_string(), 300), ("/data/kept.iso".to_string(), 100), ("/data/new.iso".to_string(), 200)],
+            &stamps,
+        );
+        let old_path = dir.join("old.json");
+        let new_path = dir.join("new.json");
+        old.save(&old_path).unwrap();
+        new.save(&new_path).unwrap();
+        assert_eq!(SavedScan::load(&new_path).unwrap().entries, new.entries);
+
+        // Unchanged entries are not reported, whatever their rank
+        let changes = diff(&old, &new);
+        assert_eq!(changes.added.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), ["/data/new.iso"]);
+        assert_eq!(changes.removed.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), ["/data/gone.iso"]);
+        assert_eq!(changes.resized.len(), 1);
+        assert_eq!((changes.resized[0].0.size, changes.resized[0].1.size), (400, 300));
+        assert_eq!(changes.resized[0].0.id, changes.resized[0].1.id);
+        assert!(diff(&new, &new).added.is_empty() && diff(&new, &new).resized.is_empty());
+
+        // The newer session can be named directly or through --session
+        let session = SessionArgs { session: Some(new_path.to_string_lossy().to_string()) };
+        let old_arg = old_path.to_str().unwrap();
+        assert!(commands::diff(old_arg, None, &session, 10, true).is_ok());
+        assert!(commands::diff(old_arg, Some(new_path.to_str().unwrap()), &SessionArgs::default(), 10, true).is_ok());
+        assert!(commands::query(Some(150), Some("**/*.iso"), None, &session, true).is_ok());
+        assert!(commands::query(None, Some("[unclosed"), None, &session, true).is_err());
+
+        let missing = SessionArgs { session: Some(dir.join("missing.json").to_string_lossy().to_string()) };
+        let err = commands::query(None, None, None, &missing, true).unwrap_err();
+        assert!(err.to_string().contains("run a scan first"), "{}", err);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_ranks_sessions_of_several_hosts() {
+        use crate::commands;
+
+        let dir = std::env::temp_dir().join(format!("ferris-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stamps = ScanStamps::default();
+        let mut web = SavedScan::new(Path::new("/"), &[("/var/log/app.log".to_string(), 300), ("/srv/www.tar".to_string(), 100)], &stamps);
+        web.provenance.host = Some("web-1".to_string());
+        web.provenance.started_at = 1_000;
+        web.provenance.finished_at = 1_060;
+        let mut db = SavedScan::new(Path::new("/"), &[("/var/log/app.log".to_string(), 500), ("/srv/dump.sql".to_string(), 200)], &stamps);
+        // As saved by a version that did not record the host
+        db.provenance.host = None;
+        db.provenance.started_at = 900;
+        db.provenance.finished_at = 1_200;
+        let web_path = dir.join("web.json");
+        let db_path = dir.join("db-2.json");
+        web.save(&web_path).unwrap();
+        db.save(&db_path).unwrap();
+
+        let output = dir.join("merged.json");
+        let sessions = [web_path.to_string_lossy().to_string(), db_path.to_string_lossy().to_string()];
+        commands::merge(&sessions, 3, Some(output.to_str().unwrap()), true).unwrap();
+        let merged = SavedScan::load(&output).unwrap();
+
+        // The largest entries of all hosts, a session without a host named after its file
+        let ranked: Vec<(&str, &str, u64)> = merged
+            .entries
+            .iter()
+            .map(|entry| (entry.host.as_deref().unwrap(), entry.path.as_str(), entry.size))
+            .collect();
+        assert_eq!(
+            ranked,
+            [("db-2", "/var/log/app.log", 500), ("web-1", "/var/log/app.log", 300), ("db-2", "/srv/dump.sql", 200)]
+        );
+        assert_ne!(merged.entries[0].id, merged.entries[1].id);
+        assert_eq!(merged.sources.len(), 2);
+        assert_eq!((merged.provenance.started_at, merged.provenance.finished_at), (900, 1_200));
+
+        // Merging a merged report keeps the hosts and sources it was made of
+        let again = crate::results::merge(&[(merged.clone(), "merged".to_string())], 10);
+        assert_eq!(again.sources.len(), 2);
+        assert_eq!(again.entries[0].host.as_deref(), Some("db-2"));
+        assert_eq!(again.entries[0].id, merged.entries[0].id);
+
+        assert!(commands::merge(&[dir.join("missing.json").to_string_lossy().to_string()], 3, None, true).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sessions_record_their_provenance() {
+        let dir = std::env::temp_dir().join(format!("ferris-provenance-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let before = crate::results::unix_now();
+        let mut scan = SavedScan::new(Path::new("/data"), &[("/data/a.iso".to_string(), 1)], &ScanStamps::default());
+        let provenance = &scan.provenance;
+        assert_eq!(provenance.root, Path::new("/data"));
+        assert_eq!(provenance.host, crate::host::hostname());
+        assert_eq!(provenance.tool_version, env!("CARGO_PKG_VERSION"));
+        assert!(provenance.started_at >= before && provenance.finished_at >= provenance.started_at);
+        assert_eq!(provenance.config_hash, None);
+
+        scan.provenance.host = Some("web-1".to_string());
+        scan.provenance.started_at = 1_714_571_100;
+        scan.provenance.finished_at = 1_714_571_175;
+        scan.provenance.config_hash = Some("fb03ce2d342cb90d".to_string());
+        let described = scan.provenance.describe();
+        assert!(described.starts_with("root /data, host web-1, scanned "), "{}", described);
+        assert!(described.contains(" for 75s, "), "{}", described);
+        assert!(described.ends_with(&format!("ferris-files {}, config fb03ce2d342cb90d", env!("CARGO_PKG_VERSION"))), "{}", described);
+        assert_eq!(Provenance::default().describe(), "root ");
+
+        let path = dir.join("scan.json");
+        scan.save(&path).unwrap();
+        assert_eq!(SavedScan::load(&path).unwrap().provenance, scan.provenance);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_explorer_menu_entry_quotes_the_executable_and_folder() {
+        use crate::integration::{install_shell_integration, menu_command, uninstall_shell_integration};
+
+        let command = menu_command(r"C:\Program Files\ferris-files.exe", "%V");
+        assert_eq!(command, r#"cmd.exe /k ""C:\Program Files\ferris-files.exe" -d "%V"""#);
+        // Once cmd strips the outer quotes, the executable and the folder stay quoted
+        let inner = command.strip_prefix("cmd.exe /k \"").unwrap().strip_suffix('"').unwrap();
+        assert_eq!(inner, r#""C:\Program Files\ferris-files.exe" -d "%V""#);
+
+        if cfg!(not(windows)) {
+            let err = install_shell_integration().unwrap_err().to_string();
+            assert!(err.contains("install-shell-integration"), "{}", err);
+            let err = uninstall_shell_integration().unwrap_err().to_string();
+            assert!(err.contains("uninstall-shell-integration"), "{}", err);
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_quick_action_script_scans_every_selected_folder() {
+        use crate::integration::{install_quick_action, quick_action_script, uninstall_quick_action, xml_escape};
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("ferris-quick action's-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Stands in for the executable, echoing the folder it was asked to scan
+        let exe = dir.join("fake ferris");
+        std::fs::write(&exe, "#!/bin/sh\necho \"scanned $2\"\n").unwrap();
+        std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let report = dir.join("report & notes.txt");
+
+        let script = quick_action_script(&exe.to_string_lossy(), &report.to_string_lossy());
+        // Everything but opening the report, which needs macOS
+        let scan = script.lines().next().unwrap();
+        let status = std::process::Command::new("/bin/sh")
+            .args(["-c", scan, "quick-action", "/Users/me/My Documents", "/tmp/it's here"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(
+            std::fs::read_to_string(&report).unwrap(),
+            "scanned /Users/me/My Documents\n\nscanned /tmp/it's here\n\n"
+        );
+        assert!(script.ends_with(&format!("open {}\n", crate::integration::shell_quote(&report.to_string_lossy()))));
+        assert_eq!(xml_escape("a < b && c > d"), "a &lt; b &amp;&amp; c &gt; d");
+
+        if cfg!(not(target_os = "macos")) {
+            assert!(install_quick_action().unwrap_err().to_string().contains("install-quick-action"));
+            assert!(uninstall_quick_action().unwrap_err().to_string().contains("uninstall-quick-action"));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_portable_mode_keeps_state_next_to_the_executable() {
+        use crate::storage::{cache_dir_for, config_dir_for, portable_root, system_config_dir};
+
+        // Computed without enabling portable mode, which would move the state of every later test
+        let exe = Path::new("/media/usb/tools/ferris-files");
+        let root = portable_root(exe).unwrap();
+        assert_eq!(root, Path::new("/media/usb/tools/ferris-files-data"));
+        assert!(portable_root(Path::new("/")).is_err());
+
+        assert_eq!(cache_dir_for(Some(&root)), Some(root.join("cache")));
+        assert_eq!(config_dir_for(Some(&root)), Some(root.join("config")));
+        // Otherwise state stays in the user's directories
+        for dir in [cache_dir_for(None), config_dir_for(None)].into_iter().flatten() {
+            assert!(!dir.starts_with(&root));
+            assert!(dir.ends_with("ferris-files"), "{}", dir.display());
+        }
+        // Rules set up by administrators still apply
+        if cfg!(unix) {
+            assert_eq!(system_config_dir(), Some(std::path::PathBuf::from("/etc/ferris-files")));
+        }
+    }
+
+    #[test]
+    fn test_capping_concurrent_listings_still_scans_every_directory() {
+        use crate::args::Args;
+        use crate::report::ReportSection;
+        use clap::Parser;
+        use std::collections::BTreeMap;
+
+        #[derive(Default)]
+        struct Sizes(BTreeMap<String, u64>);
+
+        impl Aggregator for Sizes {
+            fn observe(&mut self, file: &FileInfo) {
+                self.0.insert(file.path.clone(), file.size);
+            }
+
+            fn finish(&self) -> ReportSection {
+                ReportSection::default()
+            }
+        }
+
+        let root = std::env::temp_dir().join(format!("ferris-listings-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let root = crate::console::canonicalize(&root).unwrap();
+        let mut expected = BTreeMap::new();
+        for i in 0..6 {
+            for j in 0..4 {
+                let dir = root.join(format!("d{}/e{}", i, j));
+                std::fs::create_dir_all(&dir).unwrap();
+                let file = dir.join("data.bin");
+                std::fs::write(&file, vec![0u8; 100 * i + j]).unwrap();
+                expected.insert(file.to_string_lossy().into_owned(), (100 * i + j) as u64);
+            }
+        }
+
+        let scan = |cap: Option<&str>| {
+            // Read-only, so the developer's last scan is left alone
+            let mut argv = vec!["ferris-files", "-d", root.to_str().unwrap(), "--apparent-size", "--assert-read-only"];
+            if let Some(cap) = cap {
+                argv.extend(["--max-concurrent-dirs", cap]);
+            }
+            let config = Config::build(&Args::try_parse_from(argv).unwrap()).unwrap();
+            assert_eq!(config.max_concurrent_dirs, cap.map(|cap| cap.parse().unwrap()));
+            let mut sizes = Sizes::default();
+            crate::run_with_aggregators(config, &mut [&mut sizes]).unwrap();
+            sizes.0
+        };
+        // A single listing at a time across all workers neither loses nor stalls directories
+        assert_eq!(scan(Some("1")), expected);
+        assert!(Args::try_parse_from(["ferris-files", "--max-concurrent-dirs", "0"]).is_err());
+        assert_eq!(scan(Some("2")), expected);
+        assert_eq!(scan(None), expected);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_custom_aggregators_ride_scans_and_imports() {
+        use crate::args::Args;
+        use crate::report::ReportSection;
+        use clap::Parser;
+
+        /// Counts files and bytes, labelled so the order of the fan-out shows
+        struct Tally(&'static str, usize, u64);
+
+        impl Aggregator for Tally {
+            fn observe(&mut self, file: &FileInfo) {
+                self.1 += 1;
+                self.2 += file.size;
+            }
+
+            fn finish(&self) -> ReportSection {
+                ReportSection { heading: Some(self.0.to_string()), rows: Vec::new() }
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("ferris-plugins-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("tree/sub")).unwrap();
+        std::fs::write(dir.join("tree/a.log"), vec![0u8; 300]).unwrap();
+        std::fs::write(dir.join("tree/sub/b.dat"), vec![0u8; 50]).unwrap();
+        let inventory = dir.join("inventory.txt");
+        std::fs::write(&inventory, "5\t/srv/x.iso\n7\t/srv/y/z.tar\nbogus\n").unwrap();
+
+        let build = |extra: &[&str]| {
+            // Read-only, so the developer's last scan is left alone
+            let mut argv = vec!["ferris-files", "--apparent-size", "--report", "by-ext", "--assert-read-only"];
+            argv.extend(extra);
+            Config::build(&Args::try_parse_from(argv).unwrap()).unwrap()
+        };
+
+        // Several custom aggregators each see every sized file of a scan, beside the built-in reports
+        let (mut first, mut second) = (Tally("first", 0, 0), Tally("second", 0, 0));
+        let tree = dir.join("tree");
+        crate::run_with_aggregators(build(&["-d", tree.to_str().unwrap()]), &mut [&mut first, &mut second]).unwrap();
+        assert_eq!((first.1, first.2), (2, 350));
+        assert_eq!((second.1, second.2), (2, 350));
+
+        // Imports hand them the inventory's files, leaving out the lines that are skipped
+        let mut imported = Tally("imported", 0, 0);
+        crate::run_with_aggregators(build(&["--import", "find", inventory.to_str().unwrap()]), &mut [&mut imported]).unwrap();
+        assert_eq!((imported.1, imported.2), (2, 12));
+
+        // Custom sections come after the requested reports
+        let config = build(&[]);
+        let mut builtins = aggregators(&config, Path::new("/srv"));
+        let mut custom = [&mut first as &mut dyn Aggregator];
+        let joined = crate::with_custom(&mut builtins, &mut custom);
+        assert_eq!(joined.len(), 2);
+        assert_eq!(joined[1].finish().heading.as_deref(), Some("first"));
+        assert_ne!(joined[0].finish().heading.as_deref(), Some("first"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_entries_show_their_share_of_the_bytes_scanned() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let entries = vec![("/srv/a.iso".to_string(), 300), ("/srv/b.tar".to_string(), 100)];
+        let text = Config { num_entries: 2, ..Default::default() };
+        assert_eq!(crate::share_of(300, 1200), Some(25.0));
+        assert_eq!(crate::share_of(300, 0), None);
+        let total = crate::entries_total(&text, &entries, 1200).unwrap();
+        assert!(total.starts_with("These 2 take"), "{}", total);
+        assert!(total.contains("33.3"), "{}", total);
+        assert!(total.contains(&400u64.format_size()), "{}", total);
+        assert!(total.contains(&1200u64.format_size()), "{}", total);
+        let single = crate::entries_total(&text, &entries[..1], 1200).unwrap();
+        assert!(single.starts_with("This one takes"), "{}", single);
+        // Nothing sized leaves no share to give
+        assert_eq!(crate::entries_total(&text, &entries, 0), None);
+
+        let porcelain = Config { porcelain: true, ..text.clone() };
+        assert_eq!(crate::entries_total(&porcelain, &entries, 1200).unwrap(), "total\t400\t1200");
+        // Nested directories would be counted twice
+        let dirs = Config { dirs: true, ..text.clone() };
+        assert_eq!(crate::entries_total(&dirs, &entries, 1200), None);
+
+        // The total the shares refer to is every byte sized by the scan, not just the listed ones
+        let root = std::env::temp_dir().join(format!("ferris-share-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("big.bin"), vec![0u8; 700]).unwrap();
+        std::fs::write(root.join("sub/small.bin"), vec![0u8; 200]).unwrap();
+        std::fs::write(root.join("sub/tiny.bin"), vec![0u8; 100]).unwrap();
+        // Read-only, so the developer's last scan is left alone
+        let argv = ["ferris-files", "-d", root.to_str().unwrap(), "-n", "1", "--apparent-size", "--assert-read-only"];
+        let config = Config::build(&Args::try_parse_from(argv).unwrap()).unwrap();
+        let stats = std::sync::Arc::new(crate::stats::ScanStats::default());
+        crate::run_with_stats(config, &mut [], std::sync::Arc::clone(&stats)).unwrap();
+        assert_eq!(stats.snapshot().bytes, 1000);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_progress_feed_of_a_scan_counts_the_bytes_sized() {
+        use crate::args::Args;
+        use clap::Parser;
+
+        let dir = std::env::temp_dir().join(format!("ferris-feed-bytes-{}", std::process::id()));
+        let root = dir.join("tree");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        for (i, name) in ["a.bin", "b.bin", "sub/c.bin", "sub/d.bin"].iter().enumerate() {
+            std::fs::write(root.join(name), vec![0u8; 1000 * (i + 1)]).unwrap();
+        }
+        let feed = dir.join("progress.jsonl");
+        let argv = [
+            "ferris-files",
+            "-d",
+            root.to_str().unwrap(),
+            "--apparent-size",
+            "--progress-json",
+            feed.to_str().unwrap(),
+        ];
+        let mut config = Config::build(&Args::try_parse_from(argv).unwrap()).unwrap();
+        // Leaves the developer's last scan alone; --assert-read-only would refuse the feed itself
+        config.read_only = true;
+        crate::run(config).unwrap();
+
+        let events: Vec<serde_json::Value> = std::fs::read_to_string(&feed)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let bytes: Vec<u64> = events
+            .iter()
+            .filter(|event| event["event"] != "top")
+            .map(|event| event["bytes"].as_u64().unwrap())
+            .collect();
+        // Bytes only grow, and the scan ends with every file sized
+        assert!(bytes.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", bytes);
+        let done = events.last().unwrap();
+        assert_eq!(done["event"], "done");
+        assert_eq!(done["bytes"], 10_000);
+        assert_eq!(done["processed"], 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_server_refuses_other_hosts_and_sites() {
+        use crate::args::Args;
+        use crate::serve::{Request, ServeOptions, ServerState};
+        use clap::Parser;
+        use std::sync::Arc;
+
+        let config = Config::build(&Args::try_parse_from(["ferris-files", "-d", "/tmp"]).unwrap()).unwrap();
+        let server = |listen: &str| {
+            let options = ServeOptions { listen: listen.parse().unwrap(), token: None, allow_actions: false };
+            Arc::new(ServerState::new(config.clone(), &options))
+        };
+        let request = |headers: &[(&str, &str)]| Request {
+            method: "GET".to_string(),
+            path: "/api/status".to_string(),
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            ..Default::default()
+        };
+
+        let local = server("127.0.0.1:7878");
+        for host in ["127.0.0.1:7878", "localhost:7878", "LOCALHOST:7878"] {
+            assert_eq!(local.respond(&request(&[("host", host)])).status, 200, "{}", host);
+        }
+        // A rebound name, another port or no host at all are refused
+        for host in ["evil.example:7878", "127.0.0.1:8080", "127.0.0.1", "10.0.0.5:7878"] {
+            assert_eq!(local.respond(&request(&[("host", host)])).status, 403, "{}", host);
+        }
+        assert_eq!(local.respond(&request(&[])).status, 403);
+
+        // Pages of other sites are refused, the server's own and non-browser clients are not
+        let host = ("host", "127.0.0.1:7878");
+        assert_eq!(local.respond(&request(&[host, ("origin", "http://127.0.0.1:7878")])).status, 200);
+        assert_eq!(local.respond(&request(&[host, ("origin", "https://evil.example")])).status, 403);
+        assert_eq!(local.respond(&request(&[host, ("origin", "null")])).status, 403);
+        let upgrade = [host, ("origin", "http://evil.example"), ("upgrade", "websocket")];
+        assert!(!local.same_origin(&request(&upgrade)));
+
+        let ipv6 = server("[::1]:7878");
+        assert_eq!(ipv6.respond(&request(&[("host", "[::1]:7878")])).status, 200);
+        assert_eq!(ipv6.respond(&request(&[("host", "[::1]")])).status, 403);
+        // Every interface takes any name, since a token is required there
+        let everywhere = server("0.0.0.0:7878");
+        assert_eq!(everywhere.respond(&request(&[("host", "nas:7878")])).status, 200);
+        assert_eq!(everywhere.respond(&request(&[("host", "nas:7878"), ("origin", "http://evil.example")])).status, 403);
+    }
+
+    #[test]
+    fn test_server_drops_idle_clients_and_caps_its_connections() {
+        use crate::args::Args;
+        use crate::serve::{ServeOptions, Server};
+        use clap::Parser;
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+        use std::time::Duration;
+
+        let config = Config::build(&Args::try_parse_from(["ferris-files", "-d", "/tmp"]).unwrap()).unwrap();
+        let options = ServeOptions { listen: "127.0.0.1:0".parse().unwrap(), token: None, allow_actions: false };
+        let server = Server::bind(config, &options).unwrap().with_limits(2, Duration::from_millis(200));
+        let address = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run());
+        let answer = |mut stream: TcpStream| {
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        // Two idle clients take every slot, so a third is turned away at once
+        let idle = [TcpStream::connect(address).unwrap(), TcpStream::connect(address).unwrap()];
+        let busy = answer(TcpStream::connect(address).unwrap());
+        assert!(busy.starts_with("HTTP/1.1 503"), "{}", busy);
+
+        // The idle clients are dropped once they time out, freeing their slots
+        for stream in idle {
+            assert!(answer(stream).starts_with("HTTP/1.1 400"));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /api/status HTTP/1.1\r\nHost: {}\r\n\r\n", address).unwrap();
+        let status = answer(stream);
+        assert!(status.starts_with("HTTP/1.1 200"), "{}", status);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_moving_across_filesystems_keeps_the_file_and_never_overwrites() {
+        use crate::actions::relocate::move_file;
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = std::env::temp_dir().join(format!("ferris-cross-move-{}", std::process::id()));
+        let other = Path::new("/dev/shm").join(format!("ferris-cross-move-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        // Only meaningful where the two are separate filesystems
+        if std::fs::metadata(&dir).unwrap().dev() != std::fs::metadata(&other).unwrap().dev() {
+            let source = dir.join("report.pdf");
+            std::fs::write(&source, "quarterly").unwrap();
+            std::fs::set_permissions(&source, std::fs::Permissions::from_mode(0o640)).unwrap();
+            let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+            std::fs::File::options().write(true).open(&source).unwrap().set_modified(modified).unwrap();
+
+            let destination = other.join("nested/report.pdf");
+            move_file(&source, &destination).unwrap();
+            assert!(!source.exists());
+            let metadata = std::fs::metadata(&destination).unwrap();
+            assert_eq!(std::fs::read_to_string(&destination).unwrap(), "quarterly");
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+            assert_eq!(metadata.modified().unwrap(), modified);
+
+            // A file at the destination is kept, and so is the one that was to be moved
+            std::fs::write(&source, "draft").unwrap();
+            let err = move_file(&source, &destination).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+            assert_eq!(std::fs::read_to_string(&destination).unwrap(), "quarterly");
+            assert_eq!(std::fs::read_to_string(&source).unwrap(), "draft");
+        }
+
+        // On the same filesystem the file is linked into place, keeping its inode
+        let source = dir.join("notes.txt");
+        std::fs::write(&source, "same disk").unwrap();
+        let inode = std::fs::metadata(&source).unwrap().ino();
+        let destination = dir.join("moved/notes.txt");
+        move_file(&source, &destination).unwrap();
+        assert!(!source.exists());
+        let metadata = std::fs::metadata(&destination).unwrap();
+        assert_eq!((metadata.ino(), metadata.nlink()), (inode, 1));
+        std::fs::write(&source, "newer").unwrap();
+        assert_eq!(move_file(&source, &destination).unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&destination).unwrap(), "same disk");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&other).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_own_files_only_skips_foreign_files_and_closed_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("ferris-foreign-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "mine").unwrap();
+        let me = unsafe { libc::geteuid() };
+        // Seen by another user, everything here belongs to someone else
+        let other = me.wrapping_add(1);
+
+        let metadata = std::fs::metadata(&file).unwrap();
+        assert!(!crate::is_foreign_owned(&metadata, me));
+        assert!(crate::is_foreign_owned(&metadata, other));
+
+        // Foreign directories are only skipped when others may not list and enter them
+        let shared = dir.join("shared");
+        std::fs::create_dir(&shared).unwrap();
+        for (mode, foreign) in [(0o755, false), (0o775, false), (0o751, true), (0o754, true), (0o700, true)] {
+            std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(mode)).unwrap();
+            let metadata = std::fs::metadata(&shared).unwrap();
+            assert_eq!(crate::is_foreign_owned(&metadata, other), foreign, "{:o}", mode);
+            assert!(!crate::is_foreign_owned(&metadata, me));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ties_kept_for_the_secondary_key_are_capped() {
+        // However many files share the cutoff size, only so many of them are kept to be ordered
+        let tied: Vec<(String, u64)> = (0..MAX_TIES * 3).map(|i| (format!("/t/{:05}", i), 100)).collect();
+
+        let mut sorted = TopEntries::new(3).with_ties();
+        for (path, size) in &tied[..MAX_TIES * 2] {
+            sorted.insert(path.clone(), *size);
+        }
+        sorted.insert_batch(&tied[MAX_TIES * 2..]);
+        assert_eq!(sorted.get_entries().len(), 3 + MAX_TIES);
+
+        let max = HEAP_THRESHOLD + 1;
+        let mut heap = TopEntries::new(max).with_ties();
+        heap.insert_batch(&tied);
+        assert_eq!(heap.get_entries().len(), max + MAX_TIES);
+        assert!(!heap.insert("/t/late".to_string(), 100));
+
+        let concurrent = ConcurrentTopEntries::with_shards(3, 4).with_ties();
+        concurrent.insert_batch(&tied);
+        assert_eq!(concurrent.get_entries().len(), 3 + MAX_TIES);
+    }
+
+    #[test]
+    fn test_skip_summary_words_one_entry_in_the_singular() {
+        use crate::tally::SkipTally;
+        use std::sync::atomic::Ordering;
+
+        let tally = SkipTally::default();
+        tally.symlinks.fetch_add(1, Ordering::Relaxed);
+        tally.fifos.fetch_add(2, Ordering::Relaxed);
+        tally.excluded_dirs.fetch_add(1, Ordering::Relaxed);
+        tally.foreign_owned.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(
+            tally.summary(),
+            "1 symlink, 2 FIFOs, 1 excluded directory, 1 entry owned by another user"
+        );
+
+        tally.symlinks.fetch_add(1, Ordering::Relaxed);
+        tally.excluded_dirs.fetch_add(4, Ordering::Relaxed);
+        assert!(tally.summary().starts_with("2 symlinks, 2 FIFOs, 5 excluded directories, "));
+    }
+}
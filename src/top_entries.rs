@@ -1,8 +1,41 @@
-/// A data structure that maintains a fixed-size collection of entries sorted by numeric value in descending order.
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::hash_map::RandomState;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Number of entries above which `TopEntries` keeps a bounded min-heap instead of a sorted vector.
+///
+/// Inserting into a sorted vector shifts on average half of it, which is negligible for the usual
+/// handful of entries but adds up for `-n` in the thousands, where a heap insert stays logarithmic.
+pub const HEAP_THRESHOLD: usize = 1024;
+
+/// Number of entries tied with the smallest one that a collection keeping ties (see
+/// [`TopK::with_ties`]) keeps at most beyond its `max_entries`.
 ///
-/// `TopEntries` keeps track of the `max_entries` largest values it has seen, along with associated filepath.
+/// Files of one size can be countless (empty files, or fixed-size blocks of a store), and only
+/// so many of them are worth ordering; further ties are dropped as if ties were not kept.
+pub const MAX_TIES: usize = 1024;
+
+/// Default number of shards of a [`ConcurrentTopEntries`].
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// A data structure that maintains a fixed-size collection of entries sorted by value in descending order.
+///
+/// `TopK` keeps track of the `max_entries` largest values it has seen, along with associated filepath.
 /// When a new entry is inserted, it is automatically placed in the correct position to maintain the descending order,
-/// and if the collection exceeds its capacity, the smallest value is dropped.
+/// and if the collection exceeds its capacity, the smallest value is dropped. Each path is kept at most
+/// once: inserting a path again replaces its earlier size.
+///
+/// The values can be anything ordered: sizes in a [`TopEntries`], but also times, lengths or
+/// the keys of a custom metric (see [`crate::report::TopFilesBy`]). Use [`std::cmp::Reverse`]
+/// to keep the lowest values instead.
+///
+/// Up to [`HEAP_THRESHOLD`] entries the collection is a vector kept sorted on every insert. Above
+/// it, entries go into a min-heap that only has to know its smallest entry, and are sorted once
+/// when [`TopK::get_entries`] is called. Both keep the same entries in the same order.
 ///
 /// # Examples
 ///
@@ -21,42 +54,100 @@
 /// assert_eq!(entries[1].1, 100);  // Second largest value
 /// ```
 #[derive(Debug)]
-pub struct TopEntries {
-    pub entries: Vec<(String, u64)>,
+pub struct TopK<M = u64> {
+    entries: Vec<(String, M)>,
     pub max_entries: usize,
+    paths: HashSet<String>,
+    /// Entries keyed by value and insertion order, the smallest and oldest on top
+    heap: Option<BinaryHeap<Reverse<(M, u64, String)>>>,
+    /// Number of heap inserts so far, to order entries of equal value
+    inserted: u64,
+    /// Whether `entries` has to be rebuilt from the heap
+    unsorted: bool,
+    /// Whether entries tied with the smallest one kept are kept as well (see [`TopK::with_ties`])
+    keep_ties: bool,
+    /// Entries tied with the smallest one in the heap that did not fit in it
+    ties: Vec<(String, M)>,
 }
 
-impl TopEntries {
-    /// Creates a new `TopEntries` instance.
+/// A [`TopK`] of sizes, or of another metric mapped to a `u64` (see
+/// [`crate::report::Metric::value`]), as ranked by scans.
+pub type TopEntries = TopK<u64>;
+
+impl<M: Ord + Clone> TopK<M> {
+    /// Creates a new `TopK` instance.
     ///
     /// The internal vector is pre-allocated with capacity `max_entries + 1` to optimize
     /// for the case where we temporarily need to hold an extra entry before dropping the smallest one.
+    /// Above [`HEAP_THRESHOLD`] entries, a heap of the same capacity is used instead.
     ///
     /// # Examples
     ///
     /// ```
     /// # use ferris_files::top_entries::TopEntries;
-    /// let top = TopEntries::new(3);
+    /// let mut top = TopEntries::new(3);
     /// assert_eq!(top.get_entries().len(), 0);
     /// ```
     pub fn new(max_entries: usize) -> Self {
+        let heap =
+            (max_entries > HEAP_THRESHOLD).then(|| BinaryHeap::with_capacity(max_entries + 1));
         Self {
-            entries: Vec::with_capacity(max_entries + 1),
+            entries: if heap.is_some() {
+                Vec::new()
+            } else {
+                Vec::with_capacity(max_entries + 1)
+            },
             max_entries,
+            paths: HashSet::with_capacity(max_entries + 1),
+            heap,
+            inserted: 0,
+            unsorted: false,
+            keep_ties: false,
+            ties: Vec::new(),
         }
     }
 
+    /// Also keeps, beyond `max_entries`, up to [`MAX_TIES`] entries tied with the smallest one
+    /// kept, for callers that break ties themselves (e.g. `--then-by`) and must see them to do so.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::TopEntries;
+    /// let mut top = TopEntries::new(2).with_ties();
+    /// top.insert("a".to_string(), 200);
+    /// top.insert("b".to_string(), 100);
+    /// assert!(top.insert("c".to_string(), 100));
+    /// assert_eq!(top.get_entries().len(), 3);
+    ///
+    /// // A larger entry pushes out every entry of the old cutoff
+    /// top.insert("d".to_string(), 300);
+    /// assert_eq!(top.get_entries(), [("d".to_string(), 300), ("a".to_string(), 200)]);
+    /// ```
+    pub fn with_ties(mut self) -> Self {
+        self.keep_ties = true;
+        self
+    }
+
     /// Inserts a new entry into the collection, maintaining the descending order by size.
     ///
     /// If the new entry's size is larger than the smallest current entry (or if the collection
     /// isn't at capacity), the entry is inserted in the correct position to maintain descending order.
     /// If this causes the collection to exceed its capacity, the smallest entry is dropped.
     ///
+    /// A path that is already present (e.g. reached through a bind mount or a second root, or
+    /// sized again by a re-scan) is replaced rather than listed twice: the latest size wins, as
+    /// it is the most recent observation of the file.
+    ///
     /// # Arguments
     ///
     /// * `path` - A String identifier for the entry
     /// * `size` - The numeric value associated with the entry
     ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the entry was kept in the collection
+    ///
     /// # Examples
     ///
     /// ```
@@ -71,29 +162,293 @@ impl TopEntries {
     /// let entries = top.get_entries();
     /// assert_eq!(entries[0], ("largest".to_string(), 100));
     /// assert_eq!(entries[1], ("medium".to_string(), 50));
+    ///
+    /// // Inserting a path again updates it in place of a duplicate
+    /// top.insert("medium".to_string(), 150);
+    /// assert_eq!(top.get_entries(), [("medium".to_string(), 150), ("largest".to_string(), 100)]);
     /// ```
     ///
     /// # Notes
     ///
     /// * If the collection is at capacity and the new entry's size is smaller than or equal to
-    ///   the smallest current entry, the new entry is not inserted at all.
+    ///   the smallest current entry, the new entry is not inserted at all, unless it is equal
+    ///   and the collection keeps ties (see [`TopK::with_ties`]).
     /// * The insertion uses binary search (`partition_point`) to efficiently find the correct
     ///   position while maintaining the descending order.
-    pub fn insert(&mut self, path: String, size: u64) {
-        if self.entries.len() < self.max_entries
-            || size > self.entries.last().map(|(_, s)| *s).unwrap_or(0)
+    /// * Above [`HEAP_THRESHOLD`] entries, the entry is pushed on the heap instead and sorted
+    ///   into place by the next [`TopEntries::get_entries`].
+    pub fn insert(&mut self, path: String, size: M) -> bool {
+        if self.heap.is_some() {
+            return self.insert_into_heap(path, size);
+        }
+
+        if self.paths.remove(&path) {
+            if let Some(idx) = self.entries.iter().position(|(p, _)| *p == path) {
+                self.entries.remove(idx);
+            }
+        }
+
+        if self
+            .threshold()
+            .is_none_or(|min| size > min || (self.has_room_for_ties() && size == min))
         {
             let idx = self.entries.partition_point(|(_, s)| *s > size);
+            self.paths.insert(path.clone());
             self.entries.insert(idx, (path, size));
 
             if self.entries.len() > self.max_entries {
-                self.entries.pop();
+                // Ties of the smallest kept entry stay past `max_entries` when asked to
+                let keep = match self.threshold() {
+                    Some(min) if self.keep_ties => self
+                        .entries
+                        .partition_point(|(_, s)| *s >= min)
+                        .min(self.max_entries + MAX_TIES),
+                    _ => self.max_entries,
+                };
+                for (dropped, _) in self.entries.drain(keep..) {
+                    self.paths.remove(&dropped);
+                }
             }
+            return idx < self.entries.len();
         }
+        false
+    }
+
+    /// Inserts many entries at once, as produced by sizing a whole batch of files.
+    ///
+    /// Items that cannot make it into a full collection are filtered out first, and the rest
+    /// is sorted once and merged with the current entries, instead of shifting the entries for
+    /// every item. As with [`TopEntries::insert`], a path already present or repeated within
+    /// `items` keeps only its latest size, and among entries of equal size the later ones rank
+    /// first.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - `(path, size)` pairs, in the order they were sized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::TopEntries;
+    /// let mut top = TopEntries::new(3);
+    /// top.insert("a".to_string(), 100);
+    ///
+    /// top.insert_batch(&[
+    ///     ("b".to_string(), 300),
+    ///     ("c".to_string(), 10),
+    ///     ("d".to_string(), 200),
+    ///     ("a".to_string(), 50),
+    /// ]);
+    /// assert_eq!(
+    ///     top.get_entries(),
+    ///     [("b".to_string(), 300), ("d".to_string(), 200), ("a".to_string(), 50)]
+    /// );
+    /// ```
+    pub fn insert_batch(&mut self, items: &[(String, M)]) {
+        self.insert_all(items.iter().collect());
+    }
+
+    /// Returns true if an entry for `path` is currently kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::TopEntries;
+    /// let mut top = TopEntries::new(1);
+    /// top.insert("a".to_string(), 100);
+    /// top.insert("b".to_string(), 200);
+    /// assert!(top.contains("b"));
+    /// assert!(!top.contains("a"));
+    /// ```
+    pub fn contains(&self, path: &str) -> bool {
+        self.paths.contains(path)
+    }
+
+    /// Returns the size a new entry has to exceed to be kept, i.e. the smallest size kept once
+    /// the collection is full, or `None` while there is still room. A collection keeping ties
+    /// (see [`TopK::with_ties`]) also keeps entries of exactly that size.
+    ///
+    /// Callers can drop smaller files before inserting them. Only an entry for a path that is
+    /// already kept would still make a difference, as its new size replaces the old one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::TopEntries;
+    /// let mut top = TopEntries::new(2);
+    /// top.insert("a".to_string(), 100);
+    /// assert_eq!(top.threshold(), None);
+    ///
+    /// top.insert("b".to_string(), 200);
+    /// assert_eq!(top.threshold(), Some(100));
+    /// assert!(!top.insert("c".to_string(), 100));
+    /// ```
+    pub fn threshold(&self) -> Option<M> {
+        match &self.heap {
+            Some(heap) if heap.len() >= self.max_entries => {
+                heap.peek().map(|Reverse((s, _, _))| s.clone())
+            }
+            None if self.entries.len() >= self.max_entries => self
+                .max_entries
+                .checked_sub(1)
+                .and_then(|last| self.entries.get(last))
+                .map(|(_, s)| s.clone()),
+            _ => None,
+        }
+    }
+
+    /// Whether an entry tied with the smallest one kept would be kept as well.
+    fn has_room_for_ties(&self) -> bool {
+        self.keep_ties
+            && match self.heap {
+                Some(_) => self.ties.len() < MAX_TIES,
+                None => self.entries.len() < self.max_entries + MAX_TIES,
+            }
+    }
+
+    /// Shared implementation of [`TopEntries::insert_batch`] and
+    /// [`ConcurrentTopEntries::insert_batch`].
+    fn insert_all(&mut self, items: Vec<&(String, M)>) {
+        // Only the last occurrence of a path counts, as it would replace the earlier ones
+        let mut latest: HashMap<&str, usize> = HashMap::with_capacity(items.len());
+        for (idx, (path, _)) in items.iter().enumerate() {
+            latest.insert(path.as_str(), idx);
+        }
+        // A repeated path gives up its place, which may let smaller items in
+        if self.heap.is_none() {
+            self.entries
+                .retain(|(path, _)| !latest.contains_key(path.as_str()));
+            self.paths
+                .retain(|path| !latest.contains_key(path.as_str()));
+        }
+        // Paths still kept in the heap are let through, since their new size replaces the old one
+        let smallest = self.threshold();
+        let mut candidates: Vec<(usize, &(String, M))> = items
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, (path, size))| {
+                latest[path.as_str()] == *idx
+                    && (smallest
+                        .as_ref()
+                        .is_none_or(|min| size > min || (self.keep_ties && size == min))
+                        || self.paths.contains(path))
+            })
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        if self.heap.is_some() {
+            for (_, (path, size)) in candidates {
+                self.insert_into_heap(path.clone(), size.clone());
+            }
+            return;
+        }
+
+        candidates.sort_unstable_by(|(a_idx, a), (b_idx, b)| b.1.cmp(&a.1).then(b_idx.cmp(a_idx)));
+
+        let kept = std::mem::replace(&mut self.entries, Vec::with_capacity(self.max_entries + 1));
+        let mut kept = kept.into_iter().peekable();
+        let mut candidates = candidates.into_iter().peekable();
+        while self.entries.len() < self.max_entries {
+            let take_candidate = match (candidates.peek(), kept.peek()) {
+                (Some((_, (_, new))), Some((_, old))) => new >= old,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            if take_candidate {
+                if let Some((_, entry)) = candidates.next() {
+                    self.entries.push(entry.clone());
+                }
+            } else if let Some(entry) = kept.next() {
+                self.entries.push(entry);
+            }
+        }
+        if let Some(min) = self.keep_ties.then(|| self.threshold()).flatten() {
+            // Whatever is left is no larger than the smallest entry kept
+            let ties = kept.take_while(|(_, s)| *s == min).chain(
+                candidates
+                    .map(|(_, entry)| entry.clone())
+                    .take_while(|(_, s)| *s == min),
+            );
+            self.entries.extend(ties.take(MAX_TIES));
+        }
+        self.paths = self.entries.iter().map(|(path, _)| path.clone()).collect();
+    }
+
+    /// Heap counterpart of [`TopEntries::insert`].
+    ///
+    /// Among entries of equal size the oldest is dropped first and the newest listed first, as
+    /// with the sorted vector.
+    fn insert_into_heap(&mut self, path: String, size: M) -> bool {
+        let Some(heap) = self.heap.as_mut() else {
+            return false;
+        };
+        if self.paths.remove(&path) {
+            // Repeated paths are rare, so a linear sweep is cheaper than tracking positions
+            heap.retain(|Reverse((_, _, kept))| *kept != path);
+            self.ties.retain(|(kept, _)| *kept != path);
+            // A tie takes the place given up in the heap
+            if heap.len() < self.max_entries {
+                if let Some((tie, tied)) = self.ties.pop() {
+                    self.inserted += 1;
+                    heap.push(Reverse((tied, self.inserted, tie)));
+                }
+            }
+            self.unsorted = true;
+        }
+
+        if heap.len() < self.max_entries
+            || heap
+                .peek()
+                .is_some_and(|Reverse((smallest, _, _))| size > *smallest)
+        {
+            self.inserted += 1;
+            self.paths.insert(path.clone());
+            heap.push(Reverse((size, self.inserted, path)));
+
+            if heap.len() > self.max_entries {
+                if let Some(Reverse((dropped_size, _, dropped))) = heap.pop() {
+                    let tied = self.keep_ties
+                        && heap
+                            .peek()
+                            .is_some_and(|Reverse((smallest, _, _))| *smallest == dropped_size);
+                    if !tied {
+                        // The ties were of the same size as the dropped entry
+                        for (tie, _) in self.ties.drain(..) {
+                            self.paths.remove(&tie);
+                        }
+                    }
+                    if tied && self.ties.len() < MAX_TIES {
+                        self.ties.push((dropped, dropped_size));
+                    } else {
+                        self.paths.remove(&dropped);
+                    }
+                }
+            }
+            self.unsorted = true;
+            return true;
+        }
+        if self.keep_ties
+            && self.ties.len() < MAX_TIES
+            && heap
+                .peek()
+                .is_some_and(|Reverse((smallest, _, _))| size == *smallest)
+        {
+            self.paths.insert(path.clone());
+            self.ties.push((path, size));
+            self.unsorted = true;
+            return true;
+        }
+        false
     }
 
     /// Returns a reference to the slice containing all entries in descending order by size.
     ///
+    /// Above [`HEAP_THRESHOLD`] entries this sorts the entries inserted since the last call, which
+    /// is why it takes `&mut self`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -106,8 +461,293 @@ impl TopEntries {
     /// assert_eq!(entries.len(), 2);
     /// assert!(entries[0].1 > entries[1].1);  // Verifies descending order
     /// ```
-    #[allow(dead_code)]
-    pub fn get_entries(&self) -> &[(String, u64)] {
+    pub fn get_entries(&mut self) -> &[(String, M)] {
+        if self.unsorted {
+            if let Some(heap) = &self.heap {
+                self.entries = sorted_from_heap(heap, &self.ties);
+            }
+            self.unsorted = false;
+        }
         &self.entries
     }
+
+    /// Returns a copy of all entries in descending order by size, for holders of a shared
+    /// reference.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::TopEntries;
+    /// let mut top = TopEntries::new(2);
+    /// top.insert("a".to_string(), 100);
+    /// top.insert("b".to_string(), 200);
+    ///
+    /// let shared = &top;
+    /// assert_eq!(shared.to_vec(), [("b".to_string(), 200), ("a".to_string(), 100)]);
+    /// ```
+    pub fn to_vec(&self) -> Vec<(String, M)> {
+        match &self.heap {
+            Some(heap) if self.unsorted => sorted_from_heap(heap, &self.ties),
+            _ => self.entries.clone(),
+        }
+    }
+}
+
+/// Sorts the entries of a heap-backed `TopK` in descending order by value, followed by the
+/// `ties` of its smallest entry.
+fn sorted_from_heap<M: Ord + Clone>(
+    heap: &BinaryHeap<Reverse<(M, u64, String)>>,
+    ties: &[(String, M)],
+) -> Vec<(String, M)> {
+    let mut sorted: Vec<&(M, u64, String)> = heap.iter().map(|Reverse(entry)| entry).collect();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    sorted
+        .into_iter()
+        .map(|(size, _, path)| (path.clone(), size.clone()))
+        .chain(ties.iter().cloned())
+        .collect()
+}
+
+/// A thread-safe `TopEntries` that spreads its entries over several independently locked shards.
+///
+/// Each path always lands in the same shard, which keeps its `max_entries` largest entries. The
+/// overall largest entries are among those, so they are merged only when the entries are read.
+/// Threads inserting at the same time thus rarely wait for each other, unlike with a single
+/// `Mutex<TopEntries>`.
+///
+/// # Examples
+///
+/// ```
+/// # use ferris_files::top_entries::ConcurrentTopEntries;
+/// use std::thread;
+///
+/// let top = ConcurrentTopEntries::new(3);
+/// thread::scope(|scope| {
+///     for worker in 0..4u64 {
+///         let top = &top;
+///         scope.spawn(move || {
+///             for i in 0..100u64 {
+///                 top.insert(format!("file_{}_{}", worker, i), worker * 100 + i);
+///             }
+///         });
+///     }
+/// });
+///
+/// let entries = top.get_entries();
+/// assert_eq!(entries.len(), 3);
+/// assert_eq!(entries[0], ("file_3_99".to_string(), 399));
+/// assert_eq!(entries[2], ("file_3_97".to_string(), 397));
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentTopEntries {
+    shards: Vec<Mutex<TopEntries>>,
+    pub max_entries: usize,
+    hasher: RandomState,
+    /// Threshold of every shard, readable without locking it (0 for none, otherwise one more)
+    thresholds: Vec<AtomicU64>,
+    /// Whether entries tied with the smallest one kept are kept as well
+    keep_ties: bool,
+}
+
+impl ConcurrentTopEntries {
+    /// Creates a new `ConcurrentTopEntries` instance with [`DEFAULT_SHARDS`] shards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::ConcurrentTopEntries;
+    /// let top = ConcurrentTopEntries::new(3);
+    /// assert_eq!(top.get_entries().len(), 0);
+    /// ```
+    pub fn new(max_entries: usize) -> Self {
+        Self::with_shards(max_entries, DEFAULT_SHARDS)
+    }
+
+    /// Creates a new `ConcurrentTopEntries` instance with the given number of shards.
+    ///
+    /// More shards mean less waiting between inserting threads, but more entries to merge when
+    /// reading them, as every shard may hold up to `max_entries`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_entries` - Number of largest entries to keep
+    /// * `shards` - Number of independently locked shards, at least 1
+    pub fn with_shards(max_entries: usize, shards: usize) -> Self {
+        let shards = shards.max(1);
+        Self {
+            shards: (0..shards)
+                .map(|_| Mutex::new(TopEntries::new(max_entries)))
+                .collect(),
+            max_entries,
+            hasher: RandomState::new(),
+            thresholds: (0..shards).map(|_| AtomicU64::new(0)).collect(),
+            keep_ties: false,
+        }
+    }
+
+    /// Also keeps up to [`MAX_TIES`] entries tied with the smallest one kept, like
+    /// [`TopEntries::with_ties`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::ConcurrentTopEntries;
+    /// let top = ConcurrentTopEntries::with_shards(2, 4).with_ties();
+    /// for path in ["d", "c", "b", "a"] {
+    ///     top.insert(path.to_string(), 100);
+    /// }
+    /// assert!(top.keeps_ties());
+    /// assert_eq!(top.get_entries().len(), 4);
+    /// ```
+    pub fn with_ties(mut self) -> Self {
+        self.keep_ties = true;
+        for shard in &mut self.shards {
+            let top = shard.get_mut().unwrap_or_else(PoisonError::into_inner);
+            top.keep_ties = true;
+        }
+        self
+    }
+
+    /// Whether entries of exactly the [`ConcurrentTopEntries::threshold`] are kept as well.
+    pub fn keeps_ties(&self) -> bool {
+        self.keep_ties
+    }
+
+    /// Inserts a new entry, like [`TopEntries::insert`] but through a shared reference.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the entry was kept in its shard. An entry kept there may still fall
+    ///   outside the overall largest entries, but one that was not kept never makes it in.
+    pub fn insert(&self, path: String, size: u64) -> bool {
+        let shard = self.shard_of(&path);
+        let mut top = self.shards[shard]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let kept = top.insert(path, size);
+        self.publish_threshold(shard, &top);
+        kept
+    }
+
+    /// Inserts many entries at once, like [`TopEntries::insert_batch`], locking every shard
+    /// only once for the whole batch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::ConcurrentTopEntries;
+    /// let top = ConcurrentTopEntries::new(2);
+    /// top.insert_batch(&[
+    ///     ("a".to_string(), 100),
+    ///     ("b".to_string(), 300),
+    ///     ("c".to_string(), 200),
+    /// ]);
+    /// assert!(top.contains("b"));
+    /// assert_eq!(top.get_entries(), [("b".to_string(), 300), ("c".to_string(), 200)]);
+    /// ```
+    pub fn insert_batch(&self, items: &[(String, u64)]) {
+        let mut per_shard: Vec<Vec<&(String, u64)>> = vec![Vec::new(); self.shards.len()];
+        for item in items {
+            per_shard[self.shard_of(&item.0)].push(item);
+        }
+        per_shard
+            .into_par_iter()
+            .enumerate()
+            .filter(|(_, items)| !items.is_empty())
+            .for_each(|(shard, items)| {
+                let mut top = self.shards[shard]
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                top.insert_all(items);
+                self.publish_threshold(shard, &top);
+            });
+    }
+
+    /// Returns the size a new entry has to exceed to be kept by any shard, like
+    /// [`TopEntries::threshold`], without taking any lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::ConcurrentTopEntries;
+    /// let top = ConcurrentTopEntries::with_shards(1, 2);
+    /// assert_eq!(top.threshold(), None);
+    ///
+    /// top.insert_batch(&(0..100).map(|i| (format!("file_{}", i), i)).collect::<Vec<_>>());
+    /// let threshold = top.threshold().unwrap();
+    /// assert!(threshold < 99);
+    /// ```
+    pub fn threshold(&self) -> Option<u64> {
+        self.thresholds
+            .iter()
+            .map(|threshold| threshold.load(Ordering::Relaxed).checked_sub(1))
+            .min()
+            .flatten()
+    }
+
+    /// Makes the threshold of `shard` available to [`ConcurrentTopEntries::threshold`].
+    fn publish_threshold(&self, shard: usize, top: &TopEntries) {
+        let encoded = top
+            .threshold()
+            .map_or(0, |threshold| threshold.saturating_add(1));
+        self.thresholds[shard].store(encoded, Ordering::Relaxed);
+    }
+
+    /// Returns true if an entry for `path` is currently kept in its shard.
+    ///
+    /// A shard keeps up to `max_entries` on its own, so the entry may still be left out by
+    /// [`ConcurrentTopEntries::get_entries`].
+    pub fn contains(&self, path: &str) -> bool {
+        self.shards[self.shard_of(path)]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(path)
+    }
+
+    /// Index of the shard holding `path`.
+    fn shard_of(&self, path: &str) -> usize {
+        self.hasher.hash_one(path) as usize % self.shards.len()
+    }
+
+    /// Returns all entries in descending order by size, merged from the shards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ferris_files::top_entries::ConcurrentTopEntries;
+    /// let top = ConcurrentTopEntries::with_shards(2, 4);
+    /// top.insert("a".to_string(), 100);
+    /// top.insert("b".to_string(), 200);
+    /// top.insert("c".to_string(), 50);
+    ///
+    /// let entries = top.get_entries();
+    /// assert_eq!(entries, [("b".to_string(), 200), ("a".to_string(), 100)]);
+    /// ```
+    pub fn get_entries(&self) -> Vec<(String, u64)> {
+        let mut entries: Vec<(String, u64)> = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                shard
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get_entries()
+                    .to_vec()
+            })
+            .collect();
+        entries.sort_by_key(|(_, size)| Reverse(*size));
+        let cutoff = self
+            .max_entries
+            .checked_sub(1)
+            .and_then(|last| entries.get(last))
+            .map(|(_, size)| *size);
+        match cutoff {
+            Some(min) if self.keep_ties => {
+                entries.retain(|(_, size)| *size >= min);
+                entries.truncate(self.max_entries + MAX_TIES);
+            }
+            _ => entries.truncate(self.max_entries),
+        }
+        entries
+    }
 }
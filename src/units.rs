@@ -0,0 +1,238 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Parses a human readable size such as `100MB`, `1.5 GiB` or `4096` into a number of bytes.
+///
+/// Units follow the usual conventions:
+/// - no unit or `B` - bytes
+/// - `KB`, `MB`, `GB`, `TB` - SI units (powers of 1000)
+/// - `KiB`, `MiB`, `GiB`, `TiB` - binary units (powers of 1024)
+/// - `K`, `M`, `G`, `T` - binary units, matching tools such as `du` and `ls -h`
+///
+/// Units are case insensitive and may be separated from the number by whitespace.
+/// Fractional values are rounded down to whole bytes.
+///
+/// # Errors
+///
+/// Returns a description of the problem if the number or unit cannot be parsed,
+/// or if the value does not fit into a `u64`.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::units::parse_size;
+///
+/// assert_eq!(parse_size("512"), Ok(512));
+/// assert_eq!(parse_size("100MB"), Ok(100_000_000));
+/// assert_eq!(parse_size("1.5 GiB"), Ok(1_610_612_736));
+/// assert_eq!(parse_size("2k"), Ok(2048));
+/// assert!(parse_size("ten MB").is_err());
+/// ```
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    if number.is_empty() {
+        return Err(format!("'{}' does not start with a number", input));
+    }
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number", number))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u64.pow(2),
+        "gb" => 1000u64.pow(3),
+        "tb" => 1000u64.pow(4),
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        other => return Err(format!("unknown size unit '{}'", other)),
+    };
+
+    let bytes = value * multiplier as f64;
+    if !bytes.is_finite() || bytes >= u64::MAX as f64 {
+        return Err(format!("'{}' is too large", input));
+    }
+    Ok(bytes as u64)
+}
+
+/// Parses a human readable duration such as `90d`, `12h` or `2w` into a [`Duration`].
+///
+/// Supported units are `s` (seconds), `m` (minutes), `h` (hours), `d` (days), `w` (weeks)
+/// and `y` (365 days). A bare number is interpreted as days, the most common unit for file ages.
+///
+/// # Errors
+///
+/// Returns a description of the problem if the number or unit cannot be parsed.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::units::parse_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90 * 86_400)));
+/// assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3_600)));
+/// assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * 86_400)));
+/// assert!(parse_duration("soon").is_err());
+/// ```
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("'{}' does not start with a whole number", input))?;
+    let seconds: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "" | "d" => 86_400,
+        "w" => 7 * 86_400,
+        "y" => 365 * 86_400,
+        other => return Err(format!("unknown duration unit '{}'", other)),
+    };
+
+    value
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("'{}' is too large", input))
+}
+
+/// Whether [`format_unix_time`] renders timestamps in UTC rather than local time.
+static UTC: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`format_unix_time`] render timestamps as ISO-8601 UTC (`2024-05-01T13:45:00Z`)
+/// instead of in the local timezone, e.g. for output compared across machines.
+pub fn use_utc(utc: bool) {
+    UTC.store(utc, Ordering::Relaxed);
+}
+
+/// Formats seconds since the Unix epoch for display: in the local timezone with its offset
+/// from UTC (`2024-05-01 15:45:00 +02:00`), or as ISO-8601 UTC once [`use_utc`] is set or where
+/// the local timezone cannot be determined.
+///
+/// All human readable output renders timestamps through this function.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::units::{format_unix_time, use_utc};
+///
+/// use_utc(true);
+/// assert_eq!(format_unix_time(0), "1970-01-01T00:00:00Z");
+/// assert_eq!(format_unix_time(1_714_571_100), "2024-05-01T13:45:00Z");
+/// ```
+pub fn format_unix_time(seconds: u64) -> String {
+    let offset = if UTC.load(Ordering::Relaxed) {
+        None
+    } else {
+        local_offset(seconds)
+    };
+    format_timestamp(seconds, offset)
+}
+
+/// Formats seconds since the Unix epoch at `offset` seconds east of UTC, or as ISO-8601 UTC
+/// without an offset.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::units::format_timestamp;
+///
+/// assert_eq!(format_timestamp(1_714_571_100, None), "2024-05-01T13:45:00Z");
+/// assert_eq!(format_timestamp(1_714_571_100, Some(7_200)), "2024-05-01 15:45:00 +02:00");
+/// assert_eq!(format_timestamp(1_714_571_100, Some(-16_200)), "2024-05-01 09:15:00 -04:30");
+/// ```
+pub fn format_timestamp(seconds: u64, offset: Option<i32>) -> String {
+    let local = seconds as i64 + i64::from(offset.unwrap_or(0));
+    let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+    let time = local.rem_euclid(86_400);
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+    let clock = format!(
+        "{:02}:{:02}:{:02}",
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    );
+    match offset {
+        None => format!("{}T{}Z", date, clock),
+        Some(offset) => format!(
+            "{} {} {}{:02}:{:02}",
+            date,
+            clock,
+            if offset < 0 { '-' } else { '+' },
+            offset.unsigned_abs() / 3_600,
+            offset.unsigned_abs() % 3_600 / 60
+        ),
+    }
+}
+
+/// Offset of the local timezone from UTC in seconds at `seconds` since the Unix epoch, as the
+/// C library sees it (so `TZ` is honoured). Returns `None` on non-Unix platforms.
+fn local_offset(seconds: u64) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        let time = libc::time_t::try_from(seconds).ok()?;
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+            return None;
+        }
+        let days = days_from_civil(
+            i64::from(tm.tm_year) + 1900,
+            i64::from(tm.tm_mon) + 1,
+            i64::from(tm.tm_mday),
+        );
+        let local = days * 86_400
+            + i64::from(tm.tm_hour) * 3_600
+            + i64::from(tm.tm_min) * 60
+            + i64::from(tm.tm_sec);
+        i32::try_from(local - seconds as i64).ok()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = seconds;
+        None
+    }
+}
+
+/// Civil date (year, month, day) of a number of days since the Unix epoch (Howard Hinnant's
+/// algorithm).
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Days since the Unix epoch of a civil date, the inverse of [`civil_from_days`].
+#[cfg(unix)]
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = (month + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
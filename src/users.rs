@@ -0,0 +1,175 @@
+use crate::cost::{CostModel, GroupCosts};
+use crate::report::{share_line, Aggregator, FileInfo, ReportRow, ReportSection};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Resolves numeric user ids to user names, caching every lookup.
+///
+/// Lookups go through the system user database (`getpwuid_r`), so users provided by
+/// NSS/LDAP are resolved as well. Unknown ids fall back to their numeric form.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::users::UserNames;
+///
+/// let names = UserNames::default();
+/// // An id that is very unlikely to exist resolves to its numeric form
+/// assert_eq!(names.name(4_000_000_000), "4000000000");
+/// ```
+#[derive(Debug, Default)]
+pub struct UserNames {
+    cache: Mutex<HashMap<u32, String>>,
+}
+
+impl UserNames {
+    /// Returns the user name for `uid`, or the id itself if it cannot be resolved.
+    pub fn name(&self, uid: u32) -> String {
+        if let Ok(cache) = self.cache.lock() {
+            if let Some(name) = cache.get(&uid) {
+                return name.clone();
+            }
+        }
+
+        let name = lookup_user_name(uid).unwrap_or_else(|| uid.to_string());
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(uid, name.clone());
+        }
+        name
+    }
+}
+
+/// Bytes and number of files per owning user, for the `by-user` report.
+///
+/// Users are resolved to names once the scan is done. Files of unknown owner, such as imported
+/// entries or files scanned on platforms without numeric owners, are counted together.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::report::{Aggregator, FileInfo};
+/// use ferris_files::users::UserTotals;
+///
+/// let mut totals = UserTotals::new(10);
+/// for (path, size, uid) in [("/home/a/x", 700, Some(4_000_000_001)), ("/home/a/y", 200, Some(4_000_000_001)), ("/tmp/z", 5, None)] {
+///     totals.observe(&FileInfo { path: path.to_string(), size, modified: None, uid, accessed: None });
+/// }
+/// assert_eq!(totals.top(), [(Some(4_000_000_001), 900, 2), (None, 5, 1)]);
+/// let rows = totals.finish().rows;
+/// assert_eq!(rows[0].fields, ["900", "2", "4000000001", "4000000001"]);
+/// assert_eq!(rows[1].fields, ["5", "1", "", ""]);
+/// ```
+#[derive(Debug, Default)]
+pub struct UserTotals {
+    limit: usize,
+    totals: HashMap<Option<u32>, (u64, usize)>,
+    costs: GroupCosts<Option<u32>>,
+    names: UserNames,
+}
+
+impl UserTotals {
+    /// Creates empty totals, reporting the `limit` users owning the most bytes.
+    pub fn new(limit: usize) -> Self {
+        UserTotals {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    /// Also estimates the monthly cost of every user's files at the rates of `costs`.
+    pub fn with_costs(mut self, costs: CostModel) -> Self {
+        self.costs = GroupCosts::new(costs);
+        self
+    }
+
+    /// Returns the users owning the most bytes as `(uid, bytes, files)`, largest first; files
+    /// of unknown owner have no uid.
+    pub fn top(&self) -> Vec<(Option<u32>, u64, usize)> {
+        let mut top: Vec<(Option<u32>, u64, usize)> = self
+            .totals
+            .iter()
+            .map(|(uid, (bytes, files))| (*uid, *bytes, *files))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(self.limit);
+        top
+    }
+}
+
+impl Aggregator for UserTotals {
+    fn observe(&mut self, file: &FileInfo) {
+        if let Some(cost) = self.costs.monthly(file) {
+            self.costs.add(file.uid, cost);
+        }
+        let (bytes, files) = self.totals.entry(file.uid).or_default();
+        *bytes += file.size;
+        *files += 1;
+    }
+
+    fn finish(&self) -> ReportSection {
+        let total = self.totals.values().map(|(bytes, _)| bytes).sum();
+        let rows = self
+            .top()
+            .into_iter()
+            .map(|(uid, bytes, files)| {
+                let name = uid.map(|uid| self.names.name(uid));
+                let label = name.clone().unwrap_or_else(|| tr!("user-unknown"));
+                ReportRow {
+                    text: share_line(&label, bytes, total, files, self.costs.get(&uid)),
+                    kind: "user".to_string(),
+                    fields: vec![
+                        bytes.to_string(),
+                        files.to_string(),
+                        uid.map(|uid| uid.to_string()).unwrap_or_default(),
+                        name.unwrap_or_default(),
+                    ],
+                }
+            })
+            .collect();
+        ReportSection {
+            heading: Some(tr!("report-by-user")),
+            rows,
+        }
+    }
+}
+
+/// Looks up the name of `uid` in the system user database.
+fn lookup_user_name(uid: u32) -> Option<String> {
+    #[cfg(unix)]
+    {
+        use std::ffi::CStr;
+
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut buffer = vec![0 as libc::c_char; 1024];
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+        loop {
+            let status = unsafe {
+                libc::getpwuid_r(
+                    uid,
+                    &mut passwd,
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                    &mut result,
+                )
+            };
+            if status == libc::ERANGE && buffer.len() < 1 << 16 {
+                buffer.resize(buffer.len() * 2, 0);
+                continue;
+            }
+            if status != 0 || result.is_null() {
+                return None;
+            }
+            break;
+        }
+
+        let name = unsafe { CStr::from_ptr(passwd.pw_name) };
+        Some(name.to_string_lossy().into_owned())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = uid;
+        None
+    }
+}
@@ -0,0 +1,368 @@
+use std::io::{self, Read, Write};
+
+/// Version of the wire protocol written by this build.
+///
+/// The version is bumped whenever the encoding of an existing frame changes; new frame kinds
+/// may be added within a version, and readers skip kinds they do not know.
+///
+/// * 1 - `hello`, `entries` and `done` frames, and `mounts` frames that older readers skip
+pub const WIRE_VERSION: u8 = 1;
+
+/// Bytes every wire stream starts with, followed by the version byte.
+const MAGIC: &[u8; 3] = b"FFW";
+
+/// Number of entries sent per `entries` frame.
+pub const ENTRIES_PER_FRAME: usize = 8192;
+
+/// Largest compressed frame body a reader accepts, to fail fast on corrupt length prefixes.
+pub const MAX_FRAME_LEN: usize = 64 << 20;
+
+/// zstd level of the frame bodies; low levels already gain most on sorted paths.
+const COMPRESSION_LEVEL: i32 = 3;
+
+const KIND_HELLO: u8 = 1;
+const KIND_ENTRIES: u8 = 2;
+const KIND_DONE: u8 = 3;
+const KIND_MOUNTS: u8 = 4;
+
+/// A ranked file as sent over the wire; `modified` is in seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireEntry {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<u64>,
+}
+
+/// A mount holding ranked files, as sent over the wire, so the receiver can partition the
+/// entries by the longest mount point they start with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireMount {
+    pub mount_point: String,
+    pub fs_type: String,
+}
+
+/// A message of the wire protocol.
+///
+/// A scanning agent sends one `Hello`, any number of `Entries` (largest first), the `Mounts`
+/// of the entries if it knows them, and one `Done`.
+/// A stream that ends without `Done` was cut off, e.g. because the agent failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Hello {
+        host: Option<String>,
+        root: String,
+        tool_version: String,
+        started_at: u64,
+    },
+    Entries(Vec<WireEntry>),
+    Mounts(Vec<WireMount>),
+    Done {
+        files: u64,
+        processed: u64,
+        failed: u64,
+        finished_at: u64,
+    },
+}
+
+/// Writes messages as frames of the wire protocol.
+///
+/// A stream is the bytes `FFW` and the version byte, then frames of a kind byte, the length of
+/// the body as a little endian `u32` and the zstd compressed body. Numbers in bodies are LEB128
+/// varints and strings are length prefixed UTF-8. Paths in an `entries` frame are front coded:
+/// each stores only the length it shares with the previous path and the rest, which together
+/// with compression keeps millions of entries small on slow links.
+///
+/// # Examples
+///
+/// ```
+/// use ferris_files::wire::{FrameReader, FrameWriter, Message, WireEntry};
+///
+/// let mut stream = Vec::new();
+/// let mut writer = FrameWriter::new(&mut stream).unwrap();
+/// writer
+///     .send_entries((0..3).map(|i| WireEntry {
+///         path: format!("/srv/logs/app-{}.log", i),
+///         size: 100 - i,
+///         modified: None,
+///     }))
+///     .unwrap();
+/// writer
+///     .send(&Message::Done { files: 3, processed: 3, failed: 0, finished_at: 0 })
+///     .unwrap();
+///
+/// let mut reader = FrameReader::new(stream.as_slice()).unwrap();
+/// let Some(Message::Entries(entries)) = reader.read_message().unwrap() else { panic!() };
+/// assert_eq!(entries[2].path, "/srv/logs/app-2.log");
+/// assert!(matches!(reader.read_message().unwrap(), Some(Message::Done { files: 3, .. })));
+/// assert_eq!(reader.read_message().unwrap(), None);
+/// ```
+pub struct FrameWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> FrameWriter<W> {
+    /// Starts a stream on `out` by writing the magic bytes and version.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[WIRE_VERSION])?;
+        Ok(FrameWriter { out })
+    }
+
+    /// Sends one message as a frame and flushes it, so a reader sees it right away.
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        let mut body = Vec::new();
+        let kind = match message {
+            Message::Hello {
+                host,
+                root,
+                tool_version,
+                started_at,
+            } => {
+                put_str(&mut body, host.as_deref().unwrap_or_default());
+                put_str(&mut body, root);
+                put_str(&mut body, tool_version);
+                put_varint(&mut body, *started_at);
+                KIND_HELLO
+            }
+            Message::Entries(entries) => {
+                put_varint(&mut body, entries.len() as u64);
+                let mut previous: &[u8] = &[];
+                for entry in entries {
+                    let path = entry.path.as_bytes();
+                    let shared = previous
+                        .iter()
+                        .zip(path)
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    put_varint(&mut body, shared as u64);
+                    put_bytes(&mut body, &path[shared..]);
+                    put_varint(&mut body, entry.size);
+                    // 0 stands for an unknown modification time
+                    put_varint(&mut body, entry.modified.map_or(0, |secs| secs + 1));
+                    previous = path;
+                }
+                KIND_ENTRIES
+            }
+            Message::Mounts(mounts) => {
+                put_varint(&mut body, mounts.len() as u64);
+                for mount in mounts {
+                    put_str(&mut body, &mount.mount_point);
+                    put_str(&mut body, &mount.fs_type);
+                }
+                KIND_MOUNTS
+            }
+            Message::Done {
+                files,
+                processed,
+                failed,
+                finished_at,
+            } => {
+                for value in [files, processed, failed, finished_at] {
+                    put_varint(&mut body, *value);
+                }
+                KIND_DONE
+            }
+        };
+
+        let compressed = zstd::bulk::compress(&body, COMPRESSION_LEVEL)?;
+        let len = u32::try_from(compressed.len())
+            .ok()
+            .filter(|len| *len as usize <= MAX_FRAME_LEN)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "wire frame too large"))?;
+        self.out.write_all(&[kind])?;
+        self.out.write_all(&len.to_le_bytes())?;
+        self.out.write_all(&compressed)?;
+        self.out.flush()
+    }
+
+    /// Sends `entries` in frames of up to [`ENTRIES_PER_FRAME`] entries.
+    pub fn send_entries(&mut self, entries: impl IntoIterator<Item = WireEntry>) -> io::Result<()> {
+        let mut batch = Vec::with_capacity(ENTRIES_PER_FRAME);
+        for entry in entries {
+            batch.push(entry);
+            if batch.len() == ENTRIES_PER_FRAME {
+                self.send(&Message::Entries(std::mem::take(&mut batch)))?;
+            }
+        }
+        if !batch.is_empty() {
+            self.send(&Message::Entries(batch))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the messages of a stream written by [`FrameWriter`].
+pub struct FrameReader<R: Read> {
+    input: R,
+}
+
+impl<R: Read> FrameReader<R> {
+    /// Checks the magic bytes and version at the start of `input`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error if `input` is not a wire stream or was written by a newer
+    /// version of the protocol.
+    pub fn new(mut input: R) -> io::Result<Self> {
+        let mut preamble = [0; 4];
+        input
+            .read_exact(&mut preamble)
+            .map_err(|_| not_a_stream())?;
+        if &preamble[..3] != MAGIC {
+            return Err(not_a_stream());
+        }
+        if preamble[3] > WIRE_VERSION {
+            return Err(invalid(format!(
+                "wire stream version {} is newer than the supported version {}",
+                preamble[3], WIRE_VERSION
+            )));
+        }
+        Ok(FrameReader { input })
+    }
+
+    /// Returns the next message, or `None` at the end of the stream.
+    ///
+    /// Frames of unknown kinds, written by newer agents, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error if a frame is truncated or malformed.
+    pub fn read_message(&mut self) -> io::Result<Option<Message>> {
+        loop {
+            let mut kind = [0; 1];
+            if self.input.read(&mut kind)? == 0 {
+                return Ok(None);
+            }
+            let mut len = [0; 4];
+            self.input.read_exact(&mut len).map_err(|_| truncated())?;
+            let len = u32::from_le_bytes(len) as usize;
+            if len > MAX_FRAME_LEN {
+                return Err(invalid(format!("wire frame of {} bytes is too large", len)));
+            }
+            let mut compressed = vec![0; len];
+            self.input
+                .read_exact(&mut compressed)
+                .map_err(|_| truncated())?;
+            if ![KIND_HELLO, KIND_ENTRIES, KIND_MOUNTS, KIND_DONE].contains(&kind[0]) {
+                continue;
+            }
+            let body = zstd::stream::decode_all(compressed.as_slice())
+                .map_err(|e| invalid(format!("corrupt wire frame: {}", e)))?;
+
+            let mut body = Body(&body);
+            return Ok(Some(match kind[0] {
+                KIND_HELLO => Message::Hello {
+                    host: Some(body.str()?).filter(|host| !host.is_empty()),
+                    root: body.str()?,
+                    tool_version: body.str()?,
+                    started_at: body.varint()?,
+                },
+                KIND_ENTRIES => {
+                    let count = body.varint()?;
+                    let mut entries = Vec::new();
+                    let mut previous: Vec<u8> = Vec::new();
+                    for _ in 0..count {
+                        let shared = body.varint()? as usize;
+                        if shared > previous.len() {
+                            return Err(invalid("corrupt wire frame: bad path prefix".to_string()));
+                        }
+                        previous.truncate(shared);
+                        previous.extend_from_slice(body.bytes()?);
+                        let path = String::from_utf8(previous.clone()).map_err(|_| {
+                            invalid("corrupt wire frame: path is not UTF-8".to_string())
+                        })?;
+                        entries.push(WireEntry {
+                            path,
+                            size: body.varint()?,
+                            modified: body.varint()?.checked_sub(1),
+                        });
+                    }
+                    Message::Entries(entries)
+                }
+                KIND_MOUNTS => {
+                    let count = body.varint()?;
+                    let mut mounts = Vec::new();
+                    for _ in 0..count {
+                        mounts.push(WireMount {
+                            mount_point: body.str()?,
+                            fs_type: body.str()?,
+                        });
+                    }
+                    Message::Mounts(mounts)
+                }
+                KIND_DONE => Message::Done {
+                    files: body.varint()?,
+                    processed: body.varint()?,
+                    failed: body.varint()?,
+                    finished_at: body.varint()?,
+                },
+                _ => unreachable!("unknown frame kinds are skipped above"),
+            }));
+        }
+    }
+}
+
+/// Appends `value` as a LEB128 varint.
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Appends length prefixed bytes.
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Appends a length prefixed string.
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    put_bytes(out, value.as_bytes());
+}
+
+/// The decompressed body of a frame, consumed from the front.
+struct Body<'a>(&'a [u8]);
+
+impl<'a> Body<'a> {
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.0.split_first().ok_or_else(truncated)?;
+            self.0 = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("corrupt wire frame: varint too long".to_string()))
+    }
+
+    fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.varint()? as usize;
+        if len > self.0.len() {
+            return Err(truncated());
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn str(&mut self) -> io::Result<String> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|_| invalid("corrupt wire frame: string is not UTF-8".to_string()))
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn not_a_stream() -> io::Error {
+    invalid("not a ferris-files wire stream".to_string())
+}
+
+fn truncated() -> io::Error {
+    invalid("truncated wire frame".to_string())
+}